@@ -27,7 +27,12 @@ use core::cmp::min;
 use bit_set::BitSet;
 
 use crate::parse::ExprTree;
-use crate::{CompileError, Error, Expr, Result};
+use crate::{BacktrackingVerb, CompileError, Error, Expr, ExprArena, ExprId, Result};
+
+/// The most capture groups that a regex can have for them to be delegated to the regex crate.
+/// Its engines keep the slots of all groups for each state, which takes too much memory with many
+/// groups, so the groups of larger regexes are compiled to VM instructions instead.
+const MAX_DELEGATED_GROUPS: usize = 256;
 
 #[derive(Debug)]
 pub struct Info<'a> {
@@ -35,6 +40,11 @@ pub struct Info<'a> {
     pub(crate) end_group: usize,
     pub(crate) min_size: usize,
     pub(crate) const_size: bool,
+    /// The minimum length in bytes of the text that the expression matches
+    pub(crate) min_len: usize,
+    /// The maximum length in bytes of the text that the expression matches, or `None` if it's
+    /// unbounded
+    pub(crate) max_len: Option<usize>,
     pub(crate) hard: bool,
     pub(crate) expr: &'a Expr,
     pub(crate) children: Vec<Info<'a>>,
@@ -64,16 +74,34 @@ impl<'a> Info<'a> {
 }
 
 struct Analyzer<'a> {
+    arena: &'a ExprArena,
     backrefs: &'a BitSet,
+    subroutine_calls: &'a BitSet,
+    balanced_groups: &'a BitSet,
     group_ix: usize,
+    /// Whether the expression is in a fuzzy group, so that it has to be compiled to VM
+    /// instructions for the edits
+    fuzzy: bool,
+    /// Whether the expression is analyzed for partial matching, so that it has to be compiled to
+    /// VM instructions, which tell when a match needs more text
+    partial: bool,
+    /// Whether the groups have to be compiled to VM instructions, because there are too many of
+    /// them to be delegated
+    vm_groups: bool,
+    /// Whether the expression contains `(*ACCEPT)`, which ends a match before the rest of the
+    /// expression, so that the minimum length of a match isn't known
+    accepts: bool,
 }
 
 impl<'a> Analyzer<'a> {
-    fn visit(&mut self, expr: &'a Expr) -> Result<Info<'a>> {
+    fn visit(&mut self, expr: ExprId) -> Result<Info<'a>> {
+        let expr = &self.arena[expr];
         let start_group = self.group_ix;
         let mut children = Vec::new();
         let mut min_size = 0;
         let mut const_size = false;
+        let mut min_len = 0;
+        let mut max_len = Some(0);
         let mut hard = false;
         match *expr {
             Expr::Assertion(assertion) if assertion.is_hard() => {
@@ -86,79 +114,136 @@ impl<'a> Analyzer<'a> {
             Expr::Any { .. } => {
                 min_size = 1;
                 const_size = true;
+                min_len = 1;
+                max_len = Some(4);
             }
             Expr::Literal { ref val, casei } => {
                 // right now each character in a literal gets its own node, that might change
                 min_size = 1;
                 const_size = literal_const_size(val, casei);
+                (min_len, max_len) = literal_len(val, casei);
             }
             Expr::Concat(ref v) => {
                 const_size = true;
-                for child in v {
+                for &child in v {
                     let child_info = self.visit(child)?;
                     min_size += child_info.min_size;
                     const_size &= child_info.const_size;
+                    min_len += child_info.min_len;
+                    max_len = add_len(max_len, child_info.max_len);
                     hard |= child_info.hard;
                     children.push(child_info);
                 }
             }
             Expr::Alt(ref v) => {
-                let child_info = self.visit(&v[0])?;
+                let child_info = self.visit(v[0])?;
                 min_size = child_info.min_size;
                 const_size = child_info.const_size;
+                min_len = child_info.min_len;
+                max_len = child_info.max_len;
                 hard = child_info.hard;
                 children.push(child_info);
-                for child in &v[1..] {
+                for &child in &v[1..] {
                     let child_info = self.visit(child)?;
                     const_size &= child_info.const_size && min_size == child_info.min_size;
                     min_size = min(min_size, child_info.min_size);
+                    min_len = min(min_len, child_info.min_len);
+                    max_len = max_alt_len(max_len, child_info.max_len);
                     hard |= child_info.hard;
                     children.push(child_info);
                 }
             }
-            Expr::Group(ref child) => {
+            Expr::BranchReset(ref v) => {
+                let reset_group = self.group_ix;
+                let mut end_group = reset_group;
+                for (i, &child) in v.iter().enumerate() {
+                    self.group_ix = reset_group;
+                    let child_info = self.visit(child)?;
+                    end_group = end_group.max(self.group_ix);
+                    if i == 0 {
+                        min_size = child_info.min_size;
+                        const_size = child_info.const_size;
+                        min_len = child_info.min_len;
+                        max_len = child_info.max_len;
+                    } else {
+                        const_size &= child_info.const_size && min_size == child_info.min_size;
+                        min_size = min(min_size, child_info.min_size);
+                        min_len = min(min_len, child_info.min_len);
+                        max_len = max_alt_len(max_len, child_info.max_len);
+                    }
+                    children.push(child_info);
+                }
+                self.group_ix = end_group;
+                // the regex crate doesn't support numbering groups like this
+                hard = true;
+            }
+            Expr::Group(child) => {
                 let group = self.group_ix;
                 self.group_ix += 1;
                 let child_info = self.visit(child)?;
                 min_size = child_info.min_size;
                 const_size = child_info.const_size;
+                min_len = child_info.min_len;
+                max_len = child_info.max_len;
                 // If there's a backref to this group, we potentially have to backtrack within the
                 // group. E.g. with `(x|xy)\1` and input `xyxy`, `x` matches but then the backref
                 // doesn't, so we have to backtrack and try `xy`.
                 hard = child_info.hard | self.backrefs.contains(group);
+                // A group that is called as a subroutine has to be compiled to VM instructions,
+                // so that the call can jump to it.
+                hard |= self.subroutine_calls.contains(group);
+                // A group with a stack of captures has to be compiled to VM instructions too, to
+                // keep the stack.
+                hard |= self.balanced_groups.contains(group);
+                hard |= self.vm_groups;
                 children.push(child_info);
             }
-            Expr::LookAround(ref child, _) => {
-                let child_info = self.visit(child)?;
+            Expr::LookAround(child, _) => {
+                // look-arounds are matched exactly in fuzzy groups
+                let child_info = self.visit_exact(child)?;
                 // min_size = 0
                 const_size = true;
                 hard = true;
                 children.push(child_info);
             }
-            Expr::Repeat {
-                ref child, lo, hi, ..
-            } => {
+            Expr::Repeat { child, lo, hi, .. } => {
                 let child_info = self.visit(child)?;
                 min_size = child_info.min_size * lo;
                 const_size = child_info.const_size && lo == hi;
+                min_len = child_info.min_len.saturating_mul(lo);
+                max_len = match child_info.max_len {
+                    Some(0) => Some(0),
+                    Some(len) if hi != usize::MAX => len.checked_mul(hi),
+                    _ => None,
+                };
                 hard = child_info.hard;
                 children.push(child_info);
             }
-            Expr::Delegate { size, .. } => {
+            Expr::Delegate {
+                ref inner, size, ..
+            } => {
                 // currently only used for empty and single-char matches
                 min_size = size;
-                const_size = true;
+                // with Unicode disabled, the delegate matches single bytes, which needn't be
+                // characters
+                let bytes = size > 0 && inner.starts_with("(?-u:");
+                const_size = !bytes;
+                min_len = size;
+                max_len = Some(if bytes { size } else { size * 4 });
             }
-            Expr::Backref(group) => {
+            Expr::Backref { group, .. } => {
                 if group >= self.group_ix {
                     return Err(Error::CompileError(CompileError::InvalidBackref));
                 }
                 hard = true;
+                max_len = None;
             }
-            Expr::AtomicGroup(ref child) => {
+            Expr::AtomicGroup(child) => {
                 let child_info = self.visit(child)?;
                 min_size = child_info.min_size;
                 const_size = child_info.const_size;
+                min_len = child_info.min_len;
+                max_len = child_info.max_len;
                 hard = true; // TODO: possibly could weaken
                 children.push(child_info);
             }
@@ -178,9 +263,9 @@ impl<'a> Analyzer<'a> {
                 const_size = true;
             }
             Expr::Conditional {
-                ref condition,
-                ref true_branch,
-                ref false_branch,
+                condition,
+                true_branch,
+                false_branch,
             } => {
                 hard = true;
 
@@ -195,12 +280,93 @@ impl<'a> Analyzer<'a> {
                     && child_info_false.const_size
                     // if the condition's size plus the truth branch's size is equal to the false branch's size then it's const size
                     && child_info_condition.min_size + child_info_truth.min_size == child_info_false.min_size;
+                // the false branch matches where the condition started
+                min_len = min(
+                    child_info_condition.min_len + child_info_truth.min_len,
+                    child_info_false.min_len,
+                );
+                max_len = max_alt_len(
+                    add_len(child_info_condition.max_len, child_info_truth.max_len),
+                    child_info_false.max_len,
+                );
 
                 children.push(child_info_condition);
                 children.push(child_info_truth);
                 children.push(child_info_false);
             }
+            Expr::SubroutineCall(_) => {
+                // the group may be called before its size is known, e.g. when it calls itself
+                hard = true;
+                max_len = None;
+            }
+            Expr::BalancingGroup { child, capture, .. } => {
+                // a new group that is captured into is numbered like other groups
+                if capture == Some(self.group_ix) {
+                    self.group_ix += 1;
+                }
+                let child_info = self.visit(child)?;
+                min_size = child_info.min_size;
+                const_size = child_info.const_size;
+                min_len = child_info.min_len;
+                max_len = child_info.max_len;
+                hard = true;
+                children.push(child_info);
+            }
+            Expr::BacktrackingVerb(verb) => {
+                hard = true;
+                // the rest of the expression is skipped after (*ACCEPT)
+                const_size = verb != BacktrackingVerb::Accept;
+                self.accepts |= verb == BacktrackingVerb::Accept;
+            }
+            Expr::GraphemeCluster => {
+                // a cluster can't be split by backtracking, like in PCRE
+                min_size = 1;
+                const_size = false;
+                hard = true;
+                min_len = 1;
+                max_len = None;
+            }
+            Expr::Callout(_) | Expr::Predicate(_) => {
+                hard = true;
+                const_size = true;
+            }
+            Expr::ScriptRun(child) => {
+                let child_info = self.visit(child)?;
+                min_size = child_info.min_size;
+                const_size = child_info.const_size;
+                min_len = child_info.min_len;
+                max_len = child_info.max_len;
+                hard = true;
+                children.push(child_info);
+            }
+            Expr::Absent(child) => {
+                // the absent expression is searched for by the regex crate
+                let partial = core::mem::replace(&mut self.partial, false);
+                let vm_groups = core::mem::replace(&mut self.vm_groups, false);
+                let child_info = self.visit_exact(child);
+                self.partial = partial;
+                self.vm_groups = vm_groups;
+                let child_info = child_info?;
+                hard = true;
+                max_len = None;
+                children.push(child_info);
+            }
+            Expr::Fuzzy { child, max_cost } => {
+                let fuzzy = core::mem::replace(&mut self.fuzzy, true);
+                let child_info = self.visit(child)?;
+                self.fuzzy = fuzzy;
+                // each edit deletes at most one character
+                min_size = child_info.min_size.saturating_sub(max_cost);
+                const_size = child_info.const_size && max_cost == 0;
+                // and changes the length by at most the length of one character
+                let max_change = max_cost.saturating_mul(4);
+                min_len = child_info.min_len.saturating_sub(max_change);
+                max_len = add_len(child_info.max_len, Some(max_change));
+                hard = true;
+                children.push(child_info);
+            }
         };
+        hard |= self.fuzzy || self.partial;
 
         Ok(Info {
             expr,
@@ -209,9 +375,43 @@ impl<'a> Analyzer<'a> {
             end_group: self.group_ix,
             min_size,
             const_size,
+            min_len,
+            max_len,
             hard,
         })
     }
+
+    fn visit_exact(&mut self, expr: ExprId) -> Result<Info<'a>> {
+        let fuzzy = core::mem::replace(&mut self.fuzzy, false);
+        let result = self.visit(expr);
+        self.fuzzy = fuzzy;
+        result
+    }
+}
+
+/// Returns the sum of two maximum lengths, `None` meaning unbounded.
+fn add_len(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    a?.checked_add(b?)
+}
+
+/// Returns the maximum length of alternatives with the maximum lengths `a` and `b`.
+fn max_alt_len(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    Some(a?.max(b?))
+}
+
+/// Returns the minimum and maximum length in bytes of the text that a literal matches. Ignoring
+/// case, a character can match characters of other lengths with full case folding, like `k`
+/// the three bytes of the Kelvin sign, or `ΐ` a character that folds to three characters.
+fn literal_len(val: &str, casei: bool) -> (usize, Option<usize>) {
+    if !casei {
+        (val.len(), Some(val.len()))
+    } else if val.is_ascii() {
+        // ASCII characters only fold together with characters that are at least as long
+        (val.len(), Some(val.len() * 3))
+    } else {
+        let chars = val.chars().count();
+        ((chars + 2) / 3, Some(chars * 12))
+    }
 }
 
 fn literal_const_size(_: &str, _: bool) -> bool {
@@ -223,12 +423,47 @@ fn literal_const_size(_: &str, _: bool) -> bool {
 
 /// Analyze the parsed expression to determine whether it requires fancy features.
 pub fn analyze<'a>(tree: &'a ExprTree) -> Result<Info<'a>> {
+    analyze_with(tree, false)
+}
+
+/// Analyze the parsed expression for partial matching, where all of it is hard except for
+/// single characters, so that the VM can tell when a match needs more text.
+pub(crate) fn analyze_partial<'a>(tree: &'a ExprTree) -> Result<Info<'a>> {
+    analyze_with(tree, true)
+}
+
+fn analyze_with<'a>(tree: &'a ExprTree, partial: bool) -> Result<Info<'a>> {
     let mut analyzer = Analyzer {
+        arena: &tree.arena,
         backrefs: &tree.backrefs,
+        subroutine_calls: &tree.subroutine_calls,
+        balanced_groups: &tree.balanced_groups,
         group_ix: 0,
+        fuzzy: false,
+        partial,
+        vm_groups: false,
+        accepts: false,
     };
 
-    analyzer.visit(&tree.expr)
+    let mut info = analyzer.visit(tree.expr)?;
+    if info.end_group > MAX_DELEGATED_GROUPS + 1 {
+        // analyze again now that the number of groups is known, the whole match is group 0
+        analyzer.group_ix = 0;
+        analyzer.vm_groups = true;
+        info = analyzer.visit(tree.expr)?;
+    }
+    // calls can refer to groups that come later, so they can only be checked at the end
+    if tree
+        .subroutine_calls
+        .iter()
+        .any(|group| group >= info.end_group)
+    {
+        return Err(Error::CompileError(CompileError::InvalidSubroutineCall));
+    }
+    if analyzer.accepts {
+        info.min_len = 0;
+    }
+    Ok(info)
 }
 
 #[cfg(test)]
@@ -253,7 +488,7 @@ mod tests {
 
     #[test]
     fn invalid_backref_1() {
-        assert!(analyze(&Expr::parse_tree(".\\0").unwrap()).is_err());
+        assert!(analyze(&Expr::parse_tree(".\\k<0>").unwrap()).is_err());
     }
 
     #[test]
@@ -266,6 +501,12 @@ mod tests {
         assert!(analyze(&Expr::parse_tree("\\1(.)").unwrap()).is_err());
     }
 
+    #[test]
+    fn invalid_subroutine_call() {
+        assert!(analyze(&Expr::parse_tree("(.)(?2)").unwrap()).is_err());
+        assert!(analyze(&Expr::parse_tree("(.)(?+1)").unwrap()).is_err());
+    }
+
     #[test]
     fn is_literal() {
         let tree = Expr::parse_tree("abc").unwrap();
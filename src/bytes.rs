@@ -0,0 +1,979 @@
+//! Matching on byte strings that aren't necessarily valid UTF-8.
+//!
+//! This module mirrors the top-level API, except that haystacks, matches and replacements are
+//! `&[u8]` instead of `&str`. Patterns are still written as `&str` and keep their Unicode
+//! meaning: `.`, classes and literals only match valid UTF-8, and matches are never reported
+//! in the middle of a valid UTF-8 sequence. Bytes that aren't valid UTF-8 can appear anywhere
+//! else in the haystack, including around backreferences and look-arounds.
+//!
+//! Unicode can be disabled with the flag `u`, as in `(?-u)` or `(?-u:...)`, like in the regex
+//! crate. Then escapes such as `\xFF` match a single byte instead of the UTF-8 sequence of a
+//! character, `.` and classes match any single byte that they contain, `\w`, `\d`, `\s` and `\b`
+//! are ASCII-only, and so is case-insensitive matching. Other characters of the pattern still
+//! match their UTF-8 sequence. A look-behind whose contents match a constant number of bytes
+//! steps back over that many bytes.
+//!
+//! # Example
+//!
+//! ```rust
+//! use fancy_regex::bytes::Regex;
+//!
+//! let re = Regex::new(r"(\w+)=\1").unwrap();
+//! let haystack = b"\xff\xfeab=ab\x00";
+//! let mat = re.find(haystack).unwrap().unwrap();
+//! assert_eq!(mat.as_bytes(), b"ab=ab");
+//! assert_eq!(mat.range(), 2..7);
+//!
+//! let re = Regex::new(r"(?-u)(?<=\xFF)[\x80-\xFF]+").unwrap();
+//! let mat = re.find(b"a\xff\xfe\x80a").unwrap().unwrap();
+//! assert_eq!(mat.range(), 2..4);
+//! ```
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::ops::{Index, Range};
+use core::str::FromStr;
+
+use crate::expand::Groups;
+use crate::parse::{group_indices, DuplicateGroups, NamedGroups};
+use crate::vm::OPTION_SKIPPED_EMPTY_MATCH;
+use crate::{CaptureNames, CapturesImpl, Edit, Error, Expander, Result};
+
+/// A compiled regular expression for matching byte strings.
+///
+/// It can be created with [`Regex::new`], or converted from a top-level
+/// [`Regex`](crate::Regex) built with a [`RegexBuilder`](crate::RegexBuilder).
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Regex(crate::Regex);
+
+/// A single match of a regex or group in an input byte string
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Match<'t> {
+    text: &'t [u8],
+    start: usize,
+    end: usize,
+}
+
+/// An iterator over all non-overlapping matches for a particular byte string.
+///
+/// The iterator yields a `Result<Match>`. The iterator stops when no more
+/// matches can be found.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the matched byte string.
+#[derive(Debug)]
+pub struct Matches<'r, 't> {
+    re: &'r Regex,
+    text: &'t [u8],
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl<'r, 't> Matches<'r, 't> {
+    /// Return the text being searched.
+    pub fn text(&self) -> &'t [u8] {
+        self.text
+    }
+
+    /// Return the underlying regex.
+    pub fn regex(&self) -> &'r Regex {
+        self.re
+    }
+}
+
+impl<'r, 't> Iterator for Matches<'r, 't> {
+    type Item = Result<Match<'t>>;
+
+    /// Adapted from the `regex` crate. Calls `find_from_pos` repeatedly.
+    /// Ignores empty matches immediately after a match, unless configured otherwise.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.last_end > self.text.len() {
+            return None;
+        }
+
+        let option_flags = match self.last_match {
+            Some(last_match) if self.last_end > last_match => OPTION_SKIPPED_EMPTY_MATCH,
+            _ => 0,
+        };
+        let range = match self
+            .re
+            .0
+            .search_from_pos(self.text, self.last_end, option_flags)
+        {
+            Err(error) => return Some(Err(error)),
+            Ok(None) => return None,
+            Ok(Some(range)) => range,
+        };
+
+        if range.is_empty() {
+            // This is an empty match. To ensure we make progress, start
+            // the next search at the smallest possible starting position
+            // of the next match following this one.
+            self.last_end = self
+                .re
+                .0
+                .next_after_empty_bytes(self.text, self.last_end, range.end);
+            // Don't accept empty matches immediately following a match.
+            // Just move on to the next match.
+            if Some(range.end) == self.last_match && self.re.0.skips_empty_after_match() {
+                return self.next();
+            }
+        } else {
+            self.last_end = range.end;
+        }
+
+        self.last_match = Some(range.end);
+
+        Some(Ok(Match::new(self.text, range.start, range.end)))
+    }
+}
+
+/// An iterator that yields all non-overlapping capture groups matching a
+/// particular regular expression.
+///
+/// The iterator stops when no more matches can be found.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the matched byte string.
+#[derive(Debug)]
+pub struct CaptureMatches<'r, 't>(Matches<'r, 't>);
+
+impl<'r, 't> CaptureMatches<'r, 't> {
+    /// Return the text being searched.
+    pub fn text(&self) -> &'t [u8] {
+        self.0.text
+    }
+
+    /// Return the underlying regex.
+    pub fn regex(&self) -> &'r Regex {
+        self.0.re
+    }
+}
+
+impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
+    type Item = Result<Captures<'t>>;
+
+    /// Adapted from the `regex` crate. Calls `captures_from_pos` repeatedly.
+    /// Ignores empty matches immediately after a match, unless configured otherwise.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.last_end > self.0.text.len() {
+            return None;
+        }
+
+        let captures = match self.0.re.captures_from_pos(self.0.text, self.0.last_end) {
+            Err(error) => return Some(Err(error)),
+            Ok(None) => return None,
+            Ok(Some(captures)) => captures,
+        };
+
+        let mat = captures
+            .get(0)
+            .expect("`Captures` is expected to have entire match at 0th position");
+        if mat.start == mat.end {
+            self.0.last_end =
+                self.0
+                    .re
+                    .0
+                    .next_after_empty_bytes(self.0.text, self.0.last_end, mat.end);
+            if Some(mat.end) == self.0.last_match && self.0.re.0.skips_empty_after_match() {
+                return self.next();
+            }
+        } else {
+            self.0.last_end = mat.end;
+        }
+
+        self.0.last_match = Some(mat.end);
+
+        Some(Ok(captures))
+    }
+}
+
+/// A set of capture groups found for a regex.
+#[derive(Debug)]
+pub struct Captures<'t> {
+    text: &'t [u8],
+    inner: CapturesImpl,
+    named_groups: Arc<NamedGroups>,
+    duplicate_groups: Arc<DuplicateGroups>,
+}
+
+/// Iterator for captured groups in order in which they appear in the regex.
+#[derive(Debug)]
+pub struct SubCaptureMatches<'c, 't> {
+    caps: &'c Captures<'t>,
+    i: usize,
+}
+
+/// An iterator over all byte substrings delimited by a regex.
+///
+/// This iterator yields `Result<&'h [u8]>`, where each item is a substring of the
+/// target that is delimited by matches of the regular expression. It stops when there
+/// are no more substrings to yield.
+///
+/// `'r` is the lifetime of the compiled regular expression, and `'h` is the
+/// lifetime of the target being split.
+///
+/// This iterator can be created by the [`Regex::split`] method.
+#[derive(Debug)]
+pub struct Split<'r, 'h> {
+    matches: Matches<'r, 'h>,
+    next_start: usize,
+    target: &'h [u8],
+}
+
+impl<'r, 'h> Iterator for Split<'r, 'h> {
+    type Item = Result<&'h [u8]>;
+
+    /// Returns the next substring that results from splitting the target by the regex.
+    ///
+    /// If no more matches are found, returns the remaining part of the target,
+    /// or `None` if all substrings have been yielded.
+    fn next(&mut self) -> Option<Result<&'h [u8]>> {
+        match self.matches.next() {
+            None => {
+                let len = self.target.len();
+                if self.next_start > len {
+                    None
+                } else {
+                    let part = &self.target[self.next_start..len];
+                    self.next_start = len + 1;
+                    Some(Ok(part))
+                }
+            }
+            Some(Ok(m)) => {
+                let part = &self.target[self.next_start..m.start()];
+                self.next_start = m.end();
+                Some(Ok(part))
+            }
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'r, 'h> core::iter::FusedIterator for Split<'r, 'h> {}
+
+/// An iterator over at most `N` byte substrings delimited by a regex.
+///
+/// This iterator yields `Result<&'h [u8]>`, where each item is a substring of the
+/// target that is delimited by matches of the regular expression. It stops either when
+/// there are no more substrings to yield, or after `N` substrings have been yielded.
+///
+/// The `N`th substring is the remaining part of the target.
+///
+/// This iterator can be created by the [`Regex::splitn`] method.
+#[derive(Debug)]
+pub struct SplitN<'r, 'h> {
+    splits: Split<'r, 'h>,
+    limit: usize,
+}
+
+impl<'r, 'h> Iterator for SplitN<'r, 'h> {
+    type Item = Result<&'h [u8]>;
+
+    /// Returns the next substring resulting from splitting the target by the regex,
+    /// limited to `N` splits.
+    fn next(&mut self) -> Option<Result<&'h [u8]>> {
+        if self.limit == 0 {
+            return None;
+        }
+
+        self.limit -= 1;
+        if self.limit > 0 {
+            return self.splits.next();
+        }
+
+        let len = self.splits.target.len();
+        if self.splits.next_start > len {
+            None
+        } else {
+            let start = self.splits.next_start;
+            self.splits.next_start = len + 1;
+            Some(Ok(&self.splits.target[start..len]))
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.limit))
+    }
+}
+
+impl<'r, 'h> core::iter::FusedIterator for SplitN<'r, 'h> {}
+
+/// An iterator over all byte substrings delimited by a regex, each ending with the match that
+/// delimits it.
+///
+/// `'r` is the lifetime of the compiled regular expression, and `'h` is the
+/// lifetime of the target being split.
+///
+/// This iterator can be created by the [`Regex::split_inclusive`] method.
+#[derive(Debug)]
+pub struct SplitInclusive<'r, 'h> {
+    matches: Matches<'r, 'h>,
+    next_start: usize,
+    target: &'h [u8],
+}
+
+impl<'r, 'h> Iterator for SplitInclusive<'r, 'h> {
+    type Item = Result<&'h [u8]>;
+
+    /// Returns the next substring including the match that ends it, or the remaining part of
+    /// the target if there are no more matches and it's not empty.
+    fn next(&mut self) -> Option<Result<&'h [u8]>> {
+        match self.matches.next() {
+            None => {
+                let len = self.target.len();
+                if self.next_start >= len {
+                    None
+                } else {
+                    let part = &self.target[self.next_start..len];
+                    self.next_start = len;
+                    Some(Ok(part))
+                }
+            }
+            Some(Ok(m)) => {
+                let part = &self.target[self.next_start..m.end()];
+                self.next_start = m.end();
+                Some(Ok(part))
+            }
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'r, 'h> core::iter::FusedIterator for SplitInclusive<'r, 'h> {}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for Regex {
+    type Err = Error;
+
+    /// Attempts to parse a string into a regular expression
+    fn from_str(s: &str) -> Result<Regex> {
+        Regex::new(s)
+    }
+}
+
+impl TryFrom<&str> for Regex {
+    type Error = Error;
+
+    /// Attempts to parse a string into a regular expression
+    fn try_from(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Regex {
+    type Error = Error;
+
+    /// Attempts to parse a string into a regular expression
+    fn try_from(s: String) -> Result<Self> {
+        Self::new(&s)
+    }
+}
+
+impl From<crate::Regex> for Regex {
+    /// Uses a regex compiled for strings to match byte strings.
+    fn from(regex: crate::Regex) -> Self {
+        Regex(regex)
+    }
+}
+
+impl Regex {
+    /// Parse and compile a regex with default options, see `RegexBuilder`.
+    ///
+    /// Returns an [`Error`] if the pattern could not be parsed.
+    pub fn new(re: &str) -> Result<Regex> {
+        crate::RegexBuilder::new(re).build_bytes()
+    }
+
+    /// Returns the original string of this regex.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Check if the regex matches the input bytes.
+    ///
+    /// # Example
+    ///
+    /// Test if some bytes contain the same word twice:
+    ///
+    /// ```rust
+    /// # use fancy_regex::bytes::Regex;
+    ///
+    /// let re = Regex::new(r"(\w+) \1").unwrap();
+    /// assert!(re.is_match(b"\xffmirror mirror on the wall").unwrap());
+    /// ```
+    pub fn is_match(&self, text: &[u8]) -> Result<bool> {
+        self.0.is_match_bytes(text)
+    }
+
+    /// Returns an iterator for each successive non-overlapping match in `text`.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t [u8]) -> Matches<'r, 't> {
+        Matches {
+            re: self,
+            text,
+            last_end: 0,
+            last_match: None,
+        }
+    }
+
+    /// Find the first match in the input bytes.
+    ///
+    /// # Example
+    ///
+    /// Find a word that is followed by an exclamation point:
+    ///
+    /// ```rust
+    /// # use fancy_regex::bytes::Regex;
+    ///
+    /// let re = Regex::new(r"\w+(?=!)").unwrap();
+    /// assert_eq!(re.find(b"so\xff fancy!").unwrap().unwrap().as_bytes(), b"fancy");
+    /// ```
+    pub fn find<'t>(&self, text: &'t [u8]) -> Result<Option<Match<'t>>> {
+        self.find_from_pos(text, 0)
+    }
+
+    /// Returns the first match in `text`, starting from the specified byte position `pos`.
+    ///
+    /// See [`crate::Regex::captures_from_pos`] for how this differs from searching a slice of
+    /// `text`.
+    pub fn find_from_pos<'t>(&self, text: &'t [u8], pos: usize) -> Result<Option<Match<'t>>> {
+        let range = self.0.search_from_pos(text, pos, 0)?;
+        Ok(range.map(|range| Match::new(text, range.start, range.end)))
+    }
+
+    /// Returns an iterator over all the non-overlapping capture groups matched in `text`.
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t [u8]) -> CaptureMatches<'r, 't> {
+        CaptureMatches(self.find_iter(text))
+    }
+
+    /// Returns the capture groups for the first match in `text`.
+    ///
+    /// If no match is found, then `Ok(None)` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use fancy_regex::bytes::Regex;
+    ///
+    /// let re = Regex::new(r"(?<=\d)(\w+)").unwrap();
+    /// let captures = re.captures(b"\x80ab 1cd\xff").unwrap().unwrap();
+    /// assert_eq!(&captures[1], b"cd");
+    /// ```
+    pub fn captures<'t>(&self, text: &'t [u8]) -> Result<Option<Captures<'t>>> {
+        self.captures_from_pos(text, 0)
+    }
+
+    /// Returns the capture groups for the first match in `text`, starting from
+    /// the specified byte position `pos`.
+    pub fn captures_from_pos<'t>(
+        &self,
+        text: &'t [u8],
+        pos: usize,
+    ) -> Result<Option<Captures<'t>>> {
+        let inner = self.0.captures_impl_from_pos(text, pos)?;
+        Ok(inner.map(|inner| Captures {
+            text,
+            inner,
+            named_groups: self.0.named_groups.clone(),
+            duplicate_groups: self.0.duplicate_groups.clone(),
+        }))
+    }
+
+    /// Returns the number of captures, including the implicit capture of the entire expression.
+    pub fn captures_len(&self) -> usize {
+        self.0.captures_len()
+    }
+
+    /// Returns an iterator over the capture names.
+    pub fn capture_names(&self) -> CaptureNames<'_> {
+        self.0.capture_names()
+    }
+
+    /// Returns the indices of the capture groups named `name`, see
+    /// [`crate::Regex::group_indices`].
+    pub fn group_indices(&self, name: &str) -> &[usize] {
+        self.0.group_indices(name)
+    }
+
+    /// Replaces the leftmost-first match with the replacement provided. See
+    /// [`crate::Regex::replace`] for the syntax of replacement templates.
+    ///
+    /// ```rust
+    /// # use fancy_regex::bytes::Regex;
+    /// let re = Regex::new(r"(?P<last>[^,\s]+),\s+(?P<first>\S+)").unwrap();
+    /// let result = re.replace(b"Springsteen, Bruce\xff", &b"$first $last"[..]);
+    /// assert_eq!(&result[..], b"Bruce Springsteen\xff");
+    /// ```
+    pub fn replace<'t, R: Replacer>(&self, text: &'t [u8], rep: R) -> Cow<'t, [u8]> {
+        self.replacen(text, 1, rep)
+    }
+
+    /// Replaces all non-overlapping matches in `text` with the replacement
+    /// provided. This is the same as calling `replacen` with `limit` set to
+    /// `0`.
+    pub fn replace_all<'t, R: Replacer>(&self, text: &'t [u8], rep: R) -> Cow<'t, [u8]> {
+        self.replacen(text, 0, rep)
+    }
+
+    /// Replaces the leftmost-first match with the replacement provided. This
+    /// is the same as calling `try_replacen` with `limit` set to `1`.
+    ///
+    /// Propagates any errors encountered, such as `RuntimeError::BacktrackLimitExceeded`.
+    pub fn try_replace<'t, R: Replacer>(&self, text: &'t [u8], rep: R) -> Result<Cow<'t, [u8]>> {
+        self.try_replacen(text, 1, rep)
+    }
+
+    /// Replaces all non-overlapping matches in `text` with the replacement
+    /// provided. This is the same as calling `try_replacen` with `limit` set to
+    /// `0`.
+    ///
+    /// Propagates any errors encountered, such as `RuntimeError::BacktrackLimitExceeded`.
+    pub fn try_replace_all<'t, R: Replacer>(
+        &self,
+        text: &'t [u8],
+        rep: R,
+    ) -> Result<Cow<'t, [u8]>> {
+        self.try_replacen(text, 0, rep)
+    }
+
+    /// Replaces at most `limit` non-overlapping matches in `text` with the
+    /// replacement provided. If `limit` is 0, then all non-overlapping matches
+    /// are replaced.
+    ///
+    /// Will panic if any errors are encountered. Use `try_replacen`, which this
+    /// function unwraps, if you want to handle errors.
+    pub fn replacen<'t, R: Replacer>(&self, text: &'t [u8], limit: usize, rep: R) -> Cow<'t, [u8]> {
+        self.try_replacen(text, limit, rep).unwrap()
+    }
+
+    /// Replaces at most `limit` non-overlapping matches in `text` with the
+    /// replacement provided. If `limit` is 0, then all non-overlapping matches
+    /// are replaced.
+    ///
+    /// Propagates any errors encountered, such as `RuntimeError::BacktrackLimitExceeded`.
+    pub fn try_replacen<'t, R: Replacer>(
+        &self,
+        text: &'t [u8],
+        limit: usize,
+        mut rep: R,
+    ) -> Result<Cow<'t, [u8]>> {
+        // See `crate::Regex::try_replacen` for the fast path
+        if let Some(rep) = rep.no_expansion() {
+            let mut it = self.find_iter(text).enumerate().peekable();
+            if it.peek().is_none() {
+                return Ok(Cow::Borrowed(text));
+            }
+            let mut new = Vec::with_capacity(text.len());
+            let mut last_match = 0;
+            for (i, m) in it {
+                let m = m?;
+
+                if limit > 0 && i >= limit {
+                    break;
+                }
+                new.extend_from_slice(&text[last_match..m.start()]);
+                new.extend_from_slice(&rep);
+                last_match = m.end();
+            }
+            new.extend_from_slice(&text[last_match..]);
+            return Ok(Cow::Owned(new));
+        }
+
+        let mut it = self.captures_iter(text).enumerate().peekable();
+        if it.peek().is_none() {
+            return Ok(Cow::Borrowed(text));
+        }
+        let mut new = Vec::with_capacity(text.len());
+        let mut last_match = 0;
+        for (i, cap) in it {
+            let cap = cap?;
+
+            if limit > 0 && i >= limit {
+                break;
+            }
+            // unwrap on 0 is OK because captures only reports matches
+            let m = cap.get(0).unwrap();
+            new.extend_from_slice(&text[last_match..m.start()]);
+            rep.replace_append(&cap, &mut new);
+            last_match = m.end();
+        }
+        new.extend_from_slice(&text[last_match..]);
+        Ok(Cow::Owned(new))
+    }
+
+    /// Splits the byte string by matches of the regex.
+    ///
+    /// Returns an iterator over the substrings of the target that *aren't* matched by the
+    /// regex.
+    ///
+    /// ```rust
+    /// # use fancy_regex::bytes::Regex;
+    /// let re = Regex::new(r"[ \t]+").unwrap();
+    /// let fields: Vec<&[u8]> = re.split(b"a \xffb \t c").map(|x| x.unwrap()).collect();
+    /// assert_eq!(fields, vec![&b"a"[..], &b"\xffb"[..], &b"c"[..]]);
+    /// ```
+    pub fn split<'r, 'h>(&'r self, target: &'h [u8]) -> Split<'r, 'h> {
+        Split {
+            matches: self.find_iter(target),
+            next_start: 0,
+            target,
+        }
+    }
+
+    /// Splits the byte string by matches of the regex at most `limit` times.
+    ///
+    /// The `N`th substring is the remaining part of the target.
+    pub fn splitn<'r, 'h>(&'r self, target: &'h [u8], limit: usize) -> SplitN<'r, 'h> {
+        SplitN {
+            splits: self.split(target),
+            limit,
+        }
+    }
+
+    /// Splits the byte string by matches of the regex, keeping the matches.
+    ///
+    /// Returns an iterator over the substrings of the target that each end with a match of
+    /// the regex. If the target doesn't end with a match, the last substring is the rest of the
+    /// target.
+    pub fn split_inclusive<'r, 'h>(&'r self, target: &'h [u8]) -> SplitInclusive<'r, 'h> {
+        SplitInclusive {
+            matches: self.find_iter(target),
+            next_start: 0,
+            target,
+        }
+    }
+}
+
+impl<'t> Match<'t> {
+    /// Returns the starting byte offset of the match in the text.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending byte offset of the match in the text.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the match in text.
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the matched bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &'t [u8] {
+        &self.text[self.start..self.end]
+    }
+
+    /// Creates a new match from the given text and byte offsets.
+    fn new(text: &'t [u8], start: usize, end: usize) -> Match<'t> {
+        Match { text, start, end }
+    }
+}
+
+impl<'t> From<Match<'t>> for &'t [u8] {
+    fn from(m: Match<'t>) -> &'t [u8] {
+        m.as_bytes()
+    }
+}
+
+impl<'t> From<Match<'t>> for Range<usize> {
+    fn from(m: Match<'t>) -> Range<usize> {
+        m.range()
+    }
+}
+
+#[allow(clippy::len_without_is_empty)] // follow regex's API
+impl<'t> Captures<'t> {
+    /// Get the capture group by its index in the regex.
+    ///
+    /// If there is no match for that group or the index does not correspond to a group, `None` is
+    /// returned. The index 0 returns the whole match.
+    pub fn get(&self, i: usize) -> Option<Match<'t>> {
+        self.inner
+            .get(i)
+            .map(|range| Match::new(self.text, range.start, range.end))
+    }
+
+    /// Returns the match for a named capture group.  Returns `None` the capture
+    /// group did not match or if there is no group with the given name.
+    ///
+    /// For a name of more than one group with `(?J)`, this is the first of the groups that
+    /// matched.
+    pub fn name(&self, name: &str) -> Option<Match<'t>> {
+        group_indices(&self.named_groups, &self.duplicate_groups, name)
+            .iter()
+            .find_map(|&i| self.get(i))
+    }
+
+    /// Expands all instances of `$group` in `replacement` to the corresponding
+    /// capture group, and writes them to the `dst` buffer given. See
+    /// [`crate::Captures::expand`] for the syntax.
+    ///
+    /// For more control over expansion, see [`Expander`].
+    pub fn expand(&self, replacement: &[u8], dst: &mut Vec<u8>) {
+        Expander::default().append_expansion_bytes(dst, replacement, self);
+    }
+
+    /// Iterate over the captured groups in order in which they appeared in the regex. The first
+    /// capture corresponds to the whole match.
+    pub fn iter<'c>(&'c self) -> SubCaptureMatches<'c, 't> {
+        SubCaptureMatches { caps: self, i: 0 }
+    }
+
+    /// How many groups were captured. This is always at least 1 because group 0 returns the whole
+    /// match.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns the edits that the fuzzy groups of the regex made for the match. See
+    /// [`crate::Captures::edits`].
+    pub fn edits(&self) -> &[Edit] {
+        self.inner.edits()
+    }
+
+    /// Returns the number of edits that the fuzzy groups of the regex made for the match.
+    pub fn cost(&self) -> usize {
+        self.inner.edits().len()
+    }
+}
+
+/// Get a group by index.
+///
+/// # Panics
+///
+/// If there is no group at the given index.
+impl<'t> Index<usize> for Captures<'t> {
+    type Output = [u8];
+
+    fn index(&self, i: usize) -> &[u8] {
+        self.get(i)
+            .map(|m| m.as_bytes())
+            .unwrap_or_else(|| panic!("no group at index '{}'", i))
+    }
+}
+
+/// Get a group by name.
+///
+/// # Panics
+///
+/// If there is no group named by the given value.
+impl<'t, 'i> Index<&'i str> for Captures<'t> {
+    type Output = [u8];
+
+    fn index<'a>(&'a self, name: &'i str) -> &'a [u8] {
+        self.name(name)
+            .map(|m| m.as_bytes())
+            .unwrap_or_else(|| panic!("no group named '{}'", name))
+    }
+}
+
+impl<'c, 't> Iterator for SubCaptureMatches<'c, 't> {
+    type Item = Option<Match<'t>>;
+
+    fn next(&mut self) -> Option<Option<Match<'t>>> {
+        if self.i < self.caps.len() {
+            let result = self.caps.get(self.i);
+            self.i += 1;
+            Some(result)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'t> Groups for Captures<'t> {
+    type Text = [u8];
+
+    fn name(&self, name: &str) -> Option<&[u8]> {
+        Captures::name(self, name).map(|m| m.as_bytes())
+    }
+
+    fn get(&self, i: usize) -> Option<&[u8]> {
+        Captures::get(self, i).map(|m| m.as_bytes())
+    }
+
+    fn input(&self) -> Option<&[u8]> {
+        Captures::get(self, 0).map(|m| m.text)
+    }
+
+    fn prefix(&self) -> Option<&[u8]> {
+        Captures::get(self, 0).map(|m| &m.text[..m.start])
+    }
+
+    fn suffix(&self) -> Option<&[u8]> {
+        Captures::get(self, 0).map(|m| &m.text[m.end..])
+    }
+
+    fn contains_name(&self, name: &str) -> bool {
+        self.named_groups.contains_key(name)
+    }
+
+    fn has_names(&self) -> bool {
+        !self.named_groups.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        Captures::len(self)
+    }
+}
+
+/// Replacer describes types that can be used to replace matches in a byte string.
+///
+/// Implementations are provided for `&[u8]` along with other variants of byte string types
+/// and `FnMut(&Captures) -> T` where `T: AsRef<[u8]>`.
+pub trait Replacer {
+    /// Appends bytes to `dst` to replace the current match.
+    ///
+    /// The current match is represented by `caps`, which is guaranteed to
+    /// have a match at capture group `0`.
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>);
+
+    /// Return a fixed unchanging replacement byte string.
+    ///
+    /// When doing replacements, if access to `Captures` is not needed (e.g.,
+    /// the replacement byte string does not need `$` expansion), then it can
+    /// be beneficial to avoid finding sub-captures.
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        None
+    }
+
+    /// Return a `Replacer` that borrows and wraps this `Replacer`.
+    fn by_ref(&mut self) -> ReplacerRef<'_, Self> {
+        ReplacerRef(self)
+    }
+}
+
+/// By-reference adaptor for a `Replacer`
+///
+/// Returned by [`Replacer::by_ref`].
+#[derive(Debug)]
+pub struct ReplacerRef<'a, R: ?Sized>(&'a mut R);
+
+impl<'a, R: Replacer + ?Sized + 'a> Replacer for ReplacerRef<'a, R> {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        self.0.replace_append(caps, dst)
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        self.0.no_expansion()
+    }
+}
+
+impl Replacer for &[u8] {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        caps.expand(self, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        no_expansion(self)
+    }
+}
+
+impl<const N: usize> Replacer for &[u8; N] {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        caps.expand(&self[..], dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        no_expansion(*self)
+    }
+}
+
+impl Replacer for &Vec<u8> {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        caps.expand(self, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        no_expansion(self)
+    }
+}
+
+impl Replacer for Vec<u8> {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        caps.expand(self, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        no_expansion(self)
+    }
+}
+
+impl<'a> Replacer for Cow<'a, [u8]> {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        caps.expand(self, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        no_expansion(self)
+    }
+}
+
+impl<'a> Replacer for &'a Cow<'a, [u8]> {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        caps.expand(self, dst);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        no_expansion(self)
+    }
+}
+
+fn no_expansion<T: AsRef<[u8]>>(t: &T) -> Option<Cow<'_, [u8]>> {
+    let s = t.as_ref();
+    if s.contains(&b'$') {
+        None
+    } else {
+        Some(Cow::Borrowed(s))
+    }
+}
+
+impl<F, T> Replacer for F
+where
+    F: FnMut(&Captures<'_>) -> T,
+    T: AsRef<[u8]>,
+{
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut Vec<u8>) {
+        dst.extend_from_slice((*self)(caps).as_ref());
+    }
+}
+
+/// `NoExpand` indicates literal byte string replacement.
+///
+/// It can be used with `replace` and `replace_all` to do a literal replacement
+/// without expanding `$name` to their corresponding capture groups.
+///
+/// `'t` is the lifetime of the literal text.
+#[derive(Clone, Debug)]
+pub struct NoExpand<'t>(pub &'t [u8]);
+
+impl<'t> Replacer for NoExpand<'t> {
+    fn replace_append(&mut self, _: &Captures<'_>, dst: &mut Vec<u8>) {
+        dst.extend_from_slice(self.0);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, [u8]>> {
+        Some(Cow::Borrowed(self.0))
+    }
+}
@@ -0,0 +1,53 @@
+use alloc::boxed::Box;
+use regex_automata::meta::Cache as RaCache;
+
+use crate::vm::Scratch;
+
+/// Memory for searching with a regex that is reused between searches, created by
+/// [`Regex::create_cache`](crate::Regex::create_cache).
+///
+/// Searching with a cache, e.g. with [`find_with_cache`](crate::Regex::find_with_cache),
+/// keeps the backtracking stack and capture slots of the search in the cache, so that a search
+/// doesn't allocate once the cache is big enough. A cache can only be used by one search at a
+/// time, so to search from multiple threads, give each thread its own cache, e.g. in a
+/// thread-local.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::Regex;
+/// let re = Regex::new(r"(\w)\1").unwrap();
+/// let mut cache = re.create_cache();
+/// let found: Vec<_> = ["xaa", "ab", "bb"]
+///     .iter()
+///     .map(|text| re.is_match_with_cache(&mut cache, text).unwrap())
+///     .collect();
+///
+/// assert_eq!(found, vec![true, false, true]);
+/// ```
+#[derive(Debug)]
+pub struct Cache(pub(crate) CacheImpl);
+
+#[derive(Debug)]
+pub(crate) enum CacheImpl {
+    Wrap(Box<RaCache>),
+    Fancy(Box<Scratch>),
+}
+
+impl Cache {
+    /// Returns the cache of the regex crate, panicking if the cache is for the VM.
+    pub(crate) fn wrap(&mut self) -> &mut RaCache {
+        match &mut self.0 {
+            CacheImpl::Wrap(cache) => cache,
+            CacheImpl::Fancy(_) => panic!("cache was created by a different regex"),
+        }
+    }
+
+    /// Returns the memory for running the VM, panicking if the cache is for the regex crate.
+    pub(crate) fn scratch(&mut self) -> &mut Scratch {
+        match &mut self.0 {
+            CacheImpl::Fancy(scratch) => scratch,
+            CacheImpl::Wrap(_) => panic!("cache was created by a different regex"),
+        }
+    }
+}
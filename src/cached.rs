@@ -0,0 +1,140 @@
+use alloc::sync::Arc;
+use regex_automata::util::lazy::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::{Regex, RegexBuilder, RegexOptions, Result};
+
+/// The number of regexes that the process-wide cache of [`Regex::cached`] keeps by default.
+const DEFAULT_CAPACITY: usize = 512;
+
+static CACHED: Lazy<Mutex<Cached>> = Lazy::new(|| Mutex::new(Cached::new()));
+
+/// The regexes compiled by [`Regex::cached`] and [`RegexBuilder::build_cached`], keyed on
+/// their pattern and options. When it's full, the least recently used regex is dropped.
+struct Cached {
+    regexes: HashMap<RegexOptions, Entry>,
+    capacity: usize,
+    /// Incremented with each use of a regex, to know which one was used least recently
+    clock: u64,
+}
+
+struct Entry {
+    regex: Arc<Regex>,
+    last_used: u64,
+}
+
+impl Cached {
+    fn new() -> Cached {
+        Cached {
+            regexes: HashMap::new(),
+            capacity: DEFAULT_CAPACITY,
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, options: &RegexOptions) -> Option<Arc<Regex>> {
+        self.clock += 1;
+        let entry = self.regexes.get_mut(options)?;
+        entry.last_used = self.clock;
+        Some(entry.regex.clone())
+    }
+
+    /// Adds a regex, unless one with the same options was added in the meantime, and returns
+    /// the regex that is in the cache.
+    fn insert(&mut self, options: &RegexOptions, regex: Arc<Regex>) -> Arc<Regex> {
+        if let Some(regex) = self.get(options) {
+            return regex;
+        }
+        self.shrink_to(self.capacity.saturating_sub(1));
+        if self.capacity > 0 {
+            let entry = Entry {
+                regex: regex.clone(),
+                last_used: self.clock,
+            };
+            self.regexes.insert(options.clone(), entry);
+        }
+        regex
+    }
+
+    /// Drops the least recently used regexes until at most `len` are left.
+    fn shrink_to(&mut self, len: usize) {
+        while self.regexes.len() > len {
+            let oldest = self
+                .regexes
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(options, _)| options.clone());
+            if let Some(options) = oldest {
+                self.regexes.remove(&options);
+            }
+        }
+    }
+}
+
+/// Returns the cache, which stays usable when a thread panicked while holding the lock, as
+/// the cache is only changed after the regex was compiled.
+fn cached() -> std::sync::MutexGuard<'static, Cached> {
+    CACHED.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+impl Regex {
+    /// Returns the regex for `pattern` from a process-wide cache, compiling it and adding it to
+    /// the cache if it's not there yet.
+    ///
+    /// This is for patterns that are only known at runtime, such as those of a configuration,
+    /// which would be compiled again and again otherwise. The cache keeps the 512 regexes that
+    /// were used most recently by default, see [`set_cached_capacity`](Regex::set_cached_capacity).
+    /// Patterns that fail to compile aren't cached. To cache a regex with options, use
+    /// [`RegexBuilder::build_cached`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::sync::Arc;
+    ///
+    /// let re = Regex::cached(r"(\w)\1").unwrap();
+    /// assert!(re.is_match("xaa").unwrap());
+    /// assert!(Arc::ptr_eq(&re, &Regex::cached(r"(\w)\1").unwrap()));
+    /// ```
+    pub fn cached(pattern: &str) -> Result<Arc<Regex>> {
+        RegexBuilder::new(pattern).build_cached()
+    }
+
+    /// Sets how many regexes the process-wide cache of [`cached`](Regex::cached) keeps,
+    /// dropping the least recently used ones if there are more. A capacity of 0 turns the cache
+    /// off.
+    pub fn set_cached_capacity(capacity: usize) {
+        let mut cached = cached();
+        cached.capacity = capacity;
+        cached.shrink_to(capacity);
+    }
+}
+
+impl RegexBuilder {
+    /// Returns the regex with the pattern and options of the builder from the process-wide
+    /// cache of [`Regex::cached`], building it and adding it to the cache if it's not there yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Regex, RegexBuilder};
+    /// use std::sync::Arc;
+    ///
+    /// let re = RegexBuilder::new(r"(\w)\1")
+    ///     .backtrack_limit(1_000)
+    ///     .build_cached()
+    ///     .unwrap();
+    /// assert!(re.is_match("xaa").unwrap());
+    /// assert!(!Arc::ptr_eq(&re, &Regex::cached(r"(\w)\1").unwrap()));
+    /// ```
+    pub fn build_cached(&self) -> Result<Arc<Regex>> {
+        if let Some(regex) = cached().get(&self.0) {
+            return Ok(regex);
+        }
+        // compile without holding the lock, so that other threads can use the cache meanwhile
+        let regex = Arc::new(self.build()?);
+        Ok(cached().insert(&self.0, regex))
+    }
+}
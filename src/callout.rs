@@ -0,0 +1,120 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt;
+use core::ops::Range;
+use core::panic::{RefUnwindSafe, UnwindSafe};
+
+/// The identifier of a callout in a pattern, e.g. `(?C1)` or `(?C"tag")`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CalloutId {
+    /// A numbered callout, e.g. `(?C1)`, or `(?C)` for number 0
+    Number(usize),
+    /// A callout with a string, e.g. `(?C"tag")`
+    String(String),
+}
+
+/// What the match does after a callout, as returned by the function registered with
+/// [`RegexBuilder::callout`](crate::RegexBuilder::callout).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CalloutResult {
+    /// Continue matching after the callout
+    Continue,
+    /// Fail at the callout, so that the match backtracks and tries other alternatives
+    Fail,
+    /// Stop the search without a match
+    Abort,
+}
+
+/// The state of a match when it reaches a callout, which is passed to the function registered
+/// with [`RegexBuilder::callout`](crate::RegexBuilder::callout).
+#[derive(Clone, Copy, Debug)]
+pub struct Callout<'a> {
+    id: &'a CalloutId,
+    text: &'a [u8],
+    position: usize,
+    saves: &'a [usize],
+}
+
+impl<'a> Callout<'a> {
+    pub(crate) fn new(
+        id: &'a CalloutId,
+        text: &'a [u8],
+        position: usize,
+        saves: &'a [usize],
+    ) -> Callout<'a> {
+        Callout {
+            id,
+            text,
+            position,
+            saves,
+        }
+    }
+
+    /// Returns the identifier of the callout in the pattern.
+    pub fn id(&self) -> &'a CalloutId {
+        self.id
+    }
+
+    /// Returns the text that is searched, as bytes.
+    pub fn text(&self) -> &'a [u8] {
+        self.text
+    }
+
+    /// Returns the position in the text where the callout is reached.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the position in the text where the current match starts.
+    pub fn start(&self) -> usize {
+        self.saves[0]
+    }
+
+    /// Returns the range of the last capture of the group `i` so far, if there is one. Group 0
+    /// isn't captured until the match ends.
+    pub fn group(&self, i: usize) -> Option<Range<usize>> {
+        let start = *self.saves.get(i * 2)?;
+        let end = *self.saves.get(i * 2 + 1)?;
+        (start != usize::MAX && end != usize::MAX && start <= end).then_some(start..end)
+    }
+}
+
+/// A function that is called at callouts.
+#[derive(Clone)]
+pub(crate) struct CalloutFn(
+    pub(crate) Arc<dyn Fn(&Callout<'_>) -> CalloutResult + Send + Sync + UnwindSafe + RefUnwindSafe>,
+);
+
+impl fmt::Debug for CalloutFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CalloutFn")
+    }
+}
+
+impl PartialEq for CalloutFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// A function that is called at named predicates, e.g. `(?{name})`.
+#[derive(Clone)]
+pub(crate) struct PredicateFn(
+    pub(crate) Arc<dyn Fn(&Callout<'_>) -> bool + Send + Sync + UnwindSafe + RefUnwindSafe>,
+);
+
+impl fmt::Debug for PredicateFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PredicateFn")
+    }
+}
+
+impl PartialEq for PredicateFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// The predicates that are registered with a regex, by name.
+pub(crate) type Predicates = BTreeMap<String, PredicateFn>;
@@ -20,24 +20,36 @@
 
 //! Compilation of regexes to VM.
 
-use alloc::string::String;
+use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
+use bit_set::BitSet;
+use core::mem;
 use core::usize;
 use regex_automata::meta::Regex as RaRegex;
 use regex_automata::meta::{Builder as RaBuilder, Config as RaConfig};
+use regex_automata::MatchKind;
 #[cfg(all(test, feature = "std"))]
 use std::{collections::BTreeMap, sync::RwLock};
 
 use crate::analyze::Info;
+use crate::fuzzy::EditKind;
+use crate::onepass::delegate_first_bytes;
+use crate::parse::ExprTree;
 use crate::vm::{Insn, Prog};
 use crate::LookAround::*;
-use crate::{CompileError, Error, Expr, LookAround, RegexOptions, Result};
+use crate::{BacktrackingVerb, CalloutId, CompileError, Error, Expr, ExprArena, LookAround};
+use crate::{EmptyMatchAdvance, RegexOptions, Result};
 
 // I'm thinking it probably doesn't make a lot of sense having this split
 // out from Compiler.
 struct VMBuilder {
     prog: Vec<Insn>,
     n_saves: usize,
+    /// The memory used by the instructions so far, in bytes
+    size: usize,
+    /// The limit for the memory of the program, see `RegexBuilder::size_limit`
+    size_limit: Option<usize>,
 }
 
 impl VMBuilder {
@@ -45,6 +57,8 @@ impl VMBuilder {
         VMBuilder {
             prog: Vec::new(),
             n_saves: max_group * 2,
+            size: 0,
+            size_limit: None,
         }
     }
 
@@ -64,9 +78,21 @@ impl VMBuilder {
 
     // would "emit" be a better name?
     fn add(&mut self, insn: Insn) {
+        self.size += mem::size_of::<Insn>() + insn.memory_usage();
         self.prog.push(insn);
     }
 
+    /// Returns an error if the instructions so far use more memory than the size limit, so that
+    /// compiling a huge program stops early.
+    fn check_size_limit(&self) -> Result<()> {
+        match self.size_limit {
+            Some(limit) if self.size > limit => {
+                Err(Error::CompileError(CompileError::CompiledTooBig(limit)))
+            }
+            _ => Ok(()),
+        }
+    }
+
     fn set_jmp_target(&mut self, jmp_pc: usize, target: usize) {
         match self.prog[jmp_pc] {
             Insn::Jmp(ref mut next) => *next = target,
@@ -91,28 +117,164 @@ impl VMBuilder {
             _ => panic!("mutating instruction other than Repeat"),
         }
     }
+
+    fn set_call_target(&mut self, call_pc: usize, target: usize, end_group: usize) {
+        match self.prog[call_pc] {
+            Insn::Call {
+                target: ref mut t,
+                end_group: ref mut e,
+                ..
+            } => {
+                *t = target;
+                *e = end_group;
+            }
+            _ => panic!("mutating instruction other than Call"),
+        }
+    }
+
+    fn set_then_target(&mut self, then_pc: usize, target: usize) {
+        match self.prog[then_pc] {
+            Insn::Then(ref mut next) => *next = target,
+            _ => panic!("mutating instruction other than Then"),
+        }
+    }
+}
+
+/// The slots of a fuzzy group, see the fuzzy instructions of the VM.
+#[derive(Clone, Copy)]
+struct FuzzySlots {
+    cost: usize,
+    limit: usize,
+    start: usize,
+}
+
+/// A group or look-around that encloses the instructions being compiled.
+enum Scope {
+    Group(usize),
+    /// A look-around, with the jumps from `(*ACCEPT)` to its end
+    LookAround(Vec<usize>),
 }
 
-struct Compiler {
+struct Compiler<'a> {
+    /// The expressions that the analyzed expressions refer to their children in
+    arena: &'a ExprArena,
     b: VMBuilder,
     options: RegexOptions,
+    /// For each group that is called as a subroutine, the slot for the position of its
+    /// innermost call, or `usize::MAX` for other groups
+    call_ix_slots: Vec<usize>,
+    /// The slot for the innermost call frame, if there are subroutine calls
+    frame_slot: usize,
+    /// The instruction that starts each group
+    group_pcs: Vec<usize>,
+    /// The group after the last group inside each group
+    group_end_groups: Vec<usize>,
+    /// The instructions of subroutine calls with their groups, to set their targets at the end
+    calls: Vec<(usize, usize)>,
+    /// For each group whose captures are removed by balancing groups, the slot for the position
+    /// of its last capture in the capture history, or `usize::MAX` for other groups
+    capture_heads: Vec<usize>,
+    /// The slot for the size of the capture history, if there are balancing groups
+    history_slot: usize,
+    /// The groups and look-arounds that enclose the current instruction, innermost last
+    scopes: Vec<Scope>,
+    /// Whether the regex uses `(*THEN)`, so alternations need a branch to fail them
+    has_then: bool,
+    /// For each enclosing alternation, the `(*THEN)` instructions of its current alternative
+    thens: Vec<Vec<usize>>,
+    /// The slots of the innermost fuzzy group that encloses the current instruction, if any
+    fuzzy: Option<FuzzySlots>,
+    /// The slot for the size of the edit log, if there are fuzzy groups
+    edits_slot: usize,
+    /// Whether the program is for partial matching, so that case-insensitive literals are
+    /// matched one character at a time
+    partial: bool,
 }
 
-impl Compiler {
-    fn new(max_group: usize) -> Compiler {
+impl<'a> Compiler<'a> {
+    fn new(max_group: usize, arena: &'a ExprArena) -> Compiler<'a> {
         Compiler {
+            arena,
             b: VMBuilder::new(max_group),
             options: Default::default(),
+            call_ix_slots: vec![usize::MAX; max_group],
+            frame_slot: usize::MAX,
+            group_pcs: vec![usize::MAX; max_group],
+            group_end_groups: vec![usize::MAX; max_group],
+            calls: Vec::new(),
+            capture_heads: vec![usize::MAX; max_group],
+            history_slot: usize::MAX,
+            scopes: Vec::new(),
+            has_then: false,
+            thens: Vec::new(),
+            fuzzy: None,
+            edits_slot: usize::MAX,
+            partial: false,
         }
     }
 
+    fn add_subroutine_calls(&mut self, groups: &BitSet) {
+        for group in groups {
+            self.call_ix_slots[group] = self.b.newsave();
+        }
+        if !groups.is_empty() {
+            self.frame_slot = self.b.newsave();
+        }
+    }
+
+    fn add_balanced_groups(&mut self, groups: &BitSet) {
+        for group in groups {
+            self.capture_heads[group] = self.b.newsave();
+        }
+        if !groups.is_empty() {
+            self.history_slot = self.b.newsave();
+        }
+    }
+
+    fn set_call_targets(&mut self) -> Result<()> {
+        for &(call_pc, group) in &self.calls {
+            let target = self.group_pcs[group];
+            if target == usize::MAX {
+                return Err(Error::CompileError(CompileError::InvalidSubroutineCall));
+            }
+            self.b
+                .set_call_target(call_pc, target, self.group_end_groups[group]);
+        }
+        Ok(())
+    }
+
     fn visit(&mut self, info: &Info<'_>, hard: bool) -> Result<()> {
+        self.b.check_size_limit()?;
         if !hard && !info.hard {
             // easy case, delegate entire subexpr
             return self.compile_delegate(info);
         }
         match *info.expr {
             Expr::Empty => (),
+            Expr::Literal { ref val, casei } if self.fuzzy.is_some() || (self.partial && casei) => {
+                // each character can be edited, or be missing at the end of the text, on its own
+                for c in val.chars() {
+                    self.compile_fuzzy_char(|compiler| {
+                        if !casei {
+                            compiler.b.add(Insn::Lit(c.to_string()));
+                            return Ok(());
+                        }
+                        let mut re = String::new();
+                        let literal = Expr::Literal {
+                            val: c.to_string(),
+                            casei,
+                        };
+                        literal.to_str(compiler.arena, &mut re, 1);
+                        compiler.b.add(Insn::Delegate {
+                            inner: compile_inner(&re, &compiler.options)?,
+                            start_group: 0,
+                            end_group: 0,
+                            first_bytes: delegate_first_bytes(&re, &compiler.options),
+                        });
+                        Ok(())
+                    })?;
+                }
+            }
             Expr::Literal { ref val, casei } => {
                 if !casei {
                     self.b.add(Insn::Lit(val.clone()));
@@ -121,23 +283,52 @@ impl Compiler {
                 }
             }
             Expr::Any { newline: true } => {
-                self.b.add(Insn::Any);
+                self.compile_fuzzy_char(|compiler| {
+                    compiler.b.add(Insn::Any);
+                    Ok(())
+                })?;
             }
             Expr::Any { newline: false } => {
-                self.b.add(Insn::AnyNoNL);
+                self.compile_fuzzy_char(|compiler| {
+                    compiler.b.add(Insn::AnyNoNL);
+                    Ok(())
+                })?;
             }
             Expr::Concat(_) => {
                 self.compile_concat(info, hard)?;
             }
-            Expr::Alt(_) => {
+            Expr::Alt(_) | Expr::BranchReset(_) => {
                 let count = info.children.len();
                 self.compile_alt(count, |compiler, i| compiler.visit(&info.children[i], hard))?;
             }
             Expr::Group(_) => {
                 let group = info.start_group;
+                // in a branch reset group, a subroutine call calls the first group of the number
+                if self.group_pcs[group] == usize::MAX {
+                    self.group_pcs[group] = self.b.pc();
+                    self.group_end_groups[group] = info.end_group;
+                }
                 self.b.add(Insn::Save(group * 2));
+                self.scopes.push(Scope::Group(group));
                 self.visit(&info.children[0], hard)?;
-                self.b.add(Insn::Save(group * 2 + 1));
+                self.scopes.pop();
+                self.compile_group_end(group);
+            }
+            Expr::BalancingGroup {
+                capture, balance, ..
+            } => {
+                let start = self.b.newsave();
+                self.b.add(Insn::Save(start));
+                self.visit(&info.children[0], hard)?;
+                self.b.add(Insn::Balance {
+                    group: capture.unwrap_or(usize::MAX),
+                    balance,
+                    head: self.capture_heads[balance],
+                    start,
+                });
+                if let Some(group) = capture {
+                    self.compile_push_capture(group);
+                }
             }
             Expr::Repeat { lo, hi, greedy, .. } => {
                 self.compile_repeat(info, lo, hi, greedy, hard)?;
@@ -145,8 +336,11 @@ impl Compiler {
             Expr::LookAround(_, la) => {
                 self.compile_lookaround(info, la)?;
             }
-            Expr::Backref(group) => {
-                self.b.add(Insn::Backref(group * 2));
+            Expr::Backref { group, casei } => {
+                self.b.add(Insn::Backref {
+                    slot: group * 2,
+                    casei,
+                });
             }
             Expr::BackrefExistsCondition(group) => {
                 self.b.add(Insn::BackrefExistsCondition(group));
@@ -158,6 +352,9 @@ impl Compiler {
                 self.visit(&info.children[0], false)?;
                 self.b.add(Insn::EndAtomic);
             }
+            Expr::Delegate { size: 1, .. } => {
+                self.compile_fuzzy_char(|compiler| compiler.compile_delegate(info))?;
+            }
             Expr::Delegate { .. } => {
                 // TODO: might want to have more specialized impls
                 self.compile_delegate(info)?;
@@ -174,16 +371,189 @@ impl Compiler {
             Expr::Conditional { .. } => {
                 self.compile_conditional(|compiler, i| compiler.visit(&info.children[i], hard))?;
             }
+            Expr::SubroutineCall(group) => {
+                self.calls.push((self.b.pc(), group));
+                self.b.add(Insn::Call {
+                    target: usize::MAX,
+                    group,
+                    end_group: usize::MAX,
+                    frame: self.frame_slot,
+                    call_ix: self.call_ix_slots[group],
+                });
+            }
+            Expr::BacktrackingVerb(verb) => {
+                self.compile_backtracking_verb(verb);
+            }
+            Expr::Absent(_) => {
+                self.compile_absent(info)?;
+            }
+            Expr::GraphemeCluster => {
+                self.b.add(Insn::GraphemeCluster);
+            }
+            Expr::ScriptRun(_) => {
+                let start = self.b.newsave();
+                self.b.add(Insn::Save(start));
+                self.visit(&info.children[0], hard)?;
+                self.b.add(Insn::ScriptRun(start));
+            }
+            Expr::Callout(ref id) => {
+                self.b.add(Insn::Callout {
+                    id: id.clone(),
+                    groups: self.group_pcs.len(),
+                });
+            }
+            Expr::Predicate(ref name) => {
+                self.b.add(Insn::Predicate {
+                    id: CalloutId::String(name.clone()),
+                    groups: self.group_pcs.len(),
+                });
+            }
+            Expr::Fuzzy { max_cost, .. } => {
+                self.compile_fuzzy(info, max_cost)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_fuzzy(&mut self, info: &Info<'_>, max_cost: usize) -> Result<()> {
+        let slots = FuzzySlots {
+            cost: self.b.newsave(),
+            limit: self.b.newsave(),
+            start: self.b.newsave(),
+        };
+        if self.edits_slot == usize::MAX {
+            self.edits_slot = self.b.newsave();
+        }
+        self.b.add(Insn::BeginFuzzy {
+            limit: slots.limit,
+            start: slots.start,
+        });
+        // the group is matched with no edits first, then with one more edit each time it's
+        // backtracked into
+        self.b.add(Insn::RetryFuzzy {
+            max_cost,
+            cost: slots.cost,
+            limit: slots.limit,
+        });
+        let outer = self.fuzzy.replace(slots);
+        self.visit(&info.children[0], true)?;
+        self.fuzzy = outer;
+        self.b.add(Insn::EndFuzzy {
+            cost: slots.cost,
+            limit: slots.limit,
+        });
+        Ok(())
+    }
+
+    /// Compiles a single character with `add_char`, which adds one instruction, preceded by the
+    /// instructions for its edits if it's in a fuzzy group.
+    fn compile_fuzzy_char<F>(&mut self, add_char: F) -> Result<()>
+    where
+        F: FnOnce(&mut Compiler<'a>) -> Result<()>,
+    {
+        let Some(FuzzySlots { cost, limit, start }) = self.fuzzy else {
+            return add_char(self);
+        };
+        let pc = self.b.pc();
+        let top = self.edits_slot;
+        self.b.add(Insn::FuzzyEdit { cost, limit, start });
+        for (kind, next) in [
+            (EditKind::Substitution, pc + 5),
+            (EditKind::Deletion, pc + 5),
+            (EditKind::Insertion, pc),
+        ] {
+            self.b.add(Insn::Edit {
+                kind,
+                cost,
+                top,
+                next,
+            });
         }
+        add_char(self)?;
+        debug_assert_eq!(self.b.pc(), pc + 5);
         Ok(())
     }
 
+    fn compile_group_end(&mut self, group: usize) {
+        self.b.add(Insn::Save(group * 2 + 1));
+        self.compile_push_capture(group);
+        if self.call_ix_slots[group] != usize::MAX {
+            self.b.add(Insn::Return {
+                group,
+                frame: self.frame_slot,
+            });
+        }
+    }
+
+    fn compile_push_capture(&mut self, group: usize) {
+        if self.capture_heads[group] != usize::MAX {
+            self.b.add(Insn::PushCapture {
+                group,
+                head: self.capture_heads[group],
+                top: self.history_slot,
+            });
+        }
+    }
+
+    fn compile_backtracking_verb(&mut self, verb: BacktrackingVerb) {
+        let insn = match verb {
+            BacktrackingVerb::Fail => {
+                self.b.add(Insn::Fail);
+                return;
+            }
+            BacktrackingVerb::Accept => {
+                self.compile_accept();
+                return;
+            }
+            BacktrackingVerb::Prune => Insn::Prune,
+            BacktrackingVerb::Skip => Insn::Skip,
+            BacktrackingVerb::Then => match self.thens.last_mut() {
+                Some(thens) => {
+                    thens.push(self.b.pc() + 1);
+                    Insn::Then(usize::MAX)
+                }
+                // outside of an alternation, (*THEN) acts like (*PRUNE)
+                None => Insn::Prune,
+            },
+        };
+        // the other verbs act when they are backtracked into, so push a branch for them
+        let pc = self.b.pc();
+        self.b.add(Insn::Split(pc + 2, pc + 1));
+        self.b.add(insn);
+    }
+
+    fn compile_accept(&mut self) {
+        // close the enclosing groups, up to the innermost look-around or the end of the match
+        for i in (0..self.scopes.len()).rev() {
+            let pc = self.b.pc();
+            match self.scopes[i] {
+                Scope::Group(group) => self.compile_group_end(group),
+                Scope::LookAround(ref mut accepts) => {
+                    accepts.push(pc);
+                    self.b.add(Insn::Jmp(usize::MAX));
+                    return;
+                }
+            }
+        }
+        self.b.add(Insn::End);
+    }
+
     fn compile_alt<F>(&mut self, count: usize, mut handle_alternative: F) -> Result<()>
     where
-        F: FnMut(&mut Compiler, usize) -> Result<()>,
+        F: FnMut(&mut Compiler<'a>, usize) -> Result<()>,
     {
         let mut jmps = Vec::new();
         let mut last_pc = usize::MAX;
+        // (*THEN) in the last alternative fails the alternation, by taking this branch
+        let fail_pc = if self.has_then {
+            let pc = self.b.pc();
+            self.b.add(Insn::Split(pc + 2, pc + 1));
+            self.b.add(Insn::Fail);
+            pc + 1
+        } else {
+            usize::MAX
+        };
+        let mut thens = Vec::new();
         for i in 0..count {
             let has_next = i != count - 1;
             let pc = self.b.pc();
@@ -193,9 +563,14 @@ impl Compiler {
             if last_pc != usize::MAX {
                 self.b.set_split_target(last_pc, pc, true);
             }
+            for then_pc in thens.drain(..) {
+                self.b.set_then_target(then_pc, pc);
+            }
             last_pc = pc;
 
+            self.thens.push(Vec::new());
             handle_alternative(self, i)?;
+            thens = self.thens.pop().unwrap();
 
             if has_next {
                 // All except the last branch need to jump over instructions of
@@ -206,6 +581,9 @@ impl Compiler {
                 self.b.add(Insn::Jmp(0));
             }
         }
+        for then_pc in thens {
+            self.b.set_then_target(then_pc, fail_pc);
+        }
         let next_pc = self.b.pc();
         for jmp_pc in jmps {
             self.b.set_jmp_target(jmp_pc, next_pc);
@@ -215,7 +593,7 @@ impl Compiler {
 
     fn compile_conditional<F>(&mut self, mut handle_child: F) -> Result<()>
     where
-        F: FnMut(&mut Compiler, usize) -> Result<()>,
+        F: FnMut(&mut Compiler<'a>, usize) -> Result<()>,
     {
         // here we use atomic group functionality to be able to remove the program counter
         // relating to the split instruction's second position if the conditional succeeds
@@ -435,12 +813,48 @@ impl Compiler {
 
     fn compile_lookaround_inner(&mut self, inner: &Info<'_>, la: LookAround) -> Result<()> {
         if la == LookBehind || la == LookBehindNeg {
-            if !inner.const_size {
+            if inner.const_size {
+                self.b.add(Insn::GoBack(inner.min_size));
+            } else if inner.max_len == Some(inner.min_len) {
+                // e.g. bytes matched with Unicode disabled
+                self.b.add(Insn::GoBackBytes(inner.min_len));
+            } else {
                 return Err(Error::CompileError(CompileError::LookBehindNotConst));
             }
-            self.b.add(Insn::GoBack(inner.min_size));
         }
-        self.visit(inner, false)
+        self.scopes.push(Scope::LookAround(Vec::new()));
+        // look-arounds are matched exactly in fuzzy groups
+        let fuzzy = self.fuzzy.take();
+        self.visit(inner, false)?;
+        self.fuzzy = fuzzy;
+        // (*ACCEPT) jumps to the end of the look-around
+        if let Some(Scope::LookAround(accepts)) = self.scopes.pop() {
+            let end_pc = self.b.pc();
+            for jmp_pc in accepts {
+                self.b.set_jmp_target(jmp_pc, end_pc);
+            }
+        }
+        Ok(())
+    }
+
+    fn compile_absent(&mut self, info: &Info<'_>) -> Result<()> {
+        let inner = &info.children[0];
+        // the next match of the absent expression is searched for by the regex crate
+        if inner.hard {
+            return Err(Error::CompileError(CompileError::AbsentNotSimple));
+        }
+        let mut re = String::new();
+        inner.expr.to_str(self.arena, &mut re, 1);
+        // only the start of the next match matters, which is the same for all match kinds
+        let mut options = self.options.clone();
+        options.leftmost_longest = false;
+        let start = self.b.newsave();
+        self.b.add(Insn::Absent {
+            inner: compile_inner(&re, &options)?,
+            start,
+        });
+        self.b.add(Insn::GiveBack(start));
+        Ok(())
     }
 
     fn compile_delegates(&mut self, infos: &[Info<'_>]) -> Result<()> {
@@ -460,7 +874,7 @@ impl Compiler {
 
         let mut delegate_builder = DelegateBuilder::new();
         for info in infos {
-            delegate_builder.push(info);
+            delegate_builder.push(info, self.arena);
         }
         let delegate = delegate_builder.build(&self.options)?;
 
@@ -474,7 +888,9 @@ impl Compiler {
             info.push_literal(&mut val);
             Insn::Lit(val)
         } else {
-            DelegateBuilder::new().push(info).build(&self.options)?
+            DelegateBuilder::new()
+                .push(info, self.arena)
+                .build(&self.options)?
         };
         self.b.add(insn);
         Ok(())
@@ -490,18 +906,33 @@ static PATTERN_MAPPING: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap:
 
 pub(crate) fn compile_inner(inner_re: &str, options: &RegexOptions) -> Result<RaRegex> {
     let mut config = RaConfig::new();
-    if let Some(size_limit) = options.delegate_size_limit {
+    // a delegate can't be bigger than the whole regex
+    let size_limit = match (options.delegate_size_limit, options.size_limit) {
+        (Some(delegate_limit), Some(limit)) => Some(delegate_limit.min(limit)),
+        (delegate_limit, limit) => delegate_limit.or(limit),
+    };
+    if let Some(size_limit) = size_limit {
         config = config.nfa_size_limit(Some(size_limit));
     }
     if let Some(dfa_size_limit) = options.delegate_dfa_size_limit {
         config = config.dfa_size_limit(Some(dfa_size_limit));
     }
+    if options.leftmost_longest {
+        config = config.match_kind(MatchKind::All);
+    }
+    // byte regexes that advance past empty matches by a byte find them inside UTF-8 sequences
+    if !options.syntaxc.get_utf8() && options.empty_match_advance == EmptyMatchAdvance::Byte {
+        config = config.utf8_empty(false);
+    }
 
     let re = RaBuilder::new()
         .configure(config)
         .syntax(options.syntaxc)
         .build(inner_re)
-        .map_err(CompileError::InnerError)
+        .map_err(|error| match error.size_limit() {
+            Some(limit) if options.size_limit == Some(limit) => CompileError::CompiledTooBig(limit),
+            _ => CompileError::InnerError(error),
+        })
         .map_err(Error::CompileError)?;
 
     #[cfg(all(test, feature = "std"))]
@@ -513,14 +944,59 @@ pub(crate) fn compile_inner(inner_re: &str, options: &RegexOptions) -> Result<Ra
     Ok(re)
 }
 
-/// Compile the analyzed expressions into a program.
-pub fn compile(info: &Info<'_>) -> Result<Prog> {
-    let mut c = Compiler::new(info.end_group);
+/// Compile the analyzed expressions of `tree` into a program.
+pub fn compile(info: &Info<'_>, tree: &ExprTree) -> Result<Prog> {
+    compile_with(info, tree, &RegexOptions::default(), false)
+}
+
+/// Compile the analyzed expressions of `tree` into a program, within the size limits of
+/// `options`.
+pub(crate) fn compile_with_limits(
+    info: &Info<'_>,
+    tree: &ExprTree,
+    options: &RegexOptions,
+) -> Result<Prog> {
+    compile_with(info, tree, options, false)
+}
+
+/// Compile the expressions of `tree`, analyzed for partial matching, into a program.
+pub(crate) fn compile_partial(
+    info: &Info<'_>,
+    tree: &ExprTree,
+    options: &RegexOptions,
+) -> Result<Prog> {
+    compile_with(info, tree, options, true)
+}
+
+fn compile_with(
+    info: &Info<'_>,
+    tree: &ExprTree,
+    options: &RegexOptions,
+    partial: bool,
+) -> Result<Prog> {
+    let mut c = Compiler::new(info.end_group, &tree.arena);
+    // the delegates of the program are compiled with the limits of the options
+    c.options.delegate_size_limit = options.delegate_size_limit;
+    c.options.delegate_dfa_size_limit = options.delegate_dfa_size_limit;
+    c.options.size_limit = options.size_limit;
+    // and can match bytes if the regex matches byte strings
+    c.options.syntaxc = c.options.syntaxc.utf8(options.syntaxc.get_utf8());
+    c.b.size_limit = options.size_limit;
+    c.partial = partial;
+    c.add_subroutine_calls(&tree.subroutine_calls);
+    c.add_balanced_groups(&tree.balanced_groups);
+    c.has_then = uses_then(info);
     c.visit(info, false)?;
     c.b.add(Insn::End);
+    c.set_call_targets()?;
     Ok(c.b.build())
 }
 
+fn uses_then(info: &Info<'_>) -> bool {
+    matches!(info.expr, Expr::BacktrackingVerb(BacktrackingVerb::Then))
+        || info.children.iter().any(uses_then)
+}
+
 struct DelegateBuilder {
     re: String,
     min_size: usize,
@@ -540,7 +1016,7 @@ impl DelegateBuilder {
         }
     }
 
-    fn push(&mut self, info: &Info<'_>) -> &mut DelegateBuilder {
+    fn push(&mut self, info: &Info<'_>, arena: &ExprArena) -> &mut DelegateBuilder {
         // TODO: might want to detect case of a group with no captures
         //  inside, so we can run find() instead of captures()
 
@@ -561,7 +1037,7 @@ impl DelegateBuilder {
         // Even with a single expression, because we add `^` at the
         // beginning, we need a group. Otherwise `["a|b"]` would be turned
         // into `"^a|b"` instead of `"^(?:a|b)"`.
-        info.expr.to_str(&mut self.re, 1);
+        info.expr.to_str(arena, &mut self.re, 1);
         self
     }
 
@@ -575,6 +1051,7 @@ impl DelegateBuilder {
             inner: compiled,
             start_group,
             end_group,
+            first_bytes: delegate_first_bytes(&self.re, options),
         })
     }
 }
@@ -585,33 +1062,35 @@ mod tests {
     use crate::analyze::analyze;
     use crate::parse::ExprTree;
     use crate::vm::Insn::*;
-    use alloc::vec;
     use bit_set::BitSet;
     use matches::assert_matches;
 
     #[test]
     fn jumps_for_alternation() {
-        let tree = ExprTree {
-            expr: Expr::Alt(vec![
-                Expr::Literal {
-                    val: "a".into(),
-                    casei: false,
-                },
-                Expr::Literal {
-                    val: "b".into(),
-                    casei: false,
-                },
-                Expr::Literal {
-                    val: "c".into(),
+        let mut arena = ExprArena::default();
+        let alternatives = ["a", "b", "c"]
+            .iter()
+            .map(|val| {
+                arena.push(Expr::Literal {
+                    val: (*val).into(),
                     casei: false,
-                },
-            ]),
+                })
+            })
+            .collect();
+        let expr = arena.push(Expr::Alt(alternatives));
+        let tree = ExprTree {
+            arena,
+            expr,
             backrefs: BitSet::new(),
+            subroutine_calls: BitSet::new(),
+            balanced_groups: BitSet::new(),
             named_groups: Default::default(),
+            duplicate_groups: Default::default(),
+            predicates: Vec::new(),
         };
         let info = analyze(&tree).unwrap();
 
-        let mut c = Compiler::new(0);
+        let mut c = Compiler::new(0, &tree.arena);
         // Force "hard" so that compiler doesn't just delegate
         c.visit(&info, true).unwrap();
         c.b.add(Insn::End);
@@ -705,7 +1184,7 @@ mod tests {
     fn compile_prog(re: &str) -> Vec<Insn> {
         let tree = Expr::parse_tree(re).unwrap();
         let info = analyze(&tree).unwrap();
-        let prog = compile(&info).unwrap();
+        let prog = compile(&info, &tree).unwrap();
         prog.body
     }
 
@@ -1,5 +1,6 @@
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use core::fmt;
+use core::ops::Range;
 use regex_automata::meta::BuildError as RaBuildError;
 
 /// Result type for this crate with specific error enum.
@@ -17,6 +18,8 @@ pub enum Error {
     CompileError(CompileError),
     /// An error as a result of running a regex
     RuntimeError(RuntimeError),
+    /// An error as a result of expanding a template
+    ExpandError(ExpandError),
 }
 
 /// An error for the result of parsing a regex pattern.
@@ -37,15 +40,20 @@ pub enum ParseError {
     InvalidEscape(String),
     /// Unicode escape not closed
     UnclosedUnicodeName,
+    /// Name of a character, e.g. `\N{LATIN SMALL LETTER A}`, which isn't supported
+    UnicodeNameUnsupported(String),
     /// Invalid hex escape
     InvalidHex,
+    /// Invalid octal escape, e.g. `\o{8}`
+    InvalidOctal,
     /// Invalid codepoint for hex or unicode escape
     InvalidCodepointValue,
     /// Invalid character class
     InvalidClass,
     /// Unknown group flag
     UnknownFlag(String),
-    /// Disabling Unicode not supported
+    /// Disabling Unicode with `(?-u)`, which is only supported for a
+    /// [`bytes::Regex`](crate::bytes::Regex)
     NonUnicodeUnsupported,
     /// Invalid back reference
     InvalidBackref,
@@ -55,6 +63,15 @@ pub enum ParseError {
     InvalidGroupName,
     /// Invalid group id in escape sequence
     InvalidGroupNameBackref(String),
+    /// Unknown backtracking control verb, e.g. `(*FOO)`
+    UnknownBacktrackingVerb(String),
+    /// Invalid callout or predicate, e.g. `(?Cx)` or `(?{})`
+    InvalidCallout,
+    /// Flags that apply to the whole pattern after other parts of it, e.g. `a(?i)` in the Python
+    /// dialect
+    GlobalFlagsNotAtStart,
+    /// Braces with alternatives in a glob without closing brace, e.g. `{a,b`
+    UnclosedGlobAlternatives,
 }
 
 /// An error as the result of compiling a regex.
@@ -73,6 +90,15 @@ pub enum CompileError {
     InvalidBackref,
     /// Once named groups are used you cannot refer to groups by number
     NamedBackrefOnly,
+    /// Subroutine call to a group that doesn't exist
+    InvalidSubroutineCall,
+    /// Absent operator whose expression uses fancy features
+    AbsentNotSimple,
+    /// Call of a predicate that isn't registered with the builder, e.g. `(?{name})`
+    UnknownPredicate(String),
+    /// The compiled regex uses more memory than the size limit in bytes, see
+    /// [`RegexBuilder::size_limit`](crate::RegexBuilder::size_limit)
+    CompiledTooBig(usize),
 }
 
 /// An error as the result of executing a regex.
@@ -85,11 +111,86 @@ pub enum RuntimeError {
     /// Configure using
     /// [`RegexBuilder::backtrack_limit`](struct.RegexBuilder.html#method.backtrack_limit).
     BacktrackLimitExceeded,
+    /// The time limit for a search was exceeded while executing the regex.
+    /// Configure using
+    /// [`RegexBuilder::timeout`](struct.RegexBuilder.html#method.timeout).
+    TimeoutExceeded,
+}
+
+/// An error in a template for [`Expander`](crate::Expander), or from expanding it strictly.
+///
+/// Besides what went wrong, the error records where in the template it happened, so that
+/// user interfaces can point at the offending reference.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpandError {
+    kind: ExpandErrorKind,
+    position: usize,
+    reference: String,
+    snippet: String,
+}
+
+/// The kind of an [`ExpandError`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ExpandErrorKind {
+    /// Reference to a group name that doesn't occur in the regex
+    UnknownGroupName(String),
+    /// Reference to a group number that doesn't exist in the regex
+    UnknownGroupNumber(usize),
+    /// Reference to a group that didn't participate in the match, as written in the template
+    GroupDidNotMatch(String),
+    /// Substitution character that doesn't start a valid reference
+    BadSyntax,
+}
+
+impl ExpandError {
+    /// How many bytes of the template around the reference to include in the snippet
+    const SNIPPET_CONTEXT: usize = 10;
+
+    pub(crate) fn new(kind: ExpandErrorKind, template: &str, span: Range<usize>) -> Self {
+        let mut start = span.start.saturating_sub(Self::SNIPPET_CONTEXT);
+        while !template.is_char_boundary(start) {
+            start -= 1;
+        }
+        let mut end = (span.end + Self::SNIPPET_CONTEXT).min(template.len());
+        while !template.is_char_boundary(end) {
+            end += 1;
+        }
+        ExpandError {
+            kind,
+            position: span.start,
+            reference: template[span].to_string(),
+            snippet: template[start..end].to_string(),
+        }
+    }
+
+    /// Returns what went wrong.
+    pub fn kind(&self) -> &ExpandErrorKind {
+        &self.kind
+    }
+
+    /// Returns the byte offset of the offending reference in the template.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the offending reference as written in the template, e.g. `${name}`.
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// Returns the offending reference together with some of the template around it.
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
 }
 
 #[cfg(feature = "std")]
 impl ::std::error::Error for Error {}
 
+#[cfg(feature = "std")]
+impl ::std::error::Error for ExpandError {}
+
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -102,7 +203,11 @@ impl fmt::Display for ParseError {
             ParseError::TrailingBackslash => write!(f, "Backslash without following character"),
             ParseError::InvalidEscape(s) => write!(f, "Invalid escape: {}", s),
             ParseError::UnclosedUnicodeName => write!(f, "Unicode escape not closed"),
+            ParseError::UnicodeNameUnsupported(s) => {
+                write!(f, "Names of characters not supported: {}", s)
+            }
             ParseError::InvalidHex => write!(f, "Invalid hex escape"),
+            ParseError::InvalidOctal => write!(f, "Invalid octal escape"),
             ParseError::InvalidCodepointValue => {
                 write!(f, "Invalid codepoint for hex or unicode escape")
             }
@@ -115,6 +220,16 @@ impl fmt::Display for ParseError {
                 write!(f, "Invalid group name in back reference: {}", s)
             }
             ParseError::TargetNotRepeatable => write!(f, "Target of repeat operator is invalid"),
+            ParseError::UnknownBacktrackingVerb(s) => {
+                write!(f, "Unknown backtracking control verb: {}", s)
+            }
+            ParseError::InvalidCallout => write!(f, "Invalid callout"),
+            ParseError::GlobalFlagsNotAtStart => {
+                write!(f, "Global flags not at the start of the pattern")
+            }
+            ParseError::UnclosedGlobAlternatives => {
+                write!(f, "Opening brace without closing brace in glob")
+            }
         }
     }
 }
@@ -130,6 +245,10 @@ impl fmt::Display for CompileError {
             CompileError::InvalidGroupNameBackref(s) => write!(f, "Invalid group name in back reference: {}", s),
             CompileError::InvalidBackref => write!(f, "Invalid back reference"),
             CompileError::NamedBackrefOnly => write!(f, "Numbered backref/call not allowed because named group was used, use a named backref instead"),
+            CompileError::InvalidSubroutineCall => write!(f, "Invalid subroutine call"),
+            CompileError::AbsentNotSimple => write!(f, "Absent operator with fancy features in its expression"),
+            CompileError::UnknownPredicate(s) => write!(f, "Unknown predicate: {}", s),
+            CompileError::CompiledTooBig(limit) => write!(f, "Compiled regex exceeds size limit of {} bytes", limit),
         }
     }
 }
@@ -141,10 +260,36 @@ impl fmt::Display for RuntimeError {
             RuntimeError::BacktrackLimitExceeded => {
                 write!(f, "Max limit for backtracking count exceeded")
             }
+            RuntimeError::TimeoutExceeded => write!(f, "Time limit for the search exceeded"),
         }
     }
 }
 
+impl fmt::Display for ExpandErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExpandErrorKind::UnknownGroupName(name) => write!(f, "Unknown group name: {}", name),
+            ExpandErrorKind::UnknownGroupNumber(num) => {
+                write!(f, "Unknown group number: {}", num)
+            }
+            ExpandErrorKind::GroupDidNotMatch(group) => {
+                write!(f, "Group did not match: {}", group)
+            }
+            ExpandErrorKind::BadSyntax => write!(f, "Invalid reference"),
+        }
+    }
+}
+
+impl fmt::Display for ExpandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at position {}: `{}` in `{}`",
+            self.kind, self.position, self.reference, self.snippet
+        )
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -157,6 +302,9 @@ impl fmt::Display for Error {
             Error::RuntimeError(runtime_error) => {
                 write!(f, "Error executing regex: {}", runtime_error)
             }
+            Error::ExpandError(expand_error) => {
+                write!(f, "Error expanding template: {}", expand_error)
+            }
         }
     }
 }
@@ -166,3 +314,9 @@ impl From<CompileError> for Error {
         Error::CompileError(compile_error)
     }
 }
+
+impl From<ExpandError> for Error {
+    fn from(expand_error: ExpandError) -> Self {
+        Error::ExpandError(expand_error)
+    }
+}
@@ -1,13 +1,56 @@
 use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
 use alloc::string::{String, ToString};
+use alloc::sync::Arc;
 use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{Index, Range};
+use core::panic::{RefUnwindSafe, UnwindSafe};
 
+use self::sealed::AnyCaptures;
 use crate::parse::{parse_decimal, parse_id};
-use crate::{Captures, CompileError, Error, ParseError, Regex};
+use crate::{Captures, CompileError, Error, ExpandError, ExpandErrorKind, ParseError, Regex};
 
 /// A set of options for expanding a template string using the contents
 /// of capture groups.
-#[derive(Debug)]
+///
+/// Inside delimiters, `${name:-fallback}` expands to the literal text `fallback` if the group
+/// didn't participate in the match, instead of the empty string.
+///
+/// ```
+/// # use fancy_regex::{Expander, Regex};
+/// let re = Regex::new(r"(?<key>\w+)(?:=(?<value>\w+))?").unwrap();
+/// let caps = re.captures("debug").unwrap().unwrap();
+/// assert_eq!(Expander::default().expansion("$key=${value:-true}", &caps), "debug=true");
+/// ```
+///
+/// Similarly, `${name:+present:absent}` expands to the literal text `present` if the group
+/// participated and `absent` if it didn't. The `:absent` part can be omitted.
+///
+/// ```
+/// # use fancy_regex::{Expander, Regex};
+/// let re = Regex::new(r"(\w+)(!)?").unwrap();
+/// let caps = re.captures("stop!").unwrap().unwrap();
+/// assert_eq!(Expander::default().expansion("$1 ${2:+loudly:quietly}", &caps), "stop loudly");
+/// ```
+///
+/// A width and alignment like in `format!` pads the text of the group: `${name:<10}`,
+/// `${name:^10}` and `${name:>10}` align the text left, center or right in 10 characters,
+/// and `${name:08}` fills with zeros on the left. Without an alignment, text is aligned left.
+/// Widths above 65535 are invalid.
+///
+/// ```
+/// # use fancy_regex::{Expander, Regex};
+/// let re = Regex::new(r"(\w+)=(\d+)").unwrap();
+/// let caps = re.captures("apples=7").unwrap().unwrap();
+/// assert_eq!(Expander::default().expansion("[${1:>8}|${2:03}]", &caps), "[  apples|007]");
+/// ```
+///
+/// Options beyond the public fields, such as case-conversion escapes and filters, are set with
+/// an [`ExpanderBuilder`]. Start from [`Expander::default`] or one of the presets to set the
+/// public fields, rather than a struct literal.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct Expander {
     /// Substitution character. For example, if `sub_char` is `'$'`, replacement groups should be
     /// named $1, $2, etc. Defaults to `'$'`.
@@ -18,6 +61,18 @@ pub struct Expander {
     pub close: &'static str,
     /// Allow group names without delimiters. Defaults to `true`.
     pub allow_undelimited_name: bool,
+    // The options below are set with `ExpanderBuilder`, see the methods with the same names.
+    case_escapes: bool,
+    filters: Filters,
+    special_vars: bool,
+    match_char: Option<char>,
+    max_group_digits: Option<usize>,
+    strict: bool,
+    allow_numbered: bool,
+    case_mapping: CaseMapping,
+    /// Whether numbered references and references in delimiters follow the rules of ECMAScript,
+    /// see `Reference::resolve`
+    ecmascript_references: bool,
 }
 
 impl Default for Expander {
@@ -30,6 +85,15 @@ impl Default for Expander {
             open: "{",
             close: "}",
             allow_undelimited_name: true,
+            case_escapes: false,
+            filters: Filters::default(),
+            special_vars: false,
+            match_char: None,
+            max_group_digits: None,
+            strict: false,
+            allow_numbered: true,
+            case_mapping: CaseMapping::Unicode,
+            ecmascript_references: false,
         }
     }
 }
@@ -62,6 +126,117 @@ impl Expander {
             open: "g<",
             close: ">",
             allow_undelimited_name: false,
+            case_escapes: false,
+            filters: Filters::default(),
+            special_vars: false,
+            match_char: None,
+            max_group_digits: None,
+            strict: false,
+            allow_numbered: true,
+            case_mapping: CaseMapping::Unicode,
+            ecmascript_references: false,
+        }
+    }
+
+    /// Returns an expander that uses .NET-compatible syntax, as in `Regex.Replace`.
+    ///
+    /// Expands `$num` and `${name}` to the corresponding capture group `num` or `name`,
+    /// and recognizes the special variables `$&`, `` $` ``, `$'`, `$+` and `$_` (see
+    /// [`special_vars`](ExpanderBuilder::special_vars)). Unlike the default syntax, group names must be
+    /// delimited by braces.
+    ///
+    /// To write a literal `$`, use `$$`.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, Regex};
+    /// let re = Regex::new(r"(?<first>\w+) (\w+)").unwrap();
+    /// let caps = re.captures("to be, or not").unwrap().unwrap();
+    /// let expander = Expander::dotnet();
+    /// assert_eq!(expander.expansion("$2 ${first}$$", &caps), "be to$");
+    /// assert_eq!(expander.expansion("[$+] in [$_]", &caps), "[be] in [to be, or not]");
+    /// ```
+    pub fn dotnet() -> Expander {
+        Expander {
+            allow_undelimited_name: false,
+            special_vars: true,
+            ..Expander::default()
+        }
+    }
+
+    /// Returns an expander that uses the syntax of the `s` command in GNU sed.
+    ///
+    /// Expands `\1` to `\9` to the corresponding capture group and `&` to the whole match,
+    /// and recognizes the case-conversion escapes `\U`, `\L`, `\u`, `\l` and `\E`. Only a
+    /// single digit is used, e.g. `\10` is group 1 followed by a literal `0`.
+    ///
+    /// To write a literal `\` or `&`, use `\\` or `\&`.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, Regex};
+    /// let re = Regex::new(r"(\w+)@(\w+)").unwrap();
+    /// let caps = re.captures("user@example").unwrap().unwrap();
+    /// let expander = Expander::sed();
+    /// assert_eq!(expander.expansion(r"\U\2\E \& \10: &", &caps), "EXAMPLE & user0: user@example");
+    /// ```
+    pub fn sed() -> Expander {
+        Expander {
+            sub_char: '\\',
+            allow_undelimited_name: false,
+            case_escapes: true,
+            match_char: Some('&'),
+            max_group_digits: Some(1),
+            ..Expander::default()
+        }
+    }
+
+    /// Returns an expander that uses the syntax of `pcre2_substitute` with extended
+    /// replacement processing.
+    ///
+    /// Expands `$num`, `$name`, `${num}` and `${name}`, including the `${name:-default}` and
+    /// `${name:+present:absent}` forms, and recognizes the case-conversion escapes `\U`, `\L`,
+    /// `\u`, `\l` and `\E`.
+    ///
+    /// To write a literal `$` or `\`, use `$$` or `\\`.
+    pub fn pcre2() -> Expander {
+        Expander {
+            case_escapes: true,
+            ..Expander::default()
+        }
+    }
+
+    /// Returns an expander that uses the syntax of JavaScript's `String.prototype.replace`.
+    ///
+    /// Expands `$num` and `$<name>` to the corresponding capture group `num` or `name`, and
+    /// recognizes `$&`, `` $` `` and `$'` (see [`special_vars`](ExpanderBuilder::special_vars)).
+    /// Numbered references use at most two digits, and a two-digit reference to a group that
+    /// doesn't exist refers to the group of its first digit, followed by the second digit: with
+    /// fewer than 10 groups, `$10` is group 1 followed by a literal `0`.
+    ///
+    /// Like in JavaScript, references that don't refer to a group are kept as literal text:
+    /// `$0`, `$00`, numbered references to groups that don't exist, and `$<name>` if the regex
+    /// has no named groups. With named groups, `$<name>` for a name that doesn't exist expands
+    /// to the empty string.
+    ///
+    /// To write a literal `$`, use `$$`.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, Regex};
+    /// let re = Regex::new(r"(?<last>\w+), (\w+)").unwrap();
+    /// let caps = re.captures("Lovelace, Ada").unwrap().unwrap();
+    /// let expander = Expander::javascript();
+    /// assert_eq!(expander.expansion("$2 $<last> ($&)", &caps), "Ada Lovelace (Lovelace, Ada)");
+    /// assert_eq!(expander.expansion("$20", &caps), "Ada0");
+    /// assert_eq!(expander.expansion("$0 $3", &caps), "$0 $3");
+    /// ```
+    pub fn javascript() -> Expander {
+        Expander {
+            open: "<",
+            close: ">",
+            allow_undelimited_name: false,
+            special_vars: true,
+            max_group_digits: Some(2),
+            ecmascript_references: true,
+            ..Expander::default()
         }
     }
 
@@ -84,29 +259,176 @@ impl Expander {
                 Err(Error::CompileError(CompileError::InvalidBackref))
             }
         };
-        self.exec(template, |step| match step {
-            Step::Char(_) => Ok(()),
-            Step::GroupName(name) => {
-                if regex.named_groups.contains_key(name) {
-                    Ok(())
-                } else if let Ok(num) = name.parse() {
+        self.exec(template, |piece| {
+            let reference = match piece {
+                Piece::Literal(_) | Piece::Case(_) => return Ok(()),
+                Piece::Group(reference) => reference,
+                Piece::Error(_) => {
+                    return Err(Error::ParseError(
+                        0,
+                        ParseError::GeneralParseError(
+                            "parse error in template while expanding".to_string(),
+                        ),
+                    ))
+                }
+            };
+            let id = match reference.resolve(regex.captures_len(), !regex.named_groups.is_empty()) {
+                Resolution::Group => reference.id,
+                Resolution::Shortened(shortened) => shortened.id,
+                Resolution::Literal => return Ok(()),
+            };
+            match id {
+                GroupId::Name(name) | GroupId::ExistingName(name) => {
+                    let name = &template[name];
+                    if regex.named_groups.contains_key(name) {
+                        Ok(())
+                    } else if let Ok(num) = name.parse() {
+                        on_group_num(num)
+                    } else {
+                        Err(Error::CompileError(CompileError::InvalidBackref))
+                    }
+                }
+                GroupId::Num(num) | GroupId::ExistingNum(num) | GroupId::TwoDigitNum(num) => {
                     on_group_num(num)
-                } else {
-                    Err(Error::CompileError(CompileError::InvalidBackref))
                 }
+                GroupId::Prefix | GroupId::Suffix | GroupId::Last | GroupId::Input => Ok(()),
             }
-            Step::GroupNum(num) => on_group_num(num),
-            Step::Error => Err(Error::ParseError(
-                0,
-                ParseError::GeneralParseError(
-                    "parse error in template while expanding".to_string(),
-                ),
-            )),
         })
     }
 
+    /// Checks `template` for errors without running a match, so that user-supplied templates
+    /// can be rejected up front. Unlike [`check`](Expander::check), groups may be referred to
+    /// by number even when `regex` has named groups.
+    ///
+    /// ```
+    /// # use fancy_regex::{ExpandErrorKind, Expander, Regex};
+    /// let re = Regex::new(r"(?<year>\d{4})-(?<month>\d{2})").unwrap();
+    /// let expander = Expander::default();
+    /// assert_eq!(expander.check_template("$month/$year ($0)", &re), Ok(()));
+    ///
+    /// let err = expander.check_template("$month/$day", &re).unwrap_err();
+    /// assert_eq!(err.kind(), &ExpandErrorKind::UnknownGroupName("day".to_string()));
+    /// assert_eq!(err.position(), 7);
+    /// assert_eq!(err.reference(), "$day");
+    ///
+    /// let err = expander.check_template("${month", &re).unwrap_err();
+    /// assert_eq!(err.kind(), &ExpandErrorKind::BadSyntax);
+    /// assert_eq!(err.position(), 0);
+    /// ```
+    pub fn check_template(&self, template: &str, regex: &Regex) -> Result<(), ExpandError> {
+        let check_num = |num| {
+            if num < regex.captures_len() {
+                Ok(())
+            } else {
+                Err(ExpandErrorKind::UnknownGroupNumber(num))
+            }
+        };
+        let has_names = !regex.named_groups.is_empty();
+        self.exec(template, |piece| {
+            let (result, span) = match piece {
+                Piece::Literal(_) | Piece::Case(_) => return Ok(()),
+                Piece::Group(reference) => {
+                    let id = match reference.resolve(regex.captures_len(), has_names) {
+                        Resolution::Group => reference.id,
+                        Resolution::Shortened(shortened) => shortened.id,
+                        Resolution::Literal => return Ok(()),
+                    };
+                    let result = match id {
+                        GroupId::Name(name) | GroupId::ExistingName(name) => {
+                            let name = &template[name];
+                            if regex.named_groups.contains_key(name) {
+                                Ok(())
+                            } else if let Ok(num) = name.parse() {
+                                check_num(num)
+                            } else {
+                                Err(ExpandErrorKind::UnknownGroupName(name.to_string()))
+                            }
+                        }
+                        GroupId::Num(num)
+                        | GroupId::ExistingNum(num)
+                        | GroupId::TwoDigitNum(num) => check_num(num),
+                        GroupId::Prefix | GroupId::Suffix | GroupId::Last | GroupId::Input => {
+                            Ok(())
+                        }
+                    };
+                    (result, reference.span)
+                }
+                Piece::Error(span) => (Err(ExpandErrorKind::BadSyntax), span),
+            };
+            result.map_err(|kind| ExpandError::new(kind, template, span))
+        })
+    }
+
+    /// Returns the groups that `template` refers to, in order of first appearance and without
+    /// duplicates. Invalid references are ignored, and so are special variables other than
+    /// `$&`, which refers to group 0.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, GroupRef};
+    /// assert_eq!(
+    ///     Expander::default().template_groups("$year-${2}-$year $$3"),
+    ///     vec![GroupRef::Named("year".to_string()), GroupRef::Numbered(2)],
+    /// );
+    /// ```
+    ///
+    /// With the [`javascript`](Expander::javascript) syntax, `$0` and `$00` are literal text and
+    /// aren't returned. A two-digit reference refers to the group of its first digit if the
+    /// regex doesn't have the two-digit group, so both groups are returned.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, GroupRef};
+    /// assert_eq!(
+    ///     Expander::javascript().template_groups("$0 $20"),
+    ///     vec![GroupRef::Numbered(20), GroupRef::Numbered(2)],
+    /// );
+    /// ```
+    pub fn template_groups(&self, template: &str) -> Vec<GroupRef> {
+        let mut groups = Vec::new();
+        let mut push = |group| {
+            if !groups.contains(&group) {
+                groups.push(group);
+            }
+        };
+        let _ = self.exec(template, |piece| {
+            match piece {
+                Piece::Group(Reference {
+                    id: GroupId::Name(name) | GroupId::ExistingName(name),
+                    ..
+                }) => {
+                    let name = &template[name];
+                    push(match name.parse() {
+                        Ok(num) => GroupRef::Numbered(num),
+                        Err(_) => GroupRef::Named(name.to_string()),
+                    });
+                }
+                Piece::Group(Reference {
+                    id: GroupId::Num(num),
+                    ..
+                }) => push(GroupRef::Numbered(num)),
+                Piece::Group(Reference {
+                    id: GroupId::ExistingNum(num),
+                    ..
+                }) if num != 0 => push(GroupRef::Numbered(num)),
+                Piece::Group(Reference {
+                    id: GroupId::TwoDigitNum(num),
+                    ..
+                }) => {
+                    for num in [num, num / 10] {
+                        if num != 0 {
+                            push(GroupRef::Numbered(num));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            Ok::<_, ()>(())
+        });
+        groups
+    }
+
     /// Escapes the substitution character in `text` so it appears literally
-    /// in the output of `expansion`.
+    /// in the output of `expansion`. If [`case_escapes`](ExpanderBuilder::case_escapes) is enabled,
+    /// backslashes are escaped too, and so is the [`match_char`](ExpanderBuilder::match_char).
     ///
     /// ```
     /// assert_eq!(
@@ -115,14 +437,19 @@ impl Expander {
     /// );
     /// ```
     pub fn escape<'a>(&self, text: &'a str) -> Cow<'a, str> {
-        if text.contains(self.sub_char) {
-            let mut quoted = String::with_capacity(self.sub_char.len_utf8() * 2);
-            quoted.push(self.sub_char);
-            quoted.push(self.sub_char);
-            Cow::Owned(text.replace(self.sub_char, &quoted))
-        } else {
-            Cow::Borrowed(text)
+        if !text.contains(|c| self.is_special(c)) {
+            return Cow::Borrowed(text);
+        }
+        let mut escaped = String::with_capacity(text.len() + 1);
+        for c in text.chars() {
+            if Some(c) == self.match_char {
+                escaped.push(self.sub_char);
+            } else if self.is_special(c) {
+                escaped.push(c);
+            }
+            escaped.push(c);
         }
+        Cow::Owned(escaped)
     }
 
     #[doc(hidden)]
@@ -134,135 +461,1384 @@ impl Expander {
     /// Expands the template string `template` using the syntax defined
     /// by this expander and the values of capture groups from `captures`.
     pub fn expansion(&self, template: &str, captures: &Captures<'_>) -> String {
-        let mut cursor = Vec::with_capacity(template.len());
-        #[cfg(feature = "std")]
-        self.write_expansion(&mut cursor, template, captures)
-            .expect("expansion succeeded");
-        #[cfg(not(feature = "std"))]
-        self.write_expansion_vec(&mut cursor, template, captures)
-            .expect("expansion succeeded");
-        String::from_utf8(cursor).expect("expansion is UTF-8")
+        let mut dst = String::with_capacity(template.len());
+        self.append_expansion(&mut dst, template, captures);
+        dst
     }
 
     /// Appends the expansion produced by `expansion` to `dst`.  Potentially more efficient
     /// than calling `expansion` directly and appending to an existing string.
     pub fn append_expansion(&self, dst: &mut String, template: &str, captures: &Captures<'_>) {
-        let mut cursor = core::mem::take(dst).into_bytes();
-        #[cfg(feature = "std")]
-        self.write_expansion(&mut cursor, template, captures)
+        self.expand_fmt(dst, template, captures)
             .expect("expansion succeeded");
-        #[cfg(not(feature = "std"))]
-        self.write_expansion_vec(&mut cursor, template, captures)
-            .expect("expansion succeeded");
-        *dst = String::from_utf8(cursor).expect("expansion is UTF-8");
     }
 
-    /// Writes the expansion produced by `expansion` to `dst`.  Potentially more efficient
-    /// than calling `expansion` directly and writing the result.
+    /// Writes the expansion produced by `expansion` to `dst`, e.g. a `String` or a
+    /// `fmt::Formatter`. Only fails if writing to `dst` fails.
+    ///
+    /// ```
+    /// # use core::fmt;
+    /// # use fancy_regex::{Captures, Expander, Regex};
+    /// struct Swapped<'a, 't>(&'a Captures<'t>);
+    ///
+    /// impl fmt::Display for Swapped<'_, '_> {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         Expander::default().expand_fmt(f, "$2 $1", self.0)
+    ///     }
+    /// }
+    ///
+    /// let re = Regex::new(r"(\w+) (\w+)").unwrap();
+    /// let caps = re.captures("hello world").unwrap().unwrap();
+    /// assert_eq!(format!("<{}>", Swapped(&caps)), "<world hello>");
+    /// ```
+    pub fn expand_fmt(
+        &self,
+        dst: &mut impl fmt::Write,
+        template: &str,
+        captures: &Captures<'_>,
+    ) -> fmt::Result {
+        self.render(&mut FmtOutput(dst), template, captures)
+    }
+
+    /// Writes the expansion produced by `expansion` to `dst` piece by piece, without building
+    /// the whole expansion in memory first.
     #[cfg(feature = "std")]
     pub fn write_expansion(
         &self,
-        mut dst: impl std::io::Write,
+        dst: impl std::io::Write,
         template: &str,
         captures: &Captures<'_>,
     ) -> std::io::Result<()> {
-        self.exec(template, |step| match step {
-            Step::Char(c) => write!(dst, "{}", c),
-            Step::GroupName(name) => {
-                if let Some(m) = captures.name(name) {
-                    write!(dst, "{}", m.as_str())
-                } else if let Some(m) = name.parse().ok().and_then(|num| captures.get(num)) {
-                    write!(dst, "{}", m.as_str())
-                } else {
-                    Ok(())
-                }
-            }
-            Step::GroupNum(num) => {
-                if let Some(m) = captures.get(num) {
-                    write!(dst, "{}", m.as_str())
-                } else {
-                    Ok(())
-                }
-            }
-            Step::Error => Ok(()),
-        })
+        let mut dst = IoOutput::new(dst);
+        let result = self.render(&mut dst, template, captures);
+        dst.finish(result)
     }
 
-    /// Writes the expansion produced by `expansion` to `dst`.  Potentially more efficient
-    /// than calling `expansion` directly and writing the result.
+    /// Appends the expansion produced by `expansion` to `dst` as bytes, without building a
+    /// string first.
     pub fn write_expansion_vec(
         &self,
         dst: &mut Vec<u8>,
         template: &str,
         captures: &Captures<'_>,
     ) -> core::fmt::Result {
-        self.exec(template, |step| match step {
-            Step::Char(c) => Ok(dst.extend(c.to_string().as_bytes())),
-            Step::GroupName(name) => {
-                if let Some(m) = captures.name(name) {
-                    Ok(dst.extend(m.as_str().as_bytes()))
-                } else if let Some(m) = name.parse().ok().and_then(|num| captures.get(num)) {
-                    Ok(dst.extend(m.as_str().as_bytes()))
-                } else {
-                    Ok(())
-                }
-            }
-            Step::GroupNum(num) => {
-                if let Some(m) = captures.get(num) {
-                    Ok(dst.extend(m.as_str().as_bytes()))
-                } else {
-                    Ok(())
-                }
+        self.render(&mut ByteOutput(dst), template, captures)
+    }
+
+    /// Writes the expansion of `template` with the capture groups of `captures` to `dst`.
+    fn render(
+        &self,
+        dst: &mut impl Output,
+        template: &str,
+        captures: &Captures<'_>,
+    ) -> fmt::Result {
+        let mut render = Render::new(template, &self.filters, self.case_mapping, captures);
+        self.exec(template, |piece| render.piece(dst, &piece))
+    }
+
+    /// Like [`expansion`](Expander::expansion), but fails instead of expanding to the empty
+    /// string if a reference is invalid, names a group that doesn't exist, or names a group
+    /// that didn't participate in the match and has no default value.
+    ///
+    /// ```
+    /// # use fancy_regex::{ExpandErrorKind, Expander, Regex};
+    /// let re = Regex::new(r"(?<key>\w+)(?:=(?<value>\w+))?").unwrap();
+    /// let caps = re.captures("debug").unwrap().unwrap();
+    /// let expander = Expander::default();
+    /// assert_eq!(expander.try_expansion("$key=${value:-1}", &caps), Ok("debug=1".to_string()));
+    ///
+    /// let err = expander.try_expansion("$key=${value}", &caps).unwrap_err();
+    /// assert_eq!(err.kind(), &ExpandErrorKind::GroupDidNotMatch("value".to_string()));
+    /// assert_eq!((err.position(), err.reference()), (5, "${value}"));
+    ///
+    /// let err = expander.try_expansion("$key=$val", &caps).unwrap_err();
+    /// assert_eq!(err.kind(), &ExpandErrorKind::UnknownGroupName("val".to_string()));
+    /// ```
+    pub fn try_expansion(
+        &self,
+        template: &str,
+        captures: &Captures<'_>,
+    ) -> Result<String, ExpandError> {
+        let mut dst = String::with_capacity(template.len());
+        self.append_strict_expansion(&mut dst, template, captures)?;
+        Ok(dst)
+    }
+
+    /// Replaces all non-overlapping matches of `regex` in `haystack` with the expansion of
+    /// `template`. If [`strict`](ExpanderBuilder::strict) is enabled, expansion errors are reported
+    /// like in [`try_expansion`](Expander::try_expansion).
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, Regex};
+    /// let re = Regex::new(r"(?<first>\w+)\s+(?<last>\w+)").unwrap();
+    /// let result = Expander::python()
+    ///     .replace_all(&re, "Ada Lovelace, Alan Turing", r"\g<last> \g<first>")
+    ///     .unwrap();
+    /// assert_eq!(result, "Lovelace Ada, Turing Alan");
+    /// ```
+    pub fn replace_all(
+        &self,
+        regex: &Regex,
+        haystack: &str,
+        template: &str,
+    ) -> crate::Result<String> {
+        let mut dst = String::with_capacity(haystack.len());
+        let mut last_match = 0;
+        for captures in regex.captures_iter(haystack) {
+            let captures = captures?;
+            // unwrap on 0 is OK because captures only reports matches
+            let m = captures.get(0).unwrap();
+            dst.push_str(&haystack[last_match..m.start()]);
+            if self.strict {
+                self.append_strict_expansion(&mut dst, template, &captures)?;
+            } else {
+                self.append_expansion(&mut dst, template, &captures);
             }
-            Step::Error => Ok(()),
+            last_match = m.end();
+        }
+        dst.push_str(&haystack[last_match..]);
+        Ok(dst)
+    }
+
+    fn append_strict_expansion(
+        &self,
+        dst: &mut String,
+        template: &str,
+        captures: &Captures<'_>,
+    ) -> Result<(), ExpandError> {
+        let mut render = Render::new(template, &self.filters, self.case_mapping, captures);
+        self.exec(template, |piece| {
+            let result = match &piece {
+                Piece::Group(reference) => render
+                    .check(reference)
+                    .map_err(|kind| (kind, reference.span.clone())),
+                Piece::Error(span) => Err((ExpandErrorKind::BadSyntax, span.clone())),
+                _ => Ok(()),
+            };
+            result.map_err(|(kind, span)| ExpandError::new(kind, template, span))?;
+            render
+                .piece(&mut FmtOutput(dst), &piece)
+                .expect("expansion succeeded");
+            Ok(())
         })
     }
 
-    fn exec<'t, E>(
+    /// Expands the byte template `template` using the syntax defined by this expander and the
+    /// values of capture groups from `captures`.
+    ///
+    /// Unlike [`expansion`](Expander::expansion), the template doesn't need to be valid UTF-8:
+    /// bytes outside of references are copied to the output unchanged. Case-conversion escapes
+    /// only affect valid UTF-8, and filters and padding see invalid UTF-8 replaced with U+FFFD.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, Regex};
+    /// let re = Regex::new(r"(\w+)=(\w+)").unwrap();
+    /// let caps = re.captures("key=value").unwrap().unwrap();
+    /// let expansion = Expander::default().expansion_bytes(b"$2\xff$1", &caps);
+    /// assert_eq!(expansion, b"value\xffkey");
+    /// ```
+    ///
+    /// The captures can also be those of a [`bytes::Regex`](crate::bytes::Regex):
+    ///
+    /// ```
+    /// # use fancy_regex::{bytes, Expander};
+    /// let re = bytes::Regex::new(r"(\w+)=(\w+)").unwrap();
+    /// let caps = re.captures(b"key=value").unwrap().unwrap();
+    /// let expansion = Expander::python().expansion_bytes(br"\2:\g<1>", &caps);
+    /// assert_eq!(expansion, b"value:key");
+    /// ```
+    pub fn expansion_bytes(&self, template: &[u8], captures: &impl ExpansionCaptures) -> Vec<u8> {
+        let mut dst = Vec::with_capacity(template.len());
+        self.append_expansion_bytes(&mut dst, template, captures);
+        dst
+    }
+
+    /// Appends the expansion produced by `expansion_bytes` to `dst`.
+    pub fn append_expansion_bytes(
+        &self,
+        dst: &mut Vec<u8>,
+        template: &[u8],
+        captures: &impl ExpansionCaptures,
+    ) {
+        self.render_bytes(&mut ByteOutput(dst), template, captures)
+            .expect("expansion succeeded");
+    }
+
+    /// Writes the expansion produced by `expansion_bytes` to `dst` piece by piece, without
+    /// building the whole expansion in memory first.
+    #[cfg(feature = "std")]
+    pub fn write_expansion_bytes(
+        &self,
+        dst: impl std::io::Write,
+        template: &[u8],
+        captures: &impl ExpansionCaptures,
+    ) -> std::io::Result<()> {
+        let mut dst = IoOutput::new(dst);
+        let result = self.render_bytes(&mut dst, template, captures);
+        dst.finish(result)
+    }
+
+    /// Writes the expansion of the byte template `template` with the capture groups of
+    /// `captures` to `dst`.
+    fn render_bytes(
+        &self,
+        dst: &mut impl Output,
+        template: &[u8],
+        captures: &impl ExpansionCaptures,
+    ) -> fmt::Result {
+        match captures.any_captures() {
+            AnyCaptures::Str(captures) => self.render_groups(dst, template, captures),
+            AnyCaptures::Bytes(captures) => self.render_groups(dst, template, captures),
+        }
+    }
+
+    fn render_groups(
+        &self,
+        dst: &mut impl Output,
+        template: &[u8],
+        groups: &impl Groups,
+    ) -> fmt::Result {
+        let skeleton = utf8_skeleton(template);
+        let mut render = Render::new(template, &self.filters, self.case_mapping, groups);
+        self.exec(&skeleton, |piece| render.piece(dst, &piece))
+    }
+
+    /// Parses `template` once so that it can be expanded repeatedly without re-parsing, e.g. in
+    /// a replacement loop.
+    ///
+    /// ```
+    /// # use fancy_regex::{Expander, Regex};
+    /// let re = Regex::new(r"(?<key>\w+)=(?<value>\w+)").unwrap();
+    /// let template = Expander::default().compile("$value=$key");
+    /// let mut dst = String::new();
+    /// for caps in re.captures_iter("a=1 b=2") {
+    ///     template.expand(&caps.unwrap(), &mut dst).unwrap();
+    ///     dst.push(' ');
+    /// }
+    /// assert_eq!(dst, "1=a 2=b ");
+    /// ```
+    pub fn compile(&self, template: &str) -> Template {
+        let mut pieces = Vec::new();
+        let _ = self.exec(template, |piece| {
+            if !matches!(piece, Piece::Error(_)) {
+                pieces.push(piece);
+            }
+            Ok::<_, ()>(())
+        });
+        Template {
+            template: template.to_string(),
+            pieces,
+            filters: self.filters.clone(),
+            case_mapping: self.case_mapping,
+        }
+    }
+
+    /// Returns `template` if it doesn't contain anything to expand, not even escapes.
+    pub(crate) fn no_expansion<'a>(&self, template: &'a str) -> Option<Cow<'a, str>> {
+        if template.contains(|c| self.is_special(c)) {
+            None
+        } else {
+            Some(Cow::Borrowed(template))
+        }
+    }
+
+    /// Whether `c` has a special meaning in templates, so it has to be escaped.
+    fn is_special(&self, c: char) -> bool {
+        c == self.sub_char || (c == '\\' && self.case_escapes) || Some(c) == self.match_char
+    }
+
+    fn case_escape(&self, tail: &str) -> Option<CaseOp> {
+        if self.case_escapes {
+            tail.chars().next().and_then(CaseOp::from_escape)
+        } else {
+            None
+        }
+    }
+
+    fn special_var(&self, tail: &str) -> Option<GroupId> {
+        if !self.special_vars {
+            return None;
+        }
+        match tail.chars().next() {
+            Some('&') => Some(GroupId::Num(0)),
+            Some('`') => Some(GroupId::Prefix),
+            Some('\'') => Some(GroupId::Suffix),
+            Some('+') => Some(GroupId::Last),
+            Some('_') if !self.allow_undelimited_name => Some(GroupId::Input),
+            _ => None,
+        }
+    }
+
+    /// Parses a group reference at the start of `template[start..]`, i.e. after the
+    /// substitution character. Returns the reference and the index just after it.
+    fn parse_reference(&self, template: &str, start: usize) -> Option<(Reference, usize)> {
+        self.parse_delimited_reference(template, start)
+            .or_else(|| {
+                if !self.allow_undelimited_name {
+                    return None;
+                }
+                let (id, skip) = parse_id(&template[start..], "", "", false)?;
+                let end = start + skip;
+                Some((
+                    Reference::new(GroupId::Name(end - id.len()..end), start..end),
+                    end,
+                ))
+            })
+            .or_else(|| {
+                let digits = template[start..]
+                    .bytes()
+                    .take_while(u8::is_ascii_digit)
+                    .count()
+                    .min(self.max_group_digits.unwrap_or(usize::MAX));
+                let (end, num) = parse_decimal(&template[..start + digits], start)?;
+                let id = match end - start {
+                    2 if self.ecmascript_references => GroupId::TwoDigitNum(num),
+                    _ if self.ecmascript_references => GroupId::ExistingNum(num),
+                    _ => GroupId::Num(num),
+                };
+                Some((Reference::new(id, start..end), end))
+            })
+            .filter(|(reference, _)| self.allow_numbered || !reference.is_numbered(template))
+    }
+
+    fn parse_delimited_reference(
         &self,
-        template: &'t str,
-        mut f: impl FnMut(Step<'t>) -> Result<(), E>,
-    ) -> Result<(), E> {
+        template: &str,
+        start: usize,
+    ) -> Option<(Reference, usize)> {
+        let (id, skip) = parse_id(&template[start..], self.open, "", false)?;
+        let id_end = start + skip;
+        let mut end = id_end;
+        while let Some((filter, skip)) = parse_id(&template[end..], "|", "", false) {
+            self.filters.get(filter)?;
+            end += skip;
+        }
+        let filters = id_end..end;
+        let mut modifier = None;
+        if template[end..].starts_with(":-") {
+            let fallback_start = end + 2;
+            end = fallback_start + template[fallback_start..].find(self.close)?;
+            modifier = Some(Modifier::Default(fallback_start..end));
+        } else if template[end..].starts_with(":+") {
+            let present_start = end + 2;
+            let present_end = present_start + template[present_start..].find(self.close)?;
+            let present = &template[present_start..present_end];
+            modifier = Some(match present.find(':') {
+                Some(colon) => Modifier::Conditional {
+                    present: present_start..present_start + colon,
+                    absent: present_start + colon + 1..present_end,
+                },
+                None => Modifier::Conditional {
+                    present: present_start..present_end,
+                    absent: present_end..present_end,
+                },
+            });
+            end = present_end;
+        } else if template[end..].starts_with(':') {
+            let (pad, pad_end) = Pad::parse(template, end + 1)?;
+            modifier = Some(Modifier::Pad(pad));
+            end = pad_end;
+        }
+        if !template[end..].starts_with(self.close) {
+            return None;
+        }
+        let end = end + self.close.len();
+        let name = id_end - id.len()..id_end;
+        let reference = Reference {
+            id: if self.ecmascript_references {
+                GroupId::ExistingName(name)
+            } else {
+                GroupId::Name(name)
+            },
+            span: start..end,
+            filters,
+            modifier,
+        };
+        Some((reference, end))
+    }
+
+    /// Finds the end of what looks like a reference at `template[start..]`, for errors.
+    fn bad_reference_end(&self, template: &str, start: usize) -> usize {
+        let tail = &template[start..];
+        if tail.starts_with(self.open) {
+            tail.find(self.close)
+                .map_or(template.len(), |close| start + close + self.close.len())
+        } else {
+            tail.chars().next().map_or(start, |c| start + c.len_utf8())
+        }
+    }
+
+    fn exec<E>(&self, template: &str, mut f: impl FnMut(Piece) -> Result<(), E>) -> Result<(), E> {
         debug_assert!(!self.open.is_empty());
         debug_assert!(!self.close.is_empty());
-        let mut iter = template.chars();
-        while let Some(c) = iter.next() {
-            if c == self.sub_char {
-                let tail = iter.as_str();
-                let skip = if tail.starts_with(self.sub_char) {
-                    f(Step::Char(self.sub_char))?;
-                    1
-                } else if let Some((id, skip)) = parse_id(tail, self.open, self.close, false)
-                    .or_else(|| {
-                        if self.allow_undelimited_name {
-                            parse_id(tail, "", "", false)
-                        } else {
-                            None
-                        }
-                    })
-                {
-                    f(Step::GroupName(id))?;
-                    skip
-                } else if let Some((skip, num)) = parse_decimal(tail, 0) {
-                    f(Step::GroupNum(num))?;
-                    skip
+        let mut literal_start = 0;
+        let mut ix = 0;
+        while let Some(c) = template[ix..].chars().next() {
+            let next = ix + c.len_utf8();
+            let tail = &template[next..];
+            if c == '\\' && self.case_escapes && self.sub_char != '\\' {
+                if let Some(op) = self.case_escape(tail) {
+                    flush_literal(&mut f, literal_start..ix)?;
+                    f(Piece::Case(op))?;
+                    ix = next + 1;
+                    literal_start = ix;
+                } else if tail.starts_with('\\') {
+                    // `\\` is a literal backslash
+                    flush_literal(&mut f, literal_start..next)?;
+                    ix = next + 1;
+                    literal_start = ix;
                 } else {
-                    f(Step::Error)?;
-                    f(Step::Char(self.sub_char))?;
-                    0
-                };
-                iter = iter.as_str()[skip..].chars();
+                    ix = next;
+                }
+                continue;
+            }
+            if Some(c) == self.match_char {
+                flush_literal(&mut f, literal_start..ix)?;
+                f(Piece::Group(Reference::new(GroupId::Num(0), ix..next)))?;
+                ix = next;
+                literal_start = ix;
+                continue;
+            }
+            if c != self.sub_char {
+                ix = next;
+                continue;
+            }
+            flush_literal(&mut f, literal_start..ix)?;
+            let escaped = tail
+                .chars()
+                .next()
+                .filter(|&e| e == c || Some(e) == self.match_char);
+            if let Some(escaped) = escaped {
+                f(Piece::Literal(next..next + escaped.len_utf8()))?;
+                ix = next + escaped.len_utf8();
+            } else if let Some(op) = self.case_escape(tail).filter(|_| c == '\\') {
+                f(Piece::Case(op))?;
+                ix = next + 1;
+            } else if let Some(id) = self.special_var(tail) {
+                f(Piece::Group(Reference::new(id, ix..next + 1)))?;
+                ix = next + 1;
+            } else if let Some((mut reference, end)) = self.parse_reference(template, next) {
+                reference.span = ix..end;
+                f(Piece::Group(reference))?;
+                ix = end;
             } else {
-                f(Step::Char(c))?;
+                f(Piece::Error(ix..self.bad_reference_end(template, next)))?;
+                // keep the substitution character as a literal
+                literal_start = ix;
+                ix = next;
+                continue;
             }
+            literal_start = ix;
+        }
+        flush_literal(&mut f, literal_start..template.len())
+    }
+}
+
+fn flush_literal<E>(
+    f: &mut impl FnMut(Piece) -> Result<(), E>,
+    range: Range<usize>,
+) -> Result<(), E> {
+    if range.is_empty() {
+        Ok(())
+    } else {
+        f(Piece::Literal(range))
+    }
+}
+
+/// A reference to a capture group in a template, see [`Expander::template_groups`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum GroupRef {
+    /// A reference to a group by name, e.g. `$name`
+    Named(String),
+    /// A reference to a group by number, e.g. `$1`
+    Numbered(usize),
+}
+
+/// A template that was parsed ahead of time by [`Expander::compile`].
+#[derive(Clone, Debug)]
+pub struct Template {
+    template: String,
+    pieces: Vec<Piece>,
+    filters: Filters,
+    case_mapping: CaseMapping,
+}
+
+impl Template {
+    /// Writes the expansion of this template using the values of capture groups from
+    /// `captures` to `dst`.
+    pub fn expand(&self, captures: &Captures<'_>, dst: &mut impl fmt::Write) -> fmt::Result {
+        let mut render = Render::new(
+            self.template.as_str(),
+            &self.filters,
+            self.case_mapping,
+            captures,
+        );
+        let mut dst = FmtOutput(dst);
+        for piece in &self.pieces {
+            render.piece(&mut dst, piece)?;
         }
         Ok(())
     }
+
+    /// Returns the text of this template if it doesn't refer to any groups.
+    pub(crate) fn literal(&self) -> Option<String> {
+        let mut literal = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Literal(range) => literal.push_str(&self.template[range.clone()]),
+                _ => return None,
+            }
+        }
+        Some(literal)
+    }
+
+    /// Returns the expansion of this template using the values of capture groups from
+    /// `captures`.
+    pub fn expansion(&self, captures: &Captures<'_>) -> String {
+        let mut dst = String::new();
+        self.expand(captures, &mut dst)
+            .expect("expansion succeeded");
+        dst
+    }
+}
+
+/// A set of named functions that can be applied to the text of a group during expansion.
+/// See [`ExpanderBuilder::filter`].
+#[derive(Clone, Default)]
+pub(crate) struct Filters(BTreeMap<String, Filter>);
+
+type Filter = Arc<dyn Fn(&str) -> String + Send + Sync + UnwindSafe + RefUnwindSafe>;
+
+impl Filters {
+    /// Adds a filter called `name`, replacing any previous filter with that name.
+    fn insert<F>(&mut self, name: &str, filter: F)
+    where
+        F: Fn(&str) -> String + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.insert(name.to_string(), Arc::new(filter));
+    }
+
+    fn get(&self, name: &str) -> Option<&Filter> {
+        self.0.get(name)
+    }
 }
 
-enum Step<'a> {
-    Char(char),
-    GroupName(&'a str),
-    GroupNum(usize),
-    Error,
+impl fmt::Debug for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
+
+/// A builder for an [`Expander`], for configuring the options that aren't public fields of
+/// the expander, like filters.
+///
+/// ```
+/// # use fancy_regex::{ExpanderBuilder, Regex};
+/// let re = Regex::new(r"(\w+)@(\w+)").unwrap();
+/// let caps = re.captures("Alice@Example").unwrap().unwrap();
+/// let expander = ExpanderBuilder::new()
+///     .filter("upper", |s| s.to_uppercase())
+///     .filter("lower", |s| s.to_lowercase())
+///     .build();
+/// assert_eq!(expander.expansion("${1|upper} at ${2|lower}", &caps), "ALICE at example");
+/// ```
+#[derive(Debug, Default)]
+pub struct ExpanderBuilder(Expander);
+
+impl ExpanderBuilder {
+    /// Create a new builder starting from the default expander.
+    pub fn new() -> Self {
+        ExpanderBuilder::default()
+    }
+
+    /// Build the `Expander`.
+    pub fn build(&self) -> Expander {
+        self.0.clone()
+    }
+
+    /// Set the substitution character, see [`Expander::sub_char`].
+    pub fn sub_char(&mut self, sub_char: char) -> &mut Self {
+        self.0.sub_char = sub_char;
+        self
+    }
+
+    /// Set the group name delimiters, see [`Expander::open`] and [`Expander::close`].
+    pub fn delimiters(&mut self, open: &'static str, close: &'static str) -> &mut Self {
+        self.0.open = open;
+        self.0.close = close;
+        self
+    }
+
+    /// Allow group names without delimiters, see [`Expander::allow_undelimited_name`].
+    pub fn allow_undelimited_name(&mut self, yes: bool) -> &mut Self {
+        self.0.allow_undelimited_name = yes;
+        self
+    }
+
+    /// Recognize the Perl-style case-conversion escapes `\U`, `\L`, `\u`, `\l` and `\E`.
+    /// Defaults to `false`.
+    ///
+    /// `\U` and `\L` convert everything that follows to upper or lower case until the next
+    /// `\E` (or the next `\U` or `\L`). `\u` and `\l` only convert the next character. When
+    /// enabled, `\\` produces a literal backslash.
+    ///
+    /// ```
+    /// # use fancy_regex::{ExpanderBuilder, Regex};
+    /// let re = Regex::new(r"(\w+) (\w+)").unwrap();
+    /// let caps = re.captures("hello world").unwrap().unwrap();
+    /// let expander = ExpanderBuilder::new().case_escapes(true).build();
+    /// assert_eq!(expander.expansion(r"\U$1\E-\u$2", &caps), "HELLO-World");
+    /// ```
+    pub fn case_escapes(&mut self, yes: bool) -> &mut Self {
+        self.0.case_escapes = yes;
+        self
+    }
+
+    /// Recognize the special variables `$&` (the whole match), `` $` `` (the text before the
+    /// match), `$'` (the text after the match) and `$+` (the last group in the regex), using
+    /// the substitution character in place of `$`. If group names without delimiters aren't
+    /// allowed, `$_` (the entire input) is recognized too. Defaults to `false`.
+    ///
+    /// ```
+    /// # use fancy_regex::{ExpanderBuilder, Regex};
+    /// let re = Regex::new(r"\d+").unwrap();
+    /// let caps = re.captures("abc123def").unwrap().unwrap();
+    /// let expander = ExpanderBuilder::new().special_vars(true).build();
+    /// assert_eq!(expander.expansion("[$`|$&|$']", &caps), "[abc|123|def]");
+    /// ```
+    pub fn special_vars(&mut self, yes: bool) -> &mut Self {
+        self.0.special_vars = yes;
+        self
+    }
+
+    /// Set a character that expands to the whole match on its own, like `&` in sed. Precede it
+    /// with the substitution character to write it literally. Defaults to `None`.
+    pub fn match_char(&mut self, match_char: Option<char>) -> &mut Self {
+        self.0.match_char = match_char;
+        self
+    }
+
+    /// Set the maximum number of digits in a numbered reference such as `$12`, or `None` for
+    /// no limit. For example, with a limit of 1, `\12` refers to group 1 followed by a literal
+    /// `2`. Defaults to `None`.
+    pub fn max_group_digits(&mut self, max_group_digits: Option<usize>) -> &mut Self {
+        self.0.max_group_digits = max_group_digits;
+        self
+    }
+
+    /// Report errors instead of expanding to the empty string in methods that return a
+    /// `Result`, such as [`replace_all`](Expander::replace_all). See
+    /// [`try_expansion`](Expander::try_expansion) for what counts as an error. Defaults to
+    /// `false`.
+    pub fn strict(&mut self, yes: bool) -> &mut Self {
+        self.0.strict = yes;
+        self
+    }
+
+    /// Allow references to groups by number, like `$1` or `${1}`. If `false`, they're treated
+    /// as invalid references, so that templates have to use group names. Defaults to `true`.
+    ///
+    /// ```
+    /// # use fancy_regex::{ExpandErrorKind, ExpanderBuilder, Regex};
+    /// let re = Regex::new(r"(?<user>\w+)@(?<host>\w+)").unwrap();
+    /// let expander = ExpanderBuilder::new().allow_numbered(false).build();
+    /// assert_eq!(expander.check_template("$host: $user", &re), Ok(()));
+    /// let err = expander.check_template("$2: $1", &re).unwrap_err();
+    /// assert_eq!(err.kind(), &ExpandErrorKind::BadSyntax);
+    /// ```
+    pub fn allow_numbered(&mut self, yes: bool) -> &mut Self {
+        self.0.allow_numbered = yes;
+        self
+    }
+
+    /// Set how case-conversion escapes convert characters, see
+    /// [`case_escapes`](Self::case_escapes). Defaults to [`CaseMapping::Unicode`].
+    ///
+    /// ```
+    /// # use fancy_regex::{CaseMapping, ExpanderBuilder, Regex};
+    /// let re = Regex::new(r"\w+").unwrap();
+    /// let caps = re.captures("straße").unwrap().unwrap();
+    /// let mut builder = ExpanderBuilder::new();
+    /// builder.case_escapes(true);
+    /// assert_eq!(builder.build().expansion(r"\U$0", &caps), "STRASSE");
+    /// builder.case_mapping(CaseMapping::Ascii);
+    /// assert_eq!(builder.build().expansion(r"\U$0", &caps), "STRAßE");
+    /// ```
+    pub fn case_mapping(&mut self, case_mapping: CaseMapping) -> &mut Self {
+        self.0.case_mapping = case_mapping;
+        self
+    }
+
+    /// Register a filter called `name` that templates can apply to the text of a group inside
+    /// delimiters, e.g. `${1|name}`. Several filters can be chained, e.g. `${1|trim|upper}`,
+    /// and are applied from left to right. Registering a filter again replaces it.
+    pub fn filter<F>(&mut self, name: &str, filter: F) -> &mut Self
+    where
+        F: Fn(&str) -> String + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.filters.insert(name, filter);
+        self
+    }
+}
+
+impl From<Expander> for ExpanderBuilder {
+    fn from(expander: Expander) -> Self {
+        ExpanderBuilder(expander)
+    }
+}
+
+/// A parsed element of a template. Text is referenced by its range in the template.
+#[derive(Clone, Debug)]
+enum Piece {
+    Literal(Range<usize>),
+    Group(Reference),
+    Case(CaseOp),
+    /// A substitution character that doesn't start a valid reference
+    Error(Range<usize>),
+}
+
+#[derive(Clone, Debug)]
+struct Reference {
+    id: GroupId,
+    /// The whole reference in the template, e.g. `${name}`
+    span: Range<usize>,
+    /// The filters to apply, e.g. `|trim|upper`
+    filters: Range<usize>,
+    modifier: Option<Modifier>,
+}
+
+impl Reference {
+    fn new(id: GroupId, span: Range<usize>) -> Reference {
+        Reference {
+            id,
+            span,
+            filters: 0..0,
+            modifier: None,
+        }
+    }
+
+    fn is_numbered(&self, template: &str) -> bool {
+        match &self.id {
+            GroupId::Name(name) | GroupId::ExistingName(name) => {
+                template[name.clone()].bytes().all(|b| b.is_ascii_digit())
+            }
+            GroupId::Num(_) | GroupId::ExistingNum(_) | GroupId::TwoDigitNum(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Resolves the reference for a regex with `len` groups, including group 0, like in
+    /// ECMAScript: a numbered reference to a group other than 0 that exists refers to it, a
+    /// two-digit reference such as `$10` to a group that doesn't exist refers to the group of
+    /// its first digit, followed by the second digit as a literal, and a name refers to a group
+    /// if the regex has named groups. Anything else is kept as literal text.
+    fn resolve(&self, len: usize, has_names: bool) -> Resolution {
+        let exists = |num| num != 0 && num < len;
+        match self.id {
+            GroupId::ExistingNum(num) if !exists(num) => Resolution::Literal,
+            GroupId::TwoDigitNum(num) if !exists(num) => {
+                if exists(num / 10) {
+                    let span = self.span.start..self.span.end - 1;
+                    Resolution::Shortened(Reference::new(GroupId::Num(num / 10), span))
+                } else {
+                    Resolution::Literal
+                }
+            }
+            GroupId::ExistingName(_) if !has_names => Resolution::Literal,
+            _ => Resolution::Group,
+        }
+    }
+}
+
+/// What a reference refers to, see `Reference::resolve`.
+enum Resolution {
+    Group,
+    /// Another reference, followed by the last character of the reference as a literal
+    Shortened(Reference),
+    Literal,
+}
+
+/// Changes what a reference expands to depending on whether the group participated.
+#[derive(Clone, Debug)]
+enum Modifier {
+    /// `${name:-fallback}`: the literal text to use if the group didn't participate
+    Default(Range<usize>),
+    /// `${name:+present:absent}`: the literal text to use instead of the group's text
+    Conditional {
+        present: Range<usize>,
+        absent: Range<usize>,
+    },
+    /// `${name:>10}` and the like: pads the group's text to a width
+    Pad(Pad),
+}
+
+/// A width and alignment like in `format!`, e.g. `>10`, `^8` or `08`.
+#[derive(Clone, Debug)]
+struct Pad {
+    align: Align,
+    fill: char,
+    width: usize,
+}
+
+/// The largest width of a padding spec, like the practical limit of `format!`.
+const MAX_PAD_WIDTH: usize = u16::MAX as usize;
+
+#[derive(Clone, Copy, Debug)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Pad {
+    /// Parses the part of a padding spec after the `:`, returning the index just after it.
+    fn parse(template: &str, start: usize) -> Option<(Pad, usize)> {
+        let mut ix = start;
+        let mut align = match template[ix..].chars().next() {
+            Some('<') => Some(Align::Left),
+            Some('^') => Some(Align::Center),
+            Some('>') => Some(Align::Right),
+            _ => None,
+        };
+        if align.is_some() {
+            ix += 1;
+        }
+        let mut fill = ' ';
+        if template[ix..].starts_with('0') {
+            fill = '0';
+            align = align.or(Some(Align::Right));
+            ix += 1;
+        }
+        let (end, width) = parse_decimal(template, ix)?;
+        // a template from user input shouldn't be able to allocate without bounds
+        if width > MAX_PAD_WIDTH {
+            return None;
+        }
+        let pad = Pad {
+            align: align.unwrap_or(Align::Left),
+            fill,
+            width,
+        };
+        Some((pad, end))
+    }
+
+    fn write(&self, case: &mut CaseState, dst: &mut impl Output, text: &str) -> fmt::Result {
+        let padding = self.width.saturating_sub(text.chars().count());
+        let (before, after) = match self.align {
+            Align::Left => (0, padding),
+            Align::Center => (padding / 2, padding - padding / 2),
+            Align::Right => (padding, 0),
+        };
+        for _ in 0..before {
+            dst.write_char(self.fill)?;
+        }
+        case.write_str(dst, text)?;
+        for _ in 0..after {
+            dst.write_char(self.fill)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+enum GroupId {
+    /// A group name, or a number if it doesn't name a group
+    Name(Range<usize>),
+    Num(usize),
+    /// A group number that is kept as literal text if the group doesn't exist, see
+    /// `Reference::resolve`
+    ExistingNum(usize),
+    /// A two-digit group number, or the group of the first digit if it doesn't exist, see
+    /// `Reference::resolve`
+    TwoDigitNum(usize),
+    /// A group name that is kept as literal text if the regex has no named groups, see
+    /// `Reference::resolve`
+    ExistingName(Range<usize>),
+    /// The text before the match
+    Prefix,
+    /// The text after the match
+    Suffix,
+    /// The last group in the regex
+    Last,
+    /// The entire input
+    Input,
+}
+
+/// Writes the pieces of a template.
+struct Render<'a, T: ?Sized, G> {
+    template: &'a T,
+    filters: &'a Filters,
+    groups: &'a G,
+    case: CaseState,
+}
+
+impl<'a, T, G> Render<'a, T, G>
+where
+    T: Text + ?Sized,
+    G: Groups,
+{
+    fn new(template: &'a T, filters: &'a Filters, mapping: CaseMapping, groups: &'a G) -> Self {
+        Render {
+            template,
+            filters,
+            groups,
+            case: CaseState::new(mapping),
+        }
+    }
+
+    fn piece(&mut self, dst: &mut impl Output, piece: &Piece) -> fmt::Result {
+        match piece {
+            Piece::Literal(range) => self.literal(dst, range),
+            Piece::Group(reference) => {
+                match self.resolve(reference) {
+                    Resolution::Group => {}
+                    Resolution::Shortened(shortened) => {
+                        self.piece(dst, &Piece::Group(shortened))?;
+                        return self.literal(dst, &(reference.span.end - 1..reference.span.end));
+                    }
+                    Resolution::Literal => return self.literal(dst, &reference.span),
+                }
+                let group = self.group(&reference.id);
+                match (&reference.modifier, group) {
+                    (Some(Modifier::Conditional { present, .. }), Some(_)) => {
+                        self.literal(dst, present)
+                    }
+                    (Some(Modifier::Conditional { absent, .. }), None) => self.literal(dst, absent),
+                    (Some(Modifier::Pad(pad)), group) => {
+                        let text = match group {
+                            Some(text) => self.filter(text.to_str_lossy(), &reference.filters),
+                            None => Cow::Borrowed(""),
+                        };
+                        pad.write(&mut self.case, dst, &text)
+                    }
+                    (_, Some(text)) if reference.filters.is_empty() => {
+                        text.write_to(&mut self.case, dst)
+                    }
+                    (_, Some(text)) => {
+                        let text = self.filter(text.to_str_lossy(), &reference.filters);
+                        self.case.write_str(dst, &text)
+                    }
+                    (Some(Modifier::Default(fallback)), None) => self.literal(dst, fallback),
+                    (None, None) => Ok(()),
+                }
+            }
+            Piece::Case(op) => {
+                self.case.apply(*op);
+                Ok(())
+            }
+            Piece::Error(_) => Ok(()),
+        }
+    }
+
+    fn resolve(&self, reference: &Reference) -> Resolution {
+        reference.resolve(self.groups.len(), self.groups.has_names())
+    }
+
+    /// Checks that `reference` expands to something, for strict expansion.
+    fn check(&self, reference: &Reference) -> Result<(), ExpandErrorKind> {
+        match self.resolve(reference) {
+            Resolution::Group => {}
+            Resolution::Shortened(shortened) => return self.check(&shortened),
+            Resolution::Literal => return Ok(()),
+        }
+        let has_fallback = matches!(
+            reference.modifier,
+            Some(Modifier::Default(_) | Modifier::Conditional { .. })
+        );
+        if has_fallback || self.group(&reference.id).is_some() {
+            return Ok(());
+        }
+        let num = match &reference.id {
+            GroupId::Name(name) | GroupId::ExistingName(name) => {
+                let name = self.template[name.clone()].to_str_lossy();
+                if self.groups.contains_name(&name) {
+                    return Err(ExpandErrorKind::GroupDidNotMatch(name.into_owned()));
+                }
+                match name.parse() {
+                    Ok(num) => num,
+                    Err(_) => return Err(ExpandErrorKind::UnknownGroupName(name.into_owned())),
+                }
+            }
+            GroupId::Num(num) | GroupId::ExistingNum(num) | GroupId::TwoDigitNum(num) => *num,
+            GroupId::Last => self.groups.len() - 1,
+            GroupId::Prefix | GroupId::Suffix | GroupId::Input => 0,
+        };
+        if num < self.groups.len() {
+            Err(ExpandErrorKind::GroupDidNotMatch(num.to_string()))
+        } else {
+            Err(ExpandErrorKind::UnknownGroupNumber(num))
+        }
+    }
+
+    fn literal(&mut self, dst: &mut impl Output, range: &Range<usize>) -> fmt::Result {
+        self.template[range.clone()].write_to(&mut self.case, dst)
+    }
+
+    fn group(&self, id: &GroupId) -> Option<&'a G::Text> {
+        match id {
+            GroupId::Name(name) | GroupId::ExistingName(name) => {
+                let name = self.template[name.clone()].to_str_lossy();
+                self.groups
+                    .name(&name)
+                    .or_else(|| name.parse().ok().and_then(|num| self.groups.get(num)))
+            }
+            GroupId::Num(num) | GroupId::ExistingNum(num) | GroupId::TwoDigitNum(num) => {
+                self.groups.get(*num)
+            }
+            GroupId::Last => self.groups.get(self.groups.len() - 1),
+            GroupId::Input => self.groups.input(),
+            GroupId::Prefix => self.groups.prefix(),
+            GroupId::Suffix => self.groups.suffix(),
+        }
+    }
+
+    fn filter<'s>(&self, mut text: Cow<'s, str>, filters: &Range<usize>) -> Cow<'s, str> {
+        for name in self.template[filters.clone()]
+            .to_str_lossy()
+            .split('|')
+            .skip(1)
+        {
+            let filter = self.filters.get(name).expect("filter exists");
+            text = Cow::Owned(filter(&text));
+        }
+        text
+    }
+}
+
+/// The text of a template or of a capture group, either `str` or `[u8]`.
+pub(crate) trait Text: Index<Range<usize>, Output = Self> {
+    fn write_to(&self, case: &mut CaseState, dst: &mut impl Output) -> fmt::Result;
+
+    fn to_str_lossy(&self) -> Cow<'_, str>;
+}
+
+impl Text for str {
+    fn write_to(&self, case: &mut CaseState, dst: &mut impl Output) -> fmt::Result {
+        case.write_str(dst, self)
+    }
+
+    fn to_str_lossy(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl Text for [u8] {
+    fn write_to(&self, case: &mut CaseState, dst: &mut impl Output) -> fmt::Result {
+        utf8_chunks(self, |valid, invalid| {
+            case.write_str(dst, valid)?;
+            dst.write_bytes(invalid)
+        })
+    }
+
+    fn to_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self)
+    }
+}
+
+/// Calls `f` with each run of valid UTF-8 in `bytes` and the invalid bytes following it.
+fn utf8_chunks<E>(
+    mut bytes: &[u8],
+    mut f: impl FnMut(&str, &[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    loop {
+        match core::str::from_utf8(bytes) {
+            Ok(valid) => return f(valid, &[]),
+            Err(e) => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+                let invalid_len = e.error_len().unwrap_or(rest.len());
+                let valid = core::str::from_utf8(valid).expect("valid UTF-8");
+                f(valid, &rest[..invalid_len])?;
+                bytes = &rest[invalid_len..];
+            }
+        }
+    }
+}
+
+/// Returns a string with the same length as `bytes` where every byte that isn't part of valid
+/// UTF-8 is replaced with NUL, so that byte templates can be parsed like string templates.
+fn utf8_skeleton(bytes: &[u8]) -> Cow<'_, str> {
+    if let Ok(s) = core::str::from_utf8(bytes) {
+        return Cow::Borrowed(s);
+    }
+    let mut skeleton = String::with_capacity(bytes.len());
+    let _ = utf8_chunks(bytes, |valid, invalid| {
+        skeleton.push_str(valid);
+        skeleton.extend(invalid.iter().map(|_| '\0'));
+        Ok::<_, ()>(())
+    });
+    Cow::Owned(skeleton)
+}
+
+/// The capture groups that an expansion takes its text from.
+pub(crate) trait Groups {
+    type Text: Text + ?Sized;
+
+    fn name(&self, name: &str) -> Option<&Self::Text>;
+
+    fn get(&self, i: usize) -> Option<&Self::Text>;
+
+    fn input(&self) -> Option<&Self::Text>;
+
+    fn prefix(&self) -> Option<&Self::Text>;
+
+    fn suffix(&self) -> Option<&Self::Text>;
+
+    fn contains_name(&self, name: &str) -> bool;
+
+    fn has_names(&self) -> bool;
+
+    fn len(&self) -> usize;
+}
+
+impl<'t> Groups for Captures<'t> {
+    type Text = str;
+
+    fn name(&self, name: &str) -> Option<&str> {
+        Captures::name(self, name).map(|m| m.as_str())
+    }
+
+    fn get(&self, i: usize) -> Option<&str> {
+        Captures::get(self, i).map(|m| m.as_str())
+    }
+
+    fn input(&self) -> Option<&str> {
+        Captures::get(self, 0).map(|m| m.text)
+    }
+
+    fn prefix(&self) -> Option<&str> {
+        Captures::get(self, 0).map(|m| &m.text[..m.start])
+    }
+
+    fn suffix(&self) -> Option<&str> {
+        Captures::get(self, 0).map(|m| &m.text[m.end..])
+    }
+
+    fn contains_name(&self, name: &str) -> bool {
+        self.named_groups.contains_key(name)
+    }
+
+    fn has_names(&self) -> bool {
+        !self.named_groups.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        Captures::len(self)
+    }
+}
+
+/// The captures of a match that byte templates can be expanded with, see
+/// [`Expander::expansion_bytes`]: the [`Captures`] of a [`Regex`] and the
+/// [`bytes::Captures`](crate::bytes::Captures) of a [`bytes::Regex`](crate::bytes::Regex).
+///
+/// This trait is sealed, so it can't be implemented outside of this crate.
+pub trait ExpansionCaptures: sealed::Sealed {}
+
+impl ExpansionCaptures for Captures<'_> {}
+
+impl ExpansionCaptures for crate::bytes::Captures<'_> {}
+
+mod sealed {
+    /// Either kind of captures.
+    #[derive(Debug)]
+    pub enum AnyCaptures<'a> {
+        Str(&'a crate::Captures<'a>),
+        Bytes(&'a crate::bytes::Captures<'a>),
+    }
+
+    pub trait Sealed {
+        fn any_captures(&self) -> AnyCaptures<'_>;
+    }
+
+    impl Sealed for crate::Captures<'_> {
+        fn any_captures(&self) -> AnyCaptures<'_> {
+            AnyCaptures::Str(self)
+        }
+    }
+
+    impl Sealed for crate::bytes::Captures<'_> {
+        fn any_captures(&self) -> AnyCaptures<'_> {
+            AnyCaptures::Bytes(self)
+        }
+    }
+}
+
+/// A destination for an expansion that can also receive bytes that aren't valid UTF-8.
+pub(crate) trait Output: fmt::Write {
+    fn write_bytes(&mut self, bytes: &[u8]) -> fmt::Result;
+}
+
+/// Writes an expansion to a `fmt::Write`, replacing invalid UTF-8 with U+FFFD.
+struct FmtOutput<'a, W>(&'a mut W);
+
+impl<W: fmt::Write> fmt::Write for FmtOutput<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+}
+
+impl<W: fmt::Write> Output for FmtOutput<'_, W> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> fmt::Result {
+        self.0.write_str(&String::from_utf8_lossy(bytes))
+    }
+}
+
+/// Writes an expansion to an `io::Write`, keeping the first error to return it in place of
+/// the `fmt::Error` that stops the expansion.
+#[cfg(feature = "std")]
+struct IoOutput<W> {
+    dst: W,
+    error: Option<std::io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> IoOutput<W> {
+    fn new(dst: W) -> Self {
+        IoOutput { dst, error: None }
+    }
+
+    /// Returns the error of writing to the destination, if the expansion stopped at one.
+    fn finish(self, result: fmt::Result) -> std::io::Result<()> {
+        match (result, self.error) {
+            (Ok(()), _) => Ok(()),
+            (Err(_), Some(error)) => Err(error),
+            (Err(_), None) => Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "expansion failed",
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> fmt::Write for IoOutput<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_bytes(s.as_bytes())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> Output for IoOutput<W> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> fmt::Result {
+        self.dst.write_all(bytes).map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+/// Writes an expansion to a byte buffer.
+struct ByteOutput<'a>(&'a mut Vec<u8>);
+
+impl fmt::Write for ByteOutput<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl Output for ByteOutput<'_> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> fmt::Result {
+        self.0.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// A case-conversion escape such as `\U`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CaseOp {
+    Upper,
+    Lower,
+    UpperNext,
+    LowerNext,
+    End,
+}
+
+impl CaseOp {
+    fn from_escape(c: char) -> Option<CaseOp> {
+        match c {
+            'U' => Some(CaseOp::Upper),
+            'L' => Some(CaseOp::Lower),
+            'u' => Some(CaseOp::UpperNext),
+            'l' => Some(CaseOp::LowerNext),
+            'E' => Some(CaseOp::End),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Case {
+    Upper,
+    Lower,
+}
+
+/// How case-conversion escapes convert characters, see [`ExpanderBuilder::case_mapping`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CaseMapping {
+    /// Full Unicode case mapping, which can change the length of the text, e.g. `ß` becomes
+    /// `SS` in upper case
+    #[default]
+    Unicode,
+    /// Only convert ASCII letters, leaving other characters unchanged
+    Ascii,
+    /// Full Unicode case mapping with the Turkish and Azerbaijani rules for dotted and dotless
+    /// i, e.g. `i` becomes `İ` in upper case and `I` becomes `ı` in lower case
+    Turkic,
+}
+
+impl CaseMapping {
+    fn write_char(self, dst: &mut impl fmt::Write, c: char, case: Case) -> fmt::Result {
+        match (self, case) {
+            (CaseMapping::Ascii, Case::Upper) => dst.write_char(c.to_ascii_uppercase()),
+            (CaseMapping::Ascii, Case::Lower) => dst.write_char(c.to_ascii_lowercase()),
+            (CaseMapping::Turkic, Case::Upper) if c == 'i' => dst.write_char('İ'),
+            (CaseMapping::Turkic, Case::Lower) if c == 'I' => dst.write_char('ı'),
+            (CaseMapping::Turkic, Case::Lower) if c == 'İ' => dst.write_char('i'),
+            (_, Case::Upper) => write!(dst, "{}", c.to_uppercase()),
+            (_, Case::Lower) => write!(dst, "{}", c.to_lowercase()),
+        }
+    }
+}
+
+/// Tracks the case conversions in effect while writing an expansion.
+pub(crate) struct CaseState {
+    mapping: CaseMapping,
+    span: Option<Case>,
+    next: Option<Case>,
+}
+
+impl CaseState {
+    fn new(mapping: CaseMapping) -> Self {
+        CaseState {
+            mapping,
+            span: None,
+            next: None,
+        }
+    }
+
+    fn apply(&mut self, op: CaseOp) {
+        match op {
+            CaseOp::Upper => self.span = Some(Case::Upper),
+            CaseOp::Lower => self.span = Some(Case::Lower),
+            CaseOp::UpperNext => self.next = Some(Case::Upper),
+            CaseOp::LowerNext => self.next = Some(Case::Lower),
+            CaseOp::End => self.span = None,
+        }
+    }
+
+    fn write_char(&mut self, dst: &mut impl fmt::Write, c: char) -> fmt::Result {
+        match self.next.take().or(self.span) {
+            None => dst.write_char(c),
+            Some(case) => self.mapping.write_char(dst, c, case),
+        }
+    }
+
+    fn write_str(&mut self, dst: &mut impl fmt::Write, s: &str) -> fmt::Result {
+        if self.next.is_none() && self.span.is_none() {
+            return dst.write_str(s);
+        }
+        s.chars().try_for_each(|c| self.write_char(dst, c))
+    }
 }
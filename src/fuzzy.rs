@@ -0,0 +1,48 @@
+use core::ops::Range;
+
+/// The kind of an [`Edit`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EditKind {
+    /// A character in the text that isn't in the pattern
+    Insertion,
+    /// A character of the pattern that is missing in the text
+    Deletion,
+    /// A character in the text in place of a character of the pattern
+    Substitution,
+}
+
+/// An edit that a fuzzy group, e.g. `(?:abc){~1}`, made to match the text, as returned by
+/// [`Captures::edits`](crate::Captures::edits).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Edit {
+    kind: EditKind,
+    start: usize,
+    end: usize,
+}
+
+impl Edit {
+    pub(crate) fn new(kind: EditKind, start: usize, end: usize) -> Edit {
+        Edit { kind, start, end }
+    }
+
+    /// Returns the kind of the edit.
+    pub fn kind(&self) -> EditKind {
+        self.kind
+    }
+
+    /// Returns the starting byte offset of the edit in the text.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending byte offset of the edit in the text, which is the same as the start
+    /// for a deletion.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the edit in the text.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
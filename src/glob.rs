@@ -0,0 +1,264 @@
+use alloc::string::String;
+
+use crate::{push_quoted, Error, ParseError, Regex, Result, MAX_RECURSION};
+
+/// Options for translating a shell glob into a regex with [`Regex::from_glob`].
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{GlobOptions, Regex};
+/// let re = Regex::from_glob("*.RS", GlobOptions::new().case_insensitive(true)).unwrap();
+/// assert!(re.is_match("lib.rs").unwrap());
+/// assert!(!re.is_match("src/lib.rs").unwrap());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GlobOptions {
+    case_insensitive: bool,
+    literal_separator: bool,
+    backslash_escape: bool,
+}
+
+impl Default for GlobOptions {
+    fn default() -> Self {
+        GlobOptions {
+            case_insensitive: false,
+            literal_separator: true,
+            backslash_escape: true,
+        }
+    }
+}
+
+impl GlobOptions {
+    /// Create the default options, which translate a glob like a shell on Unix does.
+    pub fn new() -> GlobOptions {
+        GlobOptions::default()
+    }
+
+    /// Match letters case-insensitively.
+    ///
+    /// Default is false.
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut Self {
+        self.case_insensitive = yes;
+        self
+    }
+
+    /// Only let `**` match the path separator `/`, so that `*`, `?` and negated classes such as
+    /// `[!a]` don't match it, like in a shell. Otherwise all of them match `/`, so that e.g.
+    /// `*.rs` also matches `src/lib.rs`.
+    ///
+    /// Default is true.
+    pub fn literal_separator(&mut self, yes: bool) -> &mut Self {
+        self.literal_separator = yes;
+        self
+    }
+
+    /// Let `\` escape the following character, e.g. `\*` only matches `*`. Otherwise `\` is a
+    /// character that matches itself, e.g. in Windows paths.
+    ///
+    /// Default is true.
+    pub fn backslash_escape(&mut self, yes: bool) -> &mut Self {
+        self.backslash_escape = yes;
+        self
+    }
+}
+
+/// Translates a glob into the pattern of a regex.
+struct Translator<'a> {
+    glob: &'a str,
+    options: &'a GlobOptions,
+    re: String,
+}
+
+impl<'a> Translator<'a> {
+    /// Translates the glob from `ix` to its end, or inside `depth` braces to the `,` or `}`
+    /// that ends the alternative, and returns the position where it stopped.
+    fn translate(&mut self, mut ix: usize, depth: usize) -> Result<usize> {
+        let bytes = self.glob.as_bytes();
+        while ix < self.glob.len() {
+            match bytes[ix] {
+                b'*' if bytes.get(ix + 1) == Some(&b'*') => ix = self.double_star(ix, depth),
+                b'*' => {
+                    self.push_any();
+                    self.re.push('*');
+                    ix += 1;
+                }
+                b'?' => {
+                    self.push_any();
+                    ix += 1;
+                }
+                b'[' => ix = self.class(ix)?,
+                b'{' => ix = self.alternatives(ix, depth)?,
+                b',' | b'}' if depth > 0 => return Ok(ix),
+                b'\\' if self.options.backslash_escape => {
+                    if ix + 1 == self.glob.len() {
+                        return Err(Error::ParseError(ix, ParseError::TrailingBackslash));
+                    }
+                    ix = self.literal(ix + 1);
+                }
+                _ => ix = self.literal(ix),
+            }
+        }
+        Ok(ix)
+    }
+
+    /// Adds the character at `ix` and returns the position after it.
+    fn literal(&mut self, ix: usize) -> usize {
+        let len = self.glob[ix..].chars().next().map_or(0, char::len_utf8);
+        push_quoted(&mut self.re, &self.glob[ix..ix + len]);
+        ix + len
+    }
+
+    /// Adds a single character that `?` matches.
+    fn push_any(&mut self) {
+        self.re.push_str(if self.options.literal_separator {
+            "[^/]"
+        } else {
+            "."
+        });
+    }
+
+    /// Translates the `**` at `ix`, which matches any directories if it's a whole component of
+    /// the path, or otherwise the same as `*`.
+    fn double_star(&mut self, ix: usize, depth: usize) -> usize {
+        let bytes = self.glob.as_bytes();
+        let end = ix + 2;
+        let after_separator =
+            ix == 0 || bytes[ix - 1] == b'/' || depth > 0 && matches!(bytes[ix - 1], b'{' | b',');
+        match bytes.get(end) {
+            Some(b'/') if after_separator => {
+                self.re.push_str("(?:.*/)?");
+                end + 1
+            }
+            None if after_separator => {
+                self.re.push_str(".*");
+                end
+            }
+            Some(b',' | b'}') if after_separator && depth > 0 => {
+                self.re.push_str(".*");
+                end
+            }
+            _ => {
+                self.push_any();
+                self.re.push('*');
+                end
+            }
+        }
+    }
+
+    /// Translates the class that starts with the `[` at `ix`, e.g. `[a-z]` or `[!/]`, and
+    /// returns the position after it.
+    fn class(&mut self, ix: usize) -> Result<usize> {
+        let bytes = self.glob.as_bytes();
+        let mut end = ix + 1;
+        let mut class = String::from("[");
+        if matches!(bytes.get(end), Some(b'!' | b'^')) {
+            class.push('^');
+            if self.options.literal_separator {
+                class.push('/');
+            }
+            end += 1;
+        }
+        let first = end;
+        loop {
+            match bytes.get(end) {
+                None => return Err(Error::ParseError(ix, ParseError::InvalidClass)),
+                Some(b']') if end > first => break,
+                Some(b'-') if end > first && bytes.get(end + 1) != Some(&b']') => {
+                    class.push('-');
+                    end += 1;
+                }
+                Some(b'[') if self.glob[end..].starts_with("[:") => {
+                    // a POSIX class such as `[:alpha:]`
+                    let len = self.glob[end..]
+                        .find(":]")
+                        .ok_or(Error::ParseError(ix, ParseError::InvalidClass))?;
+                    class.push_str(&self.glob[end..end + len + 2]);
+                    end += len + 2;
+                }
+                Some(&b) => {
+                    if b == b'\\' && self.options.backslash_escape && end + 1 < self.glob.len() {
+                        end += 1;
+                    }
+                    let c = self.glob[end..].chars().next().unwrap();
+                    if c.is_ascii_punctuation() {
+                        class.push('\\');
+                    }
+                    class.push(c);
+                    end += c.len_utf8();
+                }
+            }
+        }
+        class.push(']');
+        self.re.push_str(&class);
+        Ok(end + 1)
+    }
+
+    /// Translates the alternatives in the braces that start at `ix`, e.g. `{a,b*}`, and
+    /// returns the position after them.
+    fn alternatives(&mut self, ix: usize, depth: usize) -> Result<usize> {
+        if depth >= MAX_RECURSION {
+            return Err(Error::ParseError(ix, ParseError::RecursionExceeded));
+        }
+        self.re.push_str("(?:");
+        let mut end = ix + 1;
+        loop {
+            end = self.translate(end, depth + 1)?;
+            match self.glob.as_bytes().get(end) {
+                Some(b',') => {
+                    self.re.push('|');
+                    end += 1;
+                }
+                Some(b'}') => {
+                    self.re.push(')');
+                    return Ok(end + 1);
+                }
+                _ => return Err(Error::ParseError(ix, ParseError::UnclosedGlobAlternatives)),
+            }
+        }
+    }
+}
+
+impl Regex {
+    /// Translates the shell glob `glob` into a regex that matches a whole text, such as a path,
+    /// if the glob matches it:
+    ///
+    /// - `*` matches any characters except `/`, and `?` matches one of them
+    /// - `**` as a whole component of the path matches any directories: `**/` matches no or
+    ///   any directories, and `/**` at the end everything in a directory. Elsewhere, it's the
+    ///   same as `*`.
+    /// - `[abc]`, `[a-z]` and `[[:alpha:]]` match one of the characters, and `[!abc]` or
+    ///   `[^abc]` any other character except `/`
+    /// - `{a,b}` matches one of the alternatives, which are globs themselves and can be nested
+    /// - `\` escapes the following character
+    ///
+    /// Other characters match themselves. Unlike in a shell, `*` and `?` also match a `.` at
+    /// the start of a file name. How `/` and `\` are treated can be changed with `options`.
+    ///
+    /// Returns an [`Error`] at the position in `glob` if a class or braces aren't closed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{GlobOptions, Regex};
+    /// let re = Regex::from_glob("src/**/*.{rs,toml}", &GlobOptions::new()).unwrap();
+    /// assert!(re.is_match("src/lib.rs").unwrap());
+    /// assert!(re.is_match("src/a/b/Cargo.toml").unwrap());
+    /// assert!(!re.is_match("src/lib.rsx").unwrap());
+    /// assert!(!re.is_match("tests/lib.rs").unwrap());
+    /// ```
+    pub fn from_glob(glob: &str, options: &GlobOptions) -> Result<Regex> {
+        let mut translator = Translator {
+            glob,
+            options,
+            re: String::from(if options.case_insensitive {
+                r"(?si)\A(?:"
+            } else {
+                r"(?s)\A(?:"
+            }),
+        };
+        translator.translate(0, 0)?;
+        translator.re.push_str(r")\z");
+        Regex::new(&translator.re)
+    }
+}
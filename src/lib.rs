@@ -29,7 +29,8 @@ If your regex or parts of it does not use any special features, the matching is
 regex crate. That means it has linear runtime. But if you use "fancy" features such as
 backreferences or look-around, an engine with backtracking needs to be used. In that case, the regex
 can be slow and take exponential time to run because of what is called "catastrophic backtracking".
-This depends on the regex and the input.
+This depends on the regex and the input. Small regexes are memoized on short texts to avoid it
+where possible, see [`RegexBuilder::memo_size_limit`].
 
 # Usage
 
@@ -103,26 +104,90 @@ assert_eq!(fields, vec!["a", "b", "c\td    e"]);
 # Syntax
 
 The regex syntax is based on the [regex] crate's, with some additional supported syntax.
+Patterns that were written for Python's `re` module or ECMAScript (JavaScript) can be parsed
+with their syntax and semantics instead, see [`RegexBuilder::syntax_dialect`].
 
 Escapes:
 
 `\h`
-: hex digit (`[0-9A-Fa-f]`) \
+: hex digit (`[0-9A-Fa-f]`), or horizontal whitespace with
+  [`RegexBuilder::h_horizontal_space`] \
 `\H`
-: not hex digit (`[^0-9A-Fa-f]`) \
+: not hex digit (`[^0-9A-Fa-f]`), or not horizontal whitespace \
+`\v`
+: vertical whitespace, i.e. `[\n\x0B\x0C\r\u{85}\u{2028}\u{2029}]` \
+`\V`
+: not vertical whitespace \
 `\e`
 : escape control character (`\x1B`) \
+`\x{41 42}`
+: the code points in hex, which may be several separated by spaces as in Oniguruma \
+`\o{101}`
+: the code points in octal, like `\x{...}` \
+`\N{U+41}`
+: the code point in hex, like in PCRE (names of characters aren't supported) \
+`\N`
+: any character except a newline, even with the `s` flag \
+`\0`, `\012`
+: `\0` followed by up to two octal digits is an octal escape, see
+  [`RegexBuilder::octal_escapes`] for other digits \
+`\Q...\E`
+: match the text in between literally, up to the end of the regex if there is no `\E` \
+`\<`, `\b{start}`
+: start of a word, i.e. a word character follows and no word character precedes \
+`\>`, `\b{end}`
+: end of a word, i.e. a word character precedes and no word character follows \
 `\K`
 : keep text matched so far out of the overall match ([docs](https://www.regular-expressions.info/keep.html))\
 `\G`
-: anchor to where the previous match ended ([docs](https://www.regular-expressions.info/continue.html))
+: anchor to where the previous match ended ([docs](https://www.regular-expressions.info/continue.html)) \
+`\R`
+: line break, i.e. `\r\n` (which isn't split by backtracking), `\n`, `\x0B`, `\x0C`, `\r`, `\u{85}`,
+  `\u{2028}` or `\u{2029}`, see [`RegexBuilder::crlf_line_breaks`] to only match `\r\n`, `\r`
+  and `\n` \
+`\X`
+: extended grapheme cluster, i.e. one user-perceived character such as `e\u{301}` or an emoji
+  sequence, which is never split by backtracking (needs the `unicode` feature, otherwise it
+  matches a single character)
+
+Unicode property escapes work the same in every part of a regex, as in the [regex] crate, e.g.
+`\p{Greek}`, `\p{Script=Greek}`, `\p{scx=Hira}`, `\p{General_Category=Lu}` or binary properties
+such as `\p{Emoji}`. As in Oniguruma and PCRE, `\p{^Greek}` is the same as `\P{Greek}` and
+`\p{L&}` is the same as `\p{LC}`.
+
+POSIX bracket expressions such as `[[:alpha:]]`, `[[:^digit:]]` or `[[:punct:][:space:]]` can be
+used in character classes anywhere in a regex. They match ASCII characters only, see
+[`RegexBuilder::unicode_posix_classes`] to match Unicode characters.
+
+Character classes can be nested and combined with the set operations of the [regex] crate
+anywhere in a regex, i.e. intersection (`[[a-z]&&[^aeiou]]`), difference (`[\p{L}--\p{Lu}]`)
+and symmetric difference (`[a-c~~b-d]`), e.g.:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"([[a-z]&&[^aeiou]])\1").unwrap();
+assert_eq!(re.find("aabb").unwrap().unwrap().as_str(), "bb");
+```
 
 Backreferences:
 
 `\1`
 : match the exact string that the first capture group matched \
 `\2`
-: backref to the second capture group, etc
+: backref to the second capture group, etc \
+`\k<-1>`
+: backref to the last capture group that was opened before, `\k<-2>` to the one before it, etc \
+`\g1`, `\g{1}`, `\g-1`, `\g{-1}`
+: same as `\1` and `\k<-1>` for compatibility with PCRE
+
+Backreferences ignore case if case-insensitive matching is enabled where they are, e.g. with
+`(?i)`. They then use full case folding, so that e.g. `ß` matches `SS`:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"(?i)<(\w+)>.*?</\1>").unwrap();
+assert!(re.is_match("<B>bold</b>").unwrap());
+```
 
 Named capture groups:
 
@@ -130,11 +195,64 @@ Named capture groups:
 : match *exp*, creating capture group named *name* \
 `\k<name>`
 : match the exact string that the capture group named *name* matched \
+`\k'name'`, `\k{name}`, `\g{name}`
+: same as `\k<name>` for compatibility with Oniguruma, PCRE, etc. \
+`(?'name'exp)`
+: same as `(?<name>exp)` for compatibility with Perl, .NET, etc. \
 `(?P<name>exp)`
 : same as `(?<name>exp)` for compatibility with Python, etc. \
 `(?P=name)`
 : same as `\k<name>` for compatibility with Python, etc.
 
+A name that is used again only names the last group with the name, unless the `J` flag allows
+duplicate names, like in PCRE. Then the name refers to the first of its groups that matched,
+both in the pattern and in the captures, and [`Regex::group_indices`] returns all of its groups:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"(?J)(?<year>\d{4})-\d\d|\d\d/(?<year>\d{4})").unwrap();
+let caps = re.captures("12/2024").unwrap().unwrap();
+assert_eq!(&caps["year"], "2024");
+```
+
+Branch reset groups using `(?|exp|exp)`, where the capture groups of each alternative are
+numbered from the same number, e.g.:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"(?|(\d+)-(\d+)|(\d+)/(\d+))\s\1").unwrap();
+let caps = re.captures("7/21 7").unwrap().unwrap();
+assert_eq!(&caps[1], "7");
+assert_eq!(&caps[2], "21");
+```
+
+Subroutine calls and recursion:
+
+`(?1)`
+: match the expression of the first capture group again at the current position, which may
+  refer to the group itself to match recursive structures \
+`(?-1)`, `(?+1)`
+: call the group before or after the current position, e.g. the previous or next group \
+`(?R)` or `(?0)`
+: recurse into the whole regex \
+`(?&name)`
+: call the capture group named *name* \
+`(?P>name)`
+: same as `(?&name)` for compatibility with Python, etc. \
+`(?(DEFINE)exp)`
+: define capture groups in *exp* that are only matched when they are called, e.g.
+  `(?(DEFINE)(?<byte>25[0-5]|2[0-4]\d|1?\d?\d))(?&byte)(?:\.(?&byte)){3}` matches an IPv4 address
+
+Captures set inside a call are reset after the call returns, and a call that would recurse
+without matching anything in between fails instead of recursing forever, e.g.:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"\((?:[^()]|(?R))*\)").unwrap();
+assert_eq!(re.find("f(a(b)(c(d)))").unwrap().unwrap().as_str(), "(a(b)(c(d)))");
+assert!(!re.is_match("((a(b").unwrap());
+```
+
 Look-around assertions for matching without changing the current position:
 
 `(?=exp)`
@@ -156,17 +274,136 @@ assert!(re.is_match("abcc").unwrap());
 assert!(!re.is_match("abc").unwrap());
 ```
 
+The absent operator as in Oniguruma, for matching everything up to something:
+
+`(?~exp)`
+: match the longest text before the next position where *exp* matches, or up to the end of the
+  text if it doesn't match, giving back characters on backtracking; *exp* can't use fancy
+  features
+
+For example, this matches an HTML comment up to the first `-->`:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"<!--(?~-->)-->").unwrap();
+assert_eq!(re.find("<!-- a --> b -->").unwrap().unwrap().as_str(), "<!-- a -->");
+```
+
+Script runs as in PCRE2, e.g. for detecting text that mixes scripts to look like other text:
+
+`(*script_run:exp)` or `(*sr:exp)`
+: match *exp* only if all the characters it matches are from the same script, apart from
+  characters that are used with any script such as punctuation, and all its decimal digits are
+  from the same set of ten; Han can be used together with Hiragana and Katakana, with Hangul or
+  with Bopomofo \
+`(*atomic_script_run:exp)` or `(*asr:exp)`
+: same as `(*sr:(?>exp))`
+
+For example, this doesn't match a word with a Cyrillic `а` among Latin letters:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"^(*sr:\w+)$").unwrap();
+assert!(re.is_match("paypal").unwrap());
+assert!(!re.is_match("p\u{430}ypal").unwrap());
+```
+
 Conditionals - if/then/else:
 
 `(?(1))`
 : continue only if first capture group matched \
 `(?(<name>))`
 : continue only if capture group named *name* matched \
+`(?(name))`
+: same as `(?(<name>))` if there is a capture group named *name* before, like in .NET \
 `(?(1)true_branch|false_branch)`
 : if the first capture group matched then execute the true_branch regex expression, else execute false_branch ([docs](https://www.regular-expressions.info/conditional.html)) \
 `(?(condition)true_branch|false_branch)`
 : if the condition matches then execute the true_branch regex expression, else execute false_branch from the point just before the condition was evaluated
 
+Balancing groups as in .NET, for matching nested constructs:
+
+`(?<name1-name2>exp)`
+: match *exp*, then remove the last capture of the group *name2*, failing if it has none, and
+  capture the text between that capture and *exp* in the group *name1* \
+`(?<-name2>exp)`
+: match *exp*, then remove the last capture of the group *name2*, failing if it has none \
+`(?'name1-name2'exp)`, `(?'-name2'exp)`
+: same as `(?<name1-name2>exp)` and `(?<-name2>exp)`
+
+Each group that a balancing group removes captures from keeps a stack of its captures, and its
+capture in [`Captures`] and for backreferences and conditionals is the last one that hasn't been
+removed, e.g.:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r"^(?:[^()]|(?<open>\()|(?<inner-open>\)))*(?(open)(*FAIL))$").unwrap();
+let caps = re.captures("(a(b)c)").unwrap().unwrap();
+assert_eq!(&caps["inner"], "a(b)c");
+assert!(caps.name("open").is_none());
+assert!(!re.is_match("(a(b)c").unwrap());
+```
+
+Backtracking control verbs:
+
+`(*FAIL)` or `(*F)`
+: always fail, forcing backtracking \
+`(*ACCEPT)`
+: end the match successfully at the current position, closing the enclosing capture groups; in
+  a look-around it ends the look-around instead, and in a subroutine call it returns from the call \
+`(*PRUNE)`
+: when backtracked into, fail the match at the current start position, the search continues at
+  the next position \
+`(*SKIP)`
+: when backtracked into, fail the match and continue searching where `(*SKIP)` was reached \
+`(*THEN)`
+: when backtracked into, continue with the next alternative of the innermost enclosing
+  alternation, or fail the alternation if it was the last one; outside of an alternation it
+  acts like `(*PRUNE)`
+
+For example, `(*SKIP)(*FAIL)` excludes parts of the text from matching:
+
+```
+# use fancy_regex::Regex;
+let re = Regex::new(r#""[^"]*"(*SKIP)(*FAIL)|\w+"#).unwrap();
+let words: Vec<_> = re.find_iter(r#"a "b c" d"#).map(|m| m.unwrap().as_str()).collect();
+assert_eq!(words, vec!["a", "d"]);
+```
+
+Callouts to the function registered with [`RegexBuilder::callout`], as in PCRE:
+
+`(?C)` or `(?C1)`
+: call the function with the callout number, which is 0 if it's omitted \
+`(?C"text")`
+: call the function with a string, which can also be delimited by `` ` ``, `'`, `^`, `%`, `#`,
+  `$` or `{}`; a doubled closing delimiter stands for itself
+
+`(?{name})`
+: call the predicate registered with [`RegexBuilder::predicate`] under the name, and only
+  continue the match if it returns `true`
+
+Fuzzy matching, as in TRE:
+
+`exp{~n}`
+: match *exp* with up to *n* edits, each of which is a character in the text that isn't in
+  *exp* (an insertion), a character of *exp* that is missing in the text (a deletion), or a
+  character in the text in place of one of *exp* (a substitution); where *exp* starts, the
+  match with the fewest edits is preferred
+
+Only the characters matched by literals, `.` and character classes in *exp* are edited, and
+inserted characters have to follow a character of the match of *exp*, as the match could
+start after them instead. The edits of a match are returned by [`Captures::edits`]:
+
+```
+# use fancy_regex::{EditKind, Regex};
+let re = Regex::new(r"\b(?:hello){~1}\b").unwrap();
+let caps = re.captures("say helo").unwrap().unwrap();
+assert_eq!(&caps[0], "helo");
+assert_eq!(caps.cost(), 1);
+assert_eq!(caps.edits()[0].kind(), EditKind::Deletion);
+assert_eq!(caps.edits()[0].range(), 7..7);
+```
+
 [regex]: https://crates.io/crates/regex
 */
 
@@ -174,6 +411,7 @@ Conditionals - if/then/else:
 #![deny(missing_docs)]
 #![deny(missing_debug_implementations)]
 #![cfg_attr(not(feature = "std"), no_std)]
+#![cfg_attr(feature = "pattern", feature(pattern))]
 
 extern crate alloc;
 
@@ -186,30 +424,88 @@ use alloc::vec::Vec;
 
 use core::convert::TryFrom;
 use core::fmt::{Debug, Formatter};
+use core::hash::{Hash, Hasher};
 use core::ops::{Index, Range};
+use core::panic::{RefUnwindSafe, UnwindSafe};
 use core::str::FromStr;
+use core::time::Duration;
 use core::{fmt, usize};
 use regex_automata::meta::Regex as RaRegex;
 use regex_automata::util::captures::Captures as RaCaptures;
+use regex_automata::util::lazy::Lazy;
 use regex_automata::util::syntax::Config as SyntaxConfig;
-use regex_automata::Input as RaInput;
+use regex_automata::{Anchored, Input as RaInput, PatternID};
 
 mod analyze;
+pub mod bytes;
+mod cache;
+#[cfg(feature = "std")]
+mod cached;
+mod callout;
 mod compile;
 mod error;
 mod expand;
+mod fuzzy;
+mod glob;
+mod multi;
+mod onepass;
+#[cfg(feature = "std")]
+mod os_str;
+#[cfg(feature = "rayon")]
+mod par;
 mod parse;
+mod partial;
+#[cfg(feature = "pattern")]
+mod pattern;
+mod prefilter;
+mod regexset;
 mod replacer;
+mod scanner;
+mod script_run;
+mod search;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod utf16;
 mod vm;
 
-use crate::analyze::analyze;
-use crate::compile::compile;
-use crate::parse::{ExprTree, NamedGroups, Parser};
-use crate::vm::{Prog, OPTION_SKIPPED_EMPTY_MATCH};
-
-pub use crate::error::{CompileError, Error, ParseError, Result, RuntimeError};
-pub use crate::expand::Expander;
+use crate::analyze::{analyze, analyze_partial};
+use crate::cache::CacheImpl;
+use crate::callout::{CalloutFn, PredicateFn, Predicates};
+use crate::compile::{compile_partial, compile_with_limits};
+use crate::parse::{
+    group_indices, DuplicateGroups, ExprTree, NamedGroups, Parser, FLAG_BYTES,
+    FLAG_CRLF_LINE_BREAKS, FLAG_ECMASCRIPT, FLAG_ECMASCRIPT_UNICODE, FLAG_ECMASCRIPT_UNICODE_SETS,
+    FLAG_EXPLICIT_CAPTURE, FLAG_H_HORIZONTAL_SPACE, FLAG_OCTAL_ESCAPES, FLAG_PYTHON,
+    FLAG_UNICODE_POSIX_CLASSES,
+};
+use crate::vm::{Prog, Scratch, OPTION_FULL_MATCH, OPTION_SKIPPED_EMPTY_MATCH};
+
+pub use crate::cache::Cache;
+pub use crate::callout::{Callout, CalloutId, CalloutResult};
+pub use crate::error::{
+    CompileError, Error, ExpandError, ExpandErrorKind, ParseError, Result, RuntimeError,
+};
+pub use crate::expand::{
+    CaseMapping, Expander, ExpanderBuilder, ExpansionCaptures, GroupRef, Template,
+};
+pub use crate::fuzzy::{Edit, EditKind};
+pub use crate::glob::GlobOptions;
+pub use crate::multi::{MultiMatch, MultiMatches, MultiRegex};
+#[cfg(feature = "std")]
+pub use crate::os_str::OsCaptures;
+#[cfg(feature = "rayon")]
+pub use crate::par::ParMatches;
+pub use crate::parse::{ExprArena, ExprId};
+pub use crate::partial::PartialMatch;
+#[cfg(feature = "pattern")]
+pub use crate::pattern::RegexSearcher;
+pub use crate::regexset::{RegexSet, SetMatches, SetMatchesIntoIter, SetMatchesIter};
 pub use crate::replacer::{NoExpand, Replacer, ReplacerRef};
+#[cfg(feature = "std")]
+pub use crate::scanner::{ReadCaptureMatches, ReadMatches, StreamCaptures};
+pub use crate::scanner::{Scanner, StreamMatch};
+pub use crate::search::SearchOptions;
+pub use crate::utf16::{Utf16CaptureMatches, Utf16Matches, Utf16Offsets};
 
 const MAX_RECURSION: usize = 64;
 
@@ -220,27 +516,47 @@ const MAX_RECURSION: usize = 64;
 pub struct RegexBuilder(RegexOptions);
 
 /// A compiled regular expression.
+///
+/// Cloning a regex is cheap, as the compiled program is shared between the clones.
 #[derive(Clone)]
 pub struct Regex {
-    inner: RegexImpl,
+    inner: Arc<RegexImpl>,
     named_groups: Arc<NamedGroups>,
+    duplicate_groups: Arc<DuplicateGroups>,
+    /// The program for partial matching, which backtracks into everything but single
+    /// characters, so that it also finds all matches. Only compiled when needed.
+    partial: Arc<LazyProg>,
+    /// The minimum length of a match in bytes, see `min_match_len`
+    min_len: usize,
+    /// The maximum length of a match in bytes, see `max_match_len`
+    max_len: Option<usize>,
 }
 
 // Separate enum because we don't want to expose any of this
-#[derive(Clone)]
 enum RegexImpl {
     // Do we want to box this? It's pretty big...
     Wrap {
         inner: RaRegex,
+        /// The regex anchored at both ends, only compiled when needed for a full match
+        full: Arc<LazyRegex>,
         options: RegexOptions,
     },
     Fancy {
-        prog: Prog,
+        prog: Box<Prog>,
         n_groups: usize,
+        static_captures_len: Option<usize>,
         options: RegexOptions,
     },
 }
 
+type RegexFn = dyn Fn() -> Result<RaRegex> + Send + Sync + UnwindSafe + RefUnwindSafe;
+
+type LazyRegex = Lazy<Result<RaRegex>, Box<RegexFn>>;
+
+type ProgFn = dyn Fn() -> Result<Prog> + Send + Sync + UnwindSafe + RefUnwindSafe;
+
+type LazyProg = Lazy<Result<Prog>, Box<ProgFn>>;
+
 /// A single match of a regex or group in an input text
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Match<'t> {
@@ -264,6 +580,121 @@ pub struct Matches<'r, 't> {
     last_match: Option<usize>,
 }
 
+/// How iterators over the matches of a regex advance past an empty match, see
+/// [`RegexBuilder::empty_match_advance`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EmptyMatchAdvance {
+    /// Continue searching after the next character, like the `regex` crate, Python and
+    /// JavaScript with the `u` flag.
+    #[default]
+    Char,
+    /// Continue searching after the next extended grapheme cluster, so that no match starts
+    /// in the middle of a user-perceived character after an empty match. This needs the
+    /// `unicode` feature, otherwise it's the same as [`Char`](EmptyMatchAdvance::Char).
+    Grapheme,
+    /// Continue searching at the next byte, like the byte regexes of the `regex` crate, so
+    /// that empty matches can be found in the middle of a UTF-8 sequence. This only applies
+    /// to regexes built with [`RegexBuilder::build_bytes`]: a `&str` can't be split in the
+    /// middle of a character, so other regexes advance past the next character instead, like
+    /// with [`Char`](EmptyMatchAdvance::Char).
+    Byte,
+}
+
+/// The syntax and semantics that a pattern is parsed with, see [`RegexBuilder::syntax_dialect`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SyntaxDialect {
+    /// The syntax of this crate, which is based on the `regex` crate's, with the additions of
+    /// Oniguruma, PCRE and .NET described in the [crate documentation](crate#syntax).
+    #[default]
+    Default,
+    /// The syntax and semantics of Python's `re` module, for patterns of `str`:
+    ///
+    /// - `$` also matches before a newline at the end of the text, and `\Z` only matches at the
+    ///   end of the text
+    /// - flags such as `(?i)` have to be at the start of the pattern and apply to all of it,
+    ///   and `(?a)` makes `\d`, `\w`, `\s` and `\b` ASCII-only
+    /// - named groups are also numbered, so that `(?P<name>...)` can be referenced with `\1`
+    /// - `\1` to `\99` are backrefs, and `\0` or three octal digits such as `\101` are
+    ///   octal escapes, as are all numbers in classes
+    /// - `\v` only matches a vertical tab, escapes of other ASCII letters that Python doesn't
+    ///   know are errors, and escapes of other characters such as `\<` match the character
+    /// - in classes, `[`, `&&`, `--` and `~~` have no special meaning
+    /// - possessive quantifiers such as `a*+` are errors
+    Python,
+    /// The syntax and semantics of ECMAScript (JavaScript) `RegExp` without the `u` and `v`
+    /// flags, including the tolerances of its Annex B:
+    ///
+    /// - `.` doesn't match any line terminator (`\n`, `\r`, `\u{2028}` and `\u{2029}`), and
+    ///   `^` and `$` with the `m` flag match at all of them, while `$` without it only matches
+    ///   at the end of the text
+    /// - `\d`, `\w` and `\b` are ASCII-only, and `\s` matches the whitespace and line
+    ///   terminators of ECMAScript
+    /// - `[]` doesn't match anything and `[^]` matches any character, and `[`, `&&` and `~~`
+    ///   have no special meaning in classes
+    /// - a backref to a group that hasn't participated in the match, e.g. because it's after
+    ///   the backref, matches the empty string, and numbered backrefs can be used with named
+    ///   groups, which only use the `(?<name>...)` and `\k<name>` syntax
+    /// - the flags `i`, `m` and `s` can only be set for a group, as in `(?i:...)`, and groups
+    ///   other than those of ECMAScript are errors
+    /// - escapes that ECMAScript doesn't know match the character, e.g. `\a` matches `a`;
+    ///   `\1` to `\7` and `\0` followed by digits are octal escapes if there is no group with
+    ///   the number, and a look-ahead can be repeated
+    /// - a `{` that doesn't start a repetition such as `{2}`, `{2,}` or `{2,3}` matches itself,
+    ///   and possessive quantifiers such as `a*+` are errors
+    ///
+    /// The flags `i`, `m` and `s` can be applied to the whole pattern by putting it in a
+    /// group such as `(?ims:...)`. As with the `u` flag, text is matched by code points
+    /// rather than UTF-16 code units, and unlike in ECMAScript, the captures of a group in a
+    /// repetition aren't reset with each iteration.
+    EcmaScript,
+    /// The syntax and semantics of ECMAScript `RegExp` with the `u` flag: like
+    /// [`EcmaScript`](SyntaxDialect::EcmaScript) without the tolerances of Annex B, so that
+    /// unknown escapes, backrefs to groups that don't exist and a `{`, `}` or `]` that has to
+    /// be escaped are errors, and with `\u{...}` and `\p{...}` escapes.
+    EcmaScriptUnicode,
+    /// The syntax and semantics of ECMAScript `RegExp` with the `v` flag: like
+    /// [`EcmaScriptUnicode`](SyntaxDialect::EcmaScriptUnicode), with nested classes and the
+    /// set operations `&&` and `--` in classes, e.g. `[\p{L}--[a-z]]`, where characters such
+    /// as `(` and `|` have to be escaped and doubled punctuation such as `!!` is reserved.
+    /// Strings in classes (`\q{...}`) and properties of strings aren't supported.
+    EcmaScriptUnicodeSets,
+}
+
+/// The position of an iteration over the matches in a text, so that the iteration can be
+/// paused and later resumed, e.g. with [`Regex::find_iter_from_state`].
+///
+/// The state is only meaningful for the same regex and text as the iteration it came from.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MatchesState {
+    last_end: usize,
+    last_match: Option<usize>,
+}
+
+impl MatchesState {
+    /// Create a state from the parts returned by [`last_end`](MatchesState::last_end) and
+    /// [`last_match`](MatchesState::last_match), e.g. after persisting them.
+    pub fn from_parts(last_end: usize, last_match: Option<usize>) -> MatchesState {
+        MatchesState {
+            last_end,
+            last_match,
+        }
+    }
+
+    /// Returns the byte position where the search for the next match starts. This is past the
+    /// end of the last match if it was empty.
+    pub fn last_end(&self) -> usize {
+        self.last_end
+    }
+
+    /// Returns the byte position where the last match ended, or `None` if there was no match
+    /// yet. An empty match at this position is skipped.
+    pub fn last_match(&self) -> Option<usize> {
+        self.last_match
+    }
+}
+
 impl<'r, 't> Matches<'r, 't> {
     /// Return the text being searched.
     pub fn text(&self) -> &'t str {
@@ -274,13 +705,22 @@ impl<'r, 't> Matches<'r, 't> {
     pub fn regex(&self) -> &'r Regex {
         &self.re
     }
+
+    /// Returns the current position of the iteration, to resume it later with
+    /// [`Regex::find_iter_from_state`].
+    pub fn state(&self) -> MatchesState {
+        MatchesState {
+            last_end: self.last_end,
+            last_match: self.last_match,
+        }
+    }
 }
 
 impl<'r, 't> Iterator for Matches<'r, 't> {
     type Item = Result<Match<'t>>;
 
     /// Adapted from the `regex` crate. Calls `find_from_pos` repeatedly.
-    /// Ignores empty matches immediately after a match.
+    /// Ignores empty matches immediately after a match, unless configured otherwise.
     fn next(&mut self) -> Option<Self::Item> {
         if self.last_end > self.text.len() {
             return None;
@@ -309,10 +749,12 @@ impl<'r, 't> Iterator for Matches<'r, 't> {
             // This is an empty match. To ensure we make progress, start
             // the next search at the smallest possible starting position
             // of the next match following this one.
-            self.last_end = next_utf8(self.text, mat.end);
+            self.last_end = self
+                .re
+                .next_after_empty(self.text.as_bytes(), self.last_end, mat.end);
             // Don't accept empty matches immediately following a match.
             // Just move on to the next match.
-            if Some(mat.end) == self.last_match {
+            if Some(mat.end) == self.last_match && self.re.skips_empty_after_match() {
                 return self.next();
             }
         } else {
@@ -325,6 +767,73 @@ impl<'r, 't> Iterator for Matches<'r, 't> {
     }
 }
 
+/// An iterator over all matches for a particular string, including overlapping ones.
+///
+/// The iterator yields a `Result<Match>` for each match, ordered by start and then by end. The
+/// iterator stops when no more matches can be found.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the matched string.
+#[derive(Debug)]
+pub struct OverlappingMatches<'r, 't> {
+    re: &'r Regex,
+    text: &'t str,
+    next_start: usize,
+    /// The matches at the current start that are still to be yielded, last one first
+    pending: Vec<(usize, usize)>,
+}
+
+impl<'r, 't> OverlappingMatches<'r, 't> {
+    /// Return the text being searched.
+    pub fn text(&self) -> &'t str {
+        self.text
+    }
+
+    /// Return the underlying regex.
+    pub fn regex(&self) -> &'r Regex {
+        self.re
+    }
+}
+
+impl<'r, 't> Iterator for OverlappingMatches<'r, 't> {
+    type Item = Result<Match<'t>>;
+
+    /// Yields the next match at the current start, or searches for all matches at the first
+    /// position after it where the regex matches.
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some((start, end)) = self.pending.pop() {
+            return Some(Ok(Match::new(self.text, start, end)));
+        }
+        if self.next_start > self.text.len() {
+            return None;
+        }
+        let prog = match Lazy::get(&self.re.partial) {
+            Ok(prog) => prog,
+            Err(error) => {
+                self.next_start = self.text.len() + 1;
+                return Some(Err(error.clone()));
+            }
+        };
+        let text = self.text.as_bytes();
+        match vm::run_all_matches(prog, text, self.next_start, self.re.options()) {
+            Err(error) => {
+                self.next_start = self.text.len() + 1;
+                Some(Err(error))
+            }
+            Ok(mut matches) => {
+                let start = matches.iter().map(|&(start, _)| start).min()?;
+                self.next_start = next_utf8(text, start);
+                matches.sort_unstable_by(|a, b| b.cmp(a));
+                matches.dedup();
+                self.pending = matches;
+                self.next()
+            }
+        }
+    }
+}
+
+impl<'r, 't> core::iter::FusedIterator for OverlappingMatches<'r, 't> {}
+
 /// An iterator that yields all non-overlapping capture groups matching a
 /// particular regular expression.
 ///
@@ -345,13 +854,19 @@ impl<'r, 't> CaptureMatches<'r, 't> {
     pub fn regex(&self) -> &'r Regex {
         &self.0.re
     }
+
+    /// Returns the current position of the iteration, to resume it later with
+    /// [`Regex::captures_iter_from_state`].
+    pub fn state(&self) -> MatchesState {
+        self.0.state()
+    }
 }
 
 impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
     type Item = Result<Captures<'t>>;
 
     /// Adapted from the `regex` crate. Calls `captures_from_pos` repeatedly.
-    /// Ignores empty matches immediately after a match.
+    /// Ignores empty matches immediately after a match, unless configured otherwise.
     fn next(&mut self) -> Option<Self::Item> {
         if self.0.last_end > self.0.text.len() {
             return None;
@@ -367,8 +882,11 @@ impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
             .get(0)
             .expect("`Captures` is expected to have entire match at 0th position");
         if mat.start == mat.end {
-            self.0.last_end = next_utf8(self.0.text, mat.end);
-            if Some(mat.end) == self.0.last_match {
+            self.0.last_end =
+                self.0
+                    .re
+                    .next_after_empty(self.0.text.as_bytes(), self.0.last_end, mat.end);
+            if Some(mat.end) == self.0.last_match && self.0.re.skips_empty_after_match() {
                 return self.next();
             }
         } else {
@@ -384,20 +902,81 @@ impl<'r, 't> Iterator for CaptureMatches<'r, 't> {
 /// A set of capture groups found for a regex.
 #[derive(Debug)]
 pub struct Captures<'t> {
-    inner: CapturesImpl<'t>,
+    text: &'t str,
+    inner: CapturesImpl,
     named_groups: Arc<NamedGroups>,
+    duplicate_groups: Arc<DuplicateGroups>,
 }
 
+// Shared with `bytes::Captures`, which only differs in the type of the text
 #[derive(Debug)]
-enum CapturesImpl<'t> {
-    Wrap {
-        text: &'t str,
-        locations: RaCaptures,
-    },
-    Fancy {
-        text: &'t str,
-        saves: Vec<usize>,
-    },
+enum CapturesImpl {
+    Wrap { locations: RaCaptures },
+    Fancy { saves: Vec<usize>, edits: Vec<Edit> },
+}
+
+impl CapturesImpl {
+    /// Keeps the captures of the groups from the saves of a match of `prog`, along with the
+    /// edits that its fuzzy groups made.
+    fn fancy(prog: &Prog, n_groups: usize, mut saves: Vec<usize>) -> CapturesImpl {
+        let edits = prog.edits(&saves);
+        saves.truncate(n_groups * 2);
+        CapturesImpl::Fancy { saves, edits }
+    }
+
+    fn get(&self, i: usize) -> Option<Range<usize>> {
+        match self {
+            CapturesImpl::Wrap { locations } => locations.get_group(i).map(|span| span.range()),
+            CapturesImpl::Fancy { saves, .. } => {
+                let slot = i * 2;
+                if slot >= saves.len() {
+                    return None;
+                }
+                let lo = saves[slot];
+                if lo == usize::MAX {
+                    return None;
+                }
+                Some(lo..saves[slot + 1])
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            // `group_len` is 0 if there is no match, so ask the regex instead
+            CapturesImpl::Wrap { locations } => locations.group_info().group_len(PatternID::ZERO),
+            CapturesImpl::Fancy { saves, .. } => saves.len() / 2,
+        }
+    }
+
+    fn edits(&self) -> &[Edit] {
+        match self {
+            CapturesImpl::Wrap { .. } => &[],
+            CapturesImpl::Fancy { edits, .. } => edits,
+        }
+    }
+}
+
+/// The locations of capture groups, which can be reused across searches to avoid allocating a
+/// new `Captures` each time.
+///
+/// It is created by [`Regex::capture_locations`] and filled by [`Regex::captures_read`].
+#[derive(Debug)]
+pub struct CaptureLocations(CapturesImpl);
+
+#[allow(clippy::len_without_is_empty)] // follow regex's API
+impl CaptureLocations {
+    /// Returns the start and end byte offsets of the capture group at index `i`, or `None` if
+    /// it didn't participate in the last match or doesn't exist. The index 0 is the whole match.
+    pub fn get(&self, i: usize) -> Option<(usize, usize)> {
+        self.0.get(i).map(|range| (range.start, range.end))
+    }
+
+    /// Returns the number of capture groups, including the whole match, regardless of
+    /// whether they matched.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
 }
 
 /// Iterator for captured groups in order in which they appear in the regex.
@@ -407,6 +986,14 @@ pub struct SubCaptureMatches<'c, 't> {
     i: usize,
 }
 
+/// Iterator for named captured groups in order in which they appear in the regex, created by
+/// [`Captures::iter_named`].
+#[derive(Debug)]
+pub struct NamedCaptureMatches<'c, 't> {
+    caps: &'c Captures<'t>,
+    names: vec::IntoIter<(&'c str, usize)>,
+}
+
 /// An iterator over all substrings delimited by a regex.
 ///
 /// This iterator yields `Result<&'h str>`, where each item is a substring of the
@@ -517,6 +1104,53 @@ impl<'r, 'h> Iterator for SplitN<'r, 'h> {
 
 impl<'r, 'h> core::iter::FusedIterator for SplitN<'r, 'h> {}
 
+/// An iterator over all substrings delimited by a regex, each ending with the match that
+/// delimits it.
+///
+/// This iterator yields `Result<&'h str>`, where each item is a substring of the target
+/// string up to and including the next match of the regular expression, followed by the rest
+/// of the target if it's not empty.
+///
+/// `'r` is the lifetime of the compiled regular expression, and `'h` is the
+/// lifetime of the target string being split.
+///
+/// This iterator can be created by the [`Regex::split_inclusive`] method.
+#[derive(Debug)]
+pub struct SplitInclusive<'r, 'h> {
+    matches: Matches<'r, 'h>,
+    next_start: usize,
+    target: &'h str,
+}
+
+impl<'r, 'h> Iterator for SplitInclusive<'r, 'h> {
+    type Item = Result<&'h str>;
+
+    /// Returns the next substring including the match that ends it, or the remaining part of
+    /// the string if there are no more matches and it's not empty.
+    fn next(&mut self) -> Option<Result<&'h str>> {
+        match self.matches.next() {
+            None => {
+                let len = self.target.len();
+                if self.next_start >= len {
+                    None
+                } else {
+                    let part = &self.target[self.next_start..len];
+                    self.next_start = len;
+                    Some(Ok(part))
+                }
+            }
+            Some(Ok(m)) => {
+                let part = &self.target[self.next_start..m.end()];
+                self.next_start = m.end();
+                Some(Ok(part))
+            }
+            Some(Err(e)) => Some(Err(e)),
+        }
+    }
+}
+
+impl<'r, 'h> core::iter::FusedIterator for SplitInclusive<'r, 'h> {}
+
 #[derive(Clone, Debug)]
 struct RegexOptions {
     pattern: String,
@@ -524,6 +1158,21 @@ struct RegexOptions {
     backtrack_limit: usize,
     delegate_size_limit: Option<usize>,
     delegate_dfa_size_limit: Option<usize>,
+    memo_size_limit: Option<usize>,
+    size_limit: Option<usize>,
+    anchored: bool,
+    leftmost_longest: bool,
+    timeout: Option<Duration>,
+    empty_match_advance: EmptyMatchAdvance,
+    allow_empty_after_match: bool,
+    crlf_line_breaks: bool,
+    h_horizontal_space: bool,
+    unicode_posix_classes: bool,
+    octal_escapes: bool,
+    explicit_capture: bool,
+    syntax_dialect: SyntaxDialect,
+    callout: Option<CalloutFn>,
+    predicates: Predicates,
 }
 
 impl Default for RegexOptions {
@@ -534,7 +1183,130 @@ impl Default for RegexOptions {
             backtrack_limit: 1_000_000,
             delegate_size_limit: None,
             delegate_dfa_size_limit: None,
+            memo_size_limit: None,
+            size_limit: None,
+            anchored: false,
+            leftmost_longest: false,
+            timeout: None,
+            empty_match_advance: EmptyMatchAdvance::Char,
+            allow_empty_after_match: false,
+            crlf_line_breaks: false,
+            h_horizontal_space: false,
+            unicode_posix_classes: false,
+            octal_escapes: false,
+            explicit_capture: false,
+            syntax_dialect: SyntaxDialect::Default,
+            callout: None,
+            predicates: Predicates::new(),
+        }
+    }
+}
+
+impl PartialEq for RegexOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+            && self.syntaxc.get_case_insensitive() == other.syntaxc.get_case_insensitive()
+            && self.syntaxc.get_utf8() == other.syntaxc.get_utf8()
+            && self.backtrack_limit == other.backtrack_limit
+            && self.delegate_size_limit == other.delegate_size_limit
+            && self.delegate_dfa_size_limit == other.delegate_dfa_size_limit
+            && self.memo_size_limit == other.memo_size_limit
+            && self.size_limit == other.size_limit
+            && self.anchored == other.anchored
+            && self.leftmost_longest == other.leftmost_longest
+            && self.timeout == other.timeout
+            && self.empty_match_advance == other.empty_match_advance
+            && self.allow_empty_after_match == other.allow_empty_after_match
+            && self.crlf_line_breaks == other.crlf_line_breaks
+            && self.h_horizontal_space == other.h_horizontal_space
+            && self.unicode_posix_classes == other.unicode_posix_classes
+            && self.octal_escapes == other.octal_escapes
+            && self.explicit_capture == other.explicit_capture
+            && self.syntax_dialect == other.syntax_dialect
+            && self.callout == other.callout
+            && self.predicates == other.predicates
+    }
+}
+
+impl Eq for RegexOptions {}
+
+impl Hash for RegexOptions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state);
+        self.syntaxc.get_case_insensitive().hash(state);
+        self.syntaxc.get_utf8().hash(state);
+        self.backtrack_limit.hash(state);
+        self.delegate_size_limit.hash(state);
+        self.delegate_dfa_size_limit.hash(state);
+        self.memo_size_limit.hash(state);
+        self.size_limit.hash(state);
+        self.anchored.hash(state);
+        self.leftmost_longest.hash(state);
+        self.timeout.hash(state);
+        self.empty_match_advance.hash(state);
+        self.allow_empty_after_match.hash(state);
+        self.crlf_line_breaks.hash(state);
+        self.h_horizontal_space.hash(state);
+        self.unicode_posix_classes.hash(state);
+        self.octal_escapes.hash(state);
+        self.explicit_capture.hash(state);
+        self.syntax_dialect.hash(state);
+    }
+}
+
+impl RegexOptions {
+    /// Returns the input for searching `text` from `pos` with a wrapped regex.
+    fn input<'h>(&self, text: &'h [u8], pos: usize) -> RaInput<'h> {
+        let input = RaInput::new(text).span(pos..text.len());
+        if self.anchored {
+            input.anchored(Anchored::Yes)
+        } else {
+            input
+        }
+    }
+
+    /// Returns an error if `size`, the memory used by the compiled regex, exceeds the size
+    /// limit.
+    fn check_size_limit(&self, size: usize) -> Result<()> {
+        match self.size_limit {
+            Some(limit) if size > limit => {
+                Err(Error::CompileError(CompileError::CompiledTooBig(limit)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the flags for parsing the pattern that are set by the options.
+    fn parse_flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.crlf_line_breaks {
+            flags |= FLAG_CRLF_LINE_BREAKS;
+        }
+        if self.h_horizontal_space {
+            flags |= FLAG_H_HORIZONTAL_SPACE;
+        }
+        if self.unicode_posix_classes {
+            flags |= FLAG_UNICODE_POSIX_CLASSES;
+        }
+        if self.octal_escapes {
+            flags |= FLAG_OCTAL_ESCAPES;
         }
+        if self.explicit_capture {
+            flags |= FLAG_EXPLICIT_CAPTURE;
+        }
+        if !self.syntaxc.get_utf8() {
+            flags |= FLAG_BYTES;
+        }
+        flags |= match self.syntax_dialect {
+            SyntaxDialect::Default => 0,
+            SyntaxDialect::Python => FLAG_PYTHON,
+            SyntaxDialect::EcmaScript => FLAG_ECMASCRIPT,
+            SyntaxDialect::EcmaScriptUnicode => FLAG_ECMASCRIPT | FLAG_ECMASCRIPT_UNICODE,
+            SyntaxDialect::EcmaScriptUnicodeSets => {
+                FLAG_ECMASCRIPT | FLAG_ECMASCRIPT_UNICODE | FLAG_ECMASCRIPT_UNICODE_SETS
+            }
+        };
+        flags
     }
 }
 
@@ -555,6 +1327,19 @@ impl RegexBuilder {
         Regex::new_options(self.0.clone())
     }
 
+    /// Build a [`bytes::Regex`] for matching byte strings.
+    ///
+    /// Unlike a `Regex` converted with `From`, Unicode can be disabled in its pattern with
+    /// `(?-u)`, so that e.g. `\xFF` matches the byte 0xFF instead of the UTF-8 sequence of
+    /// `ÿ`, see the [`bytes`] module.
+    ///
+    /// Returns an [`Error`](enum.Error.html) if the pattern could not be parsed.
+    pub fn build_bytes(&self) -> Result<bytes::Regex> {
+        let mut options = self.0.clone();
+        options.syntaxc = options.syntaxc.utf8(false);
+        Regex::new_options(options).map(bytes::Regex::from)
+    }
+
     /// Override default case insensitive
     /// this is to enable/disable casing via builder instead of a flag within
     /// the raw string provided to the regex builder
@@ -597,24 +1382,423 @@ impl RegexBuilder {
         self.0.delegate_dfa_size_limit = Some(limit);
         self
     }
-}
-
-impl fmt::Debug for Regex {
-    /// Shows the original regular expression.
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
-    }
-}
 
-impl fmt::Display for Regex {
-    /// Shows the original regular expression
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.as_str())
+    /// Memoize where backtracking failed when matching fancy regexes, using a table of at most
+    /// `limit` bytes per search. A state of the match (an instruction at a position in the text)
+    /// that was already reached then fails right away, so that regexes like `(?:a|(?=a)a)*b`
+    /// that backtrack catastrophically take time proportional to the length of the regex times
+    /// the length of the text instead.
+    ///
+    /// Only the states where the rest of the match doesn't depend on the captures of groups or
+    /// on counted repetitions are memoized, and none in regexes with callouts, subroutine calls
+    /// or fuzzy groups. If the table for a search would be larger than `limit`, the search runs
+    /// without memoization. A limit of 0 turns memoization off.
+    ///
+    /// Default is to memoize regexes of a few hundred instructions with a table of at most
+    /// 32 KiB, which is like a bit-state backtracker for small regexes on short texts.
+    ///
+    /// ```rust
+    /// use fancy_regex::RegexBuilder;
+    ///
+    /// let re = RegexBuilder::new(r"(?:a|(?=a)a)*b")
+    ///     .memo_size_limit(1 << 20)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(!re.is_match(&"a".repeat(100)).unwrap());
+    /// ```
+    pub fn memo_size_limit(&mut self, limit: usize) -> &mut Self {
+        self.0.memo_size_limit = Some(limit);
+        self
     }
-}
-
-impl FromStr for Regex {
-    type Err = Error;
+
+    /// Set the approximate limit in bytes of the memory that the compiled regex uses, as
+    /// reported by [`Regex::memory_usage`]. This covers both the program of the backtracking VM
+    /// and the regexes that parts of the regex are delegated to, so it can bound the memory of
+    /// regexes built from untrusted patterns. If the limit is exceeded, building the regex
+    /// returns an error with
+    /// [`CompileError::CompiledTooBig`](enum.CompileError.html#variant.CompiledTooBig). The
+    /// program is checked as it's compiled, so building a huge regex stops early.
+    ///
+    /// Default is no limit other than the
+    /// [`delegate_size_limit`](RegexBuilder::delegate_size_limit) of each delegated regex.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let pattern = r"(\w+)\1";
+    /// assert!(RegexBuilder::new(pattern).size_limit(1 << 20).build().is_ok());
+    /// assert!(RegexBuilder::new(pattern).size_limit(1 << 10).build().is_err());
+    /// ```
+    pub fn size_limit(&mut self, limit: usize) -> &mut Self {
+        self.0.size_limit = Some(limit);
+        self
+    }
+
+    /// Only report matches that start exactly at the position where the search starts, instead
+    /// of scanning forward for the first match. This applies to all searches, e.g. the start
+    /// position of [`Regex::find_from_pos`], or the end of the previous match for
+    /// [`Regex::find_iter`], which makes it useful for tokenizers.
+    ///
+    /// Default is false.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"\s*(\d+|\w+(?=\())").anchored(true).build().unwrap();
+    /// assert!(re.find("f(1)").unwrap().is_some());
+    /// assert!(re.find("(f)").unwrap().is_none());
+    /// let tokens: Vec<_> = re
+    ///     .find_iter("1 f(2) 3")
+    ///     .map(|m| m.unwrap().as_str().trim())
+    ///     .collect();
+    /// assert_eq!(tokens, vec!["1", "f"]);
+    /// ```
+    pub fn anchored(&mut self, yes: bool) -> &mut Self {
+        self.0.anchored = yes;
+        self
+    }
+
+    /// Use POSIX leftmost-longest semantics: of all the matches that start at the leftmost
+    /// position, report the longest one, no matter in which order the alternatives and
+    /// repetitions would otherwise be tried.
+    ///
+    /// Default is false, which means leftmost-first semantics like Perl, where the first
+    /// alternative that leads to a match wins. Note that finding the longest match needs the
+    /// backtracking engine to try every way of matching at a position, which is slower and more
+    /// likely to reach the backtrack limit. Atomic groups and possessive repetitions still
+    /// commit to the first match of their contents.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(a|ab)(c|bcd)(?!x)")
+    ///     .leftmost_longest(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(re.find("abcd").unwrap().unwrap().as_str(), "abcd");
+    /// ```
+    pub fn leftmost_longest(&mut self, yes: bool) -> &mut Self {
+        self.0.leftmost_longest = yes;
+        self
+    }
+
+    /// Limit how long a single search may take for fancy regexes (where backtracking is used).
+    /// If the time runs out, the search returns an error with
+    /// [`RuntimeError::TimeoutExceeded`], regardless of how far it is from the
+    /// [backtrack limit](RegexBuilder::backtrack_limit). The deadline starts anew for each
+    /// search, so each match of an iterator gets the full time.
+    ///
+    /// Parts of the regex that are delegated to the `regex` crate run in linear time, and are
+    /// not interrupted. The clock is only checked every few hundred backtracking steps, so a
+    /// search may run slightly over its time.
+    ///
+    /// Default is no timeout.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Error, RegexBuilder, RuntimeError};
+    /// use std::time::Duration;
+    ///
+    /// let re = RegexBuilder::new(r"(x+x+)+\1?(?=y)")
+    ///     .backtrack_limit(usize::MAX)
+    ///     .timeout(Duration::from_millis(10))
+    ///     .build()
+    ///     .unwrap();
+    /// let result = re.is_match(&"x".repeat(100));
+    /// assert!(matches!(
+    ///     result,
+    ///     Err(Error::RuntimeError(RuntimeError::TimeoutExceeded))
+    /// ));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.0.timeout = Some(timeout);
+        self
+    }
+
+    /// Set how iterators such as [`Regex::find_iter`], [`Regex::captures_iter`] and
+    /// [`Regex::split`] advance past an empty match: the next search starts after the next
+    /// character, after the next grapheme cluster, or at the next byte in a byte string.
+    ///
+    /// Default is [`EmptyMatchAdvance::Char`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{EmptyMatchAdvance, RegexBuilder};
+    /// let re = RegexBuilder::new(r"")
+    ///     .empty_match_advance(EmptyMatchAdvance::Grapheme)
+    ///     .build()
+    ///     .unwrap();
+    /// let starts: Vec<_> = re.find_iter("e\u{301}!").map(|m| m.unwrap().start()).collect();
+    /// assert_eq!(starts, vec![0, 3, 4]);
+    /// ```
+    pub fn empty_match_advance(&mut self, advance: EmptyMatchAdvance) -> &mut Self {
+        self.0.empty_match_advance = advance;
+        self
+    }
+
+    /// Report an empty match that starts where the previous match ended, as Python (since
+    /// 3.7), JavaScript and PCRE do. By default, such empty matches are skipped, like in the
+    /// `regex` crate.
+    ///
+    /// Default is false.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"\w*")
+    ///     .allow_empty_after_match(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let matches: Vec<_> = re.find_iter("ab c").map(|m| m.unwrap().as_str()).collect();
+    /// assert_eq!(matches, vec!["ab", "", "c", ""]);
+    /// ```
+    pub fn allow_empty_after_match(&mut self, yes: bool) -> &mut Self {
+        self.0.allow_empty_after_match = yes;
+        self
+    }
+
+    /// Make `\R` only match `\r\n`, `\r` and `\n`, like PCRE's `BSR_ANYCRLF` option. By
+    /// default, it also matches the other Unicode line breaks: vertical tab, form feed, `U+0085`
+    /// (next line), `U+2028` (line separator) and `U+2029` (paragraph separator).
+    ///
+    /// Default is false.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"a\Rb")
+    ///     .crlf_line_breaks(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(re.is_match("a\r\nb").unwrap());
+    /// assert!(!re.is_match("a\u{2028}b").unwrap());
+    /// ```
+    pub fn crlf_line_breaks(&mut self, yes: bool) -> &mut Self {
+        self.0.crlf_line_breaks = yes;
+        self
+    }
+
+    /// Make `\h` and `\H` match horizontal whitespace and anything else, like in PCRE and Perl,
+    /// instead of hex digits and anything else, like in Oniguruma. Horizontal whitespace is tab,
+    /// space and the other Unicode space separators, such as `U+00A0` (no-break space).
+    ///
+    /// Default is false.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"^\h*(\w+)\h*$")
+    ///     .h_horizontal_space(true)
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = re.captures(" \tword\u{A0}").unwrap().unwrap();
+    /// assert_eq!(&caps[1], "word");
+    /// assert!(!re.is_match("word\n").unwrap());
+    /// ```
+    pub fn h_horizontal_space(&mut self, yes: bool) -> &mut Self {
+        self.0.h_horizontal_space = yes;
+        self
+    }
+
+    /// Make POSIX bracket expressions such as `[[:alpha:]]` or `[[:punct:]]` match Unicode
+    /// characters, e.g. `[[:alpha:]]` matches `\p{Alphabetic}` and `[[:digit:]]` matches
+    /// `\p{Nd}`, following the POSIX compatible definitions of
+    /// [UTS #18](https://unicode.org/reports/tr18/#Compatibility_Properties). This needs the
+    /// `unicode` feature.
+    ///
+    /// Default is false, which means they only match ASCII characters.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"([[:alpha:]]+)\s\1")
+    ///     .unicode_posix_classes(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(re.find("très très").unwrap().unwrap().as_str(), "très très");
+    /// ```
+    pub fn unicode_posix_classes(&mut self, yes: bool) -> &mut Self {
+        self.0.unicode_posix_classes = yes;
+        self
+    }
+
+    /// Make `\` followed by digits an octal escape if it can't be a backreference, like in PCRE.
+    /// Then `\1` to `\9` are still backreferences, and so are larger numbers if there are at
+    /// least that many groups before, but e.g. `\101` matches `A` otherwise. In a character
+    /// class, `\101` is always an octal escape. `\0` followed by up to two octal digits, e.g.
+    /// `\012`, is always an octal escape.
+    ///
+    /// Default is false, which means `\` followed by digits is always a backreference.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(a)\1\101[\102]")
+    ///     .octal_escapes(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert!(re.is_match("aaAB").unwrap());
+    /// ```
+    pub fn octal_escapes(&mut self, yes: bool) -> &mut Self {
+        self.0.octal_escapes = yes;
+        self
+    }
+
+    /// Make plain groups `(...)` non-capturing, so that only named groups capture, like
+    /// `RegexOptions.ExplicitCapture` in .NET. This is the same as starting the pattern with the
+    /// `n` flag, `(?n)`. The named groups are numbered from 1 without the plain groups.
+    ///
+    /// Default is false, which means plain groups capture.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(\w+)@(?<host>\w+)(\.\w+)+")
+    ///     .explicit_capture(true)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(re.captures_len(), 2);
+    /// let caps = re.captures("me@example.com").unwrap().unwrap();
+    /// assert_eq!(&caps[1], "example");
+    /// ```
+    pub fn explicit_capture(&mut self, yes: bool) -> &mut Self {
+        self.0.explicit_capture = yes;
+        self
+    }
+
+    /// Parse the pattern with the syntax and semantics of another regex engine, e.g.
+    /// [`SyntaxDialect::Python`] for patterns that were written for Python's `re` module, or
+    /// [`SyntaxDialect::EcmaScriptUnicode`] for JavaScript patterns with the `u` flag. The
+    /// options of this builder still apply.
+    ///
+    /// Default is [`SyntaxDialect::Default`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{RegexBuilder, SyntaxDialect};
+    /// let re = RegexBuilder::new(r"(?P<word>\w+)$")
+    ///     .syntax_dialect(SyntaxDialect::Python)
+    ///     .build()
+    ///     .unwrap();
+    /// let caps = re.captures("last word\n").unwrap().unwrap();
+    /// assert_eq!(&caps["word"], "word");
+    /// ```
+    pub fn syntax_dialect(&mut self, dialect: SyntaxDialect) -> &mut Self {
+        self.0.syntax_dialect = dialect;
+        self
+    }
+
+    /// Register a function that is called whenever matching reaches a callout in the pattern,
+    /// such as `(?C1)` or `(?C"tag")`. It receives the identifier of the callout, the current
+    /// position and the captures so far, and decides whether the match continues. Without a
+    /// function, callouts are ignored. The function has to be unwind safe, so that the regex
+    /// is too.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{CalloutResult, RegexBuilder};
+    /// // only accept numbers that are even
+    /// let re = RegexBuilder::new(r"\b(\d+)(?C1)\b")
+    ///     .callout(|callout| {
+    ///         let digits = &callout.text()[callout.group(1).unwrap()];
+    ///         if digits.last().map_or(false, |d| d % 2 == 0) {
+    ///             CalloutResult::Continue
+    ///         } else {
+    ///             CalloutResult::Fail
+    ///         }
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// let numbers: Vec<_> = re.find_iter("13 24 35 46").map(|m| m.unwrap().as_str()).collect();
+    /// assert_eq!(numbers, vec!["24", "46"]);
+    /// ```
+    pub fn callout<F>(&mut self, callout: F) -> &mut Self
+    where
+        F: Fn(&Callout<'_>) -> CalloutResult + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0.callout = Some(CalloutFn(Arc::new(callout)));
+        self
+    }
+
+    /// Register a predicate that the pattern can call by name with `(?{name})`, for checks that
+    /// the syntax can't express. It receives the current position, the captures so far and the
+    /// text, like a function registered with [`callout`](Self::callout), with the name as a
+    /// [`CalloutId::String`], and the match only continues if it returns `true`. Calling a
+    /// predicate that isn't registered is an error when the regex is built. Like a callout
+    /// function, the predicate has to be unwind safe.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// // only match identifiers that aren't keywords
+    /// let re = RegexBuilder::new(r"\b\w+\b(?{not_keyword})")
+    ///     .predicate("not_keyword", |callout| {
+    ///         let word = &callout.text()[callout.start()..callout.position()];
+    ///         ![&b"fn"[..], b"let"].contains(&word)
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// let idents: Vec<_> = re.find_iter("let x = f(y)").map(|m| m.unwrap().as_str()).collect();
+    /// assert_eq!(idents, vec!["x", "f", "y"]);
+    /// ```
+    pub fn predicate<F>(&mut self, name: &str, predicate: F) -> &mut Self
+    where
+        F: Fn(&Callout<'_>) -> bool + Send + Sync + UnwindSafe + RefUnwindSafe + 'static,
+    {
+        self.0
+            .predicates
+            .insert(name.to_string(), PredicateFn(Arc::new(predicate)));
+        self
+    }
+}
+
+impl fmt::Debug for Regex {
+    /// Shows the original regular expression.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl fmt::Display for Regex {
+    /// Shows the original regular expression
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl PartialEq for Regex {
+    /// Regexes are equal if they were built from the same pattern with the same options, even
+    /// if they are separate compilations.
+    fn eq(&self, other: &Self) -> bool {
+        self.options() == other.options()
+    }
+}
+
+impl Eq for Regex {}
+
+impl Hash for Regex {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.options().hash(state);
+    }
+}
+
+impl FromStr for Regex {
+    type Err = Error;
 
     /// Attempts to parse a string into a regular expression
     fn from_str(s: &str) -> Result<Regex> {
@@ -635,61 +1819,183 @@ impl Regex {
     }
 
     fn new_options(options: RegexOptions) -> Result<Regex> {
-        let raw_tree = Expr::parse_tree(&options.pattern)?;
-
-        // wrapper to search for re at arbitrary start position,
-        // and to capture the match bounds
-        let tree = ExprTree {
-            expr: Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Any { newline: true }),
-                    lo: 0,
-                    hi: usize::MAX,
-                    greedy: false,
-                },
-                Expr::Group(Box::new(raw_tree.expr)),
-            ]),
-            ..raw_tree
-        };
-
+        let tree = Regex::parse_tree(&options)?;
         let info = analyze(&tree)?;
 
+        let partial_options = options.clone();
+        let partial: Box<ProgFn> = Box::new(move || {
+            let tree = Regex::parse_tree(&partial_options)?;
+            let info = analyze_partial(&tree)?;
+            let mut prog = compile_partial(&info, &tree, &partial_options)?;
+            // the prefilter isn't used for partial matches, only when finding all matches
+            if let Expr::Concat(ref v) = *tree.root() {
+                prog.set_prefilter(prefilter::prefilter(&tree.arena, v[1], &partial_options));
+            }
+            Ok(prog)
+        });
+        let partial = Arc::new(Lazy::new(partial));
+        // the prefix that searches from any position matches the empty text
+        let min_len = info.min_len;
+        let max_len = info.children[1].max_len;
+
         let inner_info = &info.children[1].children[0]; // references inner expr
         if !inner_info.hard {
             // easy case, wrap regex
 
             // we do our own to_str because escapes are different
             let mut re_cooked = String::new();
-            // same as raw_tree.expr above, but it was moved, so traverse to find it
-            let raw_e = match tree.expr {
-                Expr::Concat(ref v) => match v[1] {
-                    Expr::Group(ref child) => child,
+            // the raw expression is inside the wrapper, so traverse to find it
+            let raw_e = match *tree.root() {
+                Expr::Concat(ref v) => match tree[v[1]] {
+                    Expr::Group(child) => &tree[child],
                     _ => unreachable!(),
                 },
                 _ => unreachable!(),
             };
-            raw_e.to_str(&mut re_cooked, 0);
+            raw_e.to_str(&tree.arena, &mut re_cooked, 0);
             let inner = compile::compile_inner(&re_cooked, &options)?;
+            options.check_size_limit(inner.memory_usage())?;
+            let full_options = options.clone();
+            let full: Box<RegexFn> = Box::new(move || {
+                compile::compile_inner(&alloc::format!(r"\A(?:{})\z", re_cooked), &full_options)
+            });
             return Ok(Regex {
-                inner: RegexImpl::Wrap { inner, options },
+                inner: Arc::new(RegexImpl::Wrap {
+                    inner,
+                    full: Arc::new(Lazy::new(full)),
+                    options,
+                }),
                 named_groups: Arc::new(tree.named_groups),
+                duplicate_groups: Arc::new(tree.duplicate_groups),
+                partial,
+                min_len,
+                max_len,
             });
         }
 
-        let prog = compile(&info)?;
+        let mut prog = compile_with_limits(&info, &tree, &options)?;
+        let memoized = match options.memo_size_limit {
+            Some(limit) => limit > 0,
+            None => prog.body.len() <= vm::AUTO_MEMO_MAX_INSNS,
+        };
+        if memoized {
+            prog.memoize();
+        }
+        if let Expr::Concat(ref v) = *tree.root() {
+            prog.set_prefilter(prefilter::prefilter(&tree.arena, v[1], &options));
+            let literal_prefilter = prefilter::literal_prefilter(&tree.arena, v[1], &options);
+            if literal_prefilter.is_none() {
+                prog.set_inner_literal(prefilter::inner_literal(
+                    &tree.arena,
+                    &info.children[1],
+                    &options,
+                ));
+            }
+            prog.set_literal_prefilter(literal_prefilter);
+        }
+        prog.set_min_len(min_len);
+        options.check_size_limit(prog.memory_usage())?;
         Ok(Regex {
-            inner: RegexImpl::Fancy {
-                prog,
+            inner: Arc::new(RegexImpl::Fancy {
+                prog: Box::new(prog),
                 n_groups: info.end_group,
+                static_captures_len: tree.root().static_captures_len(&tree.arena),
                 options,
-            },
+            }),
             named_groups: Arc::new(tree.named_groups),
+            duplicate_groups: Arc::new(tree.duplicate_groups),
+            partial,
+            min_len,
+            max_len,
         })
     }
 
+    /// Parses the pattern of `options`, wrapped to search for it at any position.
+    fn parse_tree(options: &RegexOptions) -> Result<ExprTree> {
+        let mut raw_tree = Parser::parse_with_flags(&options.pattern, options.parse_flags())?;
+        if let Some(name) = raw_tree
+            .predicates
+            .iter()
+            .find(|name| !options.predicates.contains_key(*name))
+        {
+            return Err(Error::CompileError(CompileError::UnknownPredicate(
+                name.clone(),
+            )));
+        }
+
+        // wrapper to search for re at arbitrary start position,
+        // and to capture the match bounds
+        let arena = &mut raw_tree.arena;
+        let prefix = if options.anchored {
+            Expr::Empty
+        } else {
+            Expr::Repeat {
+                child: arena.push(Expr::Any { newline: true }),
+                lo: 0,
+                hi: usize::MAX,
+                greedy: false,
+            }
+        };
+        let children = vec![arena.push(prefix), arena.push(Expr::Group(raw_tree.expr))];
+        raw_tree.expr = arena.push(Expr::Concat(children));
+        Ok(raw_tree)
+    }
+
+    fn options(&self) -> &RegexOptions {
+        match &*self.inner {
+            RegexImpl::Wrap { options, .. } => options,
+            RegexImpl::Fancy { options, .. } => options,
+        }
+    }
+
+    /// Returns whether the regex uses `\G`, so that where it matches depends on the position
+    /// that the search starts at.
+    pub(crate) fn continues_from_previous_match(&self) -> bool {
+        match &*self.inner {
+            RegexImpl::Wrap { .. } => false,
+            RegexImpl::Fancy { prog, .. } => prog.continues_from_previous_match(),
+        }
+    }
+
+    /// Returns the position to continue searching at after an empty match at `i`, found by a
+    /// search that started at `pos`.
+    fn next_after_empty(&self, text: &[u8], pos: usize, i: usize) -> usize {
+        if let RegexImpl::Fancy { prog, .. } = &*self.inner {
+            // With `\K`, the match may have consumed the text before `i`, in which case the
+            // next match can start at `i`.
+            if i > pos && prog.keeps_out() {
+                return i;
+            }
+        }
+        match self.options().empty_match_advance {
+            EmptyMatchAdvance::Char | EmptyMatchAdvance::Byte => next_utf8(text, i),
+            EmptyMatchAdvance::Grapheme => next_grapheme(text, i),
+        }
+    }
+
+    /// Like `next_after_empty`, for `text` that isn't necessarily UTF-8, where
+    /// `EmptyMatchAdvance::Byte` continues at the next byte if the regex matches byte strings.
+    fn next_after_empty_bytes(&self, text: &[u8], pos: usize, i: usize) -> usize {
+        let options = self.options();
+        let next = self.next_after_empty(text, pos, i);
+        if next > i
+            && !options.syntaxc.get_utf8()
+            && options.empty_match_advance == EmptyMatchAdvance::Byte
+        {
+            i + 1
+        } else {
+            next
+        }
+    }
+
+    /// Returns whether an empty match at the end of the previous match is skipped.
+    fn skips_empty_after_match(&self) -> bool {
+        !self.options().allow_empty_after_match
+    }
+
     /// Returns the original string of this regex.
     pub fn as_str(&self) -> &str {
-        match &self.inner {
+        match &*self.inner {
             RegexImpl::Wrap { options, .. } => &options.pattern,
             RegexImpl::Fancy { options, .. } => &options.pattern,
         }
@@ -708,8 +2014,12 @@ impl Regex {
     /// assert!(re.is_match("mirror mirror on the wall").unwrap());
     /// ```
     pub fn is_match(&self, text: &str) -> Result<bool> {
-        match &self.inner {
-            RegexImpl::Wrap { ref inner, .. } => Ok(inner.is_match(text)),
+        self.is_match_bytes(text.as_bytes())
+    }
+
+    fn is_match_bytes(&self, text: &[u8]) -> Result<bool> {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. } => Ok(inner.is_match(options.input(text, 0))),
             RegexImpl::Fancy {
                 ref prog, options, ..
             } => {
@@ -739,11 +2049,127 @@ impl Regex {
     /// assert!(matches.next().is_none());
     /// ```
     pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> Matches<'r, 't> {
+        self.find_iter_from_state(text, MatchesState::default())
+    }
+
+    /// Resumes an iteration over the matches in `text` that was paused at `state`, see
+    /// [`Matches::state`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1|(?=!)").unwrap();
+    /// let text = "aa !bb !";
+    /// let mut matches = re.find_iter(text);
+    /// matches.next();
+    /// matches.next();
+    /// let state = matches.state();
+    ///
+    /// let rest: Vec<_> = re
+    ///     .find_iter_from_state(text, state)
+    ///     .map(|m| m.unwrap().range())
+    ///     .collect();
+    /// assert_eq!(rest, vec![4..6, 7..7]);
+    /// ```
+    pub fn find_iter_from_state<'r, 't>(
+        &'r self,
+        text: &'t str,
+        state: MatchesState,
+    ) -> Matches<'r, 't> {
         Matches {
             re: &self,
             text,
-            last_end: 0,
-            last_match: None,
+            last_end: state.last_end,
+            last_match: state.last_match,
+        }
+    }
+
+    /// Like [`find_iter`](Regex::find_iter), but also returns the range of each match in UTF-16
+    /// code units, as used by JavaScript and the Language Server Protocol.
+    ///
+    /// The offsets are converted with a [`Utf16Offsets`] as the matches are found, so this only
+    /// goes over the text once.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// let ranges: Vec<_> = re
+    ///     .find_iter_utf16("😀aa éé")
+    ///     .map(|result| result.unwrap().1)
+    ///     .collect();
+    /// assert_eq!(ranges, vec![2..4, 5..7]);
+    /// ```
+    pub fn find_iter_utf16<'r, 't>(&'r self, text: &'t str) -> Utf16Matches<'r, 't> {
+        Utf16Matches {
+            matches: self.find_iter(text),
+            offsets: Utf16Offsets::new(text),
+        }
+    }
+
+    /// Like [`captures_iter`](Regex::captures_iter), but also returns the range of each group
+    /// in UTF-16 code units, or `None` for groups that didn't match, see
+    /// [`find_iter_utf16`](Regex::find_iter_utf16).
+    pub fn captures_iter_utf16<'r, 't>(&'r self, text: &'t str) -> Utf16CaptureMatches<'r, 't> {
+        Utf16CaptureMatches {
+            captures: self.captures_iter(text),
+            offsets: Utf16Offsets::new(text),
+        }
+    }
+
+    /// Returns an iterator over all matches in `text`, including ones that overlap.
+    ///
+    /// For each position where the regex can match, every match starting there is reported,
+    /// with each end that backtracking can reach, so unlike [`find_iter`](Regex::find_iter), a
+    /// match can start inside the previous match, and `a|ab` finds both `a` and `ab`. Atomic
+    /// groups and possessive repetitions still commit to the first match of their contents.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\w\1").unwrap();
+    /// let matches: Vec<_> = re
+    ///     .find_overlapping_iter("ababa cdc")
+    ///     .map(|m| m.unwrap().range())
+    ///     .collect();
+    /// assert_eq!(matches, vec![0..3, 1..4, 2..5, 6..9]);
+    /// ```
+    pub fn find_overlapping_iter<'r, 't>(&'r self, text: &'t str) -> OverlappingMatches<'r, 't> {
+        OverlappingMatches {
+            re: self,
+            text,
+            next_start: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Returns the number of non-overlapping matches in `text`, the same as counting the matches
+    /// of [`find_iter`](Regex::find_iter).
+    ///
+    /// Regexes that are delegated to the `regex` crate count the matches without building them
+    /// or reporting them one by one.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\b(\w+) \1\b").unwrap();
+    /// assert_eq!(re.count_matches("a a b c c d d").unwrap(), 3);
+    /// ```
+    pub fn count_matches(&self, text: &str) -> Result<usize> {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. }
+                if options.empty_match_advance != EmptyMatchAdvance::Grapheme
+                    && !options.allow_empty_after_match =>
+            {
+                Ok(inner.find_iter(options.input(text.as_bytes(), 0)).count())
+            }
+            _ => self
+                .find_iter(text)
+                .try_fold(0, |count, m| m.map(|_| count + 1)),
         }
     }
 
@@ -766,166 +2192,777 @@ impl Regex {
         self.find_from_pos(text, 0)
     }
 
-    /// Returns the first match in `text`, starting from the specified byte position `pos`.
-    ///
-    /// # Examples
+    /// Returns the first match in `text`, starting from the specified byte position `pos`.
+    ///
+    /// # Examples
+    ///
+    /// Finding match starting at a position:
+    ///
+    /// ```
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
+    /// let text = "1 test 123\n2 foo";
+    /// let mat = re.find_from_pos(text, 7).unwrap().unwrap();
+    ///
+    /// assert_eq!(mat.start(), 11);
+    /// assert_eq!(mat.end(), 12);
+    /// ```
+    ///
+    /// Note that in some cases this is not the same as using the `find`
+    /// method and passing a slice of the string, see [Regex::captures_from_pos()] for details.
+    pub fn find_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Match<'t>>> {
+        self.find_from_pos_with_option_flags(text, pos, 0)
+    }
+
+    /// Returns the end of the first match in `text`, stopping as soon as a match is known.
+    ///
+    /// This doesn't track capture groups, and for regexes without fancy features it can stop
+    /// before the end of the match that [`find`](Regex::find) would report, so it's faster when
+    /// only the existence and rough position of a match matter.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"a+").unwrap();
+    /// assert_eq!(re.shortest_match("xaaa").unwrap(), Some(2));
+    /// assert_eq!(re.find("xaaa").unwrap().unwrap().end(), 4);
+    /// ```
+    pub fn shortest_match(&self, text: &str) -> Result<Option<usize>> {
+        self.shortest_match_at(text, 0)
+    }
+
+    /// Like [`shortest_match`](Regex::shortest_match), but starts searching at the byte
+    /// position `pos`.
+    pub fn shortest_match_at(&self, text: &str, pos: usize) -> Result<Option<usize>> {
+        let text = text.as_bytes();
+        match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. } => {
+                let input = options.input(text, pos).earliest(true);
+                Ok(inner.search_half(&input).map(|m| m.offset()))
+            }
+            RegexImpl::Fancy { prog, options, .. } => {
+                let result = vm::run(prog, text, pos, 0, options)?;
+                Ok(result.map(|saves| saves[1]))
+            }
+        }
+    }
+
+    /// Returns an iterator over the non-overlapping matches in the text read from `reader`.
+    ///
+    /// The text is read in chunks and matched with a [`Scanner`], so it doesn't need to fit in
+    /// memory, and doesn't need to be valid UTF-8. `max_len` bounds how long matches can be,
+    /// including the context around them that look-arounds inspect; see [`Scanner`] for
+    /// details. Errors from matching are returned as [`io::Error`](std::io::Error)s wrapping
+    /// an [`Error`](enum.Error.html), and end the iteration like read errors.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// let reader = std::io::BufReader::with_capacity(2, &b"xaa bb"[..]);
+    /// let matches: Vec<_> = re
+    ///     .find_read(reader, 16)
+    ///     .map(|m| m.unwrap().range())
+    ///     .collect();
+    /// assert_eq!(matches, vec![1..3, 4..6]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn find_read<R: std::io::BufRead>(&self, reader: R, max_len: usize) -> ReadMatches<'_, R> {
+        ReadMatches::new(self, reader, max_len)
+    }
+
+    /// Returns an iterator over the capture groups of the non-overlapping matches in the text
+    /// read from `reader`, see [`find_read`](Regex::find_read).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<key>\w+)=(?<value>\w+)(?=;)").unwrap();
+    /// let reader = std::io::BufReader::with_capacity(3, &b"a=1; b=2;"[..]);
+    /// let values: Vec<_> = re
+    ///     .captures_read_iter(reader, 16)
+    ///     .map(|caps| caps.unwrap().name("value").unwrap().as_bytes().to_vec())
+    ///     .collect();
+    /// assert_eq!(values, vec![b"1", b"2"]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn captures_read_iter<R: std::io::BufRead>(
+        &self,
+        reader: R,
+        max_len: usize,
+    ) -> ReadCaptureMatches<'_, R> {
+        ReadCaptureMatches::new(self, reader, max_len)
+    }
+
+    /// Returns true if the regex matches the whole of `text`.
+    ///
+    /// This is like wrapping the pattern in `\A(?:...)\z`, without having to edit the pattern
+    /// text. Unlike [`find`](Regex::find) followed by a check of the match bounds, this also
+    /// tries the alternatives that leftmost-first matching would skip.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1|\w+").unwrap();
+    /// assert!(re.is_full_match("aabb").unwrap());
+    /// assert_eq!(re.find("aabb").unwrap().unwrap().as_str(), "aa");
+    /// assert!(!re.is_full_match("aa-").unwrap());
+    /// ```
+    pub fn is_full_match(&self, text: &str) -> Result<bool> {
+        Ok(self.full_captures_impl(text.as_bytes())?.is_some())
+    }
+
+    /// Returns the first match in `text`, or a partial match if the end of `text` is reached
+    /// while a match is still possible, like `PCRE2_PARTIAL_HARD`.
+    ///
+    /// This tells input that is invalid so far (`None`) from input that could still match with
+    /// more text appended (a partial match, which always ends at the end of `text`). As soon as
+    /// a match attempt needs to look past the end of `text`, the partial match is returned, even
+    /// if a complete match could be found otherwise. That includes assertions that look at the
+    /// next character, e.g. `$` and `\b` at the end of `text`, so use
+    /// [`is_full_match`](Regex::is_full_match) to tell whether `text` is complete.
+    ///
+    /// A partial match may start at the position of the search, even in empty text, but it
+    /// never starts at the end of non-empty text.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"^(\d{3})-\1$").unwrap();
+    /// assert!(re.find_partial("123-12").unwrap().unwrap().is_partial());
+    /// assert_eq!(re.find_partial("123-13").unwrap(), None);
+    /// assert_eq!(re.find_partial("12a").unwrap(), None);
+    /// ```
+    pub fn find_partial<'t>(&self, text: &'t str) -> Result<Option<PartialMatch<'t>>> {
+        self.find_partial_from_pos(text, 0)
+    }
+
+    /// Like [`find_partial`](Regex::find_partial), but starts searching at the byte position
+    /// `pos`.
+    pub fn find_partial_from_pos<'t>(
+        &self,
+        text: &'t str,
+        pos: usize,
+    ) -> Result<Option<PartialMatch<'t>>> {
+        let prog = Lazy::get(&self.partial).as_ref().map_err(Clone::clone)?;
+        let result = vm::run_partial(prog, text.as_bytes(), pos, self.options())?;
+        Ok(result.map(|(saves, partial)| {
+            PartialMatch::new(Match::new(text, saves[0], saves[1]), partial)
+        }))
+    }
+
+    /// Returns the capture groups if the regex matches the whole of `text`, see
+    /// [`is_full_match`](Regex::is_full_match).
+    pub fn full_captures<'t>(&self, text: &'t str) -> Result<Option<Captures<'t>>> {
+        let inner = self.full_captures_impl(text.as_bytes())?;
+        Ok(inner.map(|inner| Captures {
+            text,
+            inner,
+            named_groups: self.named_groups.clone(),
+            duplicate_groups: self.duplicate_groups.clone(),
+        }))
+    }
+
+    fn full_captures_impl(&self, text: &[u8]) -> Result<Option<CapturesImpl>> {
+        match &*self.inner {
+            RegexImpl::Wrap { full, .. } => {
+                let full = Lazy::get(full).as_ref().map_err(Clone::clone)?;
+                let mut locations = full.create_captures();
+                full.captures(RaInput::new(text), &mut locations);
+                Ok(locations
+                    .is_match()
+                    .then_some(CapturesImpl::Wrap { locations }))
+            }
+            RegexImpl::Fancy {
+                prog,
+                n_groups,
+                options,
+                ..
+            } => {
+                let result = vm::run_from(
+                    prog,
+                    prog.anchored_start(),
+                    text,
+                    0,
+                    OPTION_FULL_MATCH,
+                    options,
+                )?;
+                Ok(result.map(|saves| CapturesImpl::fancy(prog, *n_groups, saves)))
+            }
+        }
+    }
+
+    /// Returns the last match in `text`, which is the last match returned by
+    /// [`find_iter`](Regex::find_iter).
+    ///
+    /// Which matches `find_iter` returns depends on the matches before them, e.g. `aa` matches
+    /// `0..2` and `2..4` in `"aaaaa"` but not `3..5`, so the text is searched from the start, in
+    /// a single pass like `find_iter`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// assert_eq!(re.rfind("aa bb cc dd").unwrap().unwrap().as_str(), "dd");
+    /// assert_eq!(re.rfind("xaaa").unwrap().unwrap().range(), 1..3);
+    /// ```
+    pub fn rfind<'t>(&self, text: &'t str) -> Result<Option<Match<'t>>> {
+        let range = self.rsearch(text)?;
+        Ok(range.map(|range| Match::new(text, range.start, range.end)))
+    }
+
+    /// Returns the capture groups for the match returned by [`rfind`](Regex::rfind).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w+)=(\d+)(?=;)").unwrap();
+    /// let caps = re.rcaptures("a=1; b=2; c=3").unwrap().unwrap();
+    /// assert_eq!(&caps[1], "b");
+    /// assert_eq!(&caps[2], "2");
+    /// ```
+    pub fn rcaptures<'t>(&self, text: &'t str) -> Result<Option<Captures<'t>>> {
+        let start = match self.rsearch(text)? {
+            Some(range) => range.start,
+            None => return Ok(None),
+        };
+        let mut inner = match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => CapturesImpl::Wrap {
+                locations: inner.create_captures(),
+            },
+            RegexImpl::Fancy { .. } => CapturesImpl::Fancy {
+                saves: Vec::new(),
+                edits: Vec::new(),
+            },
+        };
+        self.captures_read_anchored(&mut inner, text.as_bytes(), start)?;
+        Ok(Some(Captures {
+            text,
+            inner,
+            named_groups: self.named_groups.clone(),
+            duplicate_groups: self.duplicate_groups.clone(),
+        }))
+    }
+
+    /// Searches for the last match of [`find_iter`](Regex::find_iter).
+    fn rsearch(&self, text: &str) -> Result<Option<Range<usize>>> {
+        let mut last = None;
+        for mat in self.find_iter(text) {
+            last = Some(mat?.range());
+        }
+        Ok(last)
+    }
+
+    /// Like [`captures_read_impl`](Regex::captures_read_impl), but only for a match that starts
+    /// exactly at `pos`.
+    fn captures_read_anchored(
+        &self,
+        locations: &mut CapturesImpl,
+        text: &[u8],
+        pos: usize,
+    ) -> Result<bool> {
+        match (&*self.inner, locations) {
+            (RegexImpl::Wrap { inner, options, .. }, CapturesImpl::Wrap { locations }) => {
+                let input = options.input(text, pos).anchored(Anchored::Yes);
+                inner.captures(input, locations);
+                Ok(locations.is_match())
+            }
+            (
+                RegexImpl::Fancy {
+                    prog,
+                    n_groups,
+                    options,
+                    ..
+                },
+                CapturesImpl::Fancy { saves, edits },
+            ) => match vm::run_from(prog, prog.anchored_start(), text, pos, 0, options)? {
+                Some(mut result) => {
+                    *edits = prog.edits(&result);
+                    result.truncate(n_groups * 2);
+                    *saves = result;
+                    Ok(true)
+                }
+                None => {
+                    saves.iter_mut().for_each(|slot| *slot = usize::MAX);
+                    edits.clear();
+                    Ok(false)
+                }
+            },
+            _ => panic!("capture locations were created by a different regex"),
+        }
+    }
+
+    fn find_from_pos_with_option_flags<'t>(
+        &self,
+        text: &'t str,
+        pos: usize,
+        option_flags: u32,
+    ) -> Result<Option<Match<'t>>> {
+        let range = self.search_from_pos(text.as_bytes(), pos, option_flags)?;
+        Ok(range.map(|range| Match::new(text, range.start, range.end)))
+    }
+
+    fn search_from_pos(
+        &self,
+        text: &[u8],
+        pos: usize,
+        option_flags: u32,
+    ) -> Result<Option<Range<usize>>> {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. } => {
+                Ok(inner.search(&options.input(text, pos)).map(|m| m.range()))
+            }
+            RegexImpl::Fancy { prog, options, .. } => {
+                let result = vm::run(prog, text, pos, option_flags, options)?;
+                Ok(result.map(|saves| saves[0]..saves[1]))
+            }
+        }
+    }
+
+    /// Returns an iterator over all the non-overlapping capture groups matched in `text`.
+    ///
+    /// # Examples
+    ///
+    /// Finding all matches and capturing parts of each:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    ///
+    /// let re = Regex::new(r"(\d{4})-(\d{2})").unwrap();
+    /// let text = "It was between 2018-04 and 2020-01";
+    /// let mut all_captures = re.captures_iter(text);
+    ///
+    /// let first = all_captures.next().unwrap().unwrap();
+    /// assert_eq!(first.get(1).unwrap().as_str(), "2018");
+    /// assert_eq!(first.get(2).unwrap().as_str(), "04");
+    /// assert_eq!(first.get(0).unwrap().as_str(), "2018-04");
+    ///
+    /// let second = all_captures.next().unwrap().unwrap();
+    /// assert_eq!(second.get(1).unwrap().as_str(), "2020");
+    /// assert_eq!(second.get(2).unwrap().as_str(), "01");
+    /// assert_eq!(second.get(0).unwrap().as_str(), "2020-01");
+    ///
+    /// assert!(all_captures.next().is_none());
+    /// ```
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
+        CaptureMatches(self.find_iter(text))
+    }
+
+    /// Resumes an iteration over the captures in `text` that was paused at `state`, see
+    /// [`CaptureMatches::state`].
+    pub fn captures_iter_from_state<'r, 't>(
+        &'r self,
+        text: &'t str,
+        state: MatchesState,
+    ) -> CaptureMatches<'r, 't> {
+        CaptureMatches(self.find_iter_from_state(text, state))
+    }
+
+    /// Returns the capture groups for the first match in `text`.
+    ///
+    /// If no match is found, then `Ok(None)` is returned.
+    ///
+    /// # Examples
+    ///
+    /// Finding matches and capturing parts of the match:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    ///
+    /// let re = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").unwrap();
+    /// let text = "The date was 2018-04-07";
+    /// let captures = re.captures(text).unwrap().unwrap();
+    ///
+    /// assert_eq!(captures.get(1).unwrap().as_str(), "2018");
+    /// assert_eq!(captures.get(2).unwrap().as_str(), "04");
+    /// assert_eq!(captures.get(3).unwrap().as_str(), "07");
+    /// assert_eq!(captures.get(0).unwrap().as_str(), "2018-04-07");
+    /// ```
+    pub fn captures<'t>(&self, text: &'t str) -> Result<Option<Captures<'t>>> {
+        self.captures_from_pos(text, 0)
+    }
+
+    /// Returns the capture groups for the first match in `text`, starting from
+    /// the specified byte position `pos`.
+    ///
+    /// # Examples
+    ///
+    /// Finding captures starting at a position:
+    ///
+    /// ```
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
+    /// let text = "1 test 123\n2 foo";
+    /// let captures = re.captures_from_pos(text, 7).unwrap().unwrap();
+    ///
+    /// let group = captures.get(1).unwrap();
+    /// assert_eq!(group.as_str(), "2");
+    /// assert_eq!(group.start(), 11);
+    /// assert_eq!(group.end(), 12);
+    /// ```
+    ///
+    /// Note that in some cases this is not the same as using the `captures`
+    /// method and passing a slice of the string, see the capture that we get
+    /// when we do this:
+    ///
+    /// ```
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
+    /// let text = "1 test 123\n2 foo";
+    /// let captures = re.captures(&text[7..]).unwrap().unwrap();
+    /// assert_eq!(captures.get(1).unwrap().as_str(), "123");
+    /// ```
+    ///
+    /// This matched the number "123" because it's at the beginning of the text
+    /// of the string slice.
+    ///
+    pub fn captures_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Captures<'t>>> {
+        let inner = self.captures_impl_from_pos(text.as_bytes(), pos)?;
+        Ok(inner.map(|inner| Captures {
+            text,
+            inner,
+            named_groups: self.named_groups.clone(),
+            duplicate_groups: self.duplicate_groups.clone(),
+        }))
+    }
+
+    fn captures_impl_from_pos(&self, text: &[u8], pos: usize) -> Result<Option<CapturesImpl>> {
+        let mut locations = match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => CapturesImpl::Wrap {
+                locations: inner.create_captures(),
+            },
+            // filled in by the search, so don't allocate up front
+            RegexImpl::Fancy { .. } => CapturesImpl::Fancy {
+                saves: Vec::new(),
+                edits: Vec::new(),
+            },
+        };
+        let found = self.captures_read_impl(&mut locations, text, pos, None)?;
+        Ok(found.then_some(locations))
+    }
+
+    /// Returns an empty set of capture locations that can be reused in calls to
+    /// [`captures_read`](Regex::captures_read).
+    pub fn capture_locations(&self) -> CaptureLocations {
+        CaptureLocations(match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => CapturesImpl::Wrap {
+                locations: inner.create_captures(),
+            },
+            RegexImpl::Fancy { n_groups, .. } => CapturesImpl::Fancy {
+                saves: vec![usize::MAX; n_groups * 2],
+                edits: Vec::new(),
+            },
+        })
+    }
+
+    /// Searches for the first match in `text` and stores the locations of its capture groups in
+    /// `locations`, which must have been created by [`capture_locations`] on this regex.
+    ///
+    /// This is like [`captures`](Regex::captures), but reuses `locations` instead of
+    /// allocating a new `Captures` for each search. Returns the overall match, or `Ok(None)` if
+    /// there is no match, in which case all groups in `locations` are unset.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(\w)\1(\d)?").unwrap();
+    /// let mut locations = re.capture_locations();
+    /// for text in ["xaa1", "bb"] {
+    ///     re.captures_read(&mut locations, text).unwrap().unwrap();
+    /// }
+    /// assert_eq!(locations.get(1), Some((0, 1)));
+    /// assert_eq!(locations.get(2), None);
+    /// ```
+    ///
+    /// [`capture_locations`]: Regex::capture_locations
+    pub fn captures_read<'t>(
+        &self,
+        locations: &mut CaptureLocations,
+        text: &'t str,
+    ) -> Result<Option<Match<'t>>> {
+        self.captures_read_at(locations, text, 0)
+    }
+
+    /// Like [`captures_read`](Regex::captures_read), but starts searching at the byte position
+    /// `pos`, see [`captures_from_pos`](Regex::captures_from_pos).
+    pub fn captures_read_at<'t>(
+        &self,
+        locations: &mut CaptureLocations,
+        text: &'t str,
+        pos: usize,
+    ) -> Result<Option<Match<'t>>> {
+        if !self.captures_read_impl(&mut locations.0, text.as_bytes(), pos, None)? {
+            return Ok(None);
+        }
+        Ok(locations
+            .get(0)
+            .map(|(start, end)| Match::new(text, start, end)))
+    }
+
+    /// Returns a cache for searching with this regex, which keeps the memory that searches
+    /// need between them, see [`Cache`].
+    pub fn create_cache(&self) -> Cache {
+        Cache(match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => CacheImpl::Wrap(Box::new(inner.create_cache())),
+            RegexImpl::Fancy { .. } => CacheImpl::Fancy(Box::new(Scratch::new())),
+        })
+    }
+
+    /// Like [`is_match`](Regex::is_match), but uses `cache`, which must have been created by
+    /// [`create_cache`](Regex::create_cache) on this regex.
+    pub fn is_match_with_cache(&self, cache: &mut Cache, text: &str) -> Result<bool> {
+        let text = text.as_bytes();
+        match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. } => {
+                let input = options.input(text, 0).earliest(true);
+                Ok(inner.search_half_with(cache.wrap(), &input).is_some())
+            }
+            RegexImpl::Fancy { prog, options, .. } => {
+                vm::run_with_scratch(prog, cache.scratch(), text, 0, 0, options)
+            }
+        }
+    }
+
+    /// Like [`find`](Regex::find), but uses `cache`, which must have been created by
+    /// [`create_cache`](Regex::create_cache) on this regex.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"\w+(?=!)").unwrap();
+    /// let mut cache = re.create_cache();
+    /// for text in ["so fancy!", "even with! a cache"] {
+    ///     let mat = re.find_with_cache(&mut cache, text).unwrap().unwrap();
+    ///     assert!(text[mat.end()..].starts_with('!'));
+    /// }
+    /// ```
+    pub fn find_with_cache<'t>(
+        &self,
+        cache: &mut Cache,
+        text: &'t str,
+    ) -> Result<Option<Match<'t>>> {
+        self.find_from_pos_with_cache(cache, text, 0)
+    }
+
+    /// Like [`find_from_pos`](Regex::find_from_pos), but uses `cache`, which must have been
+    /// created by [`create_cache`](Regex::create_cache) on this regex.
+    pub fn find_from_pos_with_cache<'t>(
+        &self,
+        cache: &mut Cache,
+        text: &'t str,
+        pos: usize,
+    ) -> Result<Option<Match<'t>>> {
+        let bytes = text.as_bytes();
+        let range = match &*self.inner {
+            RegexImpl::Wrap { inner, options, .. } => inner
+                .search_with(cache.wrap(), &options.input(bytes, pos))
+                .map(|m| m.range()),
+            RegexImpl::Fancy { prog, options, .. } => {
+                let scratch = cache.scratch();
+                vm::run_with_scratch(prog, scratch, bytes, pos, 0, options)?
+                    .then(|| scratch.saves()[0]..scratch.saves()[1])
+            }
+        };
+        Ok(range.map(|range| Match::new(text, range.start, range.end)))
+    }
+
+    /// Like [`captures_read`](Regex::captures_read), but uses `cache`, which must have been
+    /// created by [`create_cache`](Regex::create_cache) on this regex. Together with reusing
+    /// `locations`, this makes searching for captures not allocate once the cache is big
+    /// enough.
     ///
-    /// Finding match starting at a position:
+    /// # Example
     ///
-    /// ```
+    /// ```rust
     /// # use fancy_regex::Regex;
-    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
-    /// let text = "1 test 123\n2 foo";
-    /// let mat = re.find_from_pos(text, 7).unwrap().unwrap();
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// let mut cache = re.create_cache();
+    /// let mut locations = re.capture_locations();
+    /// let mut found = Vec::new();
+    /// for text in ["xaa", "ab", "cbb"] {
+    ///     if re.captures_read_with_cache(&mut cache, &mut locations, text).unwrap().is_some() {
+    ///         found.push(locations.get(1).unwrap());
+    ///     }
+    /// }
     ///
-    /// assert_eq!(mat.start(), 11);
-    /// assert_eq!(mat.end(), 12);
+    /// assert_eq!(found, vec![(1, 2), (1, 2)]);
     /// ```
-    ///
-    /// Note that in some cases this is not the same as using the `find`
-    /// method and passing a slice of the string, see [Regex::captures_from_pos()] for details.
-    pub fn find_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Match<'t>>> {
-        self.find_from_pos_with_option_flags(text, pos, 0)
+    pub fn captures_read_with_cache<'t>(
+        &self,
+        cache: &mut Cache,
+        locations: &mut CaptureLocations,
+        text: &'t str,
+    ) -> Result<Option<Match<'t>>> {
+        self.captures_read_at_with_cache(cache, locations, text, 0)
     }
 
-    fn find_from_pos_with_option_flags<'t>(
+    /// Like [`captures_read_at`](Regex::captures_read_at), but uses `cache`, which must have
+    /// been created by [`create_cache`](Regex::create_cache) on this regex.
+    pub fn captures_read_at_with_cache<'t>(
         &self,
+        cache: &mut Cache,
+        locations: &mut CaptureLocations,
         text: &'t str,
         pos: usize,
-        option_flags: u32,
     ) -> Result<Option<Match<'t>>> {
-        match &self.inner {
-            RegexImpl::Wrap { inner, .. } => Ok(inner
-                .search(&RaInput::new(text).span(pos..text.len()))
-                .map(|m| Match::new(text, m.start(), m.end()))),
-            RegexImpl::Fancy { prog, options, .. } => {
-                let result = vm::run(prog, text, pos, option_flags, options)?;
-                Ok(result.map(|saves| Match::new(text, saves[0], saves[1])))
+        if !self.captures_read_impl(&mut locations.0, text.as_bytes(), pos, Some(cache))? {
+            return Ok(None);
+        }
+        Ok(locations
+            .get(0)
+            .map(|(start, end)| Match::new(text, start, end)))
+    }
+
+    fn captures_read_impl(
+        &self,
+        locations: &mut CapturesImpl,
+        text: &[u8],
+        pos: usize,
+        cache: Option<&mut Cache>,
+    ) -> Result<bool> {
+        match (&*self.inner, locations) {
+            (RegexImpl::Wrap { inner, options, .. }, CapturesImpl::Wrap { locations }) => {
+                let input = options.input(text, pos);
+                match cache {
+                    Some(cache) => inner.search_captures_with(cache.wrap(), &input, locations),
+                    None => inner.captures(input, locations),
+                }
+                Ok(locations.is_match())
+            }
+            (
+                RegexImpl::Fancy {
+                    prog,
+                    n_groups,
+                    options,
+                    ..
+                },
+                CapturesImpl::Fancy { saves, edits },
+            ) => {
+                let mut new_scratch;
+                let scratch = match cache {
+                    Some(cache) => cache.scratch(),
+                    None => {
+                        new_scratch = Scratch::new();
+                        &mut new_scratch
+                    }
+                };
+                if vm::run_with_scratch(prog, scratch, text, pos, 0, options)? {
+                    *edits = prog.edits(scratch.saves());
+                    saves.clear();
+                    saves.extend_from_slice(&scratch.saves()[..n_groups * 2]);
+                    Ok(true)
+                } else {
+                    saves.iter_mut().for_each(|slot| *slot = usize::MAX);
+                    edits.clear();
+                    Ok(false)
+                }
             }
+            _ => panic!("capture locations were created by a different regex"),
         }
     }
 
-    /// Returns an iterator over all the non-overlapping capture groups matched in `text`.
-    ///
-    /// # Examples
+    /// Returns the number of captures, including the implicit capture of the entire expression.
+    pub fn captures_len(&self) -> usize {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => inner.captures_len(),
+            RegexImpl::Fancy { n_groups, .. } => *n_groups,
+        }
+    }
+
+    /// Returns the number of captures that participate in every match, including the implicit
+    /// capture of the entire expression, or `None` if it depends on the match.
     ///
-    /// Finding all matches and capturing parts of each:
+    /// # Example
     ///
     /// ```rust
     /// # use fancy_regex::Regex;
-    ///
-    /// let re = Regex::new(r"(\d{4})-(\d{2})").unwrap();
-    /// let text = "It was between 2018-04 and 2020-01";
-    /// let mut all_captures = re.captures_iter(text);
-    ///
-    /// let first = all_captures.next().unwrap().unwrap();
-    /// assert_eq!(first.get(1).unwrap().as_str(), "2018");
-    /// assert_eq!(first.get(2).unwrap().as_str(), "04");
-    /// assert_eq!(first.get(0).unwrap().as_str(), "2018-04");
-    ///
-    /// let second = all_captures.next().unwrap().unwrap();
-    /// assert_eq!(second.get(1).unwrap().as_str(), "2020");
-    /// assert_eq!(second.get(2).unwrap().as_str(), "01");
-    /// assert_eq!(second.get(0).unwrap().as_str(), "2020-01");
-    ///
-    /// assert!(all_captures.next().is_none());
+    /// let re = Regex::new(r"(\w)(\d)\1").unwrap();
+    /// assert_eq!(re.static_captures_len(), Some(3));
+    /// let re = Regex::new(r"(\w)(\d)?\1").unwrap();
+    /// assert_eq!(re.static_captures_len(), None);
     /// ```
-    pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> CaptureMatches<'r, 't> {
-        CaptureMatches(self.find_iter(text))
+    pub fn static_captures_len(&self) -> Option<usize> {
+        match &*self.inner {
+            RegexImpl::Wrap { inner, .. } => inner.static_captures_len(),
+            RegexImpl::Fancy {
+                static_captures_len,
+                ..
+            } => *static_captures_len,
+        }
     }
 
-    /// Returns the capture groups for the first match in `text`.
-    ///
-    /// If no match is found, then `Ok(None)` is returned.
+    /// Returns a lower bound on the length of a match in bytes, so that text shorter than it
+    /// can't contain a match.
     ///
-    /// # Examples
+    /// The length of a match with `\K` includes the text before `\K`, which the match needs
+    /// too. Look-arounds don't add to the length.
     ///
-    /// Finding matches and capturing parts of the match:
+    /// # Example
     ///
     /// ```rust
     /// # use fancy_regex::Regex;
-    ///
-    /// let re = Regex::new(r"(\d{4})-(\d{2})-(\d{2})").unwrap();
-    /// let text = "The date was 2018-04-07";
-    /// let captures = re.captures(text).unwrap().unwrap();
-    ///
-    /// assert_eq!(captures.get(1).unwrap().as_str(), "2018");
-    /// assert_eq!(captures.get(2).unwrap().as_str(), "04");
-    /// assert_eq!(captures.get(3).unwrap().as_str(), "07");
-    /// assert_eq!(captures.get(0).unwrap().as_str(), "2018-04-07");
+    /// let re = Regex::new(r"ab\d+(?=!)").unwrap();
+    /// assert_eq!(re.min_match_len(), 3);
+    /// let re = Regex::new(r"(?<=a)b*").unwrap();
+    /// assert_eq!(re.min_match_len(), 0);
     /// ```
-    pub fn captures<'t>(&self, text: &'t str) -> Result<Option<Captures<'t>>> {
-        self.captures_from_pos(text, 0)
+    pub fn min_match_len(&self) -> usize {
+        self.min_len
     }
 
-    /// Returns the capture groups for the first match in `text`, starting from
-    /// the specified byte position `pos`.
+    /// Returns an upper bound on the length of a match in bytes, or `None` if matches can be
+    /// arbitrarily long, e.g. because of `*` or a backref.
     ///
-    /// # Examples
-    ///
-    /// Finding captures starting at a position:
+    /// # Example
     ///
-    /// ```
+    /// ```rust
     /// # use fancy_regex::Regex;
-    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
-    /// let text = "1 test 123\n2 foo";
-    /// let captures = re.captures_from_pos(text, 7).unwrap().unwrap();
-    ///
-    /// let group = captures.get(1).unwrap();
-    /// assert_eq!(group.as_str(), "2");
-    /// assert_eq!(group.start(), 11);
-    /// assert_eq!(group.end(), 12);
+    /// let re = Regex::new(r"(?:ab|c){1,3}(?!d)").unwrap();
+    /// assert_eq!(re.max_match_len(), Some(6));
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// assert_eq!(re.max_match_len(), None);
     /// ```
+    pub fn max_match_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Returns the number of instructions of the program that the backtracking VM runs for
+    /// this regex, or 0 if the whole regex is delegated to the regex crate.
     ///
-    /// Note that in some cases this is not the same as using the `captures`
-    /// method and passing a slice of the string, see the capture that we get
-    /// when we do this:
+    /// # Example
     ///
-    /// ```
+    /// ```rust
     /// # use fancy_regex::Regex;
-    /// let re = Regex::new(r"(?m:^)(\d+)").unwrap();
-    /// let text = "1 test 123\n2 foo";
-    /// let captures = re.captures(&text[7..]).unwrap().unwrap();
-    /// assert_eq!(captures.get(1).unwrap().as_str(), "123");
+    /// assert_eq!(Regex::new(r"\w+").unwrap().program_size(), 0);
+    /// assert!(Regex::new(r"(\w+)\1").unwrap().program_size() > 0);
     /// ```
-    ///
-    /// This matched the number "123" because it's at the beginning of the text
-    /// of the string slice.
-    ///
-    pub fn captures_from_pos<'t>(&self, text: &'t str, pos: usize) -> Result<Option<Captures<'t>>> {
-        let named_groups = self.named_groups.clone();
-        match &self.inner {
-            RegexImpl::Wrap { inner, .. } => {
-                let mut locations = inner.create_captures();
-                inner.captures(RaInput::new(text).span(pos..text.len()), &mut locations);
-                Ok(locations.is_match().then(|| Captures {
-                    inner: CapturesImpl::Wrap { text, locations },
-                    named_groups,
-                }))
-            }
-            RegexImpl::Fancy {
-                prog,
-                n_groups,
-                options,
-                ..
-            } => {
-                let result = vm::run(prog, text, pos, 0, options)?;
-                Ok(result.map(|mut saves| {
-                    saves.truncate(n_groups * 2);
-                    Captures {
-                        inner: CapturesImpl::Fancy { text, saves },
-                        named_groups,
-                    }
-                }))
-            }
+    pub fn program_size(&self) -> usize {
+        match *self.inner {
+            RegexImpl::Wrap { .. } => 0,
+            RegexImpl::Fancy { ref prog, .. } => prog.body.len(),
         }
     }
 
-    /// Returns the number of captures, including the implicit capture of the entire expression.
-    pub fn captures_len(&self) -> usize {
-        match &self.inner {
-            RegexImpl::Wrap { inner, .. } => inner.captures_len(),
-            RegexImpl::Fancy { n_groups, .. } => *n_groups,
+    /// Returns the approximate memory in bytes that the compiled regex uses on the heap,
+    /// including the regexes of the regex crate that it delegates to and its prefilters. This
+    /// is what [`RegexBuilder::size_limit`] limits.
+    ///
+    /// It doesn't include the memory that searches allocate, such as the caches of the
+    /// delegated regexes or the backtracking stack, nor the regexes that are only compiled
+    /// when needed, e.g. for [`Regex::is_full_match`].
+    pub fn memory_usage(&self) -> usize {
+        match *self.inner {
+            RegexImpl::Wrap { ref inner, .. } => inner.memory_usage(),
+            RegexImpl::Fancy { ref prog, .. } => prog.memory_usage(),
         }
     }
 
@@ -936,13 +2973,38 @@ impl Regex {
         for (name, &i) in self.named_groups.iter() {
             names[i] = Some(name.as_str());
         }
+        for (name, groups) in self.duplicate_groups.iter() {
+            for &i in groups {
+                names[i] = Some(name.as_str());
+            }
+        }
         CaptureNames(names.into_iter())
     }
 
+    /// Returns the indices of the capture groups named `name`, which is more than one group for
+    /// a duplicate name with `(?J)`, or none if there is no group with the name.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?J)(?<n>\d+)-(?<w>\w+)|(?<w>\w+)-(?<n>\d+)").unwrap();
+    /// assert_eq!(re.group_indices("n"), &[1, 4]);
+    /// assert_eq!(re.group_indices("w"), &[2, 3]);
+    /// assert_eq!(re.group_indices("x"), &[] as &[usize]);
+    ///
+    /// let caps = re.captures("abc-123").unwrap().unwrap();
+    /// assert_eq!(caps.name("n").unwrap().as_str(), "123");
+    /// assert_eq!(caps.name("w").unwrap().as_str(), "abc");
+    /// ```
+    pub fn group_indices(&self, name: &str) -> &[usize] {
+        group_indices(&self.named_groups, &self.duplicate_groups, name)
+    }
+
     // for debugging only
     #[doc(hidden)]
     pub fn debug_print(&self) {
-        match &self.inner {
+        match &*self.inner {
             #[cfg(feature = "std")]
             RegexImpl::Wrap { inner, .. } => println!("wrapped {:?}", inner),
             #[cfg(not(feature = "std"))]
@@ -1053,12 +3115,51 @@ impl Regex {
     /// provided. This is the same as calling `replacen` with `limit` set to
     /// `0`.
     ///
+    /// Will panic if any errors are encountered. Use `try_replace_all` if you
+    /// want to handle errors.
+    ///
     /// See the documentation for `replace` for details on how to access
     /// capturing group matches in the replacement string.
     pub fn replace_all<'t, R: Replacer>(&self, text: &'t str, rep: R) -> Cow<'t, str> {
         self.replacen(text, 0, rep)
     }
 
+    /// Replaces the leftmost-first match with the replacement provided. This
+    /// is the same as calling `try_replacen` with `limit` set to `1`.
+    ///
+    /// Propagates any errors encountered, such as `RuntimeError::BacktrackLimitExceeded`.
+    ///
+    /// See the documentation for `replace` for details on how to access
+    /// capturing group matches in the replacement string.
+    pub fn try_replace<'t, R: Replacer>(&self, text: &'t str, rep: R) -> Result<Cow<'t, str>> {
+        self.try_replacen(text, 1, rep)
+    }
+
+    /// Replaces all non-overlapping matches in `text` with the replacement
+    /// provided. This is the same as calling `try_replacen` with `limit` set to
+    /// `0`.
+    ///
+    /// Propagates any errors encountered, such as `RuntimeError::BacktrackLimitExceeded`,
+    /// instead of returning a partly replaced text.
+    ///
+    /// See the documentation for `replace` for details on how to access
+    /// capturing group matches in the replacement string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::RegexBuilder;
+    /// let re = RegexBuilder::new(r"(a|b|ab)*\1?(?=c)")
+    ///     .backtrack_limit(1_000)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(re.try_replace_all("abc", "x").unwrap(), "xc");
+    /// assert!(re.try_replace_all(&"ab".repeat(20), "x").is_err());
+    /// ```
+    pub fn try_replace_all<'t, R: Replacer>(&self, text: &'t str, rep: R) -> Result<Cow<'t, str>> {
+        self.try_replacen(text, 0, rep)
+    }
+
     /// Replaces at most `limit` non-overlapping matches in `text` with the
     /// replacement provided. If `limit` is 0, then all non-overlapping matches
     /// are replaced.
@@ -1190,6 +3291,31 @@ impl Regex {
             limit: limit,
         }
     }
+
+    /// Splits the string by matches of the regex, keeping the matches.
+    ///
+    /// Returns an iterator over the substrings of the target string that each end with a match
+    /// of the regex, like [`str::split_inclusive`]. If the target doesn't end with a match, the
+    /// last substring is the rest of the target.
+    ///
+    /// # Example
+    ///
+    /// To split a text into sentences, keeping their punctuation:
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"[.!?]+(?=\s|$)").unwrap();
+    /// let target = "Hi! Is 1.5 ok?? Yes";
+    /// let sentences: Vec<&str> = re.split_inclusive(target).map(|x| x.unwrap()).collect();
+    /// assert_eq!(sentences, vec!["Hi!", " Is 1.5 ok??", " Yes"]);
+    /// ```
+    pub fn split_inclusive<'r, 'h>(&'r self, target: &'h str) -> SplitInclusive<'r, 'h> {
+        SplitInclusive {
+            matches: self.find_iter(target),
+            next_start: 0,
+            target,
+        }
+    }
 }
 
 impl TryFrom<&str> for Regex {
@@ -1260,35 +3386,20 @@ impl<'t> Captures<'t> {
     /// If there is no match for that group or the index does not correspond to a group, `None` is
     /// returned. The index 0 returns the whole match.
     pub fn get(&self, i: usize) -> Option<Match<'t>> {
-        match &self.inner {
-            CapturesImpl::Wrap { text, locations } => locations.get_group(i).map(|span| Match {
-                text,
-                start: span.start,
-                end: span.end,
-            }),
-            CapturesImpl::Fancy { text, ref saves } => {
-                let slot = i * 2;
-                if slot >= saves.len() {
-                    return None;
-                }
-                let lo = saves[slot];
-                if lo == usize::MAX {
-                    return None;
-                }
-                let hi = saves[slot + 1];
-                Some(Match {
-                    text,
-                    start: lo,
-                    end: hi,
-                })
-            }
-        }
+        self.inner
+            .get(i)
+            .map(|range| Match::new(self.text, range.start, range.end))
     }
 
     /// Returns the match for a named capture group.  Returns `None` the capture
     /// group did not match or if there is no group with the given name.
+    ///
+    /// For a name of more than one group with `(?J)`, this is the first of the groups that
+    /// matched.
     pub fn name(&self, name: &str) -> Option<Match<'t>> {
-        self.named_groups.get(name).and_then(|i| self.get(*i))
+        group_indices(&self.named_groups, &self.duplicate_groups, name)
+            .iter()
+            .find_map(|&i| self.get(i))
     }
 
     /// Expands all instances of `$group` in `replacement` to the corresponding
@@ -1321,13 +3432,65 @@ impl<'t> Captures<'t> {
         SubCaptureMatches { caps: self, i: 0 }
     }
 
+    /// Iterate over the named groups in order in which they appeared in the regex, with the name
+    /// and match of each one. This makes it possible to turn the captures into a map without
+    /// knowing the group names up front.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<key>\w+)=(?<value>\d+)?(\s|$)").unwrap();
+    /// let caps = re.captures("a=").unwrap().unwrap();
+    /// let named: Vec<_> = caps
+    ///     .iter_named()
+    ///     .map(|(name, m)| (name, m.map(|m| m.as_str())))
+    ///     .collect();
+    /// assert_eq!(named, vec![("key", Some("a")), ("value", None)]);
+    /// ```
+    pub fn iter_named<'c>(&'c self) -> NamedCaptureMatches<'c, 't> {
+        let mut names: Vec<_> = self
+            .named_groups
+            .iter()
+            .map(|(name, &i)| (name.as_str(), i))
+            .collect();
+        names.sort_unstable_by_key(|&(_, i)| i);
+        NamedCaptureMatches {
+            caps: self,
+            names: names.into_iter(),
+        }
+    }
+
     /// How many groups were captured. This is always at least 1 because group 0 returns the whole
     /// match.
     pub fn len(&self) -> usize {
-        match &self.inner {
-            CapturesImpl::Wrap { locations, .. } => locations.group_len(),
-            CapturesImpl::Fancy { saves, .. } => saves.len() / 2,
-        }
+        self.inner.len()
+    }
+
+    /// Returns the edits that the fuzzy groups of the regex, e.g. `(?:abc){~1}`, made for the
+    /// match, in the order that they were made. This is empty for a regex without fuzzy groups.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{EditKind, Regex};
+    /// let re = Regex::new(r"(?:colour){~2}").unwrap();
+    /// let caps = re.captures("the coler red").unwrap().unwrap();
+    /// assert_eq!(&caps[0], "coler");
+    /// let edits: Vec<_> = caps.edits().iter().map(|e| (e.kind(), e.range())).collect();
+    /// assert_eq!(
+    ///     edits,
+    ///     vec![(EditKind::Substitution, 7..8), (EditKind::Deletion, 8..8)]
+    /// );
+    /// ```
+    pub fn edits(&self) -> &[Edit] {
+        self.inner.edits()
+    }
+
+    /// Returns the number of edits that the fuzzy groups of the regex made for the match, see
+    /// [`edits`](Captures::edits).
+    pub fn cost(&self) -> usize {
+        self.inner.edits().len()
     }
 }
 
@@ -1388,9 +3551,24 @@ impl<'c, 't> Iterator for SubCaptureMatches<'c, 't> {
     }
 }
 
+impl<'c, 't> Iterator for NamedCaptureMatches<'c, 't> {
+    type Item = (&'c str, Option<Match<'t>>);
+
+    fn next(&mut self) -> Option<(&'c str, Option<Match<'t>>)> {
+        self.names
+            .next()
+            .map(|(name, _)| (name, self.caps.name(name)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.names.size_hint()
+    }
+}
+
 // TODO: might be nice to implement ExactSizeIterator etc for SubCaptures
 
-/// Regular expression AST. This is public for now but may change.
+/// Regular expression AST. This is public for now but may change. The children of an
+/// expression are the [`ExprId`]s of other expressions in the [`ExprArena`] of the tree.
 #[derive(Debug, PartialEq, Eq)]
 pub enum Expr {
     /// An empty expression, e.g. the last branch in `(a|b|)`
@@ -1411,20 +3589,20 @@ pub enum Expr {
     },
     /// Concatenation of multiple expressions, must match in order, e.g. `a.` is a concatenation of
     /// the literal `a` and `.` for any character
-    Concat(Vec<Expr>),
+    Concat(Vec<ExprId>),
     /// Alternative of multiple expressions, one of them must match, e.g. `a|b` is an alternative
     /// where either the literal `a` or `b` must match
-    Alt(Vec<Expr>),
+    Alt(Vec<ExprId>),
     /// Capturing group of expression, e.g. `(a.)` matches `a` and any character and "captures"
     /// (remembers) the match
-    Group(Box<Expr>),
+    Group(ExprId),
     /// Look-around (e.g. positive/negative look-ahead or look-behind) with an expression, e.g.
     /// `(?=a)` means the next character must be `a` (but the match is not consumed)
-    LookAround(Box<Expr>, LookAround),
+    LookAround(ExprId, LookAround),
     /// Repeat of an expression, e.g. `a*` or `a+` or `a{1,3}`
     Repeat {
         /// The expression that is being repeated
-        child: Box<Expr>,
+        child: ExprId,
         /// The minimum number of repetitions
         lo: usize,
         /// The maximum number of repetitions (or `usize::MAX`)
@@ -1445,10 +3623,15 @@ pub enum Expr {
     },
     /// Back reference to a capture group, e.g. `\1` in `(abc|def)\1` references the captured group
     /// and the whole regex matches either `abcabc` or `defdef`.
-    Backref(usize),
+    Backref {
+        /// The capture group number being referenced
+        group: usize,
+        /// Whether the matching is case-insensitive or not
+        casei: bool,
+    },
     /// Atomic non-capturing group, e.g. `(?>ab|a)` in text that contains `ab` will match `ab` and
     /// never backtrack and try `a`, even if matching fails after the atomic group.
-    AtomicGroup(Box<Expr>),
+    AtomicGroup(ExprId),
     /// Keep matched text so far out of overall match
     KeepOut,
     /// Anchor to match at the position where the previous match ended
@@ -1458,11 +3641,50 @@ pub enum Expr {
     /// If/Then/Else Condition. If there is no Then/Else, these will just be empty expressions.
     Conditional {
         /// The conditional expression to evaluate
-        condition: Box<Expr>,
+        condition: ExprId,
         /// What to execute if the condition is true
-        true_branch: Box<Expr>,
+        true_branch: ExprId,
         /// What to execute if the condition is false
-        false_branch: Box<Expr>,
+        false_branch: ExprId,
+    },
+    /// Subroutine call to a capture group, e.g. `(?1)` matches the expression of the first
+    /// capture group again, and `(?R)` (group 0) recurses into the whole regex.
+    SubroutineCall(usize),
+    /// Alternative of multiple expressions whose capture groups are numbered from the same
+    /// number, e.g. in `(?|(a)|(b))` both `(a)` and `(b)` are the first capture group
+    BranchReset(Vec<ExprId>),
+    /// Backtracking control verb, e.g. `(*SKIP)`
+    BacktrackingVerb(BacktrackingVerb),
+    /// Balancing group, e.g. `(?<close-open>exp)` matches `exp`, then removes the last capture
+    /// of the group `open` and captures the text between that capture and `exp` in the group
+    /// `close`
+    BalancingGroup {
+        /// The expression of the group
+        child: ExprId,
+        /// The group that captures the text between the removed capture and the expression, if
+        /// any
+        capture: Option<usize>,
+        /// The group whose last capture is removed
+        balance: usize,
+    },
+    /// Absent operator, e.g. `(?~abc)` matches the longest text before the next match of `abc`
+    Absent(ExprId),
+    /// Callout to the function registered with [`RegexBuilder::callout`], e.g. `(?C1)`
+    Callout(CalloutId),
+    /// Call of the predicate registered with [`RegexBuilder::predicate`] under the name, e.g.
+    /// `(?{name})`
+    Predicate(String),
+    /// Extended grapheme cluster, `\X`
+    GraphemeCluster,
+    /// Script run, e.g. `(*sr:\w+)` only matches if all the characters matched by the expression
+    /// are from the same script
+    ScriptRun(ExprId),
+    /// Fuzzy group, e.g. `(?:abc){~1}` matches `abc` with up to one edit
+    Fuzzy {
+        /// The expression of the group
+        child: ExprId,
+        /// The maximum number of edits
+        max_cost: usize,
     },
 }
 
@@ -1479,6 +3701,22 @@ pub enum LookAround {
     LookBehindNeg,
 }
 
+/// Type of backtracking control verb as used for a backtracking verb expression.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BacktrackingVerb {
+    /// Always fail, e.g. `(*FAIL)`
+    Fail,
+    /// End the match successfully, e.g. `(*ACCEPT)`
+    Accept,
+    /// Fail the match at the current start position when backtracked into, e.g. `(*PRUNE)`
+    Prune,
+    /// Fail the match and continue searching at the current position when backtracked into,
+    /// e.g. `(*SKIP)`
+    Skip,
+    /// Continue with the next alternative when backtracked into, e.g. `(*THEN)`
+    Then,
+}
+
 /// An iterator over capture names in a [Regex].  The iterator
 /// returns the name of each group, or [None] if the group has
 /// no name.  Because capture group 0 cannot have a name, the
@@ -1549,6 +3787,8 @@ pub enum Assertion {
     StartText,
     /// End of input text
     EndText,
+    /// End of input text, or before a newline at the end of it, like `$` in Python
+    EndTextOptionalNewline,
     /// Start of a line
     StartLine {
         /// CRLF mode
@@ -1576,8 +3816,17 @@ impl Assertion {
             self,
             // these will make regex-automata use PikeVM
             LeftWordBoundary | RightWordBoundary | WordBoundary | NotWordBoundary
+                // this has no equivalent in regex-automata
+                | EndTextOptionalNewline
         )
     }
+
+    /// Returns whether the assertion looks at the text after the position, so that it can
+    /// change when text is added at the end.
+    pub(crate) fn needs_end(&self) -> bool {
+        use Assertion::*;
+        !matches!(self, StartText | StartLine { .. })
+    }
 }
 
 impl Expr {
@@ -1587,12 +3836,57 @@ impl Expr {
         Parser::parse(re)
     }
 
-    /// Convert expression to a regex string in the regex crate's syntax.
+    /// Returns the number of capture groups that participate in every match of the expression,
+    /// or `None` if it depends on the match.
+    fn static_captures_len(&self, arena: &ExprArena) -> Option<usize> {
+        let len = |child: &ExprId| arena[*child].static_captures_len(arena);
+        let all_equal = |mut counts: core::slice::Iter<'_, ExprId>| {
+            let first = counts.next().map_or(Some(0), len)?;
+            counts.try_fold(first, |first, child| {
+                (len(child)? == first).then_some(first)
+            })
+        };
+        match self {
+            Expr::Group(child) => Some(len(child)? + 1),
+            Expr::Concat(children) => children
+                .iter()
+                .try_fold(0, |sum, child| Some(sum + len(child)?)),
+            Expr::Alt(children) | Expr::BranchReset(children) => all_equal(children.iter()),
+            Expr::Repeat { hi: 0, .. } => Some(0),
+            Expr::Repeat { child, lo: 0, .. } => match len(child)? {
+                0 => Some(0),
+                _ => None,
+            },
+            Expr::Repeat { child, .. }
+            | Expr::AtomicGroup(child)
+            | Expr::ScriptRun(child)
+            | Expr::Fuzzy { child, .. } => len(child),
+            Expr::LookAround(child, LookAround::LookAhead | LookAround::LookBehind) => len(child),
+            // groups in a negative look-around never participate
+            Expr::LookAround(_, LookAround::LookAheadNeg | LookAround::LookBehindNeg) => Some(0),
+            Expr::Conditional {
+                condition,
+                true_branch,
+                false_branch,
+            } => {
+                let if_true = len(condition)? + len(true_branch)?;
+                (if_true == len(false_branch)?).then_some(if_true)
+            }
+            // groups after the end of the match don't participate
+            Expr::BacktrackingVerb(BacktrackingVerb::Accept) => None,
+            // removing captures makes groups not participate
+            Expr::BalancingGroup { .. } => None,
+            _ => Some(0),
+        }
+    }
+
+    /// Convert expression to a regex string in the regex crate's syntax. The children of the
+    /// expression are looked up in `arena`.
     ///
     /// # Panics
     ///
     /// Panics for expressions that are hard, i.e. can not be handled by the regex crate.
-    pub fn to_str(&self, buf: &mut String, precedence: u8) {
+    pub fn to_str(&self, arena: &ExprArena, buf: &mut String, precedence: u8) {
         match *self {
             Expr::Empty => (),
             Expr::Any { newline } => buf.push_str(if newline { "(?s:.)" } else { "." }),
@@ -1615,8 +3909,8 @@ impl Expr {
                 if precedence > 1 {
                     buf.push_str("(?:");
                 }
-                for child in children {
-                    child.to_str(buf, 2);
+                for &child in children {
+                    arena[child].to_str(arena, buf, 2);
                 }
                 if precedence > 1 {
                     buf.push(')')
@@ -1626,23 +3920,23 @@ impl Expr {
                 if precedence > 0 {
                     buf.push_str("(?:");
                 }
-                for (i, child) in children.iter().enumerate() {
+                for (i, &child) in children.iter().enumerate() {
                     if i != 0 {
                         buf.push('|');
                     }
-                    child.to_str(buf, 1);
+                    arena[child].to_str(arena, buf, 1);
                 }
                 if precedence > 0 {
                     buf.push(')');
                 }
             }
-            Expr::Group(ref child) => {
+            Expr::Group(child) => {
                 buf.push('(');
-                child.to_str(buf, 0);
+                arena[child].to_str(arena, buf, 0);
                 buf.push(')');
             }
             Expr::Repeat {
-                ref child,
+                child,
                 lo,
                 hi,
                 greedy,
@@ -1650,7 +3944,7 @@ impl Expr {
                 if precedence > 2 {
                     buf.push_str("(?:");
                 }
-                child.to_str(buf, 3);
+                arena[child].to_str(arena, buf, 3);
                 match (lo, hi) {
                     (0, 1) => buf.push('?'),
                     (0, usize::MAX) => buf.push('*'),
@@ -1692,16 +3986,19 @@ impl Expr {
 }
 
 // precondition: ix > 0
-fn prev_codepoint_ix(s: &str, mut ix: usize) -> usize {
-    let bytes = s.as_bytes();
-    loop {
-        ix -= 1;
-        // fancy bit magic for ranges 0..0x80 + 0xc0..
-        if (bytes[ix] as i8) >= -0x40 {
-            break;
-        }
+fn prev_codepoint_ix(s: &[u8], ix: usize) -> usize {
+    let min = ix.saturating_sub(4);
+    let mut start = ix - 1;
+    // fancy bit magic for ranges 0..0x80 + 0xc0..
+    while start > min && (s[start] as i8) < -0x40 {
+        start -= 1;
+    }
+    // step back over a single byte if it isn't the end of a valid sequence
+    if codepoint_len_at(s, start) == ix - start {
+        start
+    } else {
+        ix - 1
     }
-    ix
 }
 
 fn codepoint_len(b: u8) -> usize {
@@ -1713,15 +4010,61 @@ fn codepoint_len(b: u8) -> usize {
     }
 }
 
+/// Returns the length of the UTF-8 sequence at `ix`, or 1 if there is no valid sequence there.
+fn codepoint_len_at(s: &[u8], ix: usize) -> usize {
+    let len = codepoint_len(s[ix]);
+    match s.get(ix..ix + len) {
+        Some(bytes) if len == 1 || core::str::from_utf8(bytes).is_ok() => len,
+        _ => 1,
+    }
+}
+
 /// Returns the smallest possible index of the next valid UTF-8 sequence
 /// starting after `i`.
 /// Adapted from a function with the same name in the `regex` crate.
-fn next_utf8(text: &str, i: usize) -> usize {
-    let b = match text.as_bytes().get(i) {
-        None => return i + 1,
-        Some(&b) => b,
-    };
-    i + codepoint_len(b)
+fn next_utf8(text: &[u8], i: usize) -> usize {
+    if i >= text.len() {
+        return i + 1;
+    }
+    i + codepoint_len_at(text, i)
+}
+
+/// An extended grapheme cluster, as defined in Unicode Standard Annex #29.
+const GRAPHEME_PATTERN: &str = r"(?x)
+    \r\n | \p{gcb=Control} | \p{gcb=CR} | \p{gcb=LF}
+    | \p{gcb=Prepend}*
+    (?:
+        (?:
+            \p{gcb=L}* (?: \p{gcb=V}+ | \p{gcb=LV} \p{gcb=V}* | \p{gcb=LVT} ) \p{gcb=T}*
+            | \p{gcb=L}+
+            | \p{gcb=T}+
+        )
+        | \p{gcb=RI} \p{gcb=RI}
+        | \p{Extended_Pictographic} (?: \p{gcb=Extend}* \p{gcb=ZWJ} \p{Extended_Pictographic} )*
+        | [^\p{gcb=Control}\p{gcb=CR}\p{gcb=LF}]
+    )
+    [\p{gcb=Extend}\p{gcb=ZWJ}\p{gcb=SpacingMark}]*
+";
+
+/// Compiled lazily, and only available with the `unicode` feature.
+static GRAPHEME: Lazy<Option<RaRegex>> = Lazy::new(|| RaRegex::new(GRAPHEME_PATTERN).ok());
+
+/// Returns the position after the extended grapheme cluster at `i`. Without the `unicode`
+/// feature, this is the position after the character at `i`.
+fn next_grapheme(text: &[u8], i: usize) -> usize {
+    if i >= text.len() {
+        return i + 1;
+    }
+    let input = RaInput::new(text)
+        .span(i..text.len())
+        .anchored(Anchored::Yes);
+    match Lazy::get(&GRAPHEME)
+        .as_ref()
+        .and_then(|re| re.search(&input))
+    {
+        Some(m) => m.end(),
+        None => next_utf8(text, i),
+    }
 }
 
 // If this returns false, then there is no possible backref in the re
@@ -1767,50 +4110,54 @@ pub mod internal {
 #[cfg(test)]
 mod tests {
     use alloc::borrow::Cow;
-    use alloc::boxed::Box;
     use alloc::string::String;
     use alloc::{format, vec};
 
     use crate::parse::make_literal;
-    use crate::{Expr, Regex};
+    use crate::{Expr, ExprArena, Regex};
 
     //use detect_possible_backref;
 
     // tests for to_str
 
-    fn to_str(e: Expr) -> String {
+    fn to_str(arena: &ExprArena, e: Expr) -> String {
         let mut s = String::new();
-        e.to_str(&mut s, 0);
+        e.to_str(arena, &mut s, 0);
         s
     }
 
     #[test]
     fn to_str_concat_alt() {
-        let e = Expr::Concat(vec![
-            Expr::Alt(vec![make_literal("a"), make_literal("b")]),
-            make_literal("c"),
-        ]);
-        assert_eq!(to_str(e), "(?:a|b)c");
+        let mut arena = ExprArena::default();
+        let a = arena.push(make_literal("a"));
+        let b = arena.push(make_literal("b"));
+        let alt = arena.push(Expr::Alt(vec![a, b]));
+        let c = arena.push(make_literal("c"));
+        let e = Expr::Concat(vec![alt, c]);
+        assert_eq!(to_str(&arena, e), "(?:a|b)c");
     }
 
     #[test]
     fn to_str_rep_concat() {
+        let mut arena = ExprArena::default();
+        let a = arena.push(make_literal("a"));
+        let b = arena.push(make_literal("b"));
         let e = Expr::Repeat {
-            child: Box::new(Expr::Concat(vec![make_literal("a"), make_literal("b")])),
+            child: arena.push(Expr::Concat(vec![a, b])),
             lo: 2,
             hi: 3,
             greedy: true,
         };
-        assert_eq!(to_str(e), "(?:ab){2,3}");
+        assert_eq!(to_str(&arena, e), "(?:ab){2,3}");
     }
 
     #[test]
     fn to_str_group_alt() {
-        let e = Expr::Group(Box::new(Expr::Alt(vec![
-            make_literal("a"),
-            make_literal("b"),
-        ])));
-        assert_eq!(to_str(e), "(a|b)");
+        let mut arena = ExprArena::default();
+        let a = arena.push(make_literal("a"));
+        let b = arena.push(make_literal("b"));
+        let e = Expr::Group(arena.push(Expr::Alt(vec![a, b])));
+        assert_eq!(to_str(&arena, e), "(a|b)");
     }
 
     #[test]
@@ -1837,27 +4184,30 @@ mod tests {
 
     #[test]
     fn to_str_repeat() {
-        fn repeat(lo: usize, hi: usize, greedy: bool) -> Expr {
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
+        let mut arena = ExprArena::default();
+        let a = arena.push(make_literal("a"));
+        let repeat = |lo: usize, hi: usize, greedy: bool| {
+            let e = Expr::Repeat {
+                child: a,
                 lo,
                 hi,
                 greedy,
-            }
-        }
+            };
+            to_str(&arena, e)
+        };
 
-        assert_eq!(to_str(repeat(2, 2, true)), "a{2}");
-        assert_eq!(to_str(repeat(2, 2, false)), "a{2}?");
-        assert_eq!(to_str(repeat(2, 3, true)), "a{2,3}");
-        assert_eq!(to_str(repeat(2, 3, false)), "a{2,3}?");
-        assert_eq!(to_str(repeat(2, usize::MAX, true)), "a{2,}");
-        assert_eq!(to_str(repeat(2, usize::MAX, false)), "a{2,}?");
-        assert_eq!(to_str(repeat(0, 1, true)), "a?");
-        assert_eq!(to_str(repeat(0, 1, false)), "a??");
-        assert_eq!(to_str(repeat(0, usize::MAX, true)), "a*");
-        assert_eq!(to_str(repeat(0, usize::MAX, false)), "a*?");
-        assert_eq!(to_str(repeat(1, usize::MAX, true)), "a+");
-        assert_eq!(to_str(repeat(1, usize::MAX, false)), "a+?");
+        assert_eq!(repeat(2, 2, true), "a{2}");
+        assert_eq!(repeat(2, 2, false), "a{2}?");
+        assert_eq!(repeat(2, 3, true), "a{2,3}");
+        assert_eq!(repeat(2, 3, false), "a{2,3}?");
+        assert_eq!(repeat(2, usize::MAX, true), "a{2,}");
+        assert_eq!(repeat(2, usize::MAX, false), "a{2,}?");
+        assert_eq!(repeat(0, 1, true), "a?");
+        assert_eq!(repeat(0, 1, false), "a??");
+        assert_eq!(repeat(0, usize::MAX, true), "a*");
+        assert_eq!(repeat(0, usize::MAX, false), "a*?");
+        assert_eq!(repeat(1, usize::MAX, true), "a+");
+        assert_eq!(repeat(1, usize::MAX, false), "a+?");
     }
 
     #[test]
@@ -0,0 +1,260 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use regex_automata::{Input as RaInput, MatchKind};
+
+use crate::regexset::Patterns;
+use crate::vm::OPTION_SKIPPED_EMPTY_MATCH;
+use crate::{next_utf8, Match, Result};
+
+/// The index of the pattern that matched, and the range of the match.
+type PatternMatch = (usize, Range<usize>);
+
+/// Search for multiple regexes at once, reporting which pattern matched where.
+///
+/// Unlike [`RegexSet`](crate::RegexSet), which only reports which patterns match somewhere,
+/// this finds the non-overlapping matches of all the patterns, like a single regex with one
+/// alternative per pattern would: the leftmost match wins, and if several patterns match at
+/// the same position, the one that was given first wins. This makes it useful for lexers and
+/// routers.
+///
+/// Patterns that don't need backtracking are searched together in a single pass over the
+/// text. Each pattern that uses fancy features is searched separately, but each of them is
+/// still only run over the text once, as its next match is remembered until the search
+/// passes it.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::MultiRegex;
+/// let lexer = MultiRegex::new(&[r"\d+", r"\w+(?=\()", r"\w+", r"[()]"]).unwrap();
+/// let tokens: Vec<_> = lexer
+///     .find_iter("f(x1) 2")
+///     .map(|m| {
+///         let m = m.unwrap();
+///         (m.pattern(), m.as_str())
+///     })
+///     .collect();
+/// assert_eq!(
+///     tokens,
+///     vec![(1, "f"), (3, "("), (2, "x1"), (3, ")"), (0, "2")]
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiRegex {
+    patterns: Patterns,
+}
+
+/// A match of a [`MultiRegex`], with the index of the pattern that matched.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct MultiMatch<'t> {
+    pattern: usize,
+    mat: Match<'t>,
+}
+
+impl<'t> MultiMatch<'t> {
+    /// Returns the index of the pattern that matched.
+    pub fn pattern(&self) -> usize {
+        self.pattern
+    }
+
+    /// Returns the starting byte offset of the match in the text.
+    pub fn start(&self) -> usize {
+        self.mat.start()
+    }
+
+    /// Returns the ending byte offset of the match in the text.
+    pub fn end(&self) -> usize {
+        self.mat.end()
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the match in text.
+    pub fn range(&self) -> Range<usize> {
+        self.mat.range()
+    }
+
+    /// Returns the matched text.
+    pub fn as_str(&self) -> &'t str {
+        self.mat.as_str()
+    }
+
+    /// Returns the match without the pattern index.
+    pub fn as_match(&self) -> Match<'t> {
+        self.mat
+    }
+}
+
+impl MultiRegex {
+    /// Parse and compile multiple regexes with default options.
+    ///
+    /// Returns an [`Error`](enum.Error.html) if any of the patterns could not be parsed.
+    pub fn new<I, S>(patterns: I) -> Result<MultiRegex>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Ok(MultiRegex {
+            patterns: Patterns::new(patterns, MatchKind::LeftmostFirst)?,
+        })
+    }
+
+    /// Returns the first match of any of the patterns in `text`.
+    pub fn find<'t>(&self, text: &'t str) -> Result<Option<MultiMatch<'t>>> {
+        self.find_iter(text).next().transpose()
+    }
+
+    /// Returns an iterator over the non-overlapping matches of any of the patterns in `text`.
+    pub fn find_iter<'m, 't>(&'m self, text: &'t str) -> MultiMatches<'m, 't> {
+        let sources = usize::from(self.patterns.delegated.is_some()) + self.patterns.fancy.len();
+        let mut continues = vec![false; sources - self.patterns.fancy.len()];
+        continues.extend(
+            self.patterns
+                .fancy
+                .iter()
+                .map(|(_, regex)| regex.continues_from_previous_match()),
+        );
+        MultiMatches {
+            multi: self,
+            text,
+            last_end: 0,
+            last_match: None,
+            next_matches: vec![None; sources],
+            continues,
+        }
+    }
+
+    /// Returns the number of patterns.
+    pub fn len(&self) -> usize {
+        self.patterns.patterns.len()
+    }
+
+    /// Returns true if there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.patterns.is_empty()
+    }
+
+    /// Returns the patterns, in the order they were given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns.patterns
+    }
+
+    /// Searches for the first match of a source of matches, which is the combined regex of the
+    /// delegated patterns if there is one, followed by the fancy patterns.
+    fn search_source(
+        &self,
+        source: usize,
+        text: &str,
+        pos: usize,
+        option_flags: u32,
+    ) -> Result<Option<PatternMatch>> {
+        let source = match &self.patterns.delegated {
+            Some((inner, indices)) => {
+                if source == 0 {
+                    let input = RaInput::new(text).span(pos..text.len());
+                    return Ok(inner
+                        .search(&input)
+                        .map(|m| (indices[m.pattern().as_usize()], m.range())));
+                }
+                source - 1
+            }
+            None => source,
+        };
+        let (i, regex) = &self.patterns.fancy[source];
+        let range = regex.search_from_pos(text.as_bytes(), pos, option_flags)?;
+        Ok(range.map(|range| (*i, range)))
+    }
+}
+
+/// An iterator over the non-overlapping matches of a [`MultiRegex`], created by
+/// [`MultiRegex::find_iter`].
+///
+/// `'m` is the lifetime of the compiled regexes and `'t` is the lifetime of the matched string.
+#[derive(Debug)]
+pub struct MultiMatches<'m, 't> {
+    multi: &'m MultiRegex,
+    text: &'t str,
+    last_end: usize,
+    last_match: Option<usize>,
+    /// The next match of each source of matches, if it was searched for already
+    next_matches: Vec<Option<Option<PatternMatch>>>,
+    /// Whether each source of matches uses `\G`, so that its next match has to be searched for
+    /// again from each position
+    continues: Vec<bool>,
+}
+
+impl<'m, 't> MultiMatches<'m, 't> {
+    /// Return the text being searched.
+    pub fn text(&self) -> &'t str {
+        self.text
+    }
+
+    /// Returns the leftmost match starting at or after `pos`.
+    fn search(&mut self, pos: usize, option_flags: u32) -> Result<Option<PatternMatch>> {
+        let multi = self.multi;
+        let mut best: Option<PatternMatch> = None;
+        for (source, next_match) in self.next_matches.iter_mut().enumerate() {
+            // a remembered match is still the next one as long as it's not before `pos`
+            let stale = match next_match {
+                _ if self.continues[source] => true,
+                Some(Some((_, range))) => range.start < pos,
+                Some(None) => false,
+                None => true,
+            };
+            if stale {
+                *next_match = Some(multi.search_source(source, self.text, pos, option_flags)?);
+            }
+            if let Some(Some((pattern, range))) = next_match {
+                let is_better = match &best {
+                    Some((best_pattern, best_range)) => {
+                        (range.start, *pattern) < (best_range.start, *best_pattern)
+                    }
+                    None => true,
+                };
+                if is_better {
+                    best = Some((*pattern, range.clone()));
+                }
+            }
+        }
+        Ok(best)
+    }
+}
+
+impl<'m, 't> Iterator for MultiMatches<'m, 't> {
+    type Item = Result<MultiMatch<'t>>;
+
+    /// Adapted from `Matches::next`.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.last_end > self.text.len() {
+            return None;
+        }
+
+        let option_flags = match self.last_match {
+            Some(last_match) if self.last_end > last_match => OPTION_SKIPPED_EMPTY_MATCH,
+            _ => 0,
+        };
+        let (pattern, range) = match self.search(self.last_end, option_flags) {
+            Err(error) => return Some(Err(error)),
+            Ok(None) => return None,
+            Ok(Some(found)) => found,
+        };
+
+        if range.start == range.end {
+            self.last_end = next_utf8(self.text.as_bytes(), range.end);
+            if Some(range.end) == self.last_match {
+                return self.next();
+            }
+        } else {
+            self.last_end = range.end;
+        }
+
+        self.last_match = Some(range.end);
+
+        Some(Ok(MultiMatch {
+            pattern,
+            mat: Match::new(self.text, range.start, range.end),
+        }))
+    }
+}
+
+impl<'m, 't> core::iter::FusedIterator for MultiMatches<'m, 't> {}
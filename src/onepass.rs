@@ -0,0 +1,246 @@
+//! Detection of the splits of a program where the next byte of the text decides which thread
+//! can match, so that the VM can take that thread without pushing the other one.
+//!
+//! For each instruction, the analysis finds the set of bytes that the text has to continue with
+//! for the program to match from there: `\w` only continues with the first byte of a word
+//! character, a look-ahead for `!` only with `!`, and so on. When the next byte isn't in the
+//! set of one thread of a split, only the other one can match. Where the sets of the two threads
+//! are disjoint, that's the case at every position: in `\w+(?=!)` for example, the split after
+//! `\w` either continues with another word character or with the look-ahead, so the VM doesn't
+//! backtrack into the repetition. A program where all the splits after the start of the match
+//! are like that is one-pass: it matches from a position without any backtracking, in time
+//! linear in the length of the text.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use regex_automata::util::syntax;
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::vm::{is_negative_lookaround, Insn};
+use crate::RegexOptions;
+
+/// A set of bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteSet([u128; 2]);
+
+impl ByteSet {
+    /// The set of all bytes.
+    const ALL: ByteSet = ByteSet([u128::MAX; 2]);
+
+    fn insert(&mut self, b: u8) {
+        self.0[usize::from(b >> 7)] |= 1 << (b & 0x7F);
+    }
+
+    fn insert_range(&mut self, start: u8, end: u8) {
+        for b in start..=end {
+            self.insert(b);
+        }
+    }
+
+    fn remove(&mut self, b: u8) {
+        self.0[usize::from(b >> 7)] &= !(1 << (b & 0x7F));
+    }
+
+    fn union(&mut self, other: &ByteSet) {
+        self.0[0] |= other.0[0];
+        self.0[1] |= other.0[1];
+    }
+
+    /// Returns whether the set contains `b`.
+    pub(crate) fn contains(&self, b: u8) -> bool {
+        self.0[usize::from(b >> 7)] & (1 << (b & 0x7F)) != 0
+    }
+}
+
+/// Returns the bytes that a match of the pattern of a delegate can start with, or `None` if it
+/// can match the empty string.
+pub(crate) fn delegate_first_bytes(re: &str, options: &RegexOptions) -> Option<ByteSet> {
+    let hir = syntax::parse_with(re, &options.syntaxc).ok()?;
+    let mut set = ByteSet::default();
+    if hir_first_bytes(&hir, &mut set) {
+        None
+    } else {
+        Some(set)
+    }
+}
+
+/// Adds the bytes that a match of `hir` can start with to `set`, and returns whether it can
+/// match the empty string, in which case the bytes after it have to be added too. Look-around
+/// assertions are treated as empty, which only makes the set bigger.
+fn hir_first_bytes(hir: &Hir, set: &mut ByteSet) -> bool {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => true,
+        HirKind::Literal(literal) => match literal.0.first() {
+            Some(&b) => {
+                set.insert(b);
+                false
+            }
+            None => true,
+        },
+        HirKind::Class(Class::Unicode(class)) => {
+            // UTF-8 keeps the order of code points, so the first bytes of the characters in a
+            // range are between those of its ends
+            let mut buf = [0; 4];
+            for range in class.iter() {
+                let start = range.start().encode_utf8(&mut buf).as_bytes()[0];
+                let end = range.end().encode_utf8(&mut buf).as_bytes()[0];
+                set.insert_range(start, end);
+            }
+            false
+        }
+        HirKind::Class(Class::Bytes(class)) => {
+            for range in class.iter() {
+                set.insert_range(range.start(), range.end());
+            }
+            false
+        }
+        HirKind::Repetition(repetition) => {
+            hir_first_bytes(&repetition.sub, set) || repetition.min == 0
+        }
+        HirKind::Capture(capture) => hir_first_bytes(&capture.sub, set),
+        HirKind::Concat(hirs) => hirs.iter().all(|hir| hir_first_bytes(hir, set)),
+        HirKind::Alternation(hirs) => hirs
+            .iter()
+            .fold(false, |empty, hir| hir_first_bytes(hir, set) | empty),
+    }
+}
+
+/// The bytes that the threads of a split can continue with, where they're known and the VM can
+/// rely on them.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct SplitBytes {
+    first: Option<ByteSet>,
+    second: Option<ByteSet>,
+}
+
+impl SplitBytes {
+    /// Returns the thread of the split at `x` and `y` to continue with, if the other one can't
+    /// match with `next` as the next byte.
+    pub(crate) fn only_thread(&self, x: usize, y: usize, next: Option<u8>) -> Option<usize> {
+        let continues = |set: &ByteSet| next.map_or(false, |b| set.contains(b));
+        if self.first.map_or(false, |set| !continues(&set)) {
+            Some(y)
+        } else if self.second.map_or(false, |set| !continues(&set)) {
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns the bytes that the threads of each split of the program can continue with.
+///
+/// Only the first thread of the splits of negative look-arounds and of programs with
+/// `(*THEN)` can be skipped, as they discard branches up to the one that the split pushed.
+pub(crate) fn split_bytes(body: &[Insn]) -> Vec<SplitBytes> {
+    let has_then = body.iter().any(|insn| matches!(insn, Insn::Then(_)));
+    let first = first_bytes(body);
+    body.iter()
+        .enumerate()
+        .map(|(pc, insn)| match *insn {
+            Insn::Split(x, y) => SplitBytes {
+                first: first.get(x).copied().flatten(),
+                second: if has_then || is_negative_lookaround(body, pc) {
+                    None
+                } else {
+                    first.get(y).copied().flatten()
+                },
+            },
+            _ => SplitBytes::default(),
+        })
+        .collect()
+}
+
+/// Returns for each instruction the bytes that the text has to continue with for the program to
+/// match from there, or `None` if it can match without looking at the next byte, or if an
+/// instruction on the way isn't known to leave the position alone and not have side effects.
+fn first_bytes(body: &[Insn]) -> Vec<Option<ByteSet>> {
+    // the instructions that don't consume text or decide anything themselves, but continue
+    // with other instructions; atomic groups discard branches, so skipping a thread that only
+    // fails after them would keep branches alive that the group throws away
+    let succs: Vec<Vec<usize>> = body
+        .iter()
+        .enumerate()
+        .map(|(pc, insn)| match *insn {
+            Insn::Jmp(target) => vec![target],
+            Insn::Split(x, y) => vec![x, y],
+            Insn::RepeatGr { next, .. }
+            | Insn::RepeatNg { next, .. }
+            | Insn::RepeatEpsilonGr { next, .. }
+            | Insn::RepeatEpsilonNg { next, .. } => vec![pc + 1, next],
+            Insn::Lit(ref val) if val.is_empty() => vec![pc + 1],
+            Insn::Save(_)
+            | Insn::Save0(_)
+            | Insn::Assertion(_)
+            | Insn::ContinueFromPreviousMatchEnd => vec![pc + 1],
+            _ => Vec::new(),
+        })
+        .collect();
+    let mut preds = vec![Vec::new(); body.len()];
+    for (pc, pc_succs) in succs.iter().enumerate() {
+        for &succ in pc_succs {
+            if succ < body.len() {
+                preds[succ].push(pc);
+            }
+        }
+    }
+
+    // backwards data flow analysis, starting from the instructions that consume text, which
+    // finds the smallest sets, as threads that loop back without consuming text don't add any
+    let mut first: Vec<Option<ByteSet>> = body
+        .iter()
+        .map(|insn| match *insn {
+            Insn::Any => Some(ByteSet::ALL),
+            Insn::AnyNoNL => {
+                let mut set = ByteSet::ALL;
+                set.remove(b'\n');
+                Some(set)
+            }
+            Insn::Lit(ref val) => {
+                let mut set = ByteSet::default();
+                if let Some(&b) = val.as_bytes().first() {
+                    set.insert(b);
+                }
+                Some(set)
+            }
+            Insn::Delegate { first_bytes, .. } => first_bytes,
+            // this never continues, but reaching a `FailNegativeLookAround` means that the body
+            // of the look-around matched, so that the assertion fails
+            Insn::Fail => Some(ByteSet::default()),
+            _ => None,
+        })
+        .collect();
+    for (pc, pc_succs) in succs.iter().enumerate() {
+        if !pc_succs.is_empty() {
+            first[pc] = Some(ByteSet::default());
+        }
+    }
+    let mut queued = vec![true; body.len()];
+    let mut work: Vec<usize> = (0..body.len()).collect();
+    while let Some(pc) = work.pop() {
+        queued[pc] = false;
+        if succs[pc].is_empty() {
+            continue;
+        }
+        let mut pc_first = Some(ByteSet::default());
+        for &succ in &succs[pc] {
+            pc_first = match (pc_first, first.get(succ).copied().flatten()) {
+                (Some(mut set), Some(succ_set)) => {
+                    set.union(&succ_set);
+                    Some(set)
+                }
+                _ => None,
+            };
+        }
+        if pc_first != first[pc] {
+            first[pc] = pc_first;
+            for &pred in &preds[pc] {
+                if !queued[pred] {
+                    queued[pred] = true;
+                    work.push(pred);
+                }
+            }
+        }
+    }
+    first
+}
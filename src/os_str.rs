@@ -0,0 +1,258 @@
+use alloc::borrow::Cow;
+use alloc::sync::Arc;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crate::parse::{group_indices, DuplicateGroups, NamedGroups};
+use crate::{CapturesImpl, Regex, Result};
+
+/// A set of capture groups found for a regex in an OS string, see [`Regex::captures_os`].
+#[derive(Debug)]
+pub struct OsCaptures<'t> {
+    text: Cow<'t, [u8]>,
+    inner: CapturesImpl,
+    named_groups: Arc<NamedGroups>,
+    duplicate_groups: Arc<DuplicateGroups>,
+}
+
+/// Returns the bytes of `s` as they are matched: the bytes themselves on Unix, and WTF-8 on
+/// Windows, where unpaired surrogates are encoded like other code points but aren't valid
+/// UTF-8. Elsewhere, unrepresentable parts are replaced with U+FFFD.
+fn os_str_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    #[cfg(any(unix, target_os = "wasi"))]
+    {
+        #[cfg(unix)]
+        use std::os::unix::ffi::OsStrExt;
+        #[cfg(target_os = "wasi")]
+        use std::os::wasi::ffi::OsStrExt;
+        Cow::Borrowed(s.as_bytes())
+    }
+    #[cfg(windows)]
+    {
+        use alloc::vec::Vec;
+        use std::os::windows::ffi::OsStrExt;
+        if let Some(s) = s.to_str() {
+            return Cow::Borrowed(s.as_bytes());
+        }
+        let mut bytes = Vec::new();
+        for c in core::char::decode_utf16(s.encode_wide()) {
+            match c {
+                Ok(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+                Err(error) => {
+                    let u = error.unpaired_surrogate();
+                    bytes.push(0xE0 | (u >> 12) as u8);
+                    bytes.push(0x80 | ((u >> 6) & 0x3F) as u8);
+                    bytes.push(0x80 | (u & 0x3F) as u8);
+                }
+            }
+        }
+        Cow::Owned(bytes)
+    }
+    #[cfg(not(any(unix, windows, target_os = "wasi")))]
+    {
+        match s.to_string_lossy() {
+            Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
+            Cow::Owned(s) => Cow::Owned(s.into_bytes()),
+        }
+    }
+}
+
+/// The inverse of [`os_str_bytes`] for a part of its result. On Windows, a part that starts or
+/// ends inside an encoded code point has U+FFFD in its place.
+fn os_str_from_bytes(bytes: &[u8]) -> Cow<'_, OsStr> {
+    #[cfg(any(unix, target_os = "wasi"))]
+    {
+        #[cfg(unix)]
+        use std::os::unix::ffi::OsStrExt;
+        #[cfg(target_os = "wasi")]
+        use std::os::wasi::ffi::OsStrExt;
+        Cow::Borrowed(OsStr::from_bytes(bytes))
+    }
+    #[cfg(windows)]
+    {
+        use alloc::vec::Vec;
+        use std::ffi::OsString;
+        use std::os::windows::ffi::OsStringExt;
+        if let Ok(s) = core::str::from_utf8(bytes) {
+            return Cow::Borrowed(OsStr::new(s));
+        }
+        let mut wide = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            let (len, lead) = match bytes[i] {
+                b @ 0x00..=0x7F => (1, u32::from(b)),
+                b @ 0xC2..=0xDF => (2, u32::from(b & 0x1F)),
+                b @ 0xE0..=0xEF => (3, u32::from(b & 0x0F)),
+                b @ 0xF0..=0xF4 => (4, u32::from(b & 0x07)),
+                _ => (0, 0),
+            };
+            let tail = match bytes.get(i + 1..i + len.max(1)) {
+                Some(tail) if len > 0 && tail.iter().all(|&b| b & 0xC0 == 0x80) => tail,
+                _ => {
+                    wide.push(0xFFFD);
+                    i += 1;
+                    continue;
+                }
+            };
+            let c = tail
+                .iter()
+                .fold(lead, |c, &b| (c << 6) | u32::from(b & 0x3F));
+            if c >= 0x10000 {
+                let c = c - 0x10000;
+                wide.push(0xD800 | (c >> 10) as u16);
+                wide.push(0xDC00 | (c & 0x3FF) as u16);
+            } else {
+                wide.push(c as u16);
+            }
+            i += len;
+        }
+        Cow::Owned(OsString::from_wide(&wide))
+    }
+    #[cfg(not(any(unix, windows, target_os = "wasi")))]
+    {
+        use std::ffi::OsString;
+        match alloc::string::String::from_utf8_lossy(bytes) {
+            Cow::Borrowed(s) => Cow::Borrowed(OsStr::new(s)),
+            Cow::Owned(s) => Cow::Owned(OsString::from(s)),
+        }
+    }
+}
+
+impl Regex {
+    /// Check if the regex matches an OS string, without converting it to a `str` first.
+    ///
+    /// Parts of `text` that aren't valid UTF-8 (on Unix) or UTF-16 (on Windows) are matched
+    /// like invalid UTF-8 in the [`bytes`](crate::bytes) module: they're never matched by `.`,
+    /// classes or literals, but the rest of the text still matches as usual, instead of being
+    /// changed by a lossy conversion.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::ffi::OsStr;
+    ///
+    /// let re = Regex::new(r"(\w)\1").unwrap();
+    /// assert!(re.is_match_os(OsStr::new("xaa")).unwrap());
+    /// ```
+    pub fn is_match_os(&self, text: &OsStr) -> Result<bool> {
+        self.is_match_bytes(&os_str_bytes(text))
+    }
+
+    /// Check if the regex matches a path, see [`is_match_os`](Regex::is_match_os).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::path::Path;
+    ///
+    /// let re = Regex::new(r"(?<!\.min)\.js$").unwrap();
+    /// assert!(re.is_match_path(Path::new("src/app.js")).unwrap());
+    /// assert!(!re.is_match_path(Path::new("dist/app.min.js")).unwrap());
+    /// ```
+    pub fn is_match_path(&self, path: &Path) -> Result<bool> {
+        self.is_match_os(path.as_os_str())
+    }
+
+    /// Returns the first match in an OS string, see [`is_match_os`](Regex::is_match_os).
+    ///
+    /// The match is borrowed from `text` on Unix, and whenever `text` is valid UTF-8.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::ffi::OsStr;
+    ///
+    /// let re = Regex::new(r"\w+(?=\.)").unwrap();
+    /// let found = re.find_os(OsStr::new("notes.txt")).unwrap().unwrap();
+    /// assert_eq!(found, OsStr::new("notes"));
+    /// ```
+    pub fn find_os<'t>(&self, text: &'t OsStr) -> Result<Option<Cow<'t, OsStr>>> {
+        let bytes = os_str_bytes(text);
+        let range = self.search_from_pos(&bytes, 0, 0)?;
+        Ok(range.map(move |range| match bytes {
+            Cow::Borrowed(bytes) => os_str_from_bytes(&bytes[range]),
+            Cow::Owned(bytes) => Cow::Owned(os_str_from_bytes(&bytes[range]).into_owned()),
+        }))
+    }
+
+    /// Returns the first match in a path, see [`find_os`](Regex::find_os).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::ffi::OsStr;
+    /// use std::path::Path;
+    ///
+    /// let re = Regex::new(r"[^/]+$").unwrap();
+    /// let found = re.find_path(Path::new("src/lib.rs")).unwrap().unwrap();
+    /// assert_eq!(found, OsStr::new("lib.rs"));
+    /// ```
+    pub fn find_path<'t>(&self, path: &'t Path) -> Result<Option<Cow<'t, OsStr>>> {
+        self.find_os(path.as_os_str())
+    }
+
+    /// Returns the capture groups for the first match in an OS string, see
+    /// [`is_match_os`](Regex::is_match_os).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// use std::ffi::OsStr;
+    ///
+    /// let re = Regex::new(r"(?<stem>\w+)\.(?!bak$)(\w+)$").unwrap();
+    /// let captures = re.captures_os(OsStr::new("notes.txt")).unwrap().unwrap();
+    /// assert_eq!(captures.name("stem").unwrap(), OsStr::new("notes"));
+    /// assert_eq!(captures.get(2).unwrap(), OsStr::new("txt"));
+    /// ```
+    pub fn captures_os<'t>(&self, text: &'t OsStr) -> Result<Option<OsCaptures<'t>>> {
+        let bytes = os_str_bytes(text);
+        let inner = self.captures_impl_from_pos(&bytes, 0)?;
+        Ok(inner.map(|inner| OsCaptures {
+            text: bytes,
+            inner,
+            named_groups: self.named_groups.clone(),
+            duplicate_groups: self.duplicate_groups.clone(),
+        }))
+    }
+
+    /// Returns the capture groups for the first match in a path, see
+    /// [`captures_os`](Regex::captures_os).
+    pub fn captures_path<'t>(&self, path: &'t Path) -> Result<Option<OsCaptures<'t>>> {
+        self.captures_os(path.as_os_str())
+    }
+}
+
+#[allow(clippy::len_without_is_empty)] // follow regex's API
+impl<'t> OsCaptures<'t> {
+    /// Get the capture group by its index in the regex.
+    ///
+    /// If there is no match for that group or the index does not correspond to a group, `None` is
+    /// returned. The index 0 returns the whole match.
+    pub fn get(&self, i: usize) -> Option<Cow<'_, OsStr>> {
+        self.inner
+            .get(i)
+            .map(|range| os_str_from_bytes(&self.text[range]))
+    }
+
+    /// Returns the match for a named capture group.  Returns `None` the capture
+    /// group did not match or if there is no group with the given name.
+    ///
+    /// For a name of more than one group with `(?J)`, this is the first of the groups that
+    /// matched.
+    pub fn name(&self, name: &str) -> Option<Cow<'_, OsStr>> {
+        group_indices(&self.named_groups, &self.duplicate_groups, name)
+            .iter()
+            .find_map(|&i| self.get(i))
+    }
+
+    /// How many groups were captured. This is always at least 1 because group 0 returns the whole
+    /// match.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
@@ -0,0 +1,210 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Range;
+use rayon::prelude::*;
+
+use crate::vm::OPTION_SKIPPED_EMPTY_MATCH;
+use crate::{Match, MatchesState, Regex, Result, SearchOptions};
+
+/// The number of bytes of the text that each task of `par_find_iter` searches for the start
+/// of matches.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// An iterator over all non-overlapping matches for a particular string, which were found in
+/// parallel, see [`Regex::par_find_iter`].
+///
+/// The iterator yields a `Result<Match>`. The iterator stops when no more matches can be
+/// found, or after the first error.
+///
+/// `'t` is the lifetime of the matched string.
+#[derive(Debug)]
+pub struct ParMatches<'t>(vec::IntoIter<Result<Match<'t>>>);
+
+impl<'t> Iterator for ParMatches<'t> {
+    type Item = Result<Match<'t>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'t> ExactSizeIterator for ParMatches<'t> {}
+
+impl<'t> core::iter::FusedIterator for ParMatches<'t> {}
+
+/// The matches that start in a chunk of the text, as found by an iteration over the matches
+/// that starts at the start of the chunk.
+struct Chunk<'t> {
+    matches: Vec<Match<'t>>,
+    /// The state of the iteration after the last match, from which no more matches start in
+    /// the chunk, unless the search stopped at an error
+    state: MatchesState,
+    /// Whether the search stopped at an error before the end of the chunk
+    failed: bool,
+}
+
+impl Regex {
+    /// Returns an iterator over all the non-overlapping matches in `text`, like
+    /// [`find_iter`](Regex::find_iter), but splits `text` into chunks that are searched in
+    /// parallel with [rayon](https://docs.rs/rayon). This needs the `rayon` feature.
+    ///
+    /// Each chunk is searched for the matches that start in it, including the text up to the
+    /// [maximum length](Regex::max_match_len) of a match past its end, with look-around and
+    /// word boundaries seeing the whole text. Where the matches of a chunk don't line up with
+    /// the match before it, e.g. because a match ends in the next chunk, the chunk is searched
+    /// again from the end of that match until it finds a match of the chunk, so the matches are
+    /// the same as those of `find_iter`, in the same order. The [backtrack
+    /// limit](crate::RegexBuilder::backtrack_limit) applies to the search of each chunk,
+    /// rather than to the search for each match.
+    ///
+    /// The whole text is searched before the iterator is returned. A text that is short, or a
+    /// regex without a maximum length of a match, such as `\w+`, is searched on the current
+    /// thread with `find_iter`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<=\s)\w{2}(?=\s)").unwrap();
+    /// let text = "xaa bb cc ".repeat(100_000);
+    /// assert_eq!(re.par_find_iter(&text).count(), 200_000);
+    /// ```
+    pub fn par_find_iter<'t>(&self, text: &'t str) -> ParMatches<'t> {
+        let max_len = match self.max_match_len() {
+            Some(max_len) if text.len() > CHUNK_SIZE => max_len,
+            _ => return ParMatches(self.find_all(text).into_iter()),
+        };
+        let mut bounds = (0..text.len())
+            .step_by(CHUNK_SIZE)
+            .map(|start| ceil_char_boundary(text, start))
+            .collect::<Vec<_>>();
+        bounds.dedup();
+        bounds.push(text.len());
+        let chunks = bounds
+            .par_windows(2)
+            .map(|bounds| self.search_chunk(text, bounds[0]..bounds[1], max_len))
+            .collect::<Vec<_>>();
+
+        // The search of a chunk started in a state that the iteration over all the matches may
+        // not have been in. Once the iteration finds a match of the chunk, its state is the same
+        // as that of the search of the chunk after the match, so the chunk's later matches can be
+        // taken as they are.
+        let mut state = MatchesState::default();
+        let mut matches = Vec::new();
+        for (bounds, chunk) in bounds.windows(2).zip(chunks) {
+            loop {
+                let mat = match self.next_in_chunk(text, &mut state, bounds[1], max_len) {
+                    Ok(Some(mat)) => mat,
+                    Ok(None) => break,
+                    Err(error) => {
+                        matches.push(Err(error));
+                        return ParMatches(matches.into_iter());
+                    }
+                };
+                matches.push(Ok(mat));
+                let found = chunk
+                    .matches
+                    .binary_search_by_key(&mat.start, |m| m.start)
+                    .ok()
+                    .filter(|&i| chunk.matches[i].end == mat.end);
+                if let Some(i) = found {
+                    matches.extend(chunk.matches[i + 1..].iter().map(|&m| Ok(m)));
+                    state = chunk.state;
+                    if !chunk.failed {
+                        break;
+                    }
+                }
+            }
+        }
+        ParMatches(matches.into_iter())
+    }
+
+    /// Returns the matches of `find_iter`, up to and including the first error.
+    fn find_all<'t>(&self, text: &'t str) -> Vec<Result<Match<'t>>> {
+        let mut matches = Vec::new();
+        for result in self.find_iter(text) {
+            let failed = result.is_err();
+            matches.push(result);
+            if failed {
+                break;
+            }
+        }
+        matches
+    }
+
+    fn search_chunk<'t>(&self, text: &'t str, bounds: Range<usize>, max_len: usize) -> Chunk<'t> {
+        let mut state = MatchesState::from_parts(bounds.start, None);
+        let mut matches = Vec::new();
+        loop {
+            match self.next_in_chunk(text, &mut state, bounds.end, max_len) {
+                Ok(Some(mat)) => matches.push(mat),
+                Ok(None) => break,
+                Err(_) => {
+                    return Chunk {
+                        matches,
+                        state,
+                        failed: true,
+                    }
+                }
+            }
+        }
+        Chunk {
+            matches,
+            state,
+            failed: false,
+        }
+    }
+
+    /// Advances the iteration in `state` to the next match like `Matches::next`, if the match
+    /// starts before `end`, or at `end` if it's the end of the text. Only the text up to
+    /// `max_len` past `end` is searched, which is enough for such a match.
+    fn next_in_chunk<'t>(
+        &self,
+        text: &'t str,
+        state: &mut MatchesState,
+        end: usize,
+        max_len: usize,
+    ) -> Result<Option<Match<'t>>> {
+        let in_chunk = |pos: usize| pos < end || end == text.len();
+        let window_end = ceil_char_boundary(text, end.saturating_add(max_len).min(text.len()));
+        loop {
+            if state.last_end > text.len() || !in_chunk(state.last_end) {
+                return Ok(None);
+            }
+            let option_flags = match state.last_match {
+                Some(last_match) if state.last_end > last_match => OPTION_SKIPPED_EMPTY_MATCH,
+                _ => 0,
+            };
+            let mut options = SearchOptions::new();
+            options
+                .range(state.last_end..window_end)
+                .transparent_bounds(true);
+            let mat = match self.find_with_option_flags(text, &options, option_flags)? {
+                Some(mat) if in_chunk(mat.start) => mat,
+                _ => return Ok(None),
+            };
+            if mat.start == mat.end {
+                state.last_end = self.next_after_empty(text.as_bytes(), state.last_end, mat.end);
+                if Some(mat.end) == state.last_match && self.skips_empty_after_match() {
+                    continue;
+                }
+            } else {
+                state.last_end = mat.end;
+            }
+            state.last_match = Some(mat.end);
+            return Ok(Some(mat));
+        }
+    }
+}
+
+/// Returns the first character boundary of `text` at or after `pos`.
+fn ceil_char_boundary(text: &str, mut pos: usize) -> usize {
+    while !text.is_char_boundary(pos) {
+        pos += 1;
+    }
+    pos
+}
@@ -20,18 +20,19 @@
 
 //! A regex parser yielding an AST.
 
-use alloc::boxed::Box;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
 use alloc::{format, vec};
 
 use bit_set::BitSet;
 use core::convert::TryInto;
+use core::ops::{Index, IndexMut};
 use core::usize;
+use core::{fmt, mem};
 use regex_syntax::escape_into;
 
 use crate::{codepoint_len, CompileError, Error, Expr, ParseError, Result, MAX_RECURSION};
-use crate::{Assertion, LookAround::*};
+use crate::{Assertion, BacktrackingVerb, CalloutId, LookAround, LookAround::*};
 
 const FLAG_CASEI: u32 = 1;
 const FLAG_MULTI: u32 = 1 << 1;
@@ -39,80 +40,272 @@ const FLAG_DOTNL: u32 = 1 << 2;
 const FLAG_SWAP_GREED: u32 = 1 << 3;
 const FLAG_IGNORE_SPACE: u32 = 1 << 4;
 const FLAG_UNICODE: u32 = 1 << 5;
+/// Makes `\R` only match `\r\n`, `\r` and `\n`, set by an option of the regex
+pub(crate) const FLAG_CRLF_LINE_BREAKS: u32 = 1 << 6;
+/// Makes `\h` and `\H` match horizontal whitespace instead of hex digits, set by an option of the
+/// regex
+pub(crate) const FLAG_H_HORIZONTAL_SPACE: u32 = 1 << 7;
+/// Makes POSIX bracket expressions such as `[:alpha:]` match Unicode characters instead of only
+/// ASCII characters, set by an option of the regex
+pub(crate) const FLAG_UNICODE_POSIX_CLASSES: u32 = 1 << 8;
+/// Makes `\ddd` an octal escape if it can't be a backref, set by an option of the regex
+pub(crate) const FLAG_OCTAL_ESCAPES: u32 = 1 << 9;
+/// Parses the pattern with the syntax and semantics of Python's `re` module, set by the syntax
+/// dialect of the regex
+pub(crate) const FLAG_PYTHON: u32 = 1 << 10;
+/// Parses the pattern with the syntax and semantics of ECMAScript, set by the syntax dialect of
+/// the regex, which also sets the following flags for the `u` and `v` flags of ECMAScript
+pub(crate) const FLAG_ECMASCRIPT: u32 = 1 << 11;
+pub(crate) const FLAG_ECMASCRIPT_UNICODE: u32 = 1 << 12;
+pub(crate) const FLAG_ECMASCRIPT_UNICODE_SETS: u32 = 1 << 13;
+/// Allows more than one group with the same name, set by `(?J)`
+const FLAG_DUPNAMES: u32 = 1 << 14;
+/// Makes plain groups non-capturing, so that only named groups capture, set by `(?n)` or an
+/// option of the regex
+pub(crate) const FLAG_EXPLICIT_CAPTURE: u32 = 1 << 15;
+/// Allows disabling Unicode with `(?-u)`, set for regexes that match byte strings
+pub(crate) const FLAG_BYTES: u32 = 1 << 16;
+/// Makes escapes such as `\xFF`, `.` and classes match bytes instead of UTF-8 sequences, and
+/// case-insensitive matching ASCII-only, set by `(?-u)`
+const FLAG_NON_UNICODE: u32 = 1 << 17;
+
+/// Horizontal whitespace, as matched by `\h` in PCRE
+const HORIZONTAL_SPACE: &str =
+    r"\t\x20\xA0\x{1680}\x{180E}\x{2000}-\x{200A}\x{202F}\x{205F}\x{3000}";
+/// Vertical whitespace, as matched by `\v`
+const VERTICAL_SPACE: &str = r"\n\x0B\x0C\r\x{85}\x{2028}\x{2029}";
+/// The non-ASCII characters that are ignored in free-spacing mode, in addition to ASCII
+/// whitespace
+const PATTERN_WHITE_SPACE: [char; 5] = ['\u{85}', '\u{200E}', '\u{200F}', '\u{2028}', '\u{2029}'];
+/// The line terminators of ECMAScript
+const ECMASCRIPT_LINE_TERMINATORS: &str = r"\n\r\x{2028}\x{2029}";
+/// Whitespace and line terminators, as matched by `\s` in ECMAScript
+const ECMASCRIPT_SPACE: &str = r"\t\n\x0B\x0C\r\x20\xA0\x{1680}\x{2000}-\x{200A}\x{2028}\x{2029}\x{202F}\x{205F}\x{3000}\x{FEFF}";
 
 #[cfg(not(feature = "std"))]
 pub(crate) type NamedGroups = alloc::collections::BTreeMap<String, usize>;
 #[cfg(feature = "std")]
 pub(crate) type NamedGroups = std::collections::HashMap<String, usize>;
+/// All the groups of each name that is used for more than one group, in order
+pub(crate) type DuplicateGroups = alloc::collections::BTreeMap<String, Vec<usize>>;
+
+/// The index of an expression in an [`ExprArena`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExprId(usize);
+
+impl fmt::Debug for ExprId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ExprId({})", self.0)
+    }
+}
+
+/// The expressions of a tree, which refer to their children by their [`ExprId`]. They are kept
+/// in one vector instead of each child being boxed, so that trees with many nodes are cheap to
+/// build and drop.
+#[derive(Default)]
+pub struct ExprArena {
+    exprs: Vec<Expr>,
+}
+
+impl fmt::Debug for ExprArena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = (0..self.exprs.len()).map(ExprId);
+        f.debug_map().entries(ids.zip(&self.exprs)).finish()
+    }
+}
+
+impl ExprArena {
+    /// Adds the expression to the arena and returns its index.
+    pub fn push(&mut self, expr: Expr) -> ExprId {
+        self.exprs.push(expr);
+        ExprId(self.exprs.len() - 1)
+    }
+
+    /// Returns the number of expressions in the arena.
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    /// Returns whether the arena has no expressions.
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+}
+
+impl Index<ExprId> for ExprArena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0]
+    }
+}
+
+impl IndexMut<ExprId> for ExprArena {
+    fn index_mut(&mut self, id: ExprId) -> &mut Expr {
+        &mut self.exprs[id.0]
+    }
+}
 
 #[derive(Debug)]
 pub struct ExprTree {
-    pub expr: Expr,
+    /// The expressions of the tree
+    pub arena: ExprArena,
+    /// The root expression
+    pub expr: ExprId,
     pub backrefs: BitSet,
+    pub subroutine_calls: BitSet,
+    /// The groups whose last capture is removed by a balancing group
+    pub balanced_groups: BitSet,
     pub named_groups: NamedGroups,
+    /// The groups of the names that are used for more than one group with `(?J)`
+    pub duplicate_groups: DuplicateGroups,
+    /// The names of the predicates that are called, e.g. `(?{name})`
+    pub predicates: Vec<String>,
+}
+
+impl ExprTree {
+    /// Returns the root expression.
+    pub fn root(&self) -> &Expr {
+        &self.arena[self.expr]
+    }
+}
+
+impl Index<ExprId> for ExprTree {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.arena[id]
+    }
 }
 
 #[derive(Debug)]
 pub(crate) struct Parser<'a> {
     re: &'a str, // source
+    /// The expressions that have been parsed as children of others
+    arena: ExprArena,
     backrefs: BitSet,
+    subroutine_calls: BitSet,
+    balanced_groups: BitSet,
     flags: u32,
     named_groups: NamedGroups,
+    duplicate_groups: DuplicateGroups,
+    predicates: Vec<String>,
+    /// All the named groups of the regex, when parsing again to resolve references to groups
+    /// that are named after the reference
+    all_named_groups: Option<NamedGroups>,
+    /// The number of groups of the regex, when parsing again to resolve references to groups
+    /// after the reference in the ECMAScript dialects
+    all_groups: Option<usize>,
+    /// Whether there is a reference to a group that isn't named yet
+    forward_refs: bool,
     numeric_backrefs: bool,
     curr_group: usize, // need to keep track of which group number we're parsing
+    /// The capture groups that contain the current position
+    open_groups: Vec<usize>,
+    /// Whether the pattern is quoted with `\Q` at the current position, until `\E`
+    quoting: bool,
+    /// The end of the flag groups at the start of the pattern, which are the only ones allowed
+    /// without a colon in the Python dialect
+    global_flags_end: usize,
 }
 
 impl<'a> Parser<'a> {
     /// Parse the regex and return an expression (AST) and a bit set with the indexes of groups
     /// that are referenced by backrefs.
     pub(crate) fn parse(re: &str) -> Result<ExprTree> {
-        let mut p = Parser::new(re);
-        let (ix, expr) = p.parse_re(0, 0)?;
+        Parser::parse_with_flags(re, 0)
+    }
+
+    /// Parse the regex with flags that are set in addition to the default ones.
+    pub(crate) fn parse_with_flags(re: &str, flags: u32) -> Result<ExprTree> {
+        let mut p = Parser::new(re, flags);
+        let mut result = p.parse_re(0, 0);
+        if result.is_ok() && p.forward_refs {
+            let named_groups = core::mem::take(&mut p.named_groups);
+            let groups = p.curr_group;
+            p = Parser::new(re, flags);
+            p.all_named_groups = Some(named_groups);
+            p.all_groups = Some(groups);
+            result = p.parse_re(0, 0);
+        }
+        let (ix, expr) = result?;
         if ix < re.len() {
             return Err(Error::ParseError(
                 ix,
                 ParseError::GeneralParseError("end of string not reached".to_string()),
             ));
         }
+        let expr = p.arena.push(expr);
         Ok(ExprTree {
+            arena: p.arena,
             expr,
             backrefs: Default::default(),
+            subroutine_calls: p.subroutine_calls,
+            balanced_groups: p.balanced_groups,
             named_groups: p.named_groups,
+            duplicate_groups: p.duplicate_groups,
+            predicates: p.predicates,
         })
     }
 
-    fn new(re: &str) -> Parser<'_> {
+    fn new(re: &str, flags: u32) -> Parser<'_> {
         Parser {
             re,
+            arena: Default::default(),
             backrefs: Default::default(),
+            subroutine_calls: Default::default(),
+            balanced_groups: Default::default(),
             named_groups: Default::default(),
+            duplicate_groups: Default::default(),
+            predicates: Vec::new(),
+            all_named_groups: None,
+            all_groups: None,
+            forward_refs: false,
             numeric_backrefs: false,
-            flags: FLAG_UNICODE,
+            // `\d`, `\w` and `\b` are ASCII-only in ECMAScript
+            flags: if flags & FLAG_ECMASCRIPT != 0 {
+                flags
+            } else {
+                FLAG_UNICODE | flags
+            },
             curr_group: 0,
+            open_groups: Vec::new(),
+            quoting: false,
+            global_flags_end: 0,
         }
     }
 
     fn parse_re(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        // flags that are set with e.g. `(?i)` apply to the following alternatives too, but not
+        // after the end of the enclosing group
+        let oldflags = self.flags;
         let (ix, child) = self.parse_branch(ix, depth)?;
         let mut ix = self.optional_whitespace(ix)?;
         if self.re[ix..].starts_with('|') {
-            let mut children = vec![child];
+            let mut children = vec![self.arena.push(child)];
             while self.re[ix..].starts_with('|') {
                 ix += 1;
                 let (next, child) = self.parse_branch(ix, depth)?;
-                children.push(child);
+                children.push(self.arena.push(child));
                 ix = self.optional_whitespace(next)?;
             }
+            self.flags = oldflags;
             return Ok((ix, Expr::Alt(children)));
         }
+        self.flags = oldflags;
         // can't have numeric backrefs and named backrefs
-        if self.numeric_backrefs && !self.named_groups.is_empty() {
+        if self.numeric_backrefs
+            && !self.named_groups.is_empty()
+            && !self.flag(FLAG_PYTHON | FLAG_ECMASCRIPT)
+        {
             return Err(Error::CompileError(CompileError::NamedBackrefOnly));
         }
         Ok((ix, child))
     }
 
     fn parse_branch(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        // the first child is only added to the arena once there is another one
+        let mut first = None;
         let mut children = Vec::new();
         let mut ix = ix;
         while ix < self.re.len() {
@@ -121,20 +314,43 @@ impl<'a> Parser<'a> {
                 break;
             }
             if child != Expr::Empty {
-                children.push(child);
+                if let Some(first) = first.take() {
+                    children.push(self.arena.push(first));
+                }
+                if children.is_empty() {
+                    first = Some(child);
+                } else {
+                    children.push(self.arena.push(child));
+                }
             }
             ix = next;
         }
-        match children.len() {
-            0 => Ok((ix, Expr::Empty)),
-            1 => Ok((ix, children.pop().unwrap())),
-            _ => Ok((ix, Expr::Concat(children))),
+        match first {
+            Some(first) => Ok((ix, first)),
+            None if children.is_empty() => Ok((ix, Expr::Empty)),
+            None => Ok((ix, Expr::Concat(children))),
         }
     }
 
     fn parse_piece(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let (ix, child) = self.parse_atom(ix, depth)?;
+        let ix = if !self.quoting {
+            ix
+        } else if self.re[ix..].starts_with("\\E") {
+            // a repetition after `\E` applies to the last quoted character
+            self.quoting = false;
+            ix + 2
+        } else {
+            return Ok((ix, child));
+        };
         let mut ix = self.optional_whitespace(ix)?;
+        if let Some((next, max_cost)) = self.parse_fuzzy(ix)? {
+            if !self.is_repeatable(&child) {
+                return Err(Error::ParseError(ix, ParseError::TargetNotRepeatable));
+            }
+            let child = self.arena.push(child);
+            return Ok((next, Expr::Fuzzy { child, max_cost }));
+        }
         if ix < self.re.len() {
             // fail when child is empty?
             let (lo, hi) = match self.re.as_bytes()[ix] {
@@ -166,15 +382,33 @@ impl<'a> Parser<'a> {
                 ix += 1;
             }
             greedy ^= self.flag(FLAG_SWAP_GREED);
-            let mut node = Expr::Repeat {
-                child: Box::new(child),
-                lo,
-                hi,
-                greedy,
+            let mut node = if let Expr::LookAround(..) = child {
+                // a repeated look-ahead, as allowed by Annex B of ECMAScript, which doesn't
+                // consume anything, so it's either optional or the same as the look-ahead
+                if lo == 0 {
+                    Expr::Repeat {
+                        child: self.arena.push(child),
+                        lo,
+                        hi: hi.min(1),
+                        greedy,
+                    }
+                } else {
+                    child
+                }
+            } else {
+                Expr::Repeat {
+                    child: self.arena.push(child),
+                    lo,
+                    hi,
+                    greedy,
+                }
             };
             if ix < self.re.len() && self.re.as_bytes()[ix] == b'+' {
+                if self.flag(FLAG_PYTHON | FLAG_ECMASCRIPT) {
+                    return Err(Error::ParseError(ix, ParseError::InvalidRepeat));
+                }
                 ix += 1;
-                node = Expr::AtomicGroup(Box::new(node));
+                node = Expr::AtomicGroup(self.arena.push(node));
             }
             return Ok((ix, node));
         }
@@ -183,13 +417,36 @@ impl<'a> Parser<'a> {
 
     fn is_repeatable(&self, child: &Expr) -> bool {
         match child {
+            Expr::LookAround(_, LookAhead | LookAheadNeg) => self.is_annex_b(),
             Expr::LookAround(_, _) => false,
+            // an ASCII word boundary
+            Expr::Delegate { size: 0, .. } => false,
             Expr::Empty => false,
             Expr::Assertion(_) => false,
             _ => true,
         }
     }
 
+    // the maximum cost of a fuzzy group like `{~2}`, as in TRE, or `None` if there is none
+    fn parse_fuzzy(&self, ix: usize) -> Result<Option<(usize, usize)>> {
+        if !self.re[ix..].starts_with('{') || self.flag(FLAG_PYTHON | FLAG_ECMASCRIPT) {
+            return Ok(None);
+        }
+        let ix = self.optional_whitespace(ix + 1)?;
+        if !self.re[ix..].starts_with('~') {
+            return Ok(None);
+        }
+        let ix = self.optional_whitespace(ix + 1)?;
+        let Some((end, max_cost)) = parse_decimal(self.re, ix) else {
+            return Ok(None);
+        };
+        let ix = self.optional_whitespace(end)?;
+        if !self.re[ix..].starts_with('}') {
+            return Ok(None);
+        }
+        Ok(Some((ix + 1, max_cost)))
+    }
+
     // ix, lo, hi
     fn parse_repeat(&self, ix: usize) -> Result<(usize, usize, usize)> {
         let ix = self.optional_whitespace(ix + 1)?; // skip opening '{'
@@ -198,7 +455,7 @@ impl<'a> Parser<'a> {
             return Err(Error::ParseError(ix, ParseError::InvalidRepeat));
         }
         let mut end = ix;
-        let lo = if bytes[ix] == b',' {
+        let lo = if bytes[ix] == b',' && !self.flag(FLAG_ECMASCRIPT) {
             0
         } else if let Some((next, lo)) = parse_decimal(self.re, ix) {
             end = next;
@@ -232,17 +489,45 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_atom(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        if self.quoting {
+            return Ok(self.parse_literal(ix));
+        }
         let ix = self.optional_whitespace(ix)?;
         if ix == self.re.len() {
             return Ok((ix, Expr::Empty));
         }
+        if self.flag(FLAG_NON_UNICODE) && self.re.as_bytes()[ix] != b'(' {
+            let escaped = self.re.as_bytes()[ix] == b'\\';
+            let (next, expr) = self.parse_unicode_atom(ix, depth)?;
+            return Ok((next, self.non_unicode(expr, escaped)));
+        }
+        self.parse_unicode_atom(ix, depth)
+    }
+
+    /// Parses the atom at `ix`, which isn't whitespace, as if Unicode was enabled.
+    fn parse_unicode_atom(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        let ecmascript = self.flag(FLAG_ECMASCRIPT);
         match self.re.as_bytes()[ix] {
+            b'.' if ecmascript && !self.flag(FLAG_DOTNL) => Ok((
+                ix + 1,
+                Expr::Delegate {
+                    inner: format!("[^{}]", ECMASCRIPT_LINE_TERMINATORS),
+                    size: 1,
+                    casei: false,
+                },
+            )),
             b'.' => Ok((
                 ix + 1,
                 Expr::Any {
                     newline: self.flag(FLAG_DOTNL),
                 },
             )),
+            b'^' if ecmascript && self.flag(FLAG_MULTI) => {
+                Ok((ix + 1, self.ecmascript_line_boundary(LookBehindNeg)))
+            }
+            b'$' if ecmascript && self.flag(FLAG_MULTI) => {
+                Ok((ix + 1, self.ecmascript_line_boundary(LookAheadNeg)))
+            }
             b'^' => Ok((
                 ix + 1,
                 if self.flag(FLAG_MULTI) {
@@ -257,6 +542,8 @@ impl<'a> Parser<'a> {
                 if self.flag(FLAG_MULTI) {
                     // TODO: support crlf flag
                     Expr::Assertion(Assertion::EndLine { crlf: false })
+                } else if self.flag(FLAG_PYTHON) {
+                    Expr::Assertion(Assertion::EndTextOptionalNewline)
                 } else {
                     Expr::Assertion(Assertion::EndText)
                 },
@@ -264,42 +551,99 @@ impl<'a> Parser<'a> {
             b'(' => self.parse_group(ix, depth),
             b'\\' => {
                 let (next, expr) = self.parse_escape(ix, false)?;
-                if let Expr::Backref(group) = expr {
+                if let Expr::Backref { group, .. } = expr {
                     self.backrefs.insert(group);
                 }
                 Ok((next, expr))
             }
             b'+' | b'*' | b'?' | b'|' | b')' => Ok((ix, Expr::Empty)),
+            b'{' if ecmascript && self.parse_repeat(ix).is_ok() => {
+                Err(Error::ParseError(ix, ParseError::TargetNotRepeatable))
+            }
+            b'{' | b'}' if ecmascript && self.flag(FLAG_ECMASCRIPT_UNICODE) => {
+                Err(Error::ParseError(ix, ParseError::InvalidRepeat))
+            }
+            b']' if ecmascript && self.flag(FLAG_ECMASCRIPT_UNICODE) => {
+                Err(Error::ParseError(ix, ParseError::InvalidClass))
+            }
             b'[' => self.parse_class(ix),
-            b => {
-                // TODO: maybe want to match multiple codepoints?
-                let next = ix + codepoint_len(b);
-                Ok((
-                    next,
-                    Expr::Literal {
-                        val: String::from(&self.re[ix..next]),
-                        casei: self.flag(FLAG_CASEI),
-                    },
-                ))
+            _ => Ok(self.parse_literal(ix)),
+        }
+    }
+
+    /// Returns `expr`, an atom parsed with Unicode enabled, as it's matched with Unicode
+    /// disabled: escapes of code points up to 0xFF, which are `escaped`, match that byte, `.`
+    /// and classes match single bytes, and only ASCII letters match case-insensitively. The
+    /// delegates that match bytes start with `(?-u:`, see `analyze`.
+    fn non_unicode(&mut self, expr: Expr, escaped: bool) -> Expr {
+        let byte_delegate = |inner: String, casei: bool| Expr::Delegate {
+            inner: format!("(?-u:{})", inner),
+            size: 1,
+            casei,
+        };
+        match expr {
+            Expr::Any { newline } => {
+                byte_delegate(String::from(if newline { "(?s:.)" } else { "." }), false)
+            }
+            Expr::Literal { val, casei } => match byte_escape(&val) {
+                Some(escape) if escaped => byte_delegate(escape, casei),
+                _ if casei && val.bytes().all(|b| b.is_ascii_alphabetic()) => {
+                    byte_delegate(val, true)
+                }
+                _ => Expr::Literal {
+                    casei: casei && val.is_ascii(),
+                    val,
+                },
+            },
+            Expr::Concat(children) => {
+                for &child in &children {
+                    let expr = mem::replace(&mut self.arena[child], Expr::Empty);
+                    self.arena[child] = self.non_unicode(expr, escaped);
+                }
+                Expr::Concat(children)
+            }
+            Expr::Delegate { inner, size, casei } if size > 0 && !inner.starts_with("(?-u:") => {
+                byte_delegate(inner, casei)
             }
+            expr => expr,
         }
     }
 
+    fn parse_literal(&self, ix: usize) -> (usize, Expr) {
+        let Some(&b) = self.re.as_bytes().get(ix) else {
+            return (ix, Expr::Empty);
+        };
+        // TODO: maybe want to match multiple codepoints?
+        let next = ix + codepoint_len(b);
+        (
+            next,
+            Expr::Literal {
+                val: String::from(&self.re[ix..next]),
+                casei: self.flag(FLAG_CASEI),
+            },
+        )
+    }
+
     fn parse_named_backref(
-        &self,
+        &mut self,
         ix: usize,
         open: &str,
         close: &str,
         allow_relative: bool,
     ) -> Result<(usize, Expr)> {
         if let Some((id, skip)) = parse_id(&self.re[ix..], open, close, allow_relative) {
-            let group = if let Some(group) = self.named_groups.get(id) {
+            let group = if let Some(groups) = self.duplicate_groups.get(id) {
+                let groups = groups.clone();
+                return Ok((ix + skip, self.duplicate_name_backref(&groups)));
+            } else if let Some(group) = self.named_groups.get(id) {
                 Some(*group)
             } else if let Ok(group) = id.parse::<isize>() {
                 group.try_into().map_or_else(
                     |_| {
-                        // relative backref
-                        self.curr_group.checked_add_signed(group + 1)
+                        // relative backref, which can't refer to the whole match
+                        self.curr_group
+                            .checked_add_signed(group + 1)
+                            .filter(|&group| group > 0)
                     },
                     |group| Some(group),
                 )
@@ -307,7 +651,7 @@ impl<'a> Parser<'a> {
                 None
             };
             if let Some(group) = group {
-                return Ok((ix + skip, Expr::Backref(group)));
+                return Ok((ix + skip, self.backref(group)));
             }
             // here the name is parsed but it is invalid
             Err(Error::ParseError(
@@ -325,12 +669,29 @@ impl<'a> Parser<'a> {
             // protect BitSet against unreasonably large value
             if group < self.re.len() / 2 {
                 self.numeric_backrefs = true;
-                return Ok((end, Expr::Backref(group)));
+                return Ok((end, self.backref(group)));
             }
         }
         return Err(Error::ParseError(ix, ParseError::InvalidBackref));
     }
 
+    // ix points to \ character, end to the { after \b
+    fn parse_word_boundary(&self, ix: usize, end: usize) -> Result<(usize, Expr)> {
+        let close = self.re[end..].find('}');
+        let escape_end = close.map_or(end, |close| end + close + 1);
+        let assertion = match close.map(|close| &self.re[end + 1..end + close]) {
+            Some("start") => Assertion::LeftWordBoundary,
+            Some("end") => Assertion::RightWordBoundary,
+            _ => {
+                return Err(Error::ParseError(
+                    ix,
+                    ParseError::InvalidEscape(self.re[ix..escape_end].to_string()),
+                ))
+            }
+        };
+        Ok((escape_end, Expr::Assertion(assertion)))
+    }
+
     // ix points to \ character
     fn parse_escape(&mut self, ix: usize, in_class: bool) -> Result<(usize, Expr)> {
         let bytes = self.re.as_bytes();
@@ -338,28 +699,45 @@ impl<'a> Parser<'a> {
             return Err(Error::ParseError(ix, ParseError::TrailingBackslash));
         };
         let end = ix + 1 + codepoint_len(b);
-        Ok(if is_digit(b) {
+        if self.flag(FLAG_PYTHON) {
+            if let Some(result) = self.parse_python_escape(ix, in_class) {
+                return result;
+            }
+        } else if self.flag(FLAG_ECMASCRIPT) {
+            if let Some(result) = self.parse_ecmascript_escape(ix, in_class) {
+                return result;
+            }
+        }
+        Ok(if b == b'0' || self.is_octal_escape(ix + 1, in_class) {
+            return Ok(self.parse_octal(ix + 1));
+        } else if is_digit(b) {
             return self.parse_numbered_backref(ix + 1);
         } else if matches!(b, b'k') && !in_class {
-            // Named backref: \k<name>
-            if bytes.get(end) == Some(&b'\'') {
-                return self.parse_named_backref(end, "'", "'", true);
-            } else {
-                return self.parse_named_backref(end, "<", ">", true);
-            }
+            // Named backref: \k<name>, \k'name' or \k{name}
+            return match bytes.get(end) {
+                Some(b'\'') => self.parse_named_backref(end, "'", "'", true),
+                Some(b'{') => self.parse_named_backref(end, "{", "}", true),
+                _ => self.parse_named_backref(end, "<", ">", true),
+            };
+        } else if b == b'g'
+            && !in_class
+            && matches!(bytes.get(end), Some(b'{' | b'-' | b'0'..=b'9'))
+        {
+            // Backref as in PCRE: \g1, \g-1, \g{1}, \g{-1} or \g{name}
+            return match bytes[end] {
+                b'{' => self.parse_named_backref(end, "{", "}", true),
+                b'-' => self.parse_named_backref(end, "", "", true),
+                _ => self.parse_numbered_backref(end),
+            };
         } else if b == b'A' && !in_class {
             (end, Expr::Assertion(Assertion::StartText))
         } else if b == b'z' && !in_class {
             (end, Expr::Assertion(Assertion::EndText))
         } else if b == b'b' && !in_class {
             if bytes.get(end) == Some(&b'{') {
-                // Support for \b{...} is not implemented yet
-                return Err(Error::ParseError(
-                    ix,
-                    ParseError::InvalidEscape(format!("\\{}", &self.re[ix + 1..end])),
-                ));
+                return self.parse_word_boundary(ix, end);
             }
-            (end, Expr::Assertion(Assertion::WordBoundary))
+            (end, self.word_boundary(b))
         } else if b == b'B' && !in_class {
             if bytes.get(end) == Some(&b'{') {
                 // Support for \b{...} is not implemented yet
@@ -368,11 +746,26 @@ impl<'a> Parser<'a> {
                     ParseError::InvalidEscape(format!("\\{}", &self.re[ix + 1..end])),
                 ));
             }
-            (end, Expr::Assertion(Assertion::NotWordBoundary))
+            (end, self.word_boundary(b))
         } else if b == b'<' && !in_class {
             (end, Expr::Assertion(Assertion::LeftWordBoundary))
         } else if b == b'>' && !in_class {
             (end, Expr::Assertion(Assertion::RightWordBoundary))
+        } else if matches!(b | 32, b'd' | b's' | b'w') && !self.flag(FLAG_UNICODE) {
+            let chars = match b | 32 {
+                b'd' => "0-9",
+                b's' => r"\t\n\x0B\x0C\r ",
+                _ => "0-9A-Za-z_",
+            };
+            let negation = if b.is_ascii_uppercase() { "^" } else { "" };
+            (
+                end,
+                Expr::Delegate {
+                    inner: format!("[{}{}]", negation, chars),
+                    size: 1,
+                    casei: self.flag(FLAG_CASEI),
+                },
+            )
         } else if matches!(b | 32, b'd' | b's' | b'w') {
             (
                 end,
@@ -382,7 +775,7 @@ impl<'a> Parser<'a> {
                     casei: self.flag(FLAG_CASEI),
                 },
             )
-        } else if (b | 32) == b'h' {
+        } else if (b | 32) == b'h' && !self.flag(FLAG_H_HORIZONTAL_SPACE) {
             let s = if b == b'h' {
                 "[0-9A-Fa-f]"
             } else {
@@ -396,6 +789,23 @@ impl<'a> Parser<'a> {
                     casei: false,
                 },
             )
+        } else if matches!(b | 32, b'h' | b'v') {
+            let chars = if (b | 32) == b'h' {
+                HORIZONTAL_SPACE
+            } else {
+                VERTICAL_SPACE
+            };
+            let negation = if b.is_ascii_uppercase() { "^" } else { "" };
+            (
+                end,
+                Expr::Delegate {
+                    inner: format!("[{}{}]", negation, chars),
+                    size: 1,
+                    casei: false,
+                },
+            )
+        } else if b == b'o' && bytes.get(end) == Some(&b'{') {
+            return self.parse_code_points(end, 8);
         } else if b == b'x' {
             return self.parse_hex(end, 2);
         } else if b == b'u' {
@@ -422,15 +832,31 @@ impl<'a> Parser<'a> {
             (
                 end,
                 Expr::Delegate {
-                    inner: String::from(&self.re[ix..end]),
+                    inner: unicode_property(&self.re[ix..end]),
                     size: 1,
                     casei: self.flag(FLAG_CASEI),
                 },
             )
+        } else if b == b'Q' && !in_class {
+            // quote the pattern until `\E` or the end
+            self.quoting = true;
+            (end, Expr::Empty)
+        } else if b == b'E' && !in_class {
+            // `\E` without `\Q` is ignored
+            (end, Expr::Empty)
         } else if b == b'K' && !in_class {
             (end, Expr::KeepOut)
         } else if b == b'G' && !in_class {
             (end, Expr::ContinueFromPreviousMatchEnd)
+        } else if b == b'X' && !in_class {
+            (end, Expr::GraphemeCluster)
+        } else if b == b'R' && !in_class {
+            (end, self.line_break())
+        } else if b == b'N' && bytes.get(end) == Some(&b'{') {
+            return self.parse_unicode_name(ix, end);
+        } else if b == b'N' && !in_class {
+            // any character except a newline, regardless of the `s` flag
+            (end, Expr::Any { newline: false })
         } else {
             // printable ASCII (including space, see issue #29)
             (
@@ -442,7 +868,6 @@ impl<'a> Parser<'a> {
                     b'n' => "\n",   // LF
                     b'r' => "\r",   // CR
                     b't' => "\t",   // TAB
-                    b'v' => "\x0b", // VT
                     b'e' => "\x1b", // ESC
                     b' ' => " ",
                     b => {
@@ -466,8 +891,226 @@ impl<'a> Parser<'a> {
         })
     }
 
+    /// Parses the escapes that are different in the Python dialect, or returns `None` for the
+    /// escapes that are the same. ix points to the `\`.
+    fn parse_python_escape(&mut self, ix: usize, in_class: bool) -> Option<Result<(usize, Expr)>> {
+        let bytes = self.re.as_bytes();
+        let b = bytes[ix + 1];
+        let end = ix + 2;
+        let known = if in_class {
+            &b"abdDfnrsStvwWxuU"[..]
+        } else {
+            &b"abBdDfnrsStvwWZAxuU"[..]
+        };
+        Some(Ok(match b {
+            b'1'..=b'9' => {
+                let digits = &bytes[ix + 1..bytes.len().min(ix + 4)];
+                let octal = digits.len() == 3 && digits.iter().all(|b| matches!(b, b'0'..=b'7'));
+                if octal && digits[0] > b'3' {
+                    // more than 0o377
+                    return Some(Err(Error::ParseError(ix, ParseError::InvalidOctal)));
+                } else if octal || in_class && b <= b'7' {
+                    return Some(Ok(self.parse_octal(ix + 1)));
+                } else if in_class {
+                    return Some(Err(Error::ParseError(
+                        ix,
+                        ParseError::InvalidEscape(format!("\\{}", b as char)),
+                    )));
+                }
+                // at most two digits
+                let end = if digits.get(1).map_or(false, |&b| is_digit(b)) {
+                    end + 1
+                } else {
+                    end
+                };
+                let group = self.re[ix + 1..end].parse().unwrap();
+                (end, self.backref(group))
+            }
+            b'Z' if !in_class => (end, Expr::Assertion(Assertion::EndText)),
+            b'v' => (end, make_literal("\x0B")),
+            b'b' | b'B' if !in_class => (end, self.word_boundary(b)),
+            b'N' if bytes.get(end) == Some(&b'{') => return Some(self.parse_unicode_name(ix, end)),
+            b if b.is_ascii_alphabetic() && !known.contains(&b) => {
+                return Some(Err(Error::ParseError(
+                    ix,
+                    ParseError::InvalidEscape(format!("\\{}", b as char)),
+                )));
+            }
+            b if !b.is_ascii_alphanumeric() => {
+                let end = ix + 1 + codepoint_len(b);
+                (end, make_literal(&self.re[ix + 1..end]))
+            }
+            _ => return None,
+        }))
+    }
+
+    /// Parses the escapes that are different in the ECMAScript dialects, or returns `None` for
+    /// the escapes that are the same. ix points to the `\`.
+    fn parse_ecmascript_escape(
+        &mut self,
+        ix: usize,
+        in_class: bool,
+    ) -> Option<Result<(usize, Expr)>> {
+        let bytes = self.re.as_bytes();
+        let b = bytes[ix + 1];
+        let end = ix + 1 + codepoint_len(b);
+        let unicode = self.flag(FLAG_ECMASCRIPT_UNICODE);
+        let invalid = |error| Some(Err(Error::ParseError(ix, error)));
+        let identity = (end, make_literal(&self.re[ix + 1..end]));
+        // without the `u` flag, `\k` is only a named backref if there are named groups
+        let named_backref = b == b'k'
+            && !in_class
+            && (unicode
+                || !self.named_groups.is_empty()
+                || match &self.all_named_groups {
+                    Some(all_named_groups) => !all_named_groups.is_empty(),
+                    None => {
+                        // the groups may be named later, which is resolved by parsing again
+                        self.forward_refs = true;
+                        false
+                    }
+                });
+        Some(Ok(match b {
+            b'0' if !bytes.get(end).map_or(false, |&b| is_digit(b)) => (end, make_literal("\0")),
+            b'1'..=b'9' if !in_class => {
+                let len = bytes[ix + 1..].iter().take_while(|&&b| is_digit(b)).count();
+                let group = self.re[ix + 1..ix + 1 + len].parse().unwrap_or(usize::MAX);
+                let exists = match self.all_groups {
+                    Some(groups) => group <= groups,
+                    None => {
+                        // the group may be after the backref, which is resolved by parsing again
+                        self.forward_refs |= group > self.curr_group;
+                        true
+                    }
+                };
+                if exists {
+                    (ix + 1 + len, self.ecmascript_backref(group))
+                } else if unicode {
+                    return invalid(ParseError::InvalidBackref);
+                } else if b <= b'7' {
+                    self.parse_legacy_octal(ix + 1)
+                } else {
+                    identity
+                }
+            }
+            b'k' if named_backref => {
+                let Some((id, skip)) = parse_id(&self.re[end..], "<", ">", false) else {
+                    return invalid(ParseError::InvalidGroupName);
+                };
+                match self.resolve_group_name(end, id) {
+                    Ok(group) => (end + skip, self.ecmascript_backref(group)),
+                    Err(error) => return Some(Err(error)),
+                }
+            }
+            b'c' if bytes.get(end).map_or(false, |&b| {
+                b.is_ascii_alphabetic() || in_class && !unicode && (is_digit(b) || b == b'_')
+            }) =>
+            {
+                let c = char::from(bytes[end] % 32);
+                (end + 1, make_literal(c.encode_utf8(&mut [0; 4])))
+            }
+            b's' | b'S' => {
+                let negation = if b == b'S' { "^" } else { "" };
+                (
+                    end,
+                    Expr::Delegate {
+                        inner: format!("[{}{}]", negation, ECMASCRIPT_SPACE),
+                        size: 1,
+                        casei: false,
+                    },
+                )
+            }
+            b'b' | b'B' if !in_class => (end, self.word_boundary(b)),
+            b'v' => (end, make_literal("\x0B")),
+            b'x' => {
+                if self
+                    .re
+                    .get(end..end + 2)
+                    .map_or(false, |s| s.bytes().all(is_hex_digit))
+                {
+                    return None;
+                } else if unicode {
+                    return invalid(ParseError::InvalidHex);
+                }
+                identity
+            }
+            b'u' if unicode && bytes.get(end) == Some(&b'{') => {
+                return Some(self.parse_code_points(end, 16));
+            }
+            b'u' => match self.parse_utf16_escape(end) {
+                Some(result) => return Some(result),
+                None if unicode => return invalid(ParseError::InvalidHex),
+                None => identity,
+            },
+            b'p' | b'P' if unicode && bytes.get(end) == Some(&b'{') => return None,
+            b'b' | b'd' | b'D' | b'w' | b'W' | b'f' | b'n' | b'r' | b't' => return None,
+            b'0'..=b'9' if unicode => {
+                return invalid(ParseError::InvalidEscape(format!("\\{}", b as char)))
+            }
+            b'0'..=b'7' => self.parse_legacy_octal(ix + 1),
+            // `\` followed by `c` that isn't a control escape
+            b'c' if !unicode => (ix + 1, make_literal("\\")),
+            _ if !unicode => identity,
+            b'-' if in_class => identity,
+            b'^' | b'$' | b'\\' | b'.' | b'*' | b'+' | b'?' | b'(' | b')' | b'[' | b']' | b'{'
+            | b'}' | b'|' | b'/' => identity,
+            b'&' | b'!' | b'#' | b'%' | b',' | b':' | b';' | b'<' | b'=' | b'>' | b'@' | b'`'
+            | b'~'
+                if in_class && self.flag(FLAG_ECMASCRIPT_UNICODE_SETS) =>
+            {
+                identity
+            }
+            _ => return invalid(ParseError::InvalidEscape(self.re[ix..end].to_string())),
+        }))
+    }
+
+    /// Parses a `\u` escape of four hex digits in ECMAScript, which can be followed by another
+    /// one for a surrogate pair, e.g. `\uD83D\uDE00`. ix points after the `\u`.
+    fn parse_utf16_escape(&self, ix: usize) -> Option<Result<(usize, Expr)>> {
+        let code_unit = |ix: usize| {
+            let digits = self.re.get(ix..ix + 4)?;
+            if !digits.bytes().all(is_hex_digit) {
+                return None;
+            }
+            u32::from_str_radix(digits, 16).ok()
+        };
+        let mut codepoint = code_unit(ix)?;
+        let mut end = ix + 4;
+        if (0xD800..0xDC00).contains(&codepoint) && self.re[end..].starts_with("\\u") {
+            if let Some(low) = code_unit(end + 2).filter(|low| (0xDC00..0xE000).contains(low)) {
+                codepoint = 0x10000 + ((codepoint - 0xD800) << 10) + (low - 0xDC00);
+                end += 6;
+            }
+        }
+        // a lone surrogate can't be in the text
+        let Some(c) = char::from_u32(codepoint) else {
+            return Some(Err(Error::ParseError(
+                ix,
+                ParseError::InvalidCodepointValue,
+            )));
+        };
+        Some(Ok((
+            end,
+            Expr::Literal {
+                val: c.to_string(),
+                casei: self.flag(FLAG_CASEI),
+            },
+        )))
+    }
+
+    /// Parses a legacy octal escape of Annex B of ECMAScript, which is at most `\377`. ix points
+    /// to the first digit, which must be an octal digit.
+    fn parse_legacy_octal(&self, ix: usize) -> (usize, Expr) {
+        let (end, expr) = self.parse_octal(ix);
+        if end - ix == 3 && self.re.as_bytes()[ix] > b'3' {
+            self.parse_octal_digits(ix, 2)
+        } else {
+            (end, expr)
+        }
+    }
+
     // ix points after '\x', eg to 'A0' or '{12345}', or after `\u` or `\U`
-    fn parse_hex(&self, ix: usize, digits: usize) -> Result<(usize, Expr)> {
+    fn parse_hex(&mut self, ix: usize, digits: usize) -> Result<(usize, Expr)> {
         if ix >= self.re.len() {
             // Incomplete escape sequence
             return Err(Error::ParseError(ix, ParseError::InvalidHex));
@@ -480,23 +1123,7 @@ impl<'a> Parser<'a> {
             let end = ix + digits;
             (end, &self.re[ix..end])
         } else if b == b'{' {
-            let starthex = ix + 1;
-            let mut endhex = starthex;
-            loop {
-                if endhex == self.re.len() {
-                    return Err(Error::ParseError(ix, ParseError::InvalidHex));
-                }
-                let b = bytes[endhex];
-                if endhex > starthex && b == b'}' {
-                    break;
-                }
-                if is_hex_digit(b) && endhex < starthex + 8 {
-                    endhex += 1;
-                } else {
-                    return Err(Error::ParseError(ix, ParseError::InvalidHex));
-                }
-            }
-            (endhex + 1, &self.re[starthex..endhex])
+            return self.parse_code_points(ix, 16);
         } else {
             return Err(Error::ParseError(ix, ParseError::InvalidHex));
         };
@@ -516,6 +1143,109 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the code points in braces after `\x` or `\o`, e.g. `{41}`, which can be several
+    /// code points separated by spaces as in Oniguruma, e.g. `{41 42}`. ix points to the `{`.
+    fn parse_code_points(&mut self, ix: usize, radix: u32) -> Result<(usize, Expr)> {
+        let invalid = || {
+            let error = if radix == 16 {
+                ParseError::InvalidHex
+            } else {
+                ParseError::InvalidOctal
+            };
+            Error::ParseError(ix, error)
+        };
+        let len = self.re[ix..].find('}').ok_or_else(invalid)?;
+        let mut literals = Vec::new();
+        for digits in self.re[ix + 1..ix + len].split(' ') {
+            if digits.is_empty() {
+                continue;
+            }
+            if digits.len() > 8 || !digits.chars().all(|c| c.is_digit(radix)) {
+                return Err(invalid());
+            }
+            let codepoint = u32::from_str_radix(digits, radix).unwrap();
+            let Some(c) = char::from_u32(codepoint) else {
+                return Err(Error::ParseError(ix, ParseError::InvalidCodepointValue));
+            };
+            literals.push(Expr::Literal {
+                val: c.to_string(),
+                casei: self.flag(FLAG_CASEI),
+            });
+        }
+        let expr = match literals.len() {
+            0 => return Err(invalid()),
+            1 => literals.pop().unwrap(),
+            _ => Expr::Concat(self.push_all(literals)),
+        };
+        Ok((ix + len + 1, expr))
+    }
+
+    /// Parses a character given by its code point after `\N`, e.g. `{U+41}`, like in PCRE. Names
+    /// of characters, e.g. `\N{LATIN CAPITAL LETTER A}`, aren't supported. ix points to the `\`
+    /// and open to the `{`.
+    fn parse_unicode_name(&self, ix: usize, open: usize) -> Result<(usize, Expr)> {
+        let Some(len) = self.re[open..].find('}') else {
+            return Err(Error::ParseError(ix, ParseError::UnclosedUnicodeName));
+        };
+        let end = open + len + 1;
+        let Some(digits) = self.re[open + 1..end - 1].strip_prefix("U+") else {
+            return Err(Error::ParseError(
+                ix,
+                ParseError::UnicodeNameUnsupported(self.re[ix..end].to_string()),
+            ));
+        };
+        if digits.is_empty() || digits.len() > 8 || !digits.bytes().all(is_hex_digit) {
+            return Err(Error::ParseError(ix, ParseError::InvalidHex));
+        }
+        let codepoint = u32::from_str_radix(digits, 16).unwrap();
+        let Some(c) = char::from_u32(codepoint) else {
+            return Err(Error::ParseError(ix, ParseError::InvalidCodepointValue));
+        };
+        Ok((
+            end,
+            Expr::Literal {
+                val: c.to_string(),
+                casei: self.flag(FLAG_CASEI),
+            },
+        ))
+    }
+
+    /// Returns whether `\` followed by the digits at `ix` is an octal escape instead of a backref.
+    /// With the octal escapes option, this is like in PCRE: digits that start with an octal digit
+    /// are an octal escape in a character class, or otherwise if they are at least 10 and there
+    /// aren't that many groups before.
+    fn is_octal_escape(&self, ix: usize, in_class: bool) -> bool {
+        if !self.flag(FLAG_OCTAL_ESCAPES) || !matches!(self.re.as_bytes()[ix], b'1'..=b'7') {
+            return false;
+        }
+        in_class || parse_decimal(self.re, ix).map_or(true, |(_, n)| n >= 10 && n > self.curr_group)
+    }
+
+    /// Parses an octal escape of up to three octal digits, e.g. `\012`. ix points to the first
+    /// digit, which must be an octal digit.
+    fn parse_octal(&self, ix: usize) -> (usize, Expr) {
+        self.parse_octal_digits(ix, 3)
+    }
+
+    /// Parses an octal escape of up to `max_digits` octal digits. ix points to the first digit.
+    fn parse_octal_digits(&self, ix: usize, max_digits: usize) -> (usize, Expr) {
+        let bytes = self.re.as_bytes();
+        let mut end = ix;
+        while end < ix + max_digits && matches!(bytes.get(end), Some(b'0'..=b'7')) {
+            end += 1;
+        }
+        let codepoint = u32::from_str_radix(&self.re[ix..end], 8).unwrap();
+        // at most 0o777
+        let c = char::from_u32(codepoint).unwrap();
+        (
+            end,
+            Expr::Literal {
+                val: c.to_string(),
+                casei: self.flag(FLAG_CASEI),
+            },
+        )
+    }
+
     fn parse_class(&mut self, ix: usize) -> Result<(usize, Expr)> {
         let bytes = self.re.as_bytes();
         let mut ix = ix + 1; // skip opening '['
@@ -524,32 +1254,106 @@ impl<'a> Parser<'a> {
         class.push('[');
 
         // Negated character class
-        if bytes.get(ix) == Some(&b'^') {
+        let negated = bytes.get(ix) == Some(&b'^');
+        if negated {
             class.push('^');
             ix += 1;
         }
 
+        // `[]` doesn't match anything and `[^]` matches any character in ECMAScript
+        let ecmascript = self.flag(FLAG_ECMASCRIPT);
+        if ecmascript && bytes.get(ix) == Some(&b']') {
+            let negation = if negated { "" } else { "^" };
+            let class = Expr::Delegate {
+                inner: format!(r"[{}\x00-\x{{10FFFF}}]", negation),
+                size: 1,
+                casei: false,
+            };
+            return Ok((ix + 1, class));
+        }
+
+        // In the Python dialect and ECMAScript without the `v` flag, there are no nested classes
+        // or set operations, and a `-` after an item of the class makes a range with the next
+        // item, unless it's followed by the closing `]`, and otherwise it's a literal. A range
+        // can't start or end with a class escape such as `\d`, except that with the tolerances
+        // of Annex B of ECMAScript, the `-` is a literal then.
+        let python = self.flag(FLAG_PYTHON);
+        let unicode_sets = self.flag(FLAG_ECMASCRIPT_UNICODE_SETS);
+        let literal_syntax = python || ecmascript && !unicode_sets;
+        let annex_b = self.is_annex_b();
+        let mut after_item = false;
+        let mut after_class_escape = false;
+        let mut range_end = false;
+
         // `]` does not have to be escaped after opening `[` or `[^`
         if bytes.get(ix) == Some(&b']') {
-            class.push(']');
+            // escaped so that the regex crate also uses it as the start of a range
+            class.push_str(if python { "\\]" } else { "]" });
             ix += 1;
+            after_item = true;
         }
 
         loop {
             if ix == self.re.len() {
                 return Err(Error::ParseError(ix, ParseError::InvalidClass));
             }
+            let mut range = false;
+            let mut class_escape = false;
             let end = match bytes[ix] {
+                b'-' if literal_syntax => {
+                    range = after_item && bytes.get(ix + 1) != Some(&b']');
+                    if range && (after_class_escape || self.is_class_escape(ix + 1)) {
+                        if !annex_b {
+                            return Err(Error::ParseError(ix, ParseError::InvalidClass));
+                        }
+                        range = false;
+                    }
+                    class.push_str(if range { "-" } else { "\\-" });
+                    ix + 1
+                }
+                b'-' if unicode_sets
+                    && bytes[ix - 1] != b'-'
+                    && bytes.get(ix + 1) != Some(&b'-')
+                    && (after_class_escape || self.is_class_escape(ix + 1)) =>
+                {
+                    return Err(Error::ParseError(ix, ParseError::InvalidClass));
+                }
+                b @ (b'[' | b'&' | b'~') if literal_syntax => {
+                    // no nested classes or set operations
+                    class.push('\\');
+                    class.push(b as char);
+                    ix + 1
+                }
+                b'\\'
+                    if self.re[ix..].starts_with("\\Q")
+                        && !self.flag(FLAG_PYTHON | FLAG_ECMASCRIPT) =>
+                {
+                    let quoted = &self.re[ix + 2..];
+                    let len = quoted.find("\\E").unwrap_or(quoted.len());
+                    for c in quoted[..len].chars() {
+                        escape_into(c.encode_utf8(&mut [0; 4]), &mut class);
+                    }
+                    (ix + 2 + len + 2).min(self.re.len())
+                }
                 b'\\' => {
                     // We support more escapes than regex, so parse it ourselves before delegating.
                     let (end, expr) = self.parse_escape(ix, true)?;
                     match expr {
                         Expr::Literal { val, .. } => {
                             debug_assert_eq!(val.chars().count(), 1);
-                            escape_into(&val, &mut class);
+                            self.push_class_literal(&val, &mut class);
+                        }
+                        Expr::Concat(literals) => {
+                            // several code points, e.g. `\x{41 42}`
+                            for literal in literals {
+                                if let Expr::Literal { ref val, .. } = self.arena[literal] {
+                                    self.push_class_literal(val, &mut class);
+                                }
+                            }
                         }
                         Expr::Delegate { inner, .. } => {
                             class.push_str(&inner);
+                            class_escape = true;
                         }
                         _ => {
                             return Err(Error::ParseError(ix, ParseError::InvalidClass));
@@ -557,11 +1361,28 @@ impl<'a> Parser<'a> {
                     }
                     end
                 }
-                b'[' => {
+                b'[' if unicode_sets => {
+                    // a nested class, which is never a POSIX bracket expression
                     nest += 1;
                     class.push('[');
-                    ix + 1
+                    if bytes.get(ix + 1) == Some(&b':') {
+                        class.push_str("\\:");
+                        ix + 2
+                    } else {
+                        ix + 1
+                    }
                 }
+                b'[' => match self.parse_posix_class(ix) {
+                    Some((end, unicode_class)) => {
+                        class.push_str(&unicode_class);
+                        end
+                    }
+                    None => {
+                        nest += 1;
+                        class.push('[');
+                        ix + 1
+                    }
+                },
                 b']' => {
                     nest -= 1;
                     class.push(']');
@@ -570,12 +1391,29 @@ impl<'a> Parser<'a> {
                     }
                     ix + 1
                 }
+                b'(' | b')' | b'{' | b'}' | b'/' | b'|' if unicode_sets => {
+                    // syntax characters have to be escaped with the `v` flag
+                    return Err(Error::ParseError(ix, ParseError::InvalidClass));
+                }
+                b'!' | b'#' | b'$' | b'%' | b'*' | b'+' | b',' | b'.' | b':' | b';' | b'<'
+                | b'=' | b'>' | b'?' | b'@' | b'^' | b'`' | b'~'
+                    if unicode_sets && bytes.get(ix + 1) == Some(&bytes[ix]) =>
+                {
+                    // reserved double punctuators with the `v` flag
+                    return Err(Error::ParseError(ix, ParseError::InvalidClass));
+                }
+                b'&' if unicode_sets && self.re[ix..].starts_with("&&&") => {
+                    return Err(Error::ParseError(ix, ParseError::InvalidClass));
+                }
                 b => {
                     let end = ix + codepoint_len(b);
                     class.push_str(&self.re[ix..end]);
                     end
                 }
             };
+            after_item = !range && !range_end;
+            after_class_escape = class_escape;
+            range_end = range;
             ix = end;
         }
         let class = Expr::Delegate {
@@ -587,12 +1425,36 @@ impl<'a> Parser<'a> {
         Ok((ix, class))
     }
 
+    /// Adds the escaped code point `val` to `class`, as a byte if Unicode is disabled.
+    fn push_class_literal(&self, val: &str, class: &mut String) {
+        match byte_escape(val) {
+            Some(escape) if self.flag(FLAG_NON_UNICODE) => class.push_str(&escape),
+            _ => escape_into(val, class),
+        }
+    }
+
     fn parse_group(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         let depth = depth + 1;
         if depth >= MAX_RECURSION {
             return Err(Error::ParseError(ix, ParseError::RecursionExceeded));
         }
         let ix = self.optional_whitespace(ix + 1)?;
+        if self.flag(FLAG_ECMASCRIPT) {
+            self.check_ecmascript_group(ix)?;
+        }
+        for (prefix, atomic) in [
+            ("*script_run:", false),
+            ("*sr:", false),
+            ("*atomic_script_run:", true),
+            ("*asr:", true),
+        ] {
+            if self.re[ix..].starts_with(prefix) {
+                return self.parse_script_run(ix + prefix.len(), depth, atomic);
+            }
+        }
+        if self.re[ix..].starts_with('*') {
+            return self.parse_backtracking_verb(ix + 1);
+        }
         let (la, skip) = if self.re[ix..].starts_with("?=") {
             (Some(LookAhead), 2)
         } else if self.re[ix..].starts_with("?!") {
@@ -601,20 +1463,37 @@ impl<'a> Parser<'a> {
             (Some(LookBehind), 3)
         } else if self.re[ix..].starts_with("?<!") {
             (Some(LookBehindNeg), 3)
+        } else if parse_id(&self.re[ix..], "?<-", ">", false).is_some()
+            || parse_id(&self.re[ix..], "?<", "-", false).is_some()
+        {
+            return self.parse_balancing_group(ix + 2, ">", depth);
+        } else if parse_id(&self.re[ix..], "?'-", "'", false).is_some()
+            || parse_id(&self.re[ix..], "?'", "-", false).is_some()
+        {
+            return self.parse_balancing_group(ix + 2, "'", depth);
         } else if self.re[ix..].starts_with("?<") {
             // Named capture group using Oniguruma syntax: (?<name>...)
             self.curr_group += 1;
             if let Some((id, skip)) = parse_id(&self.re[ix + 1..], "<", ">", false) {
-                self.named_groups.insert(id.to_string(), self.curr_group);
+                self.name_group(id, self.curr_group);
                 (None, skip + 1)
             } else {
                 return Err(Error::ParseError(ix, ParseError::InvalidGroupName));
             }
-        } else if self.re[ix..].starts_with("?P<") {
+        } else if self.re[ix..].starts_with("?'") {
+            // Named capture group using Perl and .NET syntax: (?'name'...)
+            self.curr_group += 1;
+            if let Some((id, skip)) = parse_id(&self.re[ix + 1..], "'", "'", false) {
+                self.name_group(id, self.curr_group);
+                (None, skip + 1)
+            } else {
+                return Err(Error::ParseError(ix, ParseError::InvalidGroupName));
+            }
+        } else if self.re[ix..].starts_with("?P<") {
             // Named capture group using Python syntax: (?P<name>...)
             self.curr_group += 1; // this is a capture group
             if let Some((id, skip)) = parse_id(&self.re[ix + 2..], "<", ">", false) {
-                self.named_groups.insert(id.to_string(), self.curr_group);
+                self.name_group(id, self.curr_group);
                 (None, skip + 2)
             } else {
                 return Err(Error::ParseError(ix, ParseError::InvalidGroupName));
@@ -622,27 +1501,176 @@ impl<'a> Parser<'a> {
         } else if self.re[ix..].starts_with("?P=") {
             // Backref using Python syntax: (?P=name)
             return self.parse_named_backref(ix + 3, "", ")", false);
+        } else if is_subroutine_call(&self.re[ix..]) {
+            return self.parse_subroutine_call(ix + 1);
+        } else if self.re[ix..].starts_with("?|") {
+            return self.parse_branch_reset(ix + 2, depth);
         } else if self.re[ix..].starts_with("?>") {
             (None, 2)
         } else if self.re[ix..].starts_with("?(") {
             return self.parse_conditional(ix + 2, depth);
+        } else if self.re[ix..].starts_with("?~") {
+            return self.parse_absent(ix + 2, depth);
+        } else if self.re[ix..].starts_with("?C") {
+            return self.parse_callout(ix + 2);
+        } else if self.re[ix..].starts_with("?{") {
+            return self.parse_predicate(ix + 2);
         } else if self.re[ix..].starts_with('?') {
             return self.parse_flags(ix, depth);
+        } else if self.flag(FLAG_EXPLICIT_CAPTURE) {
+            // only named groups capture, so this is the same as `(?:...)`
+            let (ix, child) = self.parse_re(ix, depth)?;
+            let ix = self.check_for_close_paren(ix)?;
+            return Ok((ix, child));
         } else {
             self.curr_group += 1; // this is a capture group
             (None, 0)
         };
         let ix = ix + skip;
+        let capture = la.is_none() && skip != 2;
+        if capture {
+            self.open_groups.push(self.curr_group);
+        }
         let (ix, child) = self.parse_re(ix, depth)?;
+        if capture {
+            self.open_groups.pop();
+        }
         let ix = self.check_for_close_paren(ix)?;
         let result = match (la, skip) {
-            (Some(la), _) => Expr::LookAround(Box::new(child), la),
-            (None, 2) => Expr::AtomicGroup(Box::new(child)),
-            _ => Expr::Group(Box::new(child)),
+            (Some(la), _) => Expr::LookAround(self.arena.push(child), la),
+            (None, 2) => Expr::AtomicGroup(self.arena.push(child)),
+            _ => Expr::Group(self.arena.push(child)),
         };
         Ok((ix, result))
     }
 
+    /// Checks that a group is one of the groups of ECMAScript: a capture group, a named group
+    /// `(?<name>...)`, a non-capturing group with optional flags such as `(?i:...)`, or a
+    /// look-around. ix points to after the `(`.
+    fn check_ecmascript_group(&self, ix: usize) -> Result<()> {
+        let rest = &self.re[ix..];
+        if rest.starts_with('*') {
+            return Err(Error::ParseError(ix, ParseError::TargetNotRepeatable));
+        }
+        let Some(rest) = rest.strip_prefix('?') else {
+            return Ok(());
+        };
+        if rest.starts_with(&['=', '!'][..])
+            || rest.starts_with("<=")
+            || rest.starts_with("<!")
+            || parse_id(rest, "<", ">", false).is_some()
+        {
+            return Ok(());
+        }
+        let flags_len = rest
+            .find(|c| !matches!(c, 'i' | 'm' | 's' | '-'))
+            .unwrap_or(rest.len());
+        let after_flags = &rest[flags_len..];
+        if after_flags.starts_with(':') {
+            return Ok(());
+        }
+        let end = ix + 1 + flags_len + after_flags.chars().next().map_or(0, char::len_utf8);
+        Err(Error::ParseError(
+            ix,
+            ParseError::UnknownFlag(format!("({}", &self.re[ix..end])),
+        ))
+    }
+
+    // ix points to after `*` in `(*`
+    fn parse_backtracking_verb(&self, ix: usize) -> Result<(usize, Expr)> {
+        let Some(len) = self.re[ix..].find(')') else {
+            return Err(Error::ParseError(ix, ParseError::UnclosedOpenParen));
+        };
+        let name = &self.re[ix..ix + len];
+        let verb = match name {
+            "FAIL" | "F" => BacktrackingVerb::Fail,
+            "ACCEPT" => BacktrackingVerb::Accept,
+            "PRUNE" => BacktrackingVerb::Prune,
+            "SKIP" => BacktrackingVerb::Skip,
+            "THEN" => BacktrackingVerb::Then,
+            _ => {
+                return Err(Error::ParseError(
+                    ix,
+                    ParseError::UnknownBacktrackingVerb(name.to_string()),
+                ))
+            }
+        };
+        Ok((ix + len + 1, Expr::BacktrackingVerb(verb)))
+    }
+
+    // ix points to after `:` in `(*sr:`
+    fn parse_script_run(&mut self, ix: usize, depth: usize, atomic: bool) -> Result<(usize, Expr)> {
+        let (ix, mut child) = self.parse_re(ix, depth)?;
+        let ix = self.check_for_close_paren(ix)?;
+        if atomic {
+            // like `(*sr:(?>...))`
+            child = Expr::AtomicGroup(self.arena.push(child));
+        }
+        Ok((ix, Expr::ScriptRun(self.arena.push(child))))
+    }
+
+    // ix points to after `?~` in `(?~`
+    fn parse_absent(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        if self.re[ix..].starts_with('|') {
+            // the forms with an absent stopper or range clear aren't supported
+            return Err(Error::ParseError(
+                ix - 1,
+                ParseError::UnknownFlag("(?~|".to_string()),
+            ));
+        }
+        let (ix, child) = self.parse_re(ix, depth)?;
+        let ix = self.check_for_close_paren(ix)?;
+        Ok((ix, Expr::Absent(self.arena.push(child))))
+    }
+
+    // ix points to after `?C` in `(?C`
+    fn parse_callout(&self, ix: usize) -> Result<(usize, Expr)> {
+        let bytes = self.re.as_bytes();
+        let invalid = |ix| Error::ParseError(ix, ParseError::InvalidCallout);
+        let (end, id) = match bytes.get(ix) {
+            Some(b')') => (ix, CalloutId::Number(0)),
+            Some(&b) if is_digit(b) => {
+                let (end, number) = parse_decimal(self.re, ix).ok_or_else(|| invalid(ix))?;
+                (end, CalloutId::Number(number))
+            }
+            Some(&open) if b"`'\"^%#${".contains(&open) => {
+                let close = if open == b'{' { '}' } else { open as char };
+                let mut tag = String::new();
+                let mut end = ix + 1;
+                loop {
+                    let Some(len) = self.re[end..].find(close) else {
+                        return Err(invalid(ix));
+                    };
+                    tag.push_str(&self.re[end..end + len]);
+                    end += len + 1;
+                    // a doubled delimiter stands for itself
+                    if bytes.get(end) != Some(&(close as u8)) {
+                        break;
+                    }
+                    tag.push(close);
+                    end += 1;
+                }
+                (end, CalloutId::String(tag))
+            }
+            _ => return Err(invalid(ix)),
+        };
+        if bytes.get(end) != Some(&b')') {
+            return Err(invalid(end));
+        }
+        Ok((end + 1, Expr::Callout(id)))
+    }
+
+    // ix points to after `(?{`
+    fn parse_predicate(&mut self, ix: usize) -> Result<(usize, Expr)> {
+        let Some((name, skip)) = parse_id(&self.re[ix..], "", "})", false) else {
+            return Err(Error::ParseError(ix, ParseError::InvalidCallout));
+        };
+        if !self.predicates.iter().any(|predicate| predicate == name) {
+            self.predicates.push(name.to_string());
+        }
+        Ok((ix + skip, Expr::Predicate(name.to_string())))
+    }
+
     fn check_for_close_paren(&self, ix: usize) -> Result<usize> {
         let ix = self.optional_whitespace(ix)?;
         if ix == self.re.len() {
@@ -679,12 +1707,20 @@ impl<'a> Parser<'a> {
                 b'i' => self.update_flag(FLAG_CASEI, neg),
                 b'm' => self.update_flag(FLAG_MULTI, neg),
                 b's' => self.update_flag(FLAG_DOTNL, neg),
-                b'U' => self.update_flag(FLAG_SWAP_GREED, neg),
+                b'U' if !self.flag(FLAG_PYTHON) => self.update_flag(FLAG_SWAP_GREED, neg),
+                b'J' if !self.flag(FLAG_PYTHON) => self.update_flag(FLAG_DUPNAMES, neg),
+                b'n' if !self.flag(FLAG_PYTHON) => self.update_flag(FLAG_EXPLICIT_CAPTURE, neg),
                 b'x' => self.update_flag(FLAG_IGNORE_SPACE, neg),
                 b'u' => {
-                    if neg {
+                    if neg && !self.flag(FLAG_BYTES) {
                         return Err(Error::ParseError(ix, ParseError::NonUnicodeUnsupported));
                     }
+                    self.update_flag(FLAG_UNICODE, neg);
+                    self.update_flag(FLAG_NON_UNICODE, !neg);
+                }
+                b'a' if self.flag(FLAG_PYTHON) && !neg => {
+                    // ASCII-only classes and word boundaries
+                    self.update_flag(FLAG_UNICODE, true);
                 }
                 b'-' => {
                     if neg {
@@ -696,6 +1732,16 @@ impl<'a> Parser<'a> {
                     if ix == start || neg && ix == start + 1 {
                         return Err(unknown_flag(self.re, start, ix));
                     }
+                    if self.flag(FLAG_PYTHON) {
+                        // the flags apply to the whole pattern, so they have to be at the start
+                        if self.optional_whitespace(self.global_flags_end)? != start - 2 {
+                            return Err(Error::ParseError(
+                                start - 2,
+                                ParseError::GlobalFlagsNotAtStart,
+                            ));
+                        }
+                        self.global_flags_end = ix + 1;
+                    }
                     return Ok((ix + 1, Expr::Empty));
                 }
                 b':' => {
@@ -721,30 +1767,256 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // ix points to after `(?(DEFINE)`
+    fn parse_define(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        let (end, child) = self.parse_re(ix, depth)?;
+        let after = self.check_for_close_paren(end)?;
+        // the groups are only defined to be called as subroutines, they never match directly,
+        // like with `(?:...){0}`
+        Ok((
+            after,
+            Expr::Repeat {
+                child: self.arena.push(child),
+                lo: 0,
+                hi: 0,
+                greedy: true,
+            },
+        ))
+    }
+
+    // ix points to after `(?|`
+    fn parse_branch_reset(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
+        // the groups of each alternative are numbered from the same number, and the groups
+        // after the branch reset group are numbered after the alternative with the most groups
+        let start_group = self.curr_group;
+        let mut end_group = start_group;
+        let mut children = Vec::new();
+        let mut ix = ix;
+        let oldflags = self.flags;
+        loop {
+            self.curr_group = start_group;
+            let (next, child) = self.parse_branch(ix, depth)?;
+            end_group = end_group.max(self.curr_group);
+            children.push(child);
+            ix = self.optional_whitespace(next)?;
+            if !self.re[ix..].starts_with('|') {
+                break;
+            }
+            ix += 1;
+        }
+        self.flags = oldflags;
+        self.curr_group = end_group;
+        let ix = self.check_for_close_paren(ix)?;
+        let expr = if end_group > start_group {
+            Expr::BranchReset(self.push_all(children))
+        } else if children.len() == 1 {
+            // without groups, it's the same as a non-capturing group
+            children.pop().unwrap()
+        } else {
+            Expr::Alt(self.push_all(children))
+        };
+        Ok((ix, expr))
+    }
+
+    /// Names the group `group` `id`. Without `(?J)`, a name that is used again only names the
+    /// last group with the name.
+    fn name_group(&mut self, id: &str, group: usize) {
+        let first = match self.named_groups.get(id) {
+            Some(&first) if first != group && self.flag(FLAG_DUPNAMES) => first,
+            _ => {
+                self.named_groups.insert(id.to_string(), group);
+                self.duplicate_groups.remove(id);
+                return;
+            }
+        };
+        let groups = self
+            .duplicate_groups
+            .entry(id.to_string())
+            .or_insert_with(|| vec![first]);
+        if !groups.contains(&group) {
+            groups.push(group);
+        }
+    }
+
+    /// Returns a backref to the first of the groups of a duplicate name that matched, like PCRE.
+    fn duplicate_name_backref(&mut self, groups: &[usize]) -> Expr {
+        let (&last, groups) = groups.split_last().expect("duplicate groups");
+        groups
+            .iter()
+            .rev()
+            .fold(self.backref(last), |false_branch, &group| {
+                let true_branch = self.backref(group);
+                Expr::Conditional {
+                    condition: self.arena.push(Expr::BackrefExistsCondition(group)),
+                    true_branch: self.arena.push(true_branch),
+                    false_branch: self.arena.push(false_branch),
+                }
+            })
+    }
+
+    /// Parses a condition on a duplicate group name, e.g. `(?(<name>)...)`, which is true if any
+    /// of the groups with the name matched.
+    fn parse_duplicate_name_condition(&mut self, ix: usize) -> Option<(usize, Expr)> {
+        for (open, close) in [("'", "'"), ("<", ">"), ("", ")")] {
+            let Some((id, skip)) = parse_id(&self.re[ix..], open, close, false) else {
+                continue;
+            };
+            let groups = self.duplicate_groups.get(id)?;
+            // the closing paren of a bare name is the end of the condition
+            let end = if open.is_empty() {
+                ix + skip - 1
+            } else {
+                ix + skip
+            };
+            let arena = &mut self.arena;
+            let conditions = groups
+                .iter()
+                .map(|&group| arena.push(Expr::BackrefExistsCondition(group)))
+                .collect();
+            return Some((end, Expr::Alt(conditions)));
+        }
+        None
+    }
+
+    /// Returns the group named `id`, which may be named later in the regex.
+    fn resolve_group_name(&mut self, ix: usize, id: &str) -> Result<usize> {
+        let group = match (self.named_groups.get(id), &self.all_named_groups) {
+            (Some(&group), _) => Some(group),
+            (None, Some(all_named_groups)) => all_named_groups.get(id).copied(),
+            (None, None) => {
+                // the group may be named later, resolve the reference by parsing again
+                self.forward_refs = true;
+                Some(0)
+            }
+        };
+        group.ok_or_else(|| {
+            Error::ParseError(ix, ParseError::InvalidGroupNameBackref(id.to_string()))
+        })
+    }
+
+    // ix points to after `(?<` in a balancing group `(?<name1-name2>...)` or `(?<-name2>...)`, or
+    // after `(?'` if `close` is `'`
+    fn parse_balancing_group(
+        &mut self,
+        ix: usize,
+        close: &str,
+        depth: usize,
+    ) -> Result<(usize, Expr)> {
+        let (capture, ix) = match parse_id(&self.re[ix..], "", "-", false) {
+            Some((id, skip)) => {
+                // captures into the group with the name, or a new group if there is none yet
+                let group = match self.named_groups.get(id) {
+                    Some(&group) => group,
+                    None => {
+                        self.curr_group += 1;
+                        self.named_groups.insert(id.to_string(), self.curr_group);
+                        self.curr_group
+                    }
+                };
+                (Some(group), ix + skip - 1)
+            }
+            None => (None, ix),
+        };
+        let Some((id, skip)) = parse_id(&self.re[ix..], "-", close, false) else {
+            return Err(Error::ParseError(ix, ParseError::InvalidGroupName));
+        };
+        let balance = self.resolve_group_name(ix, id)?;
+        self.balanced_groups.insert(balance);
+        let (ix, child) = self.parse_re(ix + skip, depth)?;
+        let ix = self.check_for_close_paren(ix)?;
+        Ok((
+            ix,
+            Expr::BalancingGroup {
+                child: self.arena.push(child),
+                capture,
+                balance,
+            },
+        ))
+    }
+
+    // ix points to after `(?` in a subroutine call
+    fn parse_subroutine_call(&mut self, ix: usize) -> Result<(usize, Expr)> {
+        let rest = &self.re[ix..];
+        let (end, group) = if rest.starts_with("R)") {
+            (ix + 2, 0)
+        } else if rest.starts_with('&') || rest.starts_with("P>") {
+            // Named subroutine call: (?&name) or (?P>name)
+            let open = if rest.starts_with('&') { "&" } else { "P>" };
+            let Some((id, skip)) = parse_id(rest, open, ")", false) else {
+                return Err(Error::ParseError(ix, ParseError::InvalidGroupName));
+            };
+            (ix + skip, self.resolve_group_name(ix, id)?)
+        } else {
+            // Numbered subroutine call: (?1), or relative to the current group: (?-1) or (?+1)
+            let sign = rest.as_bytes()[0];
+            let start = if sign == b'+' || sign == b'-' {
+                ix + 1
+            } else {
+                ix
+            };
+            let group = parse_decimal(self.re, start).and_then(|(end, n)| {
+                let group = match sign {
+                    b'+' if n > 0 => self.curr_group.checked_add(n),
+                    b'-' if n > 0 => (self.curr_group + 1).checked_sub(n),
+                    b'+' | b'-' => None,
+                    _ => Some(n),
+                };
+                // protect BitSet against unreasonably large value
+                group
+                    .filter(|&group| group < self.re.len() && (group > 0 || start == ix))
+                    .map(|group| (end, group))
+            });
+            let Some((end, group)) = group else {
+                return Err(Error::ParseError(ix, ParseError::InvalidBackref));
+            };
+            if group > 0 {
+                self.numeric_backrefs = true;
+            }
+            (self.check_for_close_paren(end)?, group)
+        };
+        self.subroutine_calls.insert(group);
+        Ok((end, Expr::SubroutineCall(group)))
+    }
+
     // ix points to after the last ( in (?(
     fn parse_conditional(&mut self, ix: usize, depth: usize) -> Result<(usize, Expr)> {
         if ix >= self.re.len() {
             return Err(Error::ParseError(ix, ParseError::UnclosedOpenParen));
         }
+        if self.re[ix..].starts_with("DEFINE)") {
+            return self.parse_define(ix + 7, depth);
+        }
         let bytes = self.re.as_bytes();
         // get the character after the open paren
         let b = bytes[ix];
+        // a bare name is the name of a group if there is one, like in .NET, e.g. `(?(name)...)`
+        let group_name = parse_id(&self.re[ix..], "", ")", false)
+            .and_then(|(id, skip)| Some((*self.named_groups.get(id)?, skip)));
         let (mut next, condition) = if is_digit(b) {
             self.parse_numbered_backref(ix)?
+        } else if let Some(condition) = self.parse_duplicate_name_condition(ix) {
+            condition
         } else if b == b'\'' {
             self.parse_named_backref(ix, "'", "'", true)?
         } else if b == b'<' {
             self.parse_named_backref(ix, "<", ">", true)?
+        } else if let Some((group, skip)) = group_name {
+            (ix + skip - 1, self.backref(group))
         } else {
             self.parse_re(ix, depth)?
         };
         next = self.check_for_close_paren(next)?;
         let (end, child) = self.parse_re(next, depth)?;
+        let inner_condition = if let Expr::Backref { group, .. } = condition {
+            Expr::BackrefExistsCondition(group)
+        } else {
+            condition
+        };
         if end == next {
             // Backreference validity checker
-            if let Expr::Backref(group) = condition {
+            if is_group_condition(&inner_condition, &self.arena) {
                 let after = self.check_for_close_paren(end)?;
-                return Ok((after, Expr::BackrefExistsCondition(group)));
+                return Ok((after, inner_condition));
             } else {
                 return Err(Error::ParseError(
                     end,
@@ -754,8 +2026,8 @@ impl<'a> Parser<'a> {
                 ));
             }
         }
-        let if_true: Expr;
-        let mut if_false: Expr = Expr::Empty;
+        let if_true: ExprId;
+        let if_false: ExprId;
         if let Expr::Alt(mut alternatives) = child {
             // the truth branch will be the first alternative
             if_true = alternatives.remove(0);
@@ -764,33 +2036,158 @@ impl<'a> Parser<'a> {
                 if_false = alternatives.pop().expect("expected 2 alternatives");
             } else {
                 // otherwise the remaining branches become the false branch
-                if_false = Expr::Alt(alternatives);
+                if_false = self.arena.push(Expr::Alt(alternatives));
             }
         } else {
             // there is only one branch - the truth branch. i.e. "if" without "else"
-            if_true = child;
+            if_true = self.arena.push(child);
+            if_false = self.arena.push(Expr::Empty);
         }
-        let inner_condition = if let Expr::Backref(group) = condition {
-            Expr::BackrefExistsCondition(group)
-        } else {
-            condition
-        };
 
         let after = self.check_for_close_paren(end)?;
         Ok((
             after,
-            if if_true == Expr::Empty && if_false == Expr::Empty {
+            if self.arena[if_true] == Expr::Empty && self.arena[if_false] == Expr::Empty {
                 inner_condition
             } else {
                 Expr::Conditional {
-                    condition: Box::new(inner_condition),
-                    true_branch: Box::new(if_true),
-                    false_branch: Box::new(if_false),
+                    condition: self.arena.push(inner_condition),
+                    true_branch: if_true,
+                    false_branch: if_false,
                 }
             },
         ))
     }
 
+    /// Returns the expression for `\R`, which matches `\r\n` as one line break, like PCRE.
+    fn line_break(&mut self) -> Expr {
+        let others = if self.flag(FLAG_CRLF_LINE_BREAKS) {
+            r"\n\r"
+        } else {
+            VERTICAL_SPACE
+        };
+        let alternatives = self.push_all(vec![
+            make_literal("\r\n"),
+            Expr::Delegate {
+                inner: format!("[{}]", others),
+                size: 1,
+                casei: false,
+            },
+        ]);
+        Expr::AtomicGroup(self.arena.push(Expr::Alt(alternatives)))
+    }
+
+    /// Parses a POSIX bracket expression such as `[:alpha:]` or `[:^digit:]` at `ix` inside a
+    /// character class, if Unicode POSIX classes are enabled. Returns the end and the equivalent
+    /// Unicode class. Otherwise, the bracket expression is left to the `regex` crate, which
+    /// matches ASCII characters only.
+    fn parse_posix_class(&self, ix: usize) -> Option<(usize, String)> {
+        if !self.flag(FLAG_UNICODE_POSIX_CLASSES) {
+            return None;
+        }
+        let rest = self.re[ix..].strip_prefix("[:")?;
+        let (negated, rest) = match rest.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let name = &rest[..rest.find(":]")?];
+        // based on the POSIX compatible definitions of Unicode Technical Standard #18, Annex C
+        let chars = match name {
+            "alnum" => r"\p{Alphabetic}\p{Nd}",
+            "alpha" => r"\p{Alphabetic}",
+            "ascii" => r"\x00-\x7F",
+            "blank" => r"\p{Zs}\t",
+            "cntrl" => r"\p{Cc}",
+            "digit" => r"\p{Nd}",
+            "graph" => r"[^\p{White_Space}\p{Cc}\p{Cn}]",
+            "lower" => r"\p{Lowercase}",
+            "print" => r"[^\p{White_Space}\p{Cc}\p{Cn}]\p{Zs}",
+            "punct" => r"[\p{P}\p{S}--\p{Alphabetic}]",
+            "space" => r"\p{White_Space}",
+            "upper" => r"\p{Uppercase}",
+            "word" => r"\p{Alphabetic}\p{M}\p{Nd}\p{Pc}\p{Join_Control}",
+            "xdigit" => r"0-9A-Fa-f",
+            _ => return None,
+        };
+        let negation = if negated { "^" } else { "" };
+        let end = ix + 2 + usize::from(negated) + name.len() + 2;
+        Some((end, format!("[{}{}]", negation, chars)))
+    }
+
+    /// Returns a backref to `group` as in ECMAScript, which matches the empty string if the group
+    /// hasn't participated in the match, including when the group is after the backref or
+    /// contains it.
+    fn ecmascript_backref(&mut self, group: usize) -> Expr {
+        if group == 0 || group > self.curr_group || self.open_groups.contains(&group) {
+            // an empty literal, which unlike `Expr::Empty` can be repeated
+            return make_literal("");
+        }
+        let true_branch = self.backref(group);
+        Expr::Conditional {
+            condition: self.arena.push(Expr::BackrefExistsCondition(group)),
+            true_branch: self.arena.push(true_branch),
+            false_branch: self.arena.push(Expr::Empty),
+        }
+    }
+
+    /// Returns the expression for `^` or `$` with the `m` flag in ECMAScript, which match at any
+    /// line terminator.
+    fn ecmascript_line_boundary(&mut self, look_around: LookAround) -> Expr {
+        let not_line_terminator = Expr::Delegate {
+            inner: format!("[^{}]", ECMASCRIPT_LINE_TERMINATORS),
+            size: 1,
+            casei: false,
+        };
+        Expr::LookAround(self.arena.push(not_line_terminator), look_around)
+    }
+
+    /// Returns whether the pattern is parsed with the tolerances of Annex B of ECMAScript, which
+    /// only apply without the `u` and `v` flags.
+    fn is_annex_b(&self) -> bool {
+        self.flag(FLAG_ECMASCRIPT) && !self.flag(FLAG_ECMASCRIPT_UNICODE)
+    }
+
+    /// Returns the expression for `\b` or `\B`, which is ASCII-only without Unicode classes.
+    fn word_boundary(&self, b: u8) -> Expr {
+        if !self.flag(FLAG_UNICODE) {
+            Expr::Delegate {
+                inner: format!("(?-u:\\{})", b as char),
+                size: 0,
+                casei: false,
+            }
+        } else if b == b'b' {
+            Expr::Assertion(Assertion::WordBoundary)
+        } else {
+            Expr::Assertion(Assertion::NotWordBoundary)
+        }
+    }
+
+    /// Returns whether there is a class escape such as `\d` at `ix`.
+    fn is_class_escape(&self, ix: usize) -> bool {
+        let bytes = self.re.as_bytes();
+        bytes.get(ix) == Some(&b'\\')
+            && matches!(
+                bytes.get(ix + 1),
+                Some(b'd' | b'D' | b's' | b'S' | b'w' | b'W')
+            )
+    }
+
+    /// Returns a backref to `group` with the current case sensitivity.
+    fn backref(&self, group: usize) -> Expr {
+        Expr::Backref {
+            group,
+            casei: self.flag(FLAG_CASEI),
+        }
+    }
+
+    /// Adds the expressions to the arena and returns their indices.
+    fn push_all(&mut self, exprs: Vec<Expr>) -> Vec<ExprId> {
+        exprs
+            .into_iter()
+            .map(|expr| self.arena.push(expr))
+            .collect()
+    }
+
     fn flag(&self, flag: u32) -> bool {
         (self.flags & flag) != 0
     }
@@ -816,7 +2213,18 @@ impl<'a> Parser<'a> {
                         None => return Ok(self.re.len()),
                     }
                 }
-                b' ' | b'\r' | b'\n' | b'\t' if self.flag(FLAG_IGNORE_SPACE) => ix += 1,
+                b' ' | b'\t' | b'\n' | b'\x0B' | b'\x0C' | b'\r'
+                    if self.flag(FLAG_IGNORE_SPACE) =>
+                {
+                    ix += 1
+                }
+                // the other characters of Pattern_White_Space, which Python doesn't ignore
+                b if self.flag(FLAG_IGNORE_SPACE)
+                    && !self.flag(FLAG_PYTHON)
+                    && self.re[ix..].starts_with(&PATTERN_WHITE_SPACE[..]) =>
+                {
+                    ix += codepoint_len(b)
+                }
                 b'(' if bytes[ix..].starts_with(b"(?#") => {
                     ix += 3;
                     loop {
@@ -840,6 +2248,18 @@ impl<'a> Parser<'a> {
 }
 
 // return (ix, value)
+/// Returns the indices of the groups named `name`.
+pub(crate) fn group_indices<'a>(
+    named_groups: &'a NamedGroups,
+    duplicate_groups: &'a DuplicateGroups,
+    name: &str,
+) -> &'a [usize] {
+    match duplicate_groups.get(name) {
+        Some(groups) => groups,
+        None => named_groups.get(name).map_or(&[], core::slice::from_ref),
+    }
+}
+
 pub(crate) fn parse_decimal(s: &str, ix: usize) -> Option<(usize, usize)> {
     let mut end = ix;
     while end < s.len() && is_digit(s.as_bytes()[end]) {
@@ -874,7 +2294,7 @@ pub(crate) fn parse_id<'a>(
     };
     let id_len = match after_id.map(|(i, _)| i) {
         Some(id_len) if s[id_start + id_len..].starts_with(close) => Some(id_len),
-        None if close.is_empty() => Some(s.len()),
+        None if close.is_empty() => Some(s.len() - id_start),
         _ => None,
     };
     match id_len {
@@ -887,10 +2307,59 @@ pub(crate) fn parse_id<'a>(
     }
 }
 
+/// Converts a Unicode property escape such as `\p{Greek}` to the syntax of the `regex` crate,
+/// which doesn't support negation with `\p{^Greek}` or the `\p{L&}` spelling of cased letters
+/// as in Oniguruma and PCRE.
+fn unicode_property(escape: &str) -> String {
+    let (p, name) = escape.split_at(2);
+    let Some(name) = name
+        .strip_prefix('{')
+        .and_then(|name| name.strip_suffix('}'))
+    else {
+        return String::from(escape);
+    };
+    let (p, name) = match name.strip_prefix('^') {
+        Some(name) if p == "\\p" => ("\\P", name),
+        Some(name) => ("\\p", name),
+        None => (p, name),
+    };
+    let name = if name.trim() == "L&" { "LC" } else { name };
+    format!("{}{{{}}}", p, name)
+}
+
+/// Returns whether the group starting with `(` followed by `s` is a subroutine call such as
+/// `(?1)`, `(?-1)`, `(?R)` or `(?&name)`.
+fn is_subroutine_call(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let digit_at = |i: usize| bytes.get(i).map_or(false, |&b| is_digit(b));
+    if bytes.first() != Some(&b'?') {
+        return false;
+    }
+    match bytes.get(1) {
+        Some(b'R') => bytes.get(2) == Some(&b')'),
+        Some(b'&') => true,
+        Some(b'P') => bytes.get(2) == Some(&b'>'),
+        Some(b'+' | b'-') => digit_at(2),
+        _ => digit_at(1),
+    }
+}
+
 fn is_id_char(c: char) -> bool {
     c.is_alphanumeric() || c == '_'
 }
 
+/// Returns whether `condition` only checks whether groups matched, e.g. `(?(1))`, or
+/// `(?(<name>))` for a duplicate name.
+fn is_group_condition(condition: &Expr, arena: &ExprArena) -> bool {
+    match condition {
+        Expr::BackrefExistsCondition(_) => true,
+        Expr::Alt(conditions) => conditions
+            .iter()
+            .all(|&condition| matches!(arena[condition], Expr::BackrefExistsCondition(_))),
+        _ => false,
+    }
+}
+
 fn is_digit(b: u8) -> bool {
     b'0' <= b && b <= b'9'
 }
@@ -899,6 +2368,16 @@ fn is_hex_digit(b: u8) -> bool {
     is_digit(b) || (b'a' <= (b | 32) && (b | 32) <= b'f')
 }
 
+/// Returns the escape of the byte that the code point `val` is, if it's a single code point
+/// from 0x80 to 0xFF, which with Unicode disabled matches that byte instead of its UTF-8 sequence.
+fn byte_escape(val: &str) -> Option<String> {
+    let mut chars = val.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c @ '\u{80}'..='\u{FF}'), None) => Some(format!("\\x{:02X}", c as u32)),
+        _ => None,
+    }
+}
+
 pub(crate) fn make_literal(s: &str) -> Expr {
     Expr::Literal {
         val: String::from(s),
@@ -910,14 +2389,173 @@ pub(crate) fn make_literal(s: &str) -> Expr {
 mod tests {
     use alloc::boxed::Box;
     use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
     use alloc::{format, vec};
 
-    use crate::parse::{make_literal, parse_id};
+    use crate::parse::{parse_id, ExprArena, ExprId, ExprTree, Parser};
+    use crate::parse::{FLAG_BYTES, FLAG_UNICODE_POSIX_CLASSES};
+    use crate::parse::{FLAG_ECMASCRIPT, FLAG_ECMASCRIPT_UNICODE};
+    use crate::parse::{
+        FLAG_EXPLICIT_CAPTURE, FLAG_H_HORIZONTAL_SPACE, FLAG_OCTAL_ESCAPES, FLAG_PYTHON,
+    };
     use crate::LookAround::*;
-    use crate::{Assertion, Expr};
+    use crate::{Assertion, BacktrackingVerb, CalloutId, Expr, LookAround};
+
+    /// An expression with its children in place instead of in an arena, so that the expected
+    /// trees can be written out.
+    #[derive(Debug, PartialEq, Eq)]
+    enum Tree {
+        Empty,
+        Any {
+            newline: bool,
+        },
+        Assertion(Assertion),
+        Literal {
+            val: String,
+            casei: bool,
+        },
+        Concat(Vec<Tree>),
+        Alt(Vec<Tree>),
+        Group(Box<Tree>),
+        LookAround(Box<Tree>, LookAround),
+        Repeat {
+            child: Box<Tree>,
+            lo: usize,
+            hi: usize,
+            greedy: bool,
+        },
+        Delegate {
+            inner: String,
+            size: usize,
+            casei: bool,
+        },
+        Backref {
+            group: usize,
+            casei: bool,
+        },
+        AtomicGroup(Box<Tree>),
+        KeepOut,
+        ContinueFromPreviousMatchEnd,
+        BackrefExistsCondition(usize),
+        Conditional {
+            condition: Box<Tree>,
+            true_branch: Box<Tree>,
+            false_branch: Box<Tree>,
+        },
+        SubroutineCall(usize),
+        BranchReset(Vec<Tree>),
+        BacktrackingVerb(BacktrackingVerb),
+        BalancingGroup {
+            child: Box<Tree>,
+            capture: Option<usize>,
+            balance: usize,
+        },
+        Absent(Box<Tree>),
+        Callout(CalloutId),
+        Predicate(String),
+        GraphemeCluster,
+        ScriptRun(Box<Tree>),
+        Fuzzy {
+            child: Box<Tree>,
+            max_cost: usize,
+        },
+    }
+
+    impl Tree {
+        fn new(arena: &ExprArena, expr: ExprId) -> Tree {
+            let child = |child: ExprId| Box::new(Tree::new(arena, child));
+            let children = |children: &[ExprId]| {
+                children
+                    .iter()
+                    .map(|&child| Tree::new(arena, child))
+                    .collect()
+            };
+            match arena[expr] {
+                Expr::Empty => Tree::Empty,
+                Expr::Any { newline } => Tree::Any { newline },
+                Expr::Assertion(assertion) => Tree::Assertion(assertion),
+                Expr::Literal { ref val, casei } => Tree::Literal {
+                    val: val.clone(),
+                    casei,
+                },
+                Expr::Concat(ref v) => Tree::Concat(children(v)),
+                Expr::Alt(ref v) => Tree::Alt(children(v)),
+                Expr::Group(c) => Tree::Group(child(c)),
+                Expr::LookAround(c, la) => Tree::LookAround(child(c), la),
+                Expr::Repeat {
+                    child: c,
+                    lo,
+                    hi,
+                    greedy,
+                } => Tree::Repeat {
+                    child: child(c),
+                    lo,
+                    hi,
+                    greedy,
+                },
+                Expr::Delegate {
+                    ref inner,
+                    size,
+                    casei,
+                } => Tree::Delegate {
+                    inner: inner.clone(),
+                    size,
+                    casei,
+                },
+                Expr::Backref { group, casei } => Tree::Backref { group, casei },
+                Expr::AtomicGroup(c) => Tree::AtomicGroup(child(c)),
+                Expr::KeepOut => Tree::KeepOut,
+                Expr::ContinueFromPreviousMatchEnd => Tree::ContinueFromPreviousMatchEnd,
+                Expr::BackrefExistsCondition(group) => Tree::BackrefExistsCondition(group),
+                Expr::Conditional {
+                    condition,
+                    true_branch,
+                    false_branch,
+                } => Tree::Conditional {
+                    condition: child(condition),
+                    true_branch: child(true_branch),
+                    false_branch: child(false_branch),
+                },
+                Expr::SubroutineCall(group) => Tree::SubroutineCall(group),
+                Expr::BranchReset(ref v) => Tree::BranchReset(children(v)),
+                Expr::BacktrackingVerb(verb) => Tree::BacktrackingVerb(verb),
+                Expr::BalancingGroup {
+                    child: c,
+                    capture,
+                    balance,
+                } => Tree::BalancingGroup {
+                    child: child(c),
+                    capture,
+                    balance,
+                },
+                Expr::Absent(c) => Tree::Absent(child(c)),
+                Expr::Callout(ref id) => Tree::Callout(id.clone()),
+                Expr::Predicate(ref name) => Tree::Predicate(name.clone()),
+                Expr::GraphemeCluster => Tree::GraphemeCluster,
+                Expr::ScriptRun(c) => Tree::ScriptRun(child(c)),
+                Expr::Fuzzy { child: c, max_cost } => Tree::Fuzzy {
+                    child: child(c),
+                    max_cost,
+                },
+            }
+        }
+    }
+
+    impl ExprTree {
+        fn tree(&self) -> Tree {
+            Tree::new(&self.arena, self.expr)
+        }
+    }
+
+    fn make_literal(s: &str) -> Tree {
+        Tree::Literal {
+            val: String::from(s),
+            casei: false,
+        }
+    }
 
-    fn p(s: &str) -> Expr {
-        Expr::parse_tree(s).unwrap().expr
+    fn p(s: &str) -> Tree {
+        Expr::parse_tree(s).unwrap().tree()
     }
 
     #[cfg_attr(feature = "track_caller", track_caller)]
@@ -942,23 +2580,23 @@ mod tests {
 
     #[test]
     fn empty() {
-        assert_eq!(p(""), Expr::Empty);
+        assert_eq!(p(""), Tree::Empty);
     }
 
     #[test]
     fn any() {
-        assert_eq!(p("."), Expr::Any { newline: false });
-        assert_eq!(p("(?s:.)"), Expr::Any { newline: true });
+        assert_eq!(p("."), Tree::Any { newline: false });
+        assert_eq!(p("(?s:.)"), Tree::Any { newline: true });
     }
 
     #[test]
     fn start_text() {
-        assert_eq!(p("^"), Expr::Assertion(Assertion::StartText));
+        assert_eq!(p("^"), Tree::Assertion(Assertion::StartText));
     }
 
     #[test]
     fn end_text() {
-        assert_eq!(p("$"), Expr::Assertion(Assertion::EndText));
+        assert_eq!(p("$"), Tree::Assertion(Assertion::EndText));
     }
 
     #[test]
@@ -983,20 +2621,21 @@ mod tests {
         assert_eq!(parse_id("{-1}", "{", "}", true), Some(("-1", 4)));
         assert_eq!(parse_id("{-1}", "{", "}", false), None);
         assert_eq!(parse_id("{-a}", "{", "}", true), None);
+        assert_eq!(parse_id("{foo", "{", "", false), Some(("foo", 4)));
     }
 
     #[test]
     fn literal_unescaped_opening_curly() {
         // `{` in position where quantifier is not allowed results in literal `{`
         assert_eq!(p("{"), make_literal("{"));
-        assert_eq!(p("({)"), Expr::Group(Box::new(make_literal("{"),)));
+        assert_eq!(p("({)"), Tree::Group(Box::new(make_literal("{"),)));
         assert_eq!(
             p("a|{"),
-            Expr::Alt(vec![make_literal("a"), make_literal("{"),])
+            Tree::Alt(vec![make_literal("a"), make_literal("{"),])
         );
         assert_eq!(
             p("{{2}"),
-            Expr::Repeat {
+            Tree::Repeat {
                 child: Box::new(make_literal("{")),
                 lo: 2,
                 hi: 2,
@@ -1024,7 +2663,7 @@ mod tests {
     fn hex_escape() {
         assert_eq!(
             p("\\h"),
-            Expr::Delegate {
+            Tree::Delegate {
                 inner: String::from("[0-9A-Fa-f]"),
                 size: 1,
                 casei: false
@@ -1032,7 +2671,7 @@ mod tests {
         );
         assert_eq!(
             p("\\H"),
-            Expr::Delegate {
+            Tree::Delegate {
                 inner: String::from("[^0-9A-Fa-f]"),
                 size: 1,
                 casei: false
@@ -1041,275 +2680,1229 @@ mod tests {
     }
 
     #[test]
-    fn invalid_escape() {
-        assert_error(
-            "\\",
-            "Parsing error at position 0: Backslash without following character",
+    fn non_unicode() {
+        let p_bytes = |re: &str| Parser::parse_with_flags(re, FLAG_BYTES).unwrap().tree();
+        let byte_delegate = |inner: &str, casei: bool| Tree::Delegate {
+            inner: String::from(inner),
+            size: 1,
+            casei,
+        };
+        assert_eq!(p_bytes(r"(?-u)\xFF"), byte_delegate(r"(?-u:\xFF)", false));
+        assert_eq!(
+            p_bytes(r"(?-u)\o{377}"),
+            byte_delegate(r"(?-u:\xFF)", false)
         );
-        assert_error("\\q", "Parsing error at position 0: Invalid escape: \\q");
-        assert_error("\\xAG", "Parsing error at position 2: Invalid hex escape");
-        assert_error("\\xA", "Parsing error at position 2: Invalid hex escape");
-        assert_error("\\x{}", "Parsing error at position 2: Invalid hex escape");
-        assert_error("\\x{AG}", "Parsing error at position 2: Invalid hex escape");
-        assert_error("\\x{42", "Parsing error at position 2: Invalid hex escape");
-        assert_error(
-            "\\x{D800}",
-            "Parsing error at position 2: Invalid codepoint for hex or unicode escape",
+        assert_eq!(
+            p_bytes(r"(?-u:\x{41 e9})"),
+            Tree::Concat(vec![make_literal("A"), byte_delegate(r"(?-u:\xE9)", false)])
         );
-        assert_error(
-            "\\x{110000}",
-            "Parsing error at position 2: Invalid codepoint for hex or unicode escape",
+        assert_eq!(p_bytes(r"(?-u)."), byte_delegate("(?-u:.)", false));
+        assert_eq!(p_bytes(r"(?s-u)."), byte_delegate("(?-u:(?s:.))", false));
+        assert_eq!(
+            p_bytes(r"(?-u)[\x80-\xFFa]"),
+            byte_delegate(r"(?-u:[\x80-\xFFa])", false)
+        );
+        assert_eq!(
+            p_bytes(r"(?-u)\w"),
+            byte_delegate("(?-u:[0-9A-Za-z_])", false)
+        );
+        assert_eq!(p_bytes(r"(?i-u)k"), byte_delegate("(?-u:k)", true));
+        assert_eq!(
+            p_bytes(r"(?i-u)é"),
+            Tree::Literal {
+                val: String::from("é"),
+                casei: false
+            }
+        );
+        // only escapes match bytes, other characters match their UTF-8 sequence
+        assert_eq!(p_bytes(r"(?-u)ÿ"), make_literal("ÿ"));
+        // Unicode is enabled again after the group and with `(?u)`
+        assert_eq!(
+            p_bytes(r"(?-u:\xFF)\xFF(?-u)(?u)\xFF"),
+            Tree::Concat(vec![
+                byte_delegate(r"(?-u:\xFF)", false),
+                make_literal("ÿ"),
+                make_literal("ÿ"),
+            ])
         );
-        assert_error("\\u123", "Parsing error at position 2: Invalid hex escape");
-        assert_error("\\u123x", "Parsing error at position 2: Invalid hex escape");
-        assert_error("\\u{}", "Parsing error at position 2: Invalid hex escape");
         assert_error(
-            "\\U1234567",
-            "Parsing error at position 2: Invalid hex escape",
+            r"(?-u)\xFF",
+            "Parsing error at position 3: Disabling Unicode not supported",
         );
-        assert_error("\\U{}", "Parsing error at position 2: Invalid hex escape");
     }
 
     #[test]
-    fn concat() {
+    fn code_point_escapes() {
+        assert_eq!(p("\\o{101}"), make_literal("A"));
+        assert_eq!(p("\\0"), make_literal("\0"));
+        assert_eq!(
+            p("\\0123"),
+            Tree::Concat(vec![make_literal("\n"), make_literal("3")])
+        );
+        assert_eq!(
+            p("\\x{41  42}"),
+            Tree::Concat(vec![make_literal("A"), make_literal("B")])
+        );
+        assert_eq!(
+            p("[\\o{101 102}]"),
+            Tree::Delegate {
+                inner: String::from("[AB]"),
+                size: 1,
+                casei: false
+            }
+        );
+    }
+
+    #[test]
+    fn octal_escapes_option() {
+        let octal = |re| {
+            Parser::parse_with_flags(re, FLAG_OCTAL_ESCAPES)
+                .unwrap()
+                .tree()
+        };
+        assert_eq!(
+            octal("(a)\\1\\11"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Backref {
+                    group: 1,
+                    casei: false
+                },
+                make_literal("\t"),
+            ])
+        );
+        assert_eq!(
+            octal("[\\1]"),
+            Tree::Delegate {
+                inner: String::from("[\u{1}]"),
+                size: 1,
+                casei: false
+            }
+        );
+        // a backref by default
+        fail("\\101");
+    }
+
+    #[test]
+    fn explicit_capture() {
+        assert_eq!(
+            p("(?n)(a)(?<x>b)\\k<x>"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                Tree::Group(Box::new(make_literal("b"))),
+                Tree::Backref {
+                    group: 1,
+                    casei: false
+                },
+            ])
+        );
+        // the flag is scoped like other flags
+        assert_eq!(
+            p("(?n:(a))(b)"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                Tree::Group(Box::new(make_literal("b"))),
+            ])
+        );
+        assert_eq!(p("(?n)(?-n)(a)"), Tree::Group(Box::new(make_literal("a"))));
+        let tree = Parser::parse_with_flags("(a)(?<x>b)", FLAG_EXPLICIT_CAPTURE).unwrap();
+        assert_eq!(tree.named_groups.get("x"), Some(&1));
+    }
+
+    #[test]
+    fn python_dialect() {
+        let python = |re| Parser::parse_with_flags(re, FLAG_PYTHON);
+        assert_eq!(
+            python("a$").unwrap().tree(),
+            Tree::Concat(vec![
+                make_literal("a"),
+                Tree::Assertion(Assertion::EndTextOptionalNewline),
+            ])
+        );
+        assert_eq!(
+            python("\\Z\\v\\<").unwrap().tree(),
+            Tree::Concat(vec![
+                Tree::Assertion(Assertion::EndText),
+                make_literal("\x0B"),
+                make_literal("<"),
+            ])
+        );
+        assert_eq!(
+            python("(?a)\\w\\b").unwrap().tree(),
+            Tree::Concat(vec![
+                Tree::Delegate {
+                    inner: String::from("[0-9A-Za-z_]"),
+                    size: 1,
+                    casei: false,
+                },
+                Tree::Delegate {
+                    inner: String::from("(?-u:\\b)"),
+                    size: 0,
+                    casei: false,
+                },
+            ])
+        );
+        assert_eq!(
+            python("[]&[-]").unwrap().tree(),
+            Tree::Delegate {
+                inner: String::from("[\\]\\&\\[\\-]"),
+                size: 1,
+                casei: false,
+            }
+        );
+        assert_eq!(
+            python("(?P<a>a)\\1").unwrap().tree(),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Backref {
+                    group: 1,
+                    casei: false
+                },
+            ])
+        );
+        assert!(python("(?x) (?i)a").is_ok());
+        assert_eq!(
+            python("a(?i)").unwrap_err().to_string(),
+            "Parsing error at position 1: Global flags not at the start of the pattern"
+        );
+        assert!(python("(?:(?i))").is_err());
+        assert!(python("\\e").is_err());
+        assert!(python("a++").is_err());
+    }
+
+    #[test]
+    fn ecmascript_dialect() {
+        let ecmascript = |re| Parser::parse_with_flags(re, FLAG_ECMASCRIPT);
+        let unicode = |re| Parser::parse_with_flags(re, FLAG_ECMASCRIPT | FLAG_ECMASCRIPT_UNICODE);
+        assert_eq!(
+            ecmascript(".$").unwrap().tree(),
+            Tree::Concat(vec![
+                Tree::Delegate {
+                    inner: String::from("[^\\n\\r\\x{2028}\\x{2029}]"),
+                    size: 1,
+                    casei: false,
+                },
+                Tree::Assertion(Assertion::EndText),
+            ])
+        );
+        assert_eq!(
+            ecmascript("(?m:^)").unwrap().tree(),
+            Tree::LookAround(
+                Box::new(Tree::Delegate {
+                    inner: String::from("[^\\n\\r\\x{2028}\\x{2029}]"),
+                    size: 1,
+                    casei: false,
+                }),
+                LookBehindNeg
+            )
+        );
+        assert_eq!(
+            ecmascript("\\w\\b").unwrap().tree(),
+            Tree::Concat(vec![
+                Tree::Delegate {
+                    inner: String::from("[0-9A-Za-z_]"),
+                    size: 1,
+                    casei: false,
+                },
+                Tree::Delegate {
+                    inner: String::from("(?-u:\\b)"),
+                    size: 0,
+                    casei: false,
+                },
+            ])
+        );
+        assert_eq!(
+            ecmascript("(a)\\1").unwrap().tree(),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
+                    true_branch: Box::new(Tree::Backref {
+                        group: 1,
+                        casei: false
+                    }),
+                    false_branch: Box::new(Tree::Empty),
+                },
+            ])
+        );
+        // forward references and references from inside the group match the empty string
+        assert_eq!(
+            ecmascript("\\k<a>(?<a>b\\1)").unwrap().tree(),
+            Tree::Concat(vec![
+                make_literal(""),
+                Tree::Group(Box::new(Tree::Concat(vec![
+                    make_literal("b"),
+                    make_literal("")
+                ]))),
+            ])
+        );
+        assert_eq!(
+            ecmascript("[]").unwrap().tree(),
+            Tree::Delegate {
+                inner: String::from("[^\\x00-\\x{10FFFF}]"),
+                size: 1,
+                casei: false,
+            }
+        );
+        assert_eq!(
+            ecmascript("[\\d-a&]").unwrap().tree(),
+            Tree::Delegate {
+                inner: String::from("[[0-9]\\-a\\&]"),
+                size: 1,
+                casei: false,
+            }
+        );
+        // the tolerances of Annex B
+        assert_eq!(
+            ecmascript("\\a\\2\\k{").unwrap().tree(),
+            Tree::Concat(vec![
+                make_literal("a"),
+                make_literal("\x02"),
+                make_literal("k"),
+                make_literal("{"),
+            ])
+        );
+        assert_eq!(
+            ecmascript("(?=a)?").unwrap().tree(),
+            Tree::Repeat {
+                child: Box::new(Tree::LookAround(Box::new(make_literal("a")), LookAhead)),
+                lo: 0,
+                hi: 1,
+                greedy: true,
+            }
+        );
+        assert!(unicode("\\a").is_err());
+        assert!(unicode("\\2").is_err());
+        assert!(unicode("{").is_err());
+        assert!(unicode("(?=a)?").is_err());
+        assert!(unicode("[\\d-a]").is_err());
+        assert!(ecmascript("(?i)a").is_err());
+        assert!(ecmascript("(?>a)").is_err());
+        assert!(ecmascript("a{2}{3}").is_err());
+        assert!(ecmascript("a++").is_err());
+    }
+
+    #[test]
+    fn invalid_escape() {
+        assert_error(
+            "\\",
+            "Parsing error at position 0: Backslash without following character",
+        );
+        assert_error("\\q", "Parsing error at position 0: Invalid escape: \\q");
+        assert_error("\\xAG", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\xA", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\x{}", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\x{AG}", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\x{42", "Parsing error at position 2: Invalid hex escape");
+        assert_error(
+            "\\x{D800}",
+            "Parsing error at position 2: Invalid codepoint for hex or unicode escape",
+        );
+        assert_error(
+            "\\x{110000}",
+            "Parsing error at position 2: Invalid codepoint for hex or unicode escape",
+        );
+        assert_error("\\u123", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\u123x", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\u{}", "Parsing error at position 2: Invalid hex escape");
+        assert_error(
+            "\\U1234567",
+            "Parsing error at position 2: Invalid hex escape",
+        );
+        assert_error("\\U{}", "Parsing error at position 2: Invalid hex escape");
+        assert_error("\\o{}", "Parsing error at position 2: Invalid octal escape");
+        assert_error(
+            "\\o{18}",
+            "Parsing error at position 2: Invalid octal escape",
+        );
+    }
+
+    #[test]
+    fn concat() {
         assert_eq!(
             p("ab"),
-            Expr::Concat(vec![make_literal("a"), make_literal("b"),])
+            Tree::Concat(vec![make_literal("a"), make_literal("b"),])
+        );
+    }
+
+    #[test]
+    fn alt() {
+        assert_eq!(
+            p("a|b"),
+            Tree::Alt(vec![make_literal("a"), make_literal("b"),])
+        );
+    }
+
+    #[test]
+    fn group() {
+        assert_eq!(p("(a)"), Tree::Group(Box::new(make_literal("a"),)));
+    }
+
+    #[test]
+    fn group_repeat() {
+        assert_eq!(
+            p("(a){2}"),
+            Tree::Repeat {
+                child: Box::new(Tree::Group(Box::new(make_literal("a")))),
+                lo: 2,
+                hi: 2,
+                greedy: true
+            }
         );
     }
 
     #[test]
-    fn alt() {
+    fn repeat() {
+        assert_eq!(
+            p("a{2,42}"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 2,
+                hi: 42,
+                greedy: true
+            }
+        );
+        assert_eq!(
+            p("a{2,}"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 2,
+                hi: usize::MAX,
+                greedy: true
+            }
+        );
+        assert_eq!(
+            p("a{2}"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 2,
+                hi: 2,
+                greedy: true
+            }
+        );
+        assert_eq!(
+            p("a{,2}"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 0,
+                hi: 2,
+                greedy: true
+            }
+        );
+
+        assert_eq!(
+            p("a{2,42}?"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 2,
+                hi: 42,
+                greedy: false
+            }
+        );
+        assert_eq!(
+            p("a{2,}?"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 2,
+                hi: usize::MAX,
+                greedy: false
+            }
+        );
+        assert_eq!(
+            p("a{2}?"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 2,
+                hi: 2,
+                greedy: false
+            }
+        );
+        assert_eq!(
+            p("a{,2}?"),
+            Tree::Repeat {
+                child: Box::new(make_literal("a")),
+                lo: 0,
+                hi: 2,
+                greedy: false
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_repeat() {
+        // Invalid repeat syntax results in literal
+        assert_eq!(
+            p("a{"),
+            Tree::Concat(vec![make_literal("a"), make_literal("{"),])
+        );
+        assert_eq!(
+            p("a{6"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                make_literal("{"),
+                make_literal("6"),
+            ])
+        );
+        assert_eq!(
+            p("a{6,"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                make_literal("{"),
+                make_literal("6"),
+                make_literal(","),
+            ])
+        );
+    }
+
+    #[test]
+    fn delegate_zero() {
+        assert_eq!(p("\\b"), Tree::Assertion(Assertion::WordBoundary),);
+        assert_eq!(p("\\B"), Tree::Assertion(Assertion::NotWordBoundary),);
+    }
+
+    #[test]
+    fn word_start_and_end() {
+        assert_eq!(p("\\<"), Tree::Assertion(Assertion::LeftWordBoundary));
+        assert_eq!(p("\\>"), Tree::Assertion(Assertion::RightWordBoundary));
+        assert_eq!(
+            p("\\b{start}"),
+            Tree::Assertion(Assertion::LeftWordBoundary)
+        );
+        assert_eq!(p("\\b{end}"), Tree::Assertion(Assertion::RightWordBoundary));
+        assert_eq!(
+            p("\\b{end}a"),
+            Tree::Concat(vec![
+                Tree::Assertion(Assertion::RightWordBoundary),
+                make_literal("a"),
+            ])
+        );
+        assert_error(
+            "\\b{middle}",
+            "Parsing error at position 0: Invalid escape: \\b{middle}",
+        );
+        assert_error(
+            "\\b{start",
+            "Parsing error at position 0: Invalid escape: \\b",
+        );
+    }
+
+    #[test]
+    fn delegate_named_group() {
+        assert_eq!(
+            p("\\p{Greek}"),
+            Tree::Delegate {
+                inner: String::from("\\p{Greek}"),
+                size: 1,
+                casei: false
+            }
+        );
+        assert_eq!(
+            p("\\pL"),
+            Tree::Delegate {
+                inner: String::from("\\pL"),
+                size: 1,
+                casei: false
+            }
+        );
+        assert_eq!(
+            p("\\P{Greek}"),
+            Tree::Delegate {
+                inner: String::from("\\P{Greek}"),
+                size: 1,
+                casei: false
+            }
+        );
+        assert_eq!(
+            p("\\PL"),
+            Tree::Delegate {
+                inner: String::from("\\PL"),
+                size: 1,
+                casei: false
+            }
+        );
+        assert_eq!(
+            p("(?i)\\p{Ll}"),
+            Tree::Delegate {
+                inner: String::from("\\p{Ll}"),
+                size: 1,
+                casei: true
+            }
+        );
+        assert_eq!(
+            p("\\p{^Greek}[\\P{^L&}]"),
+            Tree::Concat(vec![
+                Tree::Delegate {
+                    inner: String::from("\\P{Greek}"),
+                    size: 1,
+                    casei: false
+                },
+                Tree::Delegate {
+                    inner: String::from("[\\p{LC}]"),
+                    size: 1,
+                    casei: false
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn backref() {
+        assert_eq!(
+            p("(.)\\1"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(Tree::Any { newline: false })),
+                Tree::Backref {
+                    group: 1,
+                    casei: false
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn named_backref() {
+        assert_eq!(
+            p("(?<i>.)\\k<i>"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(Tree::Any { newline: false })),
+                Tree::Backref {
+                    group: 1,
+                    casei: false
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn named_group_syntaxes() {
+        for re in ["(?<x>a)\\k<x>", "(?'x'a)\\k'x'", "(?P<x>a)(?P=x)"] {
+            let tree = Expr::parse_tree(re).unwrap();
+            assert_eq!(tree.named_groups.get("x"), Some(&1));
+            assert_eq!(
+                tree.tree(),
+                Tree::Concat(vec![
+                    Tree::Group(Box::new(make_literal("a"))),
+                    Tree::Backref {
+                        group: 1,
+                        casei: false
+                    },
+                ])
+            );
+        }
+        assert_eq!(
+            p("(?'x'a)(?P>x)"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::SubroutineCall(1),
+            ])
+        );
+        fail("(?'x)");
+        fail("(?''a)");
+    }
+
+    #[test]
+    fn duplicate_group_names() {
+        let tree = Expr::parse_tree("(?J)(?<x>a)|(?<x>b)(?<y>c)").unwrap();
+        assert_eq!(tree.named_groups.get("x"), Some(&1));
+        assert_eq!(tree.duplicate_groups.get("x"), Some(&vec![1, 2]));
+        assert_eq!(tree.duplicate_groups.get("y"), None);
+
+        // a backref to a duplicate name refers to the first of the groups that matched
+        assert_eq!(
+            p("(?J)(?<x>a)(?<x>b)\\k<x>"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Group(Box::new(make_literal("b"))),
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
+                    true_branch: Box::new(Tree::Backref {
+                        group: 1,
+                        casei: false
+                    }),
+                    false_branch: Box::new(Tree::Backref {
+                        group: 2,
+                        casei: false
+                    }),
+                },
+            ])
+        );
+        assert_eq!(
+            p("(?J)(?<x>a)(?<x>b)(?(x))"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Group(Box::new(make_literal("b"))),
+                Tree::Alt(vec![
+                    Tree::BackrefExistsCondition(1),
+                    Tree::BackrefExistsCondition(2),
+                ]),
+            ])
+        );
+
+        // without the flag, a name that is used again only names the last group
+        for re in [
+            "(?<x>a)(?<x>b)",
+            "(?J:(?<x>a))(?<x>b)",
+            "(?J)(?<x>a)(?-J)(?<x>b)",
+        ] {
+            let tree = Expr::parse_tree(re).unwrap();
+            assert_eq!(tree.named_groups.get("x"), Some(&2));
+            assert!(tree.duplicate_groups.is_empty());
+        }
+        // the same group in a branch reset group isn't a duplicate
+        let tree = Expr::parse_tree("(?J)(?|(?<x>a)|(?<x>b))").unwrap();
+        assert!(tree.duplicate_groups.is_empty());
+    }
+
+    #[test]
+    fn backref_casei() {
+        assert_eq!(
+            p("(a)(?i)\\1"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Backref {
+                    group: 1,
+                    casei: true
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn relative_backref() {
+        assert_eq!(
+            p("(a)(.)\\k<-1>"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Group(Box::new(Tree::Any { newline: false })),
+                Tree::Backref {
+                    group: 2,
+                    casei: false
+                }
+            ])
+        );
+        fail("(?P<->.)");
+        fail("(.)(?P=-)");
+        fail("(a)\\k<-2>");
+    }
+
+    #[test]
+    fn pcre_backref() {
+        let backrefs = |group| {
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Group(Box::new(make_literal("b"))),
+                Tree::Backref {
+                    group,
+                    casei: false,
+                },
+            ])
+        };
+        assert_eq!(p("(a)(b)\\g1"), backrefs(1));
+        assert_eq!(p("(a)(b)\\g{2}"), backrefs(2));
+        assert_eq!(p("(a)(b)\\g-2"), backrefs(1));
+        assert_eq!(p("(a)(b)\\g{-1}"), backrefs(2));
+        assert_eq!(p("(?<x>a)(b)\\g{x}"), backrefs(1));
+        assert_eq!(p("(?<x>a)(b)\\k{x}"), backrefs(1));
         assert_eq!(
-            p("a|b"),
-            Expr::Alt(vec![make_literal("a"), make_literal("b"),])
+            p("(a)\\g1b"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Backref {
+                    group: 1,
+                    casei: false
+                },
+                make_literal("b"),
+            ])
         );
+        fail("(a)\\g");
+        fail("(a)\\g-");
+        fail("(a)\\g{-2}");
+        fail("(a)\\g{b}");
+        fail("(a)[\\g1]");
     }
 
     #[test]
-    fn group() {
-        assert_eq!(p("(a)"), Expr::Group(Box::new(make_literal("a"),)));
-    }
-
-    #[test]
-    fn group_repeat() {
+    fn define() {
         assert_eq!(
-            p("(a){2}"),
-            Expr::Repeat {
-                child: Box::new(Expr::Group(Box::new(make_literal("a")))),
-                lo: 2,
-                hi: 2,
-                greedy: true
-            }
+            p("(?(DEFINE)(?<d>\\d))(?&d)"),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(Tree::Delegate {
+                        inner: "\\d".to_string(),
+                        size: 1,
+                        casei: false,
+                    }))),
+                    lo: 0,
+                    hi: 0,
+                    greedy: true,
+                },
+                Tree::SubroutineCall(1),
+            ])
         );
+        fail("(?(DEFINE)(a)");
     }
 
     #[test]
-    fn repeat() {
+    fn balancing_group() {
         assert_eq!(
-            p("a{2,42}"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 2,
-                hi: 42,
-                greedy: true
-            }
+            p("(?<o>a)(?<c-o>b)(?<-c>)"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::BalancingGroup {
+                    child: Box::new(make_literal("b")),
+                    capture: Some(2),
+                    balance: 1,
+                },
+                Tree::BalancingGroup {
+                    child: Box::new(Tree::Empty),
+                    capture: None,
+                    balance: 2,
+                },
+            ])
         );
+        let tree = Expr::parse_tree("(?<o>a)(?<o-o>b)").unwrap();
+        assert_eq!(tree.balanced_groups.iter().collect::<Vec<_>>(), vec![1]);
         assert_eq!(
-            p("a{2,}"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 2,
-                hi: usize::MAX,
-                greedy: true
-            }
+            tree.tree(),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::BalancingGroup {
+                    child: Box::new(make_literal("b")),
+                    capture: Some(1),
+                    balance: 1,
+                },
+            ])
         );
+        // the removed group can be named later
         assert_eq!(
-            p("a{2}"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 2,
-                hi: 2,
-                greedy: true
-            }
+            p("(?<-o>a)(?<o>b)"),
+            Tree::Concat(vec![
+                Tree::BalancingGroup {
+                    child: Box::new(make_literal("a")),
+                    capture: None,
+                    balance: 1,
+                },
+                Tree::Group(Box::new(make_literal("b"))),
+            ])
         );
         assert_eq!(
-            p("a{,2}"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 0,
-                hi: 2,
-                greedy: true
-            }
+            p("(?'o'a)(?'c-o'b)"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::BalancingGroup {
+                    child: Box::new(make_literal("b")),
+                    capture: Some(2),
+                    balance: 1,
+                },
+            ])
         );
+        fail("(?<a-b>x)");
+        fail("(?<a->x)");
+        fail("(?'o'a)(?'-o>b)");
+    }
 
+    #[test]
+    fn conditional_group_name() {
         assert_eq!(
-            p("a{2,42}?"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 2,
-                hi: 42,
-                greedy: false
-            }
+            p("(?<a>x)?(?(a)y)"),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("x")))),
+                    lo: 0,
+                    hi: 1,
+                    greedy: true,
+                },
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
+                    true_branch: Box::new(make_literal("y")),
+                    false_branch: Box::new(Tree::Empty),
+                },
+            ])
         );
+    }
+
+    #[test]
+    fn backtracking_verbs() {
         assert_eq!(
-            p("a{2,}?"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 2,
-                hi: usize::MAX,
-                greedy: false
-            }
+            p("a(*SKIP)(*F)|b(*THEN)c"),
+            Tree::Alt(vec![
+                Tree::Concat(vec![
+                    make_literal("a"),
+                    Tree::BacktrackingVerb(BacktrackingVerb::Skip),
+                    Tree::BacktrackingVerb(BacktrackingVerb::Fail),
+                ]),
+                Tree::Concat(vec![
+                    make_literal("b"),
+                    Tree::BacktrackingVerb(BacktrackingVerb::Then),
+                    make_literal("c"),
+                ]),
+            ])
         );
+        assert_eq!(p("(*FAIL)"), Tree::BacktrackingVerb(BacktrackingVerb::Fail));
         assert_eq!(
-            p("a{2}?"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 2,
-                hi: 2,
-                greedy: false
-            }
+            p("(*ACCEPT)"),
+            Tree::BacktrackingVerb(BacktrackingVerb::Accept)
         );
         assert_eq!(
-            p("a{,2}?"),
-            Expr::Repeat {
-                child: Box::new(make_literal("a")),
-                lo: 0,
-                hi: 2,
-                greedy: false
-            }
+            p("(*PRUNE)"),
+            Tree::BacktrackingVerb(BacktrackingVerb::Prune)
         );
+        fail("(*FOO)");
+        fail("(*SKIP");
     }
 
     #[test]
-    fn invalid_repeat() {
-        // Invalid repeat syntax results in literal
+    fn absent() {
         assert_eq!(
-            p("a{"),
-            Expr::Concat(vec![make_literal("a"), make_literal("{"),])
+            p("(?~a)b"),
+            Tree::Concat(vec![
+                Tree::Absent(Box::new(make_literal("a"))),
+                make_literal("b"),
+            ])
         );
+        assert_eq!(p("(?~)"), Tree::Absent(Box::new(Tree::Empty)));
+        fail("(?~|abc|d)");
+        fail("(?~abc");
+    }
+
+    #[test]
+    fn script_runs() {
         assert_eq!(
-            p("a{6"),
-            Expr::Concat(vec![
-                make_literal("a"),
-                make_literal("{"),
-                make_literal("6"),
+            p("(*sr:a)b"),
+            Tree::Concat(vec![
+                Tree::ScriptRun(Box::new(make_literal("a"))),
+                make_literal("b"),
             ])
         );
         assert_eq!(
-            p("a{6,"),
-            Expr::Concat(vec![
+            p("(*script_run:a|b)"),
+            Tree::ScriptRun(Box::new(Tree::Alt(vec![
                 make_literal("a"),
-                make_literal("{"),
-                make_literal("6"),
-                make_literal(","),
-            ])
+                make_literal("b"),
+            ])))
+        );
+        assert_eq!(
+            p("(*asr:a)"),
+            Tree::ScriptRun(Box::new(Tree::AtomicGroup(Box::new(make_literal("a")))))
+        );
+        assert_eq!(
+            p("(*atomic_script_run:)"),
+            Tree::ScriptRun(Box::new(Tree::AtomicGroup(Box::new(Tree::Empty))))
         );
+        fail("(*sr:a");
+        fail("(*SR:a)");
     }
 
     #[test]
-    fn delegate_zero() {
-        assert_eq!(p("\\b"), Expr::Assertion(Assertion::WordBoundary),);
-        assert_eq!(p("\\B"), Expr::Assertion(Assertion::NotWordBoundary),);
+    fn fuzzy_groups() {
+        let fuzzy = |child, max_cost| Tree::Fuzzy {
+            child: Box::new(child),
+            max_cost,
+        };
+        assert_eq!(
+            p("a(?:bc){~2}"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                fuzzy(Tree::Concat(vec![make_literal("b"), make_literal("c")]), 2),
+            ])
+        );
+        assert_eq!(p("a{~1}"), fuzzy(make_literal("a"), 1));
+        assert_eq!(p("(?x)a { ~ 1 }"), fuzzy(make_literal("a"), 1));
+        // anything else is a literal `{`, like an invalid repeat
+        assert_eq!(p("a{~}"), p("a\\{~\\}"));
+        assert_eq!(p("a{ ~1}"), p("a\\{ ~1\\}"));
+        let python = Parser::parse_with_flags("a{~1}", FLAG_PYTHON)
+            .unwrap()
+            .tree();
+        assert_eq!(python, p("a\\{~1\\}"));
+        fail("^{~1}");
+        fail("a{~1}*");
     }
 
     #[test]
-    fn delegate_named_group() {
+    fn space_escapes() {
         assert_eq!(
-            p("\\p{Greek}"),
-            Expr::Delegate {
-                inner: String::from("\\p{Greek}"),
+            p("\\v"),
+            Tree::Delegate {
+                inner: String::from(r"[\n\x0B\x0C\r\x{85}\x{2028}\x{2029}]"),
                 size: 1,
                 casei: false
             }
         );
         assert_eq!(
-            p("\\pL"),
-            Expr::Delegate {
-                inner: String::from("\\pL"),
+            p("[a\\V]"),
+            Tree::Delegate {
+                inner: String::from(r"[a[^\n\x0B\x0C\r\x{85}\x{2028}\x{2029}]]"),
                 size: 1,
                 casei: false
             }
         );
+        let tree = Parser::parse_with_flags("\\h", FLAG_H_HORIZONTAL_SPACE).unwrap();
         assert_eq!(
-            p("\\P{Greek}"),
-            Expr::Delegate {
-                inner: String::from("\\P{Greek}"),
+            tree.tree(),
+            Tree::Delegate {
+                inner: String::from(
+                    r"[\t\x20\xA0\x{1680}\x{180E}\x{2000}-\x{200A}\x{202F}\x{205F}\x{3000}]"
+                ),
                 size: 1,
                 casei: false
             }
         );
+    }
+
+    #[test]
+    fn posix_classes() {
         assert_eq!(
-            p("\\PL"),
-            Expr::Delegate {
-                inner: String::from("\\PL"),
+            p("[[:alpha:][:^digit:]_]"),
+            Tree::Delegate {
+                inner: String::from("[[:alpha:][:^digit:]_]"),
                 size: 1,
                 casei: false
             }
         );
+        let tree =
+            Parser::parse_with_flags("[[:alpha:][:^digit:][:foo:]_]", FLAG_UNICODE_POSIX_CLASSES)
+                .unwrap();
         assert_eq!(
-            p("(?i)\\p{Ll}"),
-            Expr::Delegate {
-                inner: String::from("\\p{Ll}"),
+            tree.tree(),
+            Tree::Delegate {
+                inner: String::from(r"[[\p{Alphabetic}][^\p{Nd}][:foo:]_]"),
                 size: 1,
-                casei: true
+                casei: false
             }
         );
     }
 
     #[test]
-    fn backref() {
+    fn quoting() {
         assert_eq!(
-            p("(.)\\1"),
-            Expr::Concat(vec![
-                Expr::Group(Box::new(Expr::Any { newline: false })),
-                Expr::Backref(1),
+            p("\\Qa(|\\E"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                make_literal("("),
+                make_literal("|"),
+            ])
+        );
+        assert_eq!(
+            p("\\Qab\\E+"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                Tree::Repeat {
+                    child: Box::new(make_literal("b")),
+                    lo: 1,
+                    hi: usize::MAX,
+                    greedy: true,
+                },
             ])
         );
+        assert_eq!(p("\\Q*"), make_literal("*"));
+        assert_eq!(p("a\\E"), make_literal("a"));
+        assert_eq!(
+            p("[\\Q]-\\E]"),
+            Tree::Delegate {
+                inner: String::from("[\\]\\-]"),
+                size: 1,
+                casei: false
+            }
+        );
     }
 
     #[test]
-    fn named_backref() {
+    fn line_break() {
         assert_eq!(
-            p("(?<i>.)\\k<i>"),
-            Expr::Concat(vec![
-                Expr::Group(Box::new(Expr::Any { newline: false })),
-                Expr::Backref(1),
+            p("\\R"),
+            Tree::AtomicGroup(Box::new(Tree::Alt(vec![
+                make_literal("\r\n"),
+                Tree::Delegate {
+                    inner: String::from(r"[\n\x0B\x0C\r\x{85}\x{2028}\x{2029}]"),
+                    size: 1,
+                    casei: false,
+                },
+            ])))
+        );
+        fail("[\\R]");
+    }
+
+    #[test]
+    fn any_except_newline() {
+        assert_eq!(p("\\N"), Tree::Any { newline: false });
+        assert_eq!(p("(?s)\\N"), Tree::Any { newline: false });
+        assert_eq!(p("\\N{U+41}"), make_literal("A"));
+        assert_eq!(
+            p("[\\N{U+61}-\\N{U+7A}]"),
+            Tree::Delegate {
+                inner: String::from("[a-z]"),
+                size: 1,
+                casei: false
+            }
+        );
+        fail("[\\N]");
+        fail("\\N{U+}");
+        fail("\\N{U+41");
+        assert_error(
+            "\\N{LATIN CAPITAL LETTER A}",
+            "Parsing error at position 0: Names of characters not supported: \\N{LATIN CAPITAL LETTER A}",
+        );
+    }
+
+    #[test]
+    fn grapheme_cluster() {
+        assert_eq!(
+            p("a\\X"),
+            Tree::Concat(vec![make_literal("a"), Tree::GraphemeCluster])
+        );
+        fail("[\\X]");
+    }
+
+    #[test]
+    fn callout() {
+        assert_eq!(
+            p("a(?C1)b"),
+            Tree::Concat(vec![
+                make_literal("a"),
+                Tree::Callout(CalloutId::Number(1)),
+                make_literal("b"),
             ])
         );
+        assert_eq!(p("(?C)"), Tree::Callout(CalloutId::Number(0)));
+        assert_eq!(
+            p("(?C\"tag\")"),
+            Tree::Callout(CalloutId::String("tag".to_string()))
+        );
+        assert_eq!(
+            p("(?C{a}}b})"),
+            Tree::Callout(CalloutId::String("a}b".to_string()))
+        );
+        assert_eq!(
+            p("(?C'')"),
+            Tree::Callout(CalloutId::String("".to_string()))
+        );
+        fail("(?Cx)");
+        fail("(?C1");
+        assert_eq!(
+            p("a(?{even})"),
+            Tree::Concat(vec![make_literal("a"), Tree::Predicate("even".to_string()),])
+        );
+        fail("(?{})");
+        fail("(?{a b})");
+        fail("(?{a}");
+        fail("(?C\"tag)");
+        fail("(?C\"tag\"x)");
     }
 
     #[test]
-    fn relative_backref() {
+    fn branch_reset() {
         assert_eq!(
-            p("(a)(.)\\k<-1>"),
-            Expr::Concat(vec![
-                Expr::Group(Box::new(make_literal("a"))),
-                Expr::Group(Box::new(Expr::Any { newline: false })),
-                Expr::Backref(2)
+            p("(?|(a)|(b)c)(d)"),
+            Tree::Concat(vec![
+                Tree::BranchReset(vec![
+                    Tree::Group(Box::new(make_literal("a"))),
+                    Tree::Concat(vec![
+                        Tree::Group(Box::new(make_literal("b"))),
+                        make_literal("c"),
+                    ]),
+                ]),
+                Tree::Group(Box::new(make_literal("d"))),
             ])
         );
-        fail("(?P<->.)");
-        fail("(.)(?P=-)")
+        assert_eq!(
+            p("(?|(a)(b)|(c))\\2"),
+            Tree::Concat(vec![
+                Tree::BranchReset(vec![
+                    Tree::Concat(vec![
+                        Tree::Group(Box::new(make_literal("a"))),
+                        Tree::Group(Box::new(make_literal("b"))),
+                    ]),
+                    Tree::Group(Box::new(make_literal("c"))),
+                ]),
+                Tree::Backref {
+                    group: 2,
+                    casei: false
+                },
+            ])
+        );
+        assert_eq!(
+            p("(?|a|b)"),
+            Tree::Alt(vec![make_literal("a"), make_literal("b")])
+        );
+        assert_eq!(p("(?|a)"), make_literal("a"));
+        fail("(?|(a)|b");
+    }
+
+    #[test]
+    fn subroutine_call() {
+        assert_eq!(
+            p("(a(?1)?)"),
+            Tree::Group(Box::new(Tree::Concat(vec![
+                make_literal("a"),
+                Tree::Repeat {
+                    child: Box::new(Tree::SubroutineCall(1)),
+                    lo: 0,
+                    hi: 1,
+                    greedy: true
+                },
+            ])))
+        );
+        assert_eq!(
+            p("a(?R)"),
+            Tree::Concat(vec![make_literal("a"), Tree::SubroutineCall(0)])
+        );
+        assert_eq!(p("(?0)"), Tree::SubroutineCall(0));
+        assert_eq!(
+            p("(?<x>.)(?&x)(?P>x)"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(Tree::Any { newline: false })),
+                Tree::SubroutineCall(1),
+                Tree::SubroutineCall(1),
+            ])
+        );
+        assert_eq!(
+            p("(a)(?-1)(?+1)(b)"),
+            Tree::Concat(vec![
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::SubroutineCall(1),
+                Tree::SubroutineCall(2),
+                Tree::Group(Box::new(make_literal("b"))),
+            ])
+        );
+        let tree = Expr::parse_tree("(a)(?2)(b)(?1)").unwrap();
+        assert_eq!(tree.subroutine_calls.iter().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            p("(?&y)(?<x>a)(?<y>b)"),
+            Tree::Concat(vec![
+                Tree::SubroutineCall(2),
+                Tree::Group(Box::new(make_literal("a"))),
+                Tree::Group(Box::new(make_literal("b"))),
+            ])
+        );
+        fail("(?-1)");
+        fail("(?+0)");
+        fail("(?&x)");
+        fail("(?&)");
+        fail("(?1");
+        assert_error(
+            "(?<x>.)(?1)",
+            "Error compiling regex: Numbered backref/call not allowed because named group was used, use a named backref instead",
+        );
     }
 
     #[test]
     fn lookaround() {
         assert_eq!(
             p("(?=a)"),
-            Expr::LookAround(Box::new(make_literal("a")), LookAhead)
+            Tree::LookAround(Box::new(make_literal("a")), LookAhead)
         );
         assert_eq!(
             p("(?!a)"),
-            Expr::LookAround(Box::new(make_literal("a")), LookAheadNeg)
+            Tree::LookAround(Box::new(make_literal("a")), LookAheadNeg)
         );
         assert_eq!(
             p("(?<=a)"),
-            Expr::LookAround(Box::new(make_literal("a")), LookBehind)
+            Tree::LookAround(Box::new(make_literal("a")), LookBehind)
         );
         assert_eq!(
             p("(?<!a)"),
-            Expr::LookAround(Box::new(make_literal("a")), LookBehindNeg)
+            Tree::LookAround(Box::new(make_literal("a")), LookBehindNeg)
         );
     }
 
@@ -1317,8 +3910,8 @@ mod tests {
     fn shy_group() {
         assert_eq!(
             p("(?:ab)c"),
-            Expr::Concat(vec![
-                Expr::Concat(vec![make_literal("a"), make_literal("b"),]),
+            Tree::Concat(vec![
+                Tree::Concat(vec![make_literal("a"), make_literal("b"),]),
                 make_literal("c"),
             ])
         );
@@ -1326,35 +3919,61 @@ mod tests {
 
     #[test]
     fn flag_state() {
-        assert_eq!(p("(?s)."), Expr::Any { newline: true });
-        assert_eq!(p("(?s:(?-s:.))"), Expr::Any { newline: false });
+        assert_eq!(p("(?s)."), Tree::Any { newline: true });
+        assert_eq!(p("(?s:(?-s:.))"), Tree::Any { newline: false });
         assert_eq!(
             p("(?s:.)."),
-            Expr::Concat(vec![
-                Expr::Any { newline: true },
-                Expr::Any { newline: false },
+            Tree::Concat(vec![
+                Tree::Any { newline: true },
+                Tree::Any { newline: false },
             ])
         );
         assert_eq!(
             p("(?:(?s).)."),
-            Expr::Concat(vec![
-                Expr::Any { newline: true },
-                Expr::Any { newline: false },
+            Tree::Concat(vec![
+                Tree::Any { newline: true },
+                Tree::Any { newline: false },
+            ])
+        );
+        // flags are scoped to all kinds of groups, but apply to the following alternatives
+        for re in ["((?s).).", "(?>(?s).).", "(?=(?s).).", "(?|(?s).)."] {
+            let Tree::Concat(children) = p(re) else {
+                panic!("{}", re);
+            };
+            assert_eq!(children[1], Tree::Any { newline: false }, "{}", re);
+        }
+        assert_eq!(
+            p("(?:a(?i)b|c)d"),
+            Tree::Concat(vec![
+                Tree::Alt(vec![
+                    Tree::Concat(vec![
+                        make_literal("a"),
+                        Tree::Literal {
+                            val: String::from("b"),
+                            casei: true
+                        },
+                    ]),
+                    Tree::Literal {
+                        val: String::from("c"),
+                        casei: true
+                    },
+                ]),
+                make_literal("d"),
             ])
         );
     }
 
     #[test]
     fn flag_multiline() {
-        assert_eq!(p("^"), Expr::Assertion(Assertion::StartText));
+        assert_eq!(p("^"), Tree::Assertion(Assertion::StartText));
         assert_eq!(
             p("(?m:^)"),
-            Expr::Assertion(Assertion::StartLine { crlf: false })
+            Tree::Assertion(Assertion::StartLine { crlf: false })
         );
-        assert_eq!(p("$"), Expr::Assertion(Assertion::EndText));
+        assert_eq!(p("$"), Tree::Assertion(Assertion::EndText));
         assert_eq!(
             p("(?m:$)"),
-            Expr::Assertion(Assertion::EndLine { crlf: false })
+            Tree::Assertion(Assertion::EndLine { crlf: false })
         );
     }
 
@@ -1377,15 +3996,15 @@ mod tests {
     fn lifetime() {
         assert_eq!(
             p("\\'[a-zA-Z_][a-zA-Z0-9_]*(?!\\')\\b"),
-            Expr::Concat(vec![
+            Tree::Concat(vec![
                 make_literal("'"),
-                Expr::Delegate {
+                Tree::Delegate {
                     inner: String::from("[a-zA-Z_]"),
                     size: 1,
                     casei: false
                 },
-                Expr::Repeat {
-                    child: Box::new(Expr::Delegate {
+                Tree::Repeat {
+                    child: Box::new(Tree::Delegate {
                         inner: String::from("[a-zA-Z0-9_]"),
                         size: 1,
                         casei: false
@@ -1394,8 +4013,8 @@ mod tests {
                     hi: usize::MAX,
                     greedy: true
                 },
-                Expr::LookAround(Box::new(make_literal("'")), LookAheadNeg),
-                Expr::Assertion(Assertion::WordBoundary),
+                Tree::LookAround(Box::new(make_literal("'")), LookAheadNeg),
+                Tree::Assertion(Assertion::WordBoundary),
             ])
         );
     }
@@ -1423,6 +4042,12 @@ mod tests {
         assert_eq!(p("(?x: [ \\] \\\\] )"), p("[ \\] \\\\]"));
         assert_eq!(p("(?x: a\\ b )"), p("a b"));
         assert_eq!(p("(?x: a (?-x:#) b )"), p("a#b"));
+        assert_eq!(p("(?x:\x0Ba\x0C\u{85}b\u{2028})"), p("ab"));
+        // in the bodies of look-arounds and atomic groups
+        assert_eq!(p("(?x)(?<= a # ) comment\n b )"), p("(?<=ab)"));
+        assert_eq!(p("(?x)(?! a | b\tc )"), p("(?!a|bc)"));
+        assert_eq!(p("(?x)(?> a # (\n b )"), p("(?>ab)"));
+        assert_eq!(p("(?=(?x) a b ) c d"), p("(?=ab) c d"));
     }
 
     #[test]
@@ -1440,14 +4065,14 @@ mod tests {
 
     #[test]
     fn atomic_group() {
-        assert_eq!(p("(?>a)"), Expr::AtomicGroup(Box::new(make_literal("a"))));
+        assert_eq!(p("(?>a)"), Tree::AtomicGroup(Box::new(make_literal("a"))));
     }
 
     #[test]
     fn possessive() {
         assert_eq!(
             p("a++"),
-            Expr::AtomicGroup(Box::new(Expr::Repeat {
+            Tree::AtomicGroup(Box::new(Tree::Repeat {
                 child: Box::new(make_literal("a")),
                 lo: 1,
                 hi: usize::MAX,
@@ -1456,7 +4081,7 @@ mod tests {
         );
         assert_eq!(
             p("a*+"),
-            Expr::AtomicGroup(Box::new(Expr::Repeat {
+            Tree::AtomicGroup(Box::new(Tree::Repeat {
                 child: Box::new(make_literal("a")),
                 lo: 0,
                 hi: usize::MAX,
@@ -1465,7 +4090,7 @@ mod tests {
         );
         assert_eq!(
             p("a?+"),
-            Expr::AtomicGroup(Box::new(Expr::Repeat {
+            Tree::AtomicGroup(Box::new(Tree::Repeat {
                 child: Box::new(make_literal("a")),
                 lo: 0,
                 hi: 1,
@@ -1589,7 +4214,7 @@ mod tests {
     fn keepout() {
         assert_eq!(
             p("a\\Kb"),
-            Expr::Concat(vec![make_literal("a"), Expr::KeepOut, make_literal("b"),])
+            Tree::Concat(vec![make_literal("a"), Tree::KeepOut, make_literal("b"),])
         );
     }
 
@@ -1597,26 +4222,26 @@ mod tests {
     fn backref_exists_condition() {
         assert_eq!(
             p("(h)?(?(1))"),
-            Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Group(Box::new(make_literal("h")))),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("h")))),
                     lo: 0,
                     hi: 1,
                     greedy: true
                 },
-                Expr::BackrefExistsCondition(1)
+                Tree::BackrefExistsCondition(1)
             ])
         );
         assert_eq!(
             p("(?<h>h)?(?('h'))"),
-            Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Group(Box::new(make_literal("h")))),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("h")))),
                     lo: 0,
                     hi: 1,
                     greedy: true
                 },
-                Expr::BackrefExistsCondition(1)
+                Tree::BackrefExistsCondition(1)
             ])
         );
     }
@@ -1641,15 +4266,15 @@ mod tests {
     fn backref_condition_with_one_two_or_three_branches() {
         assert_eq!(
             p("(h)?(?(1)i|x)"),
-            Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Group(Box::new(make_literal("h")))),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("h")))),
                     lo: 0,
                     hi: 1,
                     greedy: true
                 },
-                Expr::Conditional {
-                    condition: Box::new(Expr::BackrefExistsCondition(1)),
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
                     true_branch: Box::new(make_literal("i")),
                     false_branch: Box::new(make_literal("x")),
                 },
@@ -1658,37 +4283,37 @@ mod tests {
 
         assert_eq!(
             p("(h)?(?(1)i)"),
-            Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Group(Box::new(make_literal("h")))),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("h")))),
                     lo: 0,
                     hi: 1,
                     greedy: true
                 },
-                Expr::Conditional {
-                    condition: Box::new(Expr::BackrefExistsCondition(1)),
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
                     true_branch: Box::new(make_literal("i")),
-                    false_branch: Box::new(Expr::Empty),
+                    false_branch: Box::new(Tree::Empty),
                 },
             ])
         );
 
         assert_eq!(
             p("(h)?(?(1)ii|xy|z)"),
-            Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Group(Box::new(make_literal("h")))),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("h")))),
                     lo: 0,
                     hi: 1,
                     greedy: true
                 },
-                Expr::Conditional {
-                    condition: Box::new(Expr::BackrefExistsCondition(1)),
-                    true_branch: Box::new(Expr::Concat(
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
+                    true_branch: Box::new(Tree::Concat(
                         vec![make_literal("i"), make_literal("i"),]
                     )),
-                    false_branch: Box::new(Expr::Alt(vec![
-                        Expr::Concat(vec![make_literal("x"), make_literal("y"),]),
+                    false_branch: Box::new(Tree::Alt(vec![
+                        Tree::Concat(vec![make_literal("x"), make_literal("y"),]),
                         make_literal("z"),
                     ])),
                 },
@@ -1697,20 +4322,20 @@ mod tests {
 
         assert_eq!(
             p("(?<cap>h)?(?(<cap>)ii|xy|z)"),
-            Expr::Concat(vec![
-                Expr::Repeat {
-                    child: Box::new(Expr::Group(Box::new(make_literal("h")))),
+            Tree::Concat(vec![
+                Tree::Repeat {
+                    child: Box::new(Tree::Group(Box::new(make_literal("h")))),
                     lo: 0,
                     hi: 1,
                     greedy: true
                 },
-                Expr::Conditional {
-                    condition: Box::new(Expr::BackrefExistsCondition(1)),
-                    true_branch: Box::new(Expr::Concat(
+                Tree::Conditional {
+                    condition: Box::new(Tree::BackrefExistsCondition(1)),
+                    true_branch: Box::new(Tree::Concat(
                         vec![make_literal("i"), make_literal("i"),]
                     )),
-                    false_branch: Box::new(Expr::Alt(vec![
-                        Expr::Concat(vec![make_literal("x"), make_literal("y"),]),
+                    false_branch: Box::new(Tree::Alt(vec![
+                        Tree::Concat(vec![make_literal("x"), make_literal("y"),]),
                         make_literal("z"),
                     ])),
                 },
@@ -1722,33 +4347,36 @@ mod tests {
     fn conditional() {
         assert_eq!(
             p("((?(a)b|c))(\\1)"),
-            Expr::Concat(vec![
-                Expr::Group(Box::new(Expr::Conditional {
+            Tree::Concat(vec![
+                Tree::Group(Box::new(Tree::Conditional {
                     condition: Box::new(make_literal("a")),
                     true_branch: Box::new(make_literal("b")),
                     false_branch: Box::new(make_literal("c"))
                 })),
-                Expr::Group(Box::new(Expr::Backref(1)))
+                Tree::Group(Box::new(Tree::Backref {
+                    group: 1,
+                    casei: false
+                }))
             ])
         );
 
         assert_eq!(
             p(r"^(?(\d)abc|\d!)$"),
-            Expr::Concat(vec![
-                Expr::Assertion(Assertion::StartText),
-                Expr::Conditional {
-                    condition: Box::new(Expr::Delegate {
+            Tree::Concat(vec![
+                Tree::Assertion(Assertion::StartText),
+                Tree::Conditional {
+                    condition: Box::new(Tree::Delegate {
                         inner: "\\d".to_string(),
                         size: 1,
                         casei: false,
                     }),
-                    true_branch: Box::new(Expr::Concat(vec![
+                    true_branch: Box::new(Tree::Concat(vec![
                         make_literal("a"),
                         make_literal("b"),
                         make_literal("c"),
                     ])),
-                    false_branch: Box::new(Expr::Concat(vec![
-                        Expr::Delegate {
+                    false_branch: Box::new(Tree::Concat(vec![
+                        Tree::Delegate {
                             inner: "\\d".to_string(),
                             size: 1,
                             casei: false,
@@ -1756,22 +4384,22 @@ mod tests {
                         make_literal("!"),
                     ])),
                 },
-                Expr::Assertion(Assertion::EndText),
+                Tree::Assertion(Assertion::EndText),
             ])
         );
 
         assert_eq!(
             p(r"(?((?=\d))\w|!)"),
-            Expr::Conditional {
-                condition: Box::new(Expr::LookAround(
-                    Box::new(Expr::Delegate {
+            Tree::Conditional {
+                condition: Box::new(Tree::LookAround(
+                    Box::new(Tree::Delegate {
                         inner: "\\d".to_string(),
                         size: 1,
                         casei: false
                     }),
                     LookAhead
                 )),
-                true_branch: Box::new(Expr::Delegate {
+                true_branch: Box::new(Tree::Delegate {
                     inner: "\\w".to_string(),
                     size: 1,
                     casei: false,
@@ -1782,8 +4410,8 @@ mod tests {
 
         assert_eq!(
             p(r"(?((ab))c|d)"),
-            Expr::Conditional {
-                condition: Box::new(Expr::Group(Box::new(Expr::Concat(vec![
+            Tree::Conditional {
+                condition: Box::new(Tree::Group(Box::new(Tree::Concat(vec![
                     make_literal("a"),
                     make_literal("b"),
                 ]),))),
@@ -0,0 +1,48 @@
+use core::ops::Range;
+
+use crate::Match;
+
+/// A match of [`Regex::find_partial`](crate::Regex::find_partial), which is partial if the
+/// end of the text was reached while the match could still continue.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct PartialMatch<'t> {
+    mat: Match<'t>,
+    partial: bool,
+}
+
+impl<'t> PartialMatch<'t> {
+    pub(crate) fn new(mat: Match<'t>, partial: bool) -> PartialMatch<'t> {
+        PartialMatch { mat, partial }
+    }
+
+    /// Returns whether the match is partial, so that more text is needed to complete it.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Returns the starting byte offset of the match in the text.
+    pub fn start(&self) -> usize {
+        self.mat.start()
+    }
+
+    /// Returns the ending byte offset of the match in the text, which is the end of the text
+    /// for a partial match.
+    pub fn end(&self) -> usize {
+        self.mat.end()
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the match in text.
+    pub fn range(&self) -> Range<usize> {
+        self.mat.range()
+    }
+
+    /// Returns the matched text.
+    pub fn as_str(&self) -> &'t str {
+        self.mat.as_str()
+    }
+
+    /// Returns the match without whether it's partial.
+    pub fn as_match(&self) -> Match<'t> {
+        self.mat
+    }
+}
@@ -0,0 +1,65 @@
+use core::str::pattern::{Pattern, SearchStep, Searcher};
+
+use crate::{Match, Matches, Regex};
+
+/// Searches a string for the matches of a regex, so that a `&Regex` can be used as a
+/// [`Pattern`] with methods like [`str::find`], [`str::split`] and [`str::matches`].
+///
+/// The matches are the ones that [`Regex::find_iter`] returns. `Pattern` has no way to report
+/// errors, so a search that fails, e.g. because the backtrack limit was exceeded, ends as if
+/// there were no more matches.
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the lifetime of the
+/// searched string.
+#[derive(Debug)]
+pub struct RegexSearcher<'r, 't> {
+    matches: Matches<'r, 't>,
+    next_match: Option<Match<'t>>,
+    /// End of the last step that was returned
+    pos: usize,
+}
+
+/// A search with a `&Regex` pattern reports the matches of [`Regex::find_iter`] until a search
+/// fails, e.g. because the backtrack limit was exceeded; `Pattern` has no way to report the
+/// error, so the rest of the string is treated as if there were no more matches. Use
+/// [`Regex::find_iter`] directly to see such errors.
+impl<'r> Pattern for &'r Regex {
+    type Searcher<'t> = RegexSearcher<'r, 't>;
+
+    fn into_searcher(self, haystack: &str) -> RegexSearcher<'r, '_> {
+        RegexSearcher {
+            matches: self.find_iter(haystack),
+            next_match: None,
+            pos: 0,
+        }
+    }
+}
+
+unsafe impl<'r, 't> Searcher<'t> for RegexSearcher<'r, 't> {
+    fn haystack(&self) -> &'t str {
+        self.matches.text()
+    }
+
+    fn next(&mut self) -> SearchStep {
+        if self.next_match.is_none() {
+            self.next_match = self.matches.next().and_then(|result| result.ok());
+        }
+        let pos = self.pos;
+        match self.next_match {
+            Some(mat) if pos < mat.start() => {
+                self.pos = mat.start();
+                SearchStep::Reject(pos, mat.start())
+            }
+            Some(mat) => {
+                self.next_match = None;
+                self.pos = mat.end();
+                SearchStep::Match(mat.start(), mat.end())
+            }
+            None if pos < self.haystack().len() => {
+                self.pos = self.haystack().len();
+                SearchStep::Reject(pos, self.pos)
+            }
+            None => SearchStep::Done,
+        }
+    }
+}
@@ -0,0 +1,418 @@
+//! Prefilter for fancy regexes, which finds where a match can start before running the VM.
+//!
+//! The prefilter is a regex of the regex crate that matches a superset of what the fancy regex
+//! matches: look-arounds are treated as empty, backrefs and subroutine calls as any text, and
+//! so on. No match of the fancy regex can start before the leftmost match of the prefilter, so
+//! the VM can start searching there, and doesn't need to run at all without a match.
+//!
+//! When every match starts with one of a few literals, the VM also skips to the next position
+//! where one of them occurs each time it tries another start position, using memchr or
+//! Aho-Corasick instead of setting up the VM at every position.
+//!
+//! Otherwise, when every match contains one of a few literals at a bounded distance from its
+//! start, like `@example.com` in `([a-z]{2,8})@example\.com(?!\.)` at most 32 bytes in, the VM
+//! only tries the start positions in the window before each occurrence of the literals.
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use regex_automata::meta::Regex as RaRegex;
+use regex_automata::meta::{Builder as RaBuilder, Config as RaConfig};
+use regex_automata::util::prefilter::Prefilter;
+use regex_automata::{MatchKind, Span};
+
+use crate::analyze::Info;
+use crate::{BacktrackingVerb, Expr, ExprArena, ExprId, RegexOptions};
+
+/// Any character, or a byte that isn't valid UTF-8 like the VM matches it.
+const ANY: &str = r"(?s:.|(?-u:[\x80-\xFF]))";
+/// Any character except newline, or a byte that isn't valid UTF-8.
+const ANY_NO_NL: &str = r"(?:.|(?-u:[\x80-\xFF]))";
+
+/// Returns the prefilter for the expression of a fancy regex, or `None` if there is no useful
+/// prefilter, e.g. because the expression uses `\G` or can match the empty text.
+pub(crate) fn prefilter(
+    arena: &ExprArena,
+    expr: ExprId,
+    options: &RegexOptions,
+) -> Option<RaRegex> {
+    if options.anchored {
+        return None;
+    }
+    let mut re = String::new();
+    let mut approximation = ExprArena::default();
+    approximate(arena, expr, &mut approximation)?.to_str(&approximation, &mut re, 0);
+    let mut config = RaConfig::new().utf8_empty(false);
+    if let Some(size_limit) = options.delegate_size_limit {
+        config = config.nfa_size_limit(Some(size_limit));
+    }
+    if let Some(dfa_size_limit) = options.delegate_dfa_size_limit {
+        config = config.dfa_size_limit(Some(dfa_size_limit));
+    }
+    let prefilter = RaBuilder::new()
+        .configure(config)
+        .syntax(options.syntaxc.utf8(false))
+        .build(&re)
+        .ok()?;
+    // a prefilter that matches everywhere would only slow the search down
+    if prefilter.is_match("") {
+        return None;
+    }
+    Some(prefilter)
+}
+
+/// The maximum number of literals that a match can start with for the literal prefilter.
+const MAX_PREFIXES: usize = 64;
+
+/// Returns the literal prefilter for the expression of a fancy regex, which finds the literals
+/// that every match starts with one of, or `None` if there are no such literals.
+pub(crate) fn literal_prefilter(
+    arena: &ExprArena,
+    expr: ExprId,
+    options: &RegexOptions,
+) -> Option<Prefilter> {
+    if options.anchored {
+        return None;
+    }
+    let prefixes = prefixes(arena, expr)?;
+    if prefixes.literals.iter().any(String::is_empty) {
+        return None;
+    }
+    Prefilter::new(MatchKind::LeftmostFirst, &prefixes.literals).filter(Prefilter::is_fast)
+}
+
+/// Literals that every match contains one of, at a bounded distance from the start of the
+/// match.
+#[derive(Clone, Debug)]
+pub(crate) struct InnerLiteral {
+    prefilter: Prefilter,
+    /// The minimum distance of the literal from the start of the match in bytes
+    min_offset: usize,
+    /// The maximum distance of the literal from the start of the match in bytes
+    max_offset: usize,
+}
+
+impl InnerLiteral {
+    /// Returns the heap memory used by the prefilter for the literals, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.prefilter.memory_usage()
+    }
+
+    /// Returns the start positions from `ix` where a match can start for the next occurrence of
+    /// the literals before `end`, or `None` if there is no match from `ix` that ends before `end`.
+    pub(crate) fn next_starts(
+        &self,
+        s: &[u8],
+        ix: usize,
+        end: usize,
+    ) -> Option<RangeInclusive<usize>> {
+        let from = ix + self.min_offset;
+        if from > end {
+            return None;
+        }
+        let hit = self.prefilter.find(s, Span::from(from..end))?.start;
+        let mut start = hit.saturating_sub(self.max_offset).max(ix);
+        // start at a character, which isn't a continuation byte
+        while start > ix && s.get(start).map_or(false, |&b| b & 0xC0 == 0x80) {
+            start -= 1;
+        }
+        Some(start..=hit - self.min_offset)
+    }
+}
+
+/// Returns the inner literal prefilter for the info of a fancy regex's expression, for the
+/// longest literals that every match contains after a prefix of bounded length, or `None` if
+/// there are no such literals other than at the start of the match.
+pub(crate) fn inner_literal(
+    arena: &ExprArena,
+    info: &Info<'_>,
+    options: &RegexOptions,
+) -> Option<InnerLiteral> {
+    if options.anchored {
+        return None;
+    }
+    let mut info = info;
+    while let Expr::Group(_) = *info.expr {
+        info = &info.children[0];
+    }
+    let Expr::Concat(ref children) = *info.expr else {
+        return None;
+    };
+    let is_literal = |expr: &Expr| matches!(*expr, Expr::Literal { casei: false, .. });
+    let mut best: Option<(Prefixes, usize, usize)> = None;
+    let mut min_offset = 0;
+    let mut max_offset = Some(0);
+    for (i, child) in info.children.iter().enumerate() {
+        // (*ACCEPT) can end the match before the literals, and callouts have to be called at
+        // every start position
+        if calls_out(child) {
+            break;
+        }
+        let Some(max) = max_offset else {
+            break;
+        };
+        // only the start of a run of literals, which has the longest literals of the run
+        if i > 0 && !is_literal(&arena[children[i - 1]]) {
+            if let Some(prefixes) = concat_prefixes(arena, &children[i..]) {
+                let len = shortest_len(&prefixes);
+                if len > 0
+                    && best
+                        .as_ref()
+                        .map_or(true, |best| len > shortest_len(&best.0))
+                {
+                    best = Some((prefixes, min_offset, max));
+                }
+            }
+        }
+        min_offset += child.min_len;
+        max_offset = max.checked_add(child.max_len?);
+    }
+    let (prefixes, min_offset, max_offset) = best?;
+    let prefilter = Prefilter::new(MatchKind::LeftmostFirst, &prefixes.literals)?;
+    prefilter.is_fast().then(|| InnerLiteral {
+        prefilter,
+        min_offset,
+        max_offset,
+    })
+}
+
+/// Returns whether the expression has callouts or predicates, or `(*ACCEPT)`.
+fn calls_out(info: &Info<'_>) -> bool {
+    matches!(
+        *info.expr,
+        Expr::Callout(_) | Expr::Predicate(_) | Expr::BacktrackingVerb(BacktrackingVerb::Accept)
+    ) || info.children.iter().any(calls_out)
+}
+
+/// Returns the length of the shortest literal.
+fn shortest_len(prefixes: &Prefixes) -> usize {
+    prefixes.literals.iter().map(String::len).min().unwrap_or(0)
+}
+
+/// The literals that every match of an expression starts with one of.
+struct Prefixes {
+    literals: Vec<String>,
+    /// Whether every match is one of the literals, so that what follows can extend them
+    exact: bool,
+}
+
+impl Prefixes {
+    fn exact(literal: &str) -> Prefixes {
+        Prefixes {
+            literals: vec![String::from(literal)],
+            exact: true,
+        }
+    }
+
+    fn inexact(self) -> Prefixes {
+        Prefixes {
+            exact: false,
+            ..self
+        }
+    }
+}
+
+/// Returns the literals that every match of `expr` starts with one of, or `None` if they
+/// aren't known. An empty literal means that a match can start with anything.
+fn prefixes(arena: &ExprArena, expr: ExprId) -> Option<Prefixes> {
+    match arena[expr] {
+        Expr::Empty => Some(Prefixes::exact("")),
+        Expr::Literal { ref val, casei } if !casei => Some(Prefixes::exact(val)),
+        Expr::Concat(ref children) => concat_prefixes(arena, children),
+        Expr::Alt(ref children) | Expr::BranchReset(ref children) => {
+            let mut acc = Prefixes {
+                literals: Vec::new(),
+                exact: true,
+            };
+            for &child in children {
+                let next = prefixes(arena, child)?;
+                acc.literals.extend(next.literals);
+                acc.exact &= next.exact;
+                if acc.literals.len() > MAX_PREFIXES {
+                    return None;
+                }
+            }
+            Some(acc)
+        }
+        Expr::Repeat { lo: 0, .. } => Some(Prefixes::exact("").inexact()),
+        Expr::Repeat { child, lo, hi, .. } => {
+            let child = prefixes(arena, child)?;
+            Some(if lo == 1 && hi == 1 {
+                child
+            } else {
+                child.inexact()
+            })
+        }
+        Expr::Group(child)
+        | Expr::AtomicGroup(child)
+        | Expr::ScriptRun(child)
+        | Expr::BalancingGroup { child, .. } => prefixes(arena, child),
+        // zero-width items only restrict where a match can start
+        Expr::Assertion(_)
+        | Expr::LookAround(..)
+        | Expr::KeepOut
+        | Expr::BackrefExistsCondition(_)
+        | Expr::ContinueFromPreviousMatchEnd
+        | Expr::BacktrackingVerb(BacktrackingVerb::Fail)
+        | Expr::BacktrackingVerb(BacktrackingVerb::Prune)
+        | Expr::BacktrackingVerb(BacktrackingVerb::Skip)
+        | Expr::BacktrackingVerb(BacktrackingVerb::Then) => Some(Prefixes::exact("")),
+        // (*ACCEPT) ends the match, so nothing after it has to match
+        Expr::BacktrackingVerb(BacktrackingVerb::Accept) => Some(Prefixes::exact("").inexact()),
+        // callouts and predicates have to be called at every start position
+        _ => None,
+    }
+}
+
+/// Returns the literals that every match of a concatenation of `children` starts with one of.
+fn concat_prefixes(arena: &ExprArena, children: &[ExprId]) -> Option<Prefixes> {
+    let mut acc = Prefixes::exact("");
+    for &child in children {
+        // a child without known prefixes still follows one of the literals so far
+        let Some(next) = prefixes(arena, child) else {
+            return Some(acc.inexact());
+        };
+        if acc.literals.len() * next.literals.len() > MAX_PREFIXES {
+            return Some(acc.inexact());
+        }
+        let mut literals = Vec::new();
+        for prefix in &acc.literals {
+            for suffix in &next.literals {
+                let mut literal = prefix.clone();
+                literal.push_str(suffix);
+                literals.push(literal);
+            }
+        }
+        acc = Prefixes {
+            literals,
+            exact: next.exact,
+        };
+        if !acc.exact {
+            break;
+        }
+    }
+    Some(acc)
+}
+
+/// Returns an expression that the regex crate can match, which matches a superset of the texts
+/// that `expr` matches, or `None` if there is none. The children of the expression are added to
+/// `out`.
+fn approximate(arena: &ExprArena, expr: ExprId, out: &mut ExprArena) -> Option<Expr> {
+    let approximate_child = |child: ExprId, out: &mut ExprArena| {
+        let child = approximate(arena, child, out)?;
+        Some(out.push(child))
+    };
+    let approximate_all = |children: &[ExprId], out: &mut ExprArena| {
+        children
+            .iter()
+            .map(|&child| approximate_child(child, out))
+            .collect::<Option<Vec<_>>>()
+    };
+    Some(match arena[expr] {
+        Expr::Empty => Expr::Empty,
+        Expr::Delegate {
+            ref inner,
+            size,
+            casei,
+        } => Expr::Delegate {
+            inner: inner.clone(),
+            size,
+            casei,
+        },
+        Expr::Any { newline } => any(if newline { ANY } else { ANY_NO_NL }),
+        Expr::Literal { ref val, casei } if casei && !is_simple_casei(val) => any_text(out),
+        Expr::Literal { ref val, casei } => Expr::Literal {
+            val: val.clone(),
+            casei,
+        },
+        Expr::Assertion(assertion) if assertion.is_hard() => Expr::Empty,
+        Expr::Assertion(assertion) => Expr::Assertion(assertion),
+        Expr::Concat(ref children) => Expr::Concat(approximate_all(children, out)?),
+        Expr::Alt(ref children) | Expr::BranchReset(ref children) => {
+            Expr::Alt(approximate_all(children, out)?)
+        }
+        Expr::Repeat {
+            child,
+            lo,
+            hi,
+            greedy,
+        } => match approximate(arena, child, out)? {
+            // a repeat without a child would apply to the expression before it
+            Expr::Empty => Expr::Empty,
+            child => Expr::Repeat {
+                child: out.push(child),
+                lo,
+                hi,
+                greedy,
+            },
+        },
+        Expr::Group(child)
+        | Expr::AtomicGroup(child)
+        | Expr::ScriptRun(child)
+        | Expr::BalancingGroup { child, .. } => approximate(arena, child, out)?,
+        Expr::Conditional {
+            condition,
+            true_branch,
+            false_branch,
+        } => {
+            let if_true = vec![
+                approximate_child(condition, out)?,
+                approximate_child(true_branch, out)?,
+            ];
+            let if_true = out.push(Expr::Concat(if_true));
+            Expr::Alt(vec![if_true, approximate_child(false_branch, out)?])
+        }
+        Expr::LookAround(..)
+        | Expr::KeepOut
+        | Expr::BackrefExistsCondition(_)
+        | Expr::Predicate(_) => Expr::Empty,
+        Expr::Backref { .. } | Expr::SubroutineCall(_) | Expr::Absent(_) => any_text(out),
+        Expr::GraphemeCluster => Expr::Repeat {
+            child: out.push(any(ANY)),
+            lo: 1,
+            hi: usize::MAX,
+            greedy: false,
+        },
+        Expr::BacktrackingVerb(BacktrackingVerb::Fail)
+        | Expr::BacktrackingVerb(BacktrackingVerb::Prune)
+        | Expr::BacktrackingVerb(BacktrackingVerb::Then) => Expr::Empty,
+        // (*ACCEPT) ends the match early, (*SKIP) skips start positions, callouts have to be
+        // called at every start position, and fuzzy groups can match almost anything
+        Expr::BacktrackingVerb(BacktrackingVerb::Accept)
+        | Expr::BacktrackingVerb(BacktrackingVerb::Skip)
+        | Expr::ContinueFromPreviousMatchEnd
+        | Expr::Callout(_)
+        | Expr::Fuzzy { .. } => return None,
+    })
+}
+
+/// Returns whether the regex crate matches the literal ignoring case like the VM. The VM uses
+/// full case folding, so that e.g. `ss` matches `ß`, which is only the same for ASCII literals
+/// without the letters that ligatures fold to.
+fn is_simple_casei(literal: &str) -> bool {
+    let lower = literal.to_ascii_lowercase();
+    literal.is_ascii()
+        && !["ss", "st", "ff", "fi", "fl"]
+            .iter()
+            .any(|pair| lower.contains(pair))
+}
+
+/// Returns an expression for a character in the syntax of the regex crate.
+fn any(re: &str) -> Expr {
+    Expr::Delegate {
+        inner: String::from(re),
+        size: 1,
+        casei: false,
+    }
+}
+
+/// Returns an expression that matches any text, preferring to match as little as possible.
+fn any_text(out: &mut ExprArena) -> Expr {
+    Expr::Repeat {
+        child: out.push(any(ANY)),
+        lo: 0,
+        hi: usize::MAX,
+        greedy: false,
+    }
+}
@@ -0,0 +1,231 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::Enumerate;
+use core::slice;
+use regex_automata::meta::{Builder as RaBuilder, Config as RaConfig, Regex as RaRegex};
+use regex_automata::{Input as RaInput, MatchKind, PatternSet};
+
+use crate::{CompileError, Error, Expr, Regex, RegexImpl, Result};
+
+/// Match multiple, possibly overlapping, regexes in a single search.
+///
+/// Patterns that don't need backtracking are combined into a single set in the backing regex
+/// engine, which answers which of them match in one pass over the text. Only the patterns that
+/// use fancy features such as look-around or backreferences are run one at a time.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::RegexSet;
+/// let set = RegexSet::new([r"\w+", r"\d+", r"(\w)\1", r"(?<=-)\w+", r"foo"]).unwrap();
+/// let matches: Vec<_> = set.matches("abc-cc").unwrap().into_iter().collect();
+/// assert_eq!(matches, vec![0, 2, 3]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RegexSet {
+    patterns: Patterns,
+}
+
+/// Multiple patterns, with the ones that don't need backtracking combined into a single regex.
+#[derive(Clone, Debug)]
+pub(crate) struct Patterns {
+    pub(crate) patterns: Vec<String>,
+    /// The patterns that are delegated, with the index of each one in `patterns`
+    pub(crate) delegated: Option<(RaRegex, Vec<usize>)>,
+    /// The patterns that need backtracking, with the index of each one in `patterns`
+    pub(crate) fancy: Vec<(usize, Regex)>,
+}
+
+/// The set of patterns of a [`RegexSet`] that matched a text.
+#[derive(Clone, Debug)]
+pub struct SetMatches {
+    matched: Vec<bool>,
+}
+
+/// An iterator over the indices of the patterns that matched, created by
+/// [`SetMatches::iter`].
+#[derive(Clone, Debug)]
+pub struct SetMatchesIter<'a>(Enumerate<slice::Iter<'a, bool>>);
+
+/// An owned iterator over the indices of the patterns that matched, created by
+/// [`SetMatches::into_iter`].
+#[derive(Clone, Debug)]
+pub struct SetMatchesIntoIter(Enumerate<vec::IntoIter<bool>>);
+
+impl Patterns {
+    /// Compile the patterns, using `match_kind` for the combined regex.
+    pub(crate) fn new<I, S>(patterns: I, match_kind: MatchKind) -> Result<Patterns>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut set = Patterns {
+            patterns: Vec::new(),
+            delegated: None,
+            fancy: Vec::new(),
+        };
+        let mut delegated_patterns = Vec::new();
+        let mut delegated_indices = Vec::new();
+        for (i, pattern) in patterns.into_iter().enumerate() {
+            let pattern = pattern.as_ref();
+            let regex = Regex::new(pattern)?;
+            if let RegexImpl::Wrap { .. } = *regex.inner {
+                // same escaping as `Regex::new` uses for a wrapped regex
+                let mut re_cooked = String::new();
+                let tree = Expr::parse_tree(pattern)?;
+                tree.root().to_str(&tree.arena, &mut re_cooked, 0);
+                delegated_patterns.push(re_cooked);
+                delegated_indices.push(i);
+            } else {
+                set.fancy.push((i, regex));
+            }
+            set.patterns.push(pattern.to_string());
+        }
+        if !delegated_patterns.is_empty() {
+            let inner = RaBuilder::new()
+                .configure(RaConfig::new().match_kind(match_kind))
+                .build_many(&delegated_patterns)
+                .map_err(CompileError::InnerError)
+                .map_err(Error::CompileError)?;
+            set.delegated = Some((inner, delegated_indices));
+        }
+        Ok(set)
+    }
+}
+
+impl RegexSet {
+    /// Parse and compile a set of regexes with default options.
+    ///
+    /// Returns an [`Error`](enum.Error.html) if any of the patterns could not be parsed.
+    pub fn new<I, S>(patterns: I) -> Result<RegexSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Ok(RegexSet {
+            patterns: Patterns::new(patterns, MatchKind::All)?,
+        })
+    }
+
+    /// Create a set that doesn't contain any patterns and never matches.
+    pub fn empty() -> RegexSet {
+        RegexSet::new(core::iter::empty::<&str>()).expect("empty set is valid")
+    }
+
+    /// Returns true if any of the patterns match `text`.
+    ///
+    /// This stops as soon as a match is found, so it's faster than checking
+    /// [`SetMatches::matched_any`].
+    pub fn is_match(&self, text: &str) -> Result<bool> {
+        if let Some((inner, _)) = &self.patterns.delegated {
+            if inner.is_match(text) {
+                return Ok(true);
+            }
+        }
+        for (_, regex) in &self.patterns.fancy {
+            if regex.is_match(text)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns which of the patterns match `text`.
+    ///
+    /// Patterns are matched independently of each other, so the matches may overlap.
+    pub fn matches(&self, text: &str) -> Result<SetMatches> {
+        let mut matched = vec![false; self.len()];
+        if let Some((inner, indices)) = &self.patterns.delegated {
+            let mut pattern_set = PatternSet::new(inner.pattern_len());
+            inner.which_overlapping_matches(&RaInput::new(text), &mut pattern_set);
+            for id in pattern_set.iter() {
+                matched[indices[id.as_usize()]] = true;
+            }
+        }
+        for (i, regex) in &self.patterns.fancy {
+            matched[*i] = regex.is_match(text)?;
+        }
+        Ok(SetMatches { matched })
+    }
+
+    /// Returns the number of patterns in the set.
+    pub fn len(&self) -> usize {
+        self.patterns.patterns.len()
+    }
+
+    /// Returns true if the set doesn't contain any patterns.
+    pub fn is_empty(&self) -> bool {
+        self.patterns.patterns.is_empty()
+    }
+
+    /// Returns the patterns of the set, in the order they were given.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns.patterns
+    }
+}
+
+impl SetMatches {
+    /// Returns true if any of the patterns matched.
+    pub fn matched_any(&self) -> bool {
+        self.matched.contains(&true)
+    }
+
+    /// Returns true if the pattern at index `i` matched.
+    ///
+    /// # Panics
+    ///
+    /// If `i` isn't the index of a pattern in the set.
+    pub fn matched(&self, i: usize) -> bool {
+        self.matched[i]
+    }
+
+    /// Returns the number of patterns in the set that was searched, not the number of matches.
+    pub fn len(&self) -> usize {
+        self.matched.len()
+    }
+
+    /// Returns true if the set that was searched doesn't contain any patterns.
+    pub fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+
+    /// Returns an iterator over the indices of the patterns that matched, in ascending order.
+    pub fn iter(&self) -> SetMatchesIter<'_> {
+        SetMatchesIter(self.matched.iter().enumerate())
+    }
+}
+
+impl IntoIterator for SetMatches {
+    type Item = usize;
+    type IntoIter = SetMatchesIntoIter;
+
+    fn into_iter(self) -> SetMatchesIntoIter {
+        SetMatchesIntoIter(self.matched.into_iter().enumerate())
+    }
+}
+
+impl<'a> IntoIterator for &'a SetMatches {
+    type Item = usize;
+    type IntoIter = SetMatchesIter<'a>;
+
+    fn into_iter(self) -> SetMatchesIter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> Iterator for SetMatchesIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0.find(|(_, &matched)| matched).map(|(i, _)| i)
+    }
+}
+
+impl Iterator for SetMatchesIntoIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.0.find(|(_, matched)| *matched).map(|(i, _)| i)
+    }
+}
@@ -1,7 +1,7 @@
 use alloc::borrow::Cow;
 use alloc::string::String;
 
-use crate::Captures;
+use crate::{Captures, Expander, Template};
 
 /// Replacer describes types that can be used to replace matches in a string.
 ///
@@ -140,6 +140,45 @@ where
     }
 }
 
+/// Expands a template using the syntax of the `Expander`.
+///
+/// ```
+/// use fancy_regex::{Expander, Regex};
+///
+/// let re = Regex::new(r"(\w+)@(\w+)").unwrap();
+/// let result = re.replace_all("alice@home bob@work", (Expander::python(), r"\2:\1"));
+/// assert_eq!(result, "home:alice work:bob");
+/// ```
+impl Replacer for (Expander, &str) {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        self.0.append_expansion(dst, self.1, caps);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        self.0.no_expansion(self.1)
+    }
+}
+
+impl Replacer for (&Expander, &str) {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        self.0.append_expansion(dst, self.1, caps);
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        self.0.no_expansion(self.1)
+    }
+}
+
+impl Replacer for &Template {
+    fn replace_append(&mut self, caps: &Captures<'_>, dst: &mut String) {
+        self.expand(caps, dst).expect("expansion succeeded");
+    }
+
+    fn no_expansion(&mut self) -> Option<Cow<'_, str>> {
+        self.literal().map(Cow::Owned)
+    }
+}
+
 /// `NoExpand` indicates literal string replacement.
 ///
 /// It can be used with `replace` and `replace_all` to do a literal string
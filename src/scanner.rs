@@ -0,0 +1,377 @@
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::io::{self, BufRead};
+
+#[cfg(feature = "std")]
+use crate::parse::{group_indices, DuplicateGroups, NamedGroups};
+use crate::vm::OPTION_SKIPPED_EMPTY_MATCH;
+use crate::{Regex, Result};
+
+/// Finds the matches of a regex in a stream of bytes that is fed in chunks, without keeping the
+/// whole stream in memory.
+///
+/// The stream doesn't have to be valid UTF-8, see the [`bytes`](crate::bytes) module for how
+/// such input is matched.
+///
+/// A backtracking regex can in general look arbitrarily far ahead and behind, so the scanner
+/// needs a bound on that: `max_len` is the maximum number of bytes that a match, together with
+/// the text that its look-arounds and word boundaries inspect before and after it, can span.
+/// The scanner keeps this many bytes of the stream around the search position, and only reports
+/// a match once enough of the stream follows it that no later chunk can change it. Matches
+/// found in the chunks are the same as the ones [`Regex::find_iter`] finds in the whole stream,
+/// as long as none of them exceed `max_len`.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{Regex, Scanner};
+/// let re = Regex::new(r"(\w)\1").unwrap();
+/// let mut scanner = Scanner::new(&re, 4);
+/// let mut matches = Vec::new();
+/// for chunk in [&b"xa"[..], b"a b", b"b yy"] {
+///     matches.extend(scanner.push(chunk).unwrap());
+/// }
+/// matches.extend(scanner.finish().unwrap());
+/// let ranges: Vec<_> = matches.iter().map(|m| m.range()).collect();
+/// assert_eq!(ranges, vec![1..3, 4..6, 7..9]);
+/// assert_eq!(matches[2].as_bytes(), b"yy");
+/// ```
+#[derive(Debug)]
+pub struct Scanner<'r> {
+    regex: &'r Regex,
+    max_len: usize,
+    /// The part of the stream that is still needed
+    buffer: Vec<u8>,
+    /// Stream offset of the start of `buffer`
+    offset: usize,
+    /// Stream offset to continue searching from
+    last_end: usize,
+    /// Stream offset of the end of the last match
+    last_match: Option<usize>,
+}
+
+/// A match found by a [`Scanner`], with offsets in the whole stream.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamMatch {
+    start: usize,
+    bytes: Vec<u8>,
+}
+
+impl StreamMatch {
+    /// Returns the starting byte offset of the match in the stream.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending byte offset of the match in the stream.
+    pub fn end(&self) -> usize {
+        self.start + self.bytes.len()
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the match in the stream.
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end()
+    }
+
+    /// Returns the matched bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the matched bytes, without copying them.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// The capture groups of a match found by a [`Regex::captures_read_iter`], with offsets in the
+/// whole stream.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct StreamCaptures {
+    groups: Vec<Option<StreamMatch>>,
+    named_groups: Arc<NamedGroups>,
+    duplicate_groups: Arc<DuplicateGroups>,
+}
+
+#[cfg(feature = "std")]
+impl StreamCaptures {
+    /// Get the capture group by its index in the regex.
+    ///
+    /// If there is no match for that group or the index does not correspond to a group, `None`
+    /// is returned. The index 0 returns the whole match.
+    pub fn get(&self, i: usize) -> Option<&StreamMatch> {
+        self.groups.get(i).and_then(Option::as_ref)
+    }
+
+    /// Returns the match for a named capture group.  Returns `None` the capture
+    /// group did not match or if there is no group with the given name.
+    pub fn name(&self, name: &str) -> Option<&StreamMatch> {
+        group_indices(&self.named_groups, &self.duplicate_groups, name)
+            .iter()
+            .find_map(|&i| self.get(i))
+    }
+
+    /// Returns the number of captured groups. This is guaranteed to be at least 1, since group
+    /// 0 is the whole match.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+impl<'r> Scanner<'r> {
+    /// Create a scanner for `regex` whose matches, including the context that they depend on,
+    /// span at most `max_len` bytes.
+    pub fn new(regex: &'r Regex, max_len: usize) -> Scanner<'r> {
+        Scanner {
+            regex,
+            max_len,
+            buffer: Vec::new(),
+            offset: 0,
+            last_end: 0,
+            last_match: None,
+        }
+    }
+
+    /// Returns the underlying regex.
+    pub fn regex(&self) -> &'r Regex {
+        self.regex
+    }
+
+    /// Returns the number of bytes that were pushed so far.
+    pub fn stream_len(&self) -> usize {
+        self.offset + self.buffer.len()
+    }
+
+    /// Appends `chunk` to the stream and returns the matches that can no longer change.
+    ///
+    /// Matches near the end of the stream are held back until more of the stream is pushed, or
+    /// until [`finish`](Scanner::finish) is called.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<StreamMatch>> {
+        self.push_with(chunk, false, |scanner, range| {
+            Ok(scanner.stream_match(range))
+        })
+    }
+
+    /// Ends the stream and returns the remaining matches.
+    pub fn finish(mut self) -> Result<Vec<StreamMatch>> {
+        self.push_with(&[], true, |scanner, range| Ok(scanner.stream_match(range)))
+    }
+
+    /// Appends `chunk`, and calls `build` with the matches while their text is still in the
+    /// buffer.
+    fn push_with<T>(
+        &mut self,
+        chunk: &[u8],
+        at_end: bool,
+        build: impl Fn(&Self, Range<usize>) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        self.buffer.extend_from_slice(chunk);
+        let ranges = self.scan(at_end)?;
+        let items = ranges
+            .into_iter()
+            .map(|range| build(self, range))
+            .collect::<Result<Vec<T>>>()?;
+
+        // keep the context before the search position, including the byte before it so that
+        // the start of the buffer is never mistaken for the start of the stream
+        let keep = self.last_end.saturating_sub(self.max_len + 1);
+        if keep > self.offset {
+            self.buffer.drain(..keep - self.offset);
+            self.offset = keep;
+        }
+        Ok(items)
+    }
+
+    fn stream_match(&self, range: Range<usize>) -> StreamMatch {
+        StreamMatch {
+            start: range.start,
+            bytes: self.buffer[range.start - self.offset..range.end - self.offset].to_vec(),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn stream_captures(&self, range: Range<usize>) -> Result<StreamCaptures> {
+        let inner = self
+            .regex
+            .captures_impl_from_pos(&self.buffer, range.start - self.offset)?
+            .expect("match was found in the same buffer");
+        let groups = (0..inner.len())
+            .map(|i| {
+                inner.get(i).map(|group| {
+                    self.stream_match(group.start + self.offset..group.end + self.offset)
+                })
+            })
+            .collect();
+        Ok(StreamCaptures {
+            groups,
+            named_groups: self.regex.named_groups.clone(),
+            duplicate_groups: self.regex.duplicate_groups.clone(),
+        })
+    }
+
+    /// Adapted from `Matches::next`, but only reports matches that start early enough in the
+    /// buffer unless `at_end` is set.
+    fn scan(&mut self, at_end: bool) -> Result<Vec<Range<usize>>> {
+        let mut matches = Vec::new();
+        let stream_len = self.stream_len();
+        // a match starting before this doesn't depend on the end of the buffer
+        let settled = stream_len.saturating_sub(self.max_len);
+        while self.last_end <= stream_len {
+            let option_flags = match self.last_match {
+                Some(last_match) if self.last_end > last_match => OPTION_SKIPPED_EMPTY_MATCH,
+                _ => 0,
+            };
+            let range = self.regex.search_from_pos(
+                &self.buffer,
+                self.last_end - self.offset,
+                option_flags,
+            )?;
+            let (start, end) = match range {
+                Some(range) => (range.start + self.offset, range.end + self.offset),
+                None => {
+                    if !at_end {
+                        // no match starts before the settled part of the stream
+                        self.last_end = self.last_end.max(settled);
+                    }
+                    break;
+                }
+            };
+            if !at_end && start >= settled {
+                self.last_end = self.last_end.max(settled);
+                break;
+            }
+
+            if start == end {
+                self.last_end = self.offset
+                    + self.regex.next_after_empty(
+                        &self.buffer,
+                        self.last_end - self.offset,
+                        end - self.offset,
+                    );
+                if Some(end) == self.last_match && self.regex.skips_empty_after_match() {
+                    continue;
+                }
+            } else {
+                self.last_end = end;
+            }
+            self.last_match = Some(end);
+            matches.push(start..end);
+        }
+        Ok(matches)
+    }
+}
+
+/// Reads the stream from a reader in chunks and queues up the items built from the matches.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct ReadScanner<'r, R, T> {
+    reader: R,
+    scanner: Scanner<'r>,
+    pending: VecDeque<T>,
+    done: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'r, R: BufRead, T> ReadScanner<'r, R, T> {
+    fn new(regex: &'r Regex, reader: R, max_len: usize) -> Self {
+        ReadScanner {
+            reader,
+            scanner: Scanner::new(regex, max_len),
+            pending: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn next(
+        &mut self,
+        build: impl Fn(&Scanner<'r>, Range<usize>) -> Result<T> + Copy,
+    ) -> Option<io::Result<T>> {
+        loop {
+            if let Some(item) = self.pending.pop_front() {
+                return Some(Ok(item));
+            }
+            if self.done {
+                return None;
+            }
+            let result = match self.reader.fill_buf() {
+                Ok([]) => {
+                    self.done = true;
+                    self.scanner.push_with(&[], true, build)
+                }
+                Ok(chunk) => {
+                    let len = chunk.len();
+                    let result = self.scanner.push_with(chunk, false, build);
+                    self.reader.consume(len);
+                    result
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(error));
+                }
+            };
+            match result {
+                Ok(items) => self.pending.extend(items),
+                Err(error) => {
+                    self.done = true;
+                    return Some(Err(io::Error::new(io::ErrorKind::Other, error)));
+                }
+            }
+        }
+    }
+}
+
+/// An iterator over the matches of a regex in a reader, created by [`Regex::find_read`].
+///
+/// `'r` is the lifetime of the compiled regular expression.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ReadMatches<'r, R>(ReadScanner<'r, R, StreamMatch>);
+
+#[cfg(feature = "std")]
+impl<'r, R: BufRead> ReadMatches<'r, R> {
+    pub(crate) fn new(regex: &'r Regex, reader: R, max_len: usize) -> Self {
+        ReadMatches(ReadScanner::new(regex, reader, max_len))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, R: BufRead> Iterator for ReadMatches<'r, R> {
+    type Item = io::Result<StreamMatch>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0
+            .next(|scanner, range| Ok(scanner.stream_match(range)))
+    }
+}
+
+/// An iterator over the capture groups of the matches of a regex in a reader, created by
+/// [`Regex::captures_read_iter`].
+///
+/// `'r` is the lifetime of the compiled regular expression.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct ReadCaptureMatches<'r, R>(ReadScanner<'r, R, StreamCaptures>);
+
+#[cfg(feature = "std")]
+impl<'r, R: BufRead> ReadCaptureMatches<'r, R> {
+    pub(crate) fn new(regex: &'r Regex, reader: R, max_len: usize) -> Self {
+        ReadCaptureMatches(ReadScanner::new(regex, reader, max_len))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'r, R: BufRead> Iterator for ReadCaptureMatches<'r, R> {
+    type Item = io::Result<StreamCaptures>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next(Scanner::stream_captures)
+    }
+}
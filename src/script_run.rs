@@ -0,0 +1,287 @@
+//! Script runs, e.g. `(*sr:...)`, which only match text whose characters are all from the same
+//! script.
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use regex_automata::util::lazy::Lazy;
+use regex_syntax::hir::{Class, HirKind};
+
+/// The scripts of Unicode, except `Common` and `Inherited`, whose characters can be used with
+/// any script.
+const SCRIPTS: &[&str] = &[
+    "Adlam",
+    "Ahom",
+    "Anatolian_Hieroglyphs",
+    "Arabic",
+    "Armenian",
+    "Avestan",
+    "Balinese",
+    "Bamum",
+    "Bassa_Vah",
+    "Batak",
+    "Bengali",
+    "Bhaiksuki",
+    "Bopomofo",
+    "Brahmi",
+    "Braille",
+    "Buginese",
+    "Buhid",
+    "Canadian_Aboriginal",
+    "Carian",
+    "Caucasian_Albanian",
+    "Chakma",
+    "Cham",
+    "Cherokee",
+    "Chorasmian",
+    "Coptic",
+    "Cuneiform",
+    "Cypriot",
+    "Cypro_Minoan",
+    "Cyrillic",
+    "Deseret",
+    "Devanagari",
+    "Dives_Akuru",
+    "Dogra",
+    "Duployan",
+    "Egyptian_Hieroglyphs",
+    "Elbasan",
+    "Elymaic",
+    "Ethiopic",
+    "Garay",
+    "Georgian",
+    "Glagolitic",
+    "Gothic",
+    "Grantha",
+    "Greek",
+    "Gujarati",
+    "Gunjala_Gondi",
+    "Gurmukhi",
+    "Gurung_Khema",
+    "Han",
+    "Hangul",
+    "Hanifi_Rohingya",
+    "Hanunoo",
+    "Hatran",
+    "Hebrew",
+    "Hiragana",
+    "Imperial_Aramaic",
+    "Inscriptional_Pahlavi",
+    "Inscriptional_Parthian",
+    "Javanese",
+    "Kaithi",
+    "Kannada",
+    "Katakana",
+    "Kawi",
+    "Kayah_Li",
+    "Kharoshthi",
+    "Khitan_Small_Script",
+    "Khmer",
+    "Khojki",
+    "Khudawadi",
+    "Kirat_Rai",
+    "Lao",
+    "Latin",
+    "Lepcha",
+    "Limbu",
+    "Linear_A",
+    "Linear_B",
+    "Lisu",
+    "Lycian",
+    "Lydian",
+    "Mahajani",
+    "Makasar",
+    "Malayalam",
+    "Mandaic",
+    "Manichaean",
+    "Marchen",
+    "Masaram_Gondi",
+    "Medefaidrin",
+    "Meetei_Mayek",
+    "Mende_Kikakui",
+    "Meroitic_Cursive",
+    "Meroitic_Hieroglyphs",
+    "Miao",
+    "Modi",
+    "Mongolian",
+    "Mro",
+    "Multani",
+    "Myanmar",
+    "Nabataean",
+    "Nag_Mundari",
+    "Nandinagari",
+    "New_Tai_Lue",
+    "Newa",
+    "Nko",
+    "Nushu",
+    "Nyiakeng_Puachue_Hmong",
+    "Ogham",
+    "Ol_Chiki",
+    "Ol_Onal",
+    "Old_Hungarian",
+    "Old_Italic",
+    "Old_North_Arabian",
+    "Old_Permic",
+    "Old_Persian",
+    "Old_Sogdian",
+    "Old_South_Arabian",
+    "Old_Turkic",
+    "Old_Uyghur",
+    "Oriya",
+    "Osage",
+    "Osmanya",
+    "Pahawh_Hmong",
+    "Palmyrene",
+    "Pau_Cin_Hau",
+    "Phags_Pa",
+    "Phoenician",
+    "Psalter_Pahlavi",
+    "Rejang",
+    "Runic",
+    "Samaritan",
+    "Saurashtra",
+    "Sharada",
+    "Shavian",
+    "Siddham",
+    "SignWriting",
+    "Sinhala",
+    "Sogdian",
+    "Sora_Sompeng",
+    "Soyombo",
+    "Sundanese",
+    "Sunuwar",
+    "Syloti_Nagri",
+    "Syriac",
+    "Tagalog",
+    "Tagbanwa",
+    "Tai_Le",
+    "Tai_Tham",
+    "Tai_Viet",
+    "Takri",
+    "Tamil",
+    "Tangsa",
+    "Tangut",
+    "Telugu",
+    "Thaana",
+    "Thai",
+    "Tibetan",
+    "Tifinagh",
+    "Tirhuta",
+    "Todhri",
+    "Toto",
+    "Tulu_Tigalari",
+    "Ugaritic",
+    "Vai",
+    "Vithkuqi",
+    "Wancho",
+    "Warang_Citi",
+    "Yezidi",
+    "Yi",
+    "Zanabazar_Square",
+];
+
+/// Combinations of scripts that are written together, which count as a single script like in
+/// PCRE2: Japanese, Korean and Chinese with Bopomofo.
+const COMBINED_SCRIPTS: &[&str] = &[
+    r"[\p{scx=Han}\p{scx=Hiragana}\p{scx=Katakana}]",
+    r"[\p{scx=Han}\p{scx=Hangul}]",
+    r"[\p{scx=Han}\p{scx=Bopomofo}]",
+];
+
+type Ranges = Vec<(char, char)>;
+
+struct ScriptTables {
+    /// The characters of each script, including the characters that are used with several
+    /// scripts (the `Script_Extensions` property)
+    scripts: Vec<Ranges>,
+    /// The characters that can be used with any script
+    any: Ranges,
+    /// The decimal digits, which come in sets of ten consecutive characters from 0 to 9
+    digits: Ranges,
+}
+
+/// Built lazily, and only available with the `unicode` feature.
+static TABLES: Lazy<Option<ScriptTables>> = Lazy::new(ScriptTables::new);
+
+impl ScriptTables {
+    fn new() -> Option<ScriptTables> {
+        let any = class_ranges(r"[\p{scx=Common}\p{scx=Inherited}]")?;
+        let digits = class_ranges(r"\p{Nd}")?;
+        let scripts = SCRIPTS
+            .iter()
+            .map(|name| format!(r"\p{{scx={}}}", name))
+            .chain(COMBINED_SCRIPTS.iter().map(|class| class.to_string()))
+            // scripts that are newer than the Unicode version of regex-syntax are left out
+            .filter_map(|class| class_ranges(&class))
+            .collect();
+        Some(ScriptTables {
+            scripts,
+            any,
+            digits,
+        })
+    }
+}
+
+fn class_ranges(class: &str) -> Option<Ranges> {
+    let hir = regex_syntax::Parser::new().parse(class).ok()?;
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => Some(
+            class
+                .ranges()
+                .iter()
+                .map(|range| (range.start(), range.end()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Returns the index of the range that contains `c`, if any.
+fn find_range(ranges: &Ranges, c: char) -> Option<usize> {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < c {
+                core::cmp::Ordering::Less
+            } else if start > c {
+                core::cmp::Ordering::Greater
+            } else {
+                core::cmp::Ordering::Equal
+            }
+        })
+        .ok()
+}
+
+/// Returns whether the text is a script run, i.e. all its characters are from the same script,
+/// apart from characters that are used with any script, and all its decimal digits are from the
+/// same set of ten digits. Bytes that aren't valid UTF-8 aren't part of any script. Without the
+/// `unicode` feature, all text is a script run.
+pub(crate) fn is_script_run(s: &[u8]) -> bool {
+    let Some(tables) = Lazy::get(&TABLES) else {
+        return true;
+    };
+    let Ok(text) = core::str::from_utf8(s) else {
+        return false;
+    };
+    // the scripts that contain all the characters so far, from the first character that can't
+    // be used with any script on
+    let mut candidates: Option<Vec<&Ranges>> = None;
+    let mut zero = None;
+    for c in text.chars() {
+        if let Some(i) = find_range(&tables.digits, c) {
+            let start = tables.digits[i].0 as u32;
+            let digit_zero = start + (c as u32 - start) / 10 * 10;
+            if *zero.get_or_insert(digit_zero) != digit_zero {
+                return false;
+            }
+        }
+        if find_range(&tables.any, c).is_some() {
+            continue;
+        }
+        let scripts = candidates.get_or_insert_with(|| tables.scripts.iter().collect());
+        scripts.retain(|script| find_range(script, c).is_some());
+        if scripts.is_empty() {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,340 @@
+use core::ops::Range;
+#[cfg(feature = "std")]
+use core::time::Duration;
+use regex_automata::Anchored;
+
+use crate::vm;
+use crate::{Captures, CapturesImpl, Match, Regex, RegexImpl, Result};
+
+/// Options for a single search, overriding the options the regex was built with.
+///
+/// This allows different call sites of the same compiled regex to use different limits, or to
+/// only search part of a text, see [`Regex::find_with`].
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{Regex, SearchOptions};
+/// let re = Regex::new(r"(\w)\1").unwrap();
+/// let text = "xaa bb cc";
+/// let mut options = SearchOptions::new();
+/// options.range(2..text.len()).anchored(true);
+/// assert!(re.find_with(text, &options).unwrap().is_none());
+/// options.range(4..text.len());
+/// assert_eq!(re.find_with(text, &options).unwrap().unwrap().as_str(), "bb");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    backtrack_limit: Option<usize>,
+    #[cfg(feature = "std")]
+    timeout: Option<Duration>,
+    anchored: bool,
+    start: usize,
+    end: Option<usize>,
+    transparent_bounds: bool,
+    earliest: bool,
+}
+
+impl SearchOptions {
+    /// Create options that search the whole text with the options of the regex.
+    pub fn new() -> SearchOptions {
+        SearchOptions::default()
+    }
+
+    /// Override the [backtrack limit](crate::RegexBuilder::backtrack_limit) of the regex for
+    /// this search.
+    pub fn backtrack_limit(&mut self, limit: usize) -> &mut Self {
+        self.backtrack_limit = Some(limit);
+        self
+    }
+
+    /// Override the [timeout](crate::RegexBuilder::timeout) of the regex for this search.
+    #[cfg(feature = "std")]
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Only report a match that starts exactly at the start of the searched range, see
+    /// [`RegexBuilder::anchored`](crate::RegexBuilder::anchored). A regex that was built
+    /// anchored is always anchored.
+    ///
+    /// Default is false.
+    pub fn anchored(&mut self, yes: bool) -> &mut Self {
+        self.anchored = yes;
+        self
+    }
+
+    /// Only search for a match in `range` of the text.
+    ///
+    /// Like with [`Regex::find_from_pos`], the text before the start of the range is still
+    /// seen by look-behind, word boundaries and `^`. The text after the end of the range is
+    /// ignored, as if the text ended there.
+    ///
+    /// Default is the whole text.
+    pub fn range(&mut self, range: Range<usize>) -> &mut Self {
+        self.start = range.start;
+        self.end = Some(range.end);
+        self
+    }
+
+    /// Let look-around, word boundaries and `$` see the text after the end of the
+    /// [range](SearchOptions::range), so that only the match itself has to be within the range.
+    /// This makes searching a region of a text work the same as searching the whole text, except
+    /// that matches outside the region aren't reported.
+    ///
+    /// Default is false, which means the text after the range is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Regex, SearchOptions};
+    /// let re = Regex::new(r"\b\w+\b").unwrap();
+    /// let mut options = SearchOptions::new();
+    /// options.range(0..3);
+    /// assert_eq!(re.find_with("foobar", &options).unwrap().unwrap().as_str(), "foo");
+    /// options.transparent_bounds(true);
+    /// assert!(re.find_with("foobar", &options).unwrap().is_none());
+    /// ```
+    pub fn transparent_bounds(&mut self, yes: bool) -> &mut Self {
+        self.transparent_bounds = yes;
+        self
+    }
+
+    /// Report a match as soon as one is known, instead of the match that the regex would
+    /// report otherwise. Regexes that are delegated to the `regex` crate may then report a
+    /// shorter match, and a [leftmost-longest](crate::RegexBuilder::leftmost_longest) regex
+    /// reports the first match it finds rather than the longest one. This is useful when only
+    /// the existence or the start of a match matters.
+    ///
+    /// Default is false.
+    pub fn earliest(&mut self, yes: bool) -> &mut Self {
+        self.earliest = yes;
+        self
+    }
+
+    pub(crate) fn get_backtrack_limit(&self) -> Option<usize> {
+        self.backtrack_limit
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub(crate) fn get_earliest(&self) -> bool {
+        self.earliest
+    }
+
+    /// Returns the position that a match must not end after, if any.
+    pub(crate) fn get_end(&self) -> Option<usize> {
+        self.end
+    }
+
+    /// Returns the text that is seen by the search, and the range that is searched.
+    fn bounds<'t>(&self, text: &'t str) -> (&'t [u8], Range<usize>) {
+        let end = self.end.unwrap_or(text.len());
+        assert!(
+            self.start <= end && text.is_char_boundary(self.start) && text.is_char_boundary(end),
+            "search range {}..{} is out of bounds or not on character boundaries",
+            self.start,
+            end,
+        );
+        let seen = if self.transparent_bounds {
+            text.as_bytes()
+        } else {
+            &text.as_bytes()[..end]
+        };
+        (seen, self.start..end)
+    }
+}
+
+impl Regex {
+    /// Returns the first match in `text`, with the limits and bounds of the search set by
+    /// `options` instead of the regex.
+    ///
+    /// # Panics
+    ///
+    /// If the range of `options` is out of bounds or not on character boundaries.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::{Regex, SearchOptions};
+    /// let re = Regex::new(r"(a|b|ab)*\1?(?=c)").unwrap();
+    /// let text = "ab".repeat(20);
+    /// let result = re.find_with(&text, SearchOptions::new().backtrack_limit(1_000));
+    /// assert!(result.is_err());
+    /// ```
+    pub fn find_with<'t>(
+        &self,
+        text: &'t str,
+        options: &SearchOptions,
+    ) -> Result<Option<Match<'t>>> {
+        self.find_with_option_flags(text, options, 0)
+    }
+
+    /// Like `find_with`, with the option flags of the VM, see `Regex::find_from_pos`.
+    pub(crate) fn find_with_option_flags<'t>(
+        &self,
+        text: &'t str,
+        options: &SearchOptions,
+        option_flags: u32,
+    ) -> Result<Option<Match<'t>>> {
+        let (bytes, span) = options.bounds(text);
+        let range = match &*self.inner {
+            RegexImpl::Wrap {
+                inner,
+                options: regex_options,
+                ..
+            } => {
+                let input = with_options(regex_options.input(bytes, 0).span(span.clone()), options);
+                inner.search(&input).map(|m| m.range())
+            }
+            RegexImpl::Fancy {
+                prog,
+                options: regex_options,
+                ..
+            } => {
+                let start_pc = if options.anchored {
+                    prog.anchored_start()
+                } else {
+                    0
+                };
+                let result = vm::run_with(
+                    prog,
+                    start_pc,
+                    bytes,
+                    span.start,
+                    option_flags,
+                    regex_options,
+                    options,
+                )?;
+                result.map(|saves| saves[0]..saves[1])
+            }
+        };
+        Ok(range.map(|range| Match::new(text, range.start, range.end)))
+    }
+
+    /// Returns the capture groups for the first match in `text`, with the limits and bounds of
+    /// the search set by `options`, see [`find_with`](Regex::find_with).
+    ///
+    /// # Panics
+    ///
+    /// If the range of `options` is out of bounds or not on character boundaries.
+    pub fn captures_with<'t>(
+        &self,
+        text: &'t str,
+        options: &SearchOptions,
+    ) -> Result<Option<Captures<'t>>> {
+        let (bytes, span) = options.bounds(text);
+        let inner = match &*self.inner {
+            RegexImpl::Wrap {
+                inner,
+                options: regex_options,
+                ..
+            } => {
+                let mut locations = inner.create_captures();
+                let input = with_options(regex_options.input(bytes, 0).span(span.clone()), options);
+                inner.captures(input, &mut locations);
+                locations
+                    .is_match()
+                    .then_some(CapturesImpl::Wrap { locations })
+            }
+            RegexImpl::Fancy {
+                prog,
+                n_groups,
+                options: regex_options,
+                ..
+            } => {
+                let start_pc = if options.anchored {
+                    prog.anchored_start()
+                } else {
+                    0
+                };
+                let result =
+                    vm::run_with(prog, start_pc, bytes, span.start, 0, regex_options, options)?;
+                result.map(|saves| CapturesImpl::fancy(prog, *n_groups, saves))
+            }
+        };
+        Ok(inner.map(|inner| Captures {
+            text,
+            inner,
+            named_groups: self.named_groups.clone(),
+            duplicate_groups: self.duplicate_groups.clone(),
+        }))
+    }
+
+    /// Returns the first match that is within `region` of `text`, while look-around and word
+    /// boundaries still see the text around the region.
+    ///
+    /// Unlike searching a slice of the text, this finds the same matches as searching the whole
+    /// text would, except for those outside the region. It's a shorthand for
+    /// [`find_with`](Regex::find_with) with a [range](SearchOptions::range) and
+    /// [transparent bounds](SearchOptions::transparent_bounds).
+    ///
+    /// # Panics
+    ///
+    /// If `region` is out of bounds or not on character boundaries.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use fancy_regex::Regex;
+    /// let re = Regex::new(r"(?<=\$)\d+\b").unwrap();
+    /// let text = "$12 $345 $6";
+    /// assert_eq!(re.find_in_region(text, 1..7).unwrap().unwrap().as_str(), "12");
+    /// assert!(re.find_in_region(text, 5..7).unwrap().is_none());
+    /// assert!(re.find(&text[5..]).unwrap().is_some());
+    /// ```
+    pub fn find_in_region<'t>(
+        &self,
+        text: &'t str,
+        region: Range<usize>,
+    ) -> Result<Option<Match<'t>>> {
+        self.find_with(
+            text,
+            SearchOptions::new().range(region).transparent_bounds(true),
+        )
+    }
+
+    /// Returns the capture groups for the first match that is within `region` of `text`, see
+    /// [`find_in_region`](Regex::find_in_region).
+    ///
+    /// # Panics
+    ///
+    /// If `region` is out of bounds or not on character boundaries.
+    pub fn captures_in_region<'t>(
+        &self,
+        text: &'t str,
+        region: Range<usize>,
+    ) -> Result<Option<Captures<'t>>> {
+        self.captures_with(
+            text,
+            SearchOptions::new().range(region).transparent_bounds(true),
+        )
+    }
+
+    /// Check if the regex matches `text`, with the limits and bounds of the search set by
+    /// `options`, see [`find_with`](Regex::find_with).
+    ///
+    /// # Panics
+    ///
+    /// If the range of `options` is out of bounds or not on character boundaries.
+    pub fn is_match_with(&self, text: &str, options: &SearchOptions) -> Result<bool> {
+        Ok(self.find_with(text, options)?.is_some())
+    }
+}
+
+/// Applies the anchoring and earliest mode of `options` to the input of a wrapped regex.
+fn with_options<'h>(
+    input: regex_automata::Input<'h>,
+    options: &SearchOptions,
+) -> regex_automata::Input<'h> {
+    let input = input.earliest(options.earliest);
+    if options.anchored {
+        input.anchored(Anchored::Yes)
+    } else {
+        input
+    }
+}
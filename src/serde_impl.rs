@@ -0,0 +1,210 @@
+use alloc::string::String;
+use core::fmt;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeStruct, Serializer};
+
+use crate::{EmptyMatchAdvance, Regex, RegexBuilder, RegexOptions, SyntaxDialect};
+
+const FIELDS: &[&str] = &[
+    "pattern",
+    "case_insensitive",
+    "backtrack_limit",
+    "delegate_size_limit",
+    "delegate_dfa_size_limit",
+    "memo_size_limit",
+    "size_limit",
+    "anchored",
+    "leftmost_longest",
+    "timeout",
+    "empty_match_advance",
+    "allow_empty_after_match",
+    "crlf_line_breaks",
+    "h_horizontal_space",
+    "unicode_posix_classes",
+    "octal_escapes",
+    "explicit_capture",
+    "syntax_dialect",
+];
+
+const EMPTY_MATCH_ADVANCES: &[&str] = &["char", "grapheme", "byte"];
+
+const SYNTAX_DIALECTS: &[&str] = &[
+    "default",
+    "python",
+    "ecmascript",
+    "ecmascript_unicode",
+    "ecmascript_unicode_sets",
+];
+
+/// Serializes the pattern as a string if the regex was built with the default options, and
+/// as a map of the pattern and the options otherwise. Regexes with a callout or predicates
+/// can't be serialized, as the functions can't be.
+impl Serialize for Regex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let options = self.options();
+        if options.callout.is_some() {
+            return Err(ser::Error::custom("can't serialize a regex with a callout"));
+        }
+        if !options.predicates.is_empty() {
+            return Err(ser::Error::custom(
+                "can't serialize a regex with predicates",
+            ));
+        }
+        let defaults = RegexOptions::default();
+        let case_insensitive = options.syntaxc.get_case_insensitive();
+        if !case_insensitive
+            && options.backtrack_limit == defaults.backtrack_limit
+            && options.delegate_size_limit.is_none()
+            && options.delegate_dfa_size_limit.is_none()
+            && options.memo_size_limit.is_none()
+            && options.size_limit.is_none()
+            && !options.anchored
+            && !options.leftmost_longest
+            && options.timeout.is_none()
+            && options.empty_match_advance == EmptyMatchAdvance::Char
+            && !options.allow_empty_after_match
+            && !options.crlf_line_breaks
+            && !options.h_horizontal_space
+            && !options.unicode_posix_classes
+            && !options.octal_escapes
+            && !options.explicit_capture
+            && options.syntax_dialect == SyntaxDialect::Default
+        {
+            return serializer.serialize_str(&options.pattern);
+        }
+        let mut state = serializer.serialize_struct("Regex", FIELDS.len())?;
+        state.serialize_field("pattern", &options.pattern)?;
+        state.serialize_field("case_insensitive", &case_insensitive)?;
+        state.serialize_field("backtrack_limit", &options.backtrack_limit)?;
+        state.serialize_field("delegate_size_limit", &options.delegate_size_limit)?;
+        state.serialize_field("delegate_dfa_size_limit", &options.delegate_dfa_size_limit)?;
+        state.serialize_field("memo_size_limit", &options.memo_size_limit)?;
+        state.serialize_field("size_limit", &options.size_limit)?;
+        state.serialize_field("anchored", &options.anchored)?;
+        state.serialize_field("leftmost_longest", &options.leftmost_longest)?;
+        state.serialize_field("timeout", &options.timeout)?;
+        let empty_match_advance = match options.empty_match_advance {
+            EmptyMatchAdvance::Char => "char",
+            EmptyMatchAdvance::Grapheme => "grapheme",
+            EmptyMatchAdvance::Byte => "byte",
+        };
+        state.serialize_field("empty_match_advance", empty_match_advance)?;
+        state.serialize_field("allow_empty_after_match", &options.allow_empty_after_match)?;
+        state.serialize_field("crlf_line_breaks", &options.crlf_line_breaks)?;
+        state.serialize_field("h_horizontal_space", &options.h_horizontal_space)?;
+        state.serialize_field("unicode_posix_classes", &options.unicode_posix_classes)?;
+        state.serialize_field("octal_escapes", &options.octal_escapes)?;
+        state.serialize_field("explicit_capture", &options.explicit_capture)?;
+        let syntax_dialect = match options.syntax_dialect {
+            SyntaxDialect::Default => "default",
+            SyntaxDialect::Python => "python",
+            SyntaxDialect::EcmaScript => "ecmascript",
+            SyntaxDialect::EcmaScriptUnicode => "ecmascript_unicode",
+            SyntaxDialect::EcmaScriptUnicodeSets => "ecmascript_unicode_sets",
+        };
+        state.serialize_field("syntax_dialect", syntax_dialect)?;
+        state.end()
+    }
+}
+
+/// Deserializes either a pattern string, or a map with the pattern and optionally any of the
+/// options, and compiles the regex.
+impl<'de> Deserialize<'de> for Regex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        deserializer.deserialize_any(RegexVisitor)
+    }
+}
+
+struct RegexVisitor;
+
+impl<'de> Visitor<'de> for RegexVisitor {
+    type Value = Regex;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a regex pattern, or a map with a pattern and options")
+    }
+
+    fn visit_str<E: de::Error>(self, pattern: &str) -> Result<Regex, E> {
+        Regex::new(pattern).map_err(E::custom)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Regex, A::Error> {
+        let mut pattern: Option<String> = None;
+        let mut builder = RegexBuilder::new("");
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "pattern" => pattern = Some(map.next_value()?),
+                "case_insensitive" => {
+                    builder.case_insensitive(map.next_value()?);
+                }
+                "backtrack_limit" => {
+                    builder.backtrack_limit(map.next_value()?);
+                }
+                "delegate_size_limit" => {
+                    builder.0.delegate_size_limit = map.next_value()?;
+                }
+                "delegate_dfa_size_limit" => {
+                    builder.0.delegate_dfa_size_limit = map.next_value()?;
+                }
+                "memo_size_limit" => {
+                    builder.0.memo_size_limit = map.next_value()?;
+                }
+                "size_limit" => {
+                    builder.0.size_limit = map.next_value()?;
+                }
+                "anchored" => {
+                    builder.anchored(map.next_value()?);
+                }
+                "leftmost_longest" => {
+                    builder.leftmost_longest(map.next_value()?);
+                }
+                "timeout" => {
+                    builder.0.timeout = map.next_value()?;
+                }
+                "empty_match_advance" => {
+                    let advance = match map.next_value::<String>()?.as_str() {
+                        "char" => EmptyMatchAdvance::Char,
+                        "grapheme" => EmptyMatchAdvance::Grapheme,
+                        "byte" => EmptyMatchAdvance::Byte,
+                        other => {
+                            return Err(de::Error::unknown_variant(other, EMPTY_MATCH_ADVANCES))
+                        }
+                    };
+                    builder.empty_match_advance(advance);
+                }
+                "allow_empty_after_match" => {
+                    builder.allow_empty_after_match(map.next_value()?);
+                }
+                "crlf_line_breaks" => {
+                    builder.crlf_line_breaks(map.next_value()?);
+                }
+                "h_horizontal_space" => {
+                    builder.h_horizontal_space(map.next_value()?);
+                }
+                "unicode_posix_classes" => {
+                    builder.unicode_posix_classes(map.next_value()?);
+                }
+                "octal_escapes" => {
+                    builder.octal_escapes(map.next_value()?);
+                }
+                "explicit_capture" => {
+                    builder.explicit_capture(map.next_value()?);
+                }
+                "syntax_dialect" => {
+                    let dialect = match map.next_value::<String>()?.as_str() {
+                        "default" => SyntaxDialect::Default,
+                        "python" => SyntaxDialect::Python,
+                        "ecmascript" => SyntaxDialect::EcmaScript,
+                        "ecmascript_unicode" => SyntaxDialect::EcmaScriptUnicode,
+                        "ecmascript_unicode_sets" => SyntaxDialect::EcmaScriptUnicodeSets,
+                        other => return Err(de::Error::unknown_variant(other, SYNTAX_DIALECTS)),
+                    };
+                    builder.syntax_dialect(dialect);
+                }
+                _ => return Err(de::Error::unknown_field(&key, FIELDS)),
+            }
+        }
+        builder.0.pattern = pattern.ok_or_else(|| de::Error::missing_field("pattern"))?;
+        builder.build().map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,127 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{CaptureMatches, Captures, Match, Matches, Result};
+
+/// Converts byte offsets in a text to offsets in UTF-16 code units, as used by JavaScript and
+/// the Language Server Protocol.
+///
+/// The converter remembers the last offset it converted, and only counts the text between that
+/// and the next offset. Converting the offsets of matches in the order they were found is
+/// therefore linear in the length of the text overall, rather than for each match.
+///
+/// # Example
+///
+/// ```rust
+/// # use fancy_regex::{Regex, Utf16Offsets};
+/// let text = "é 😀 ab";
+/// let m = Regex::new(r"(?<=\s)\w+").unwrap().find(text).unwrap().unwrap();
+/// assert_eq!(m.range(), 8..10);
+/// let mut offsets = Utf16Offsets::new(text);
+/// assert_eq!(offsets.range(m.range()), 5..7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Utf16Offsets<'t> {
+    text: &'t str,
+    /// The last converted byte offset
+    byte: usize,
+    /// The last converted byte offset in UTF-16 code units
+    utf16: usize,
+}
+
+impl<'t> Utf16Offsets<'t> {
+    /// Create a converter for offsets in `text`.
+    pub fn new(text: &'t str) -> Utf16Offsets<'t> {
+        Utf16Offsets {
+            text,
+            byte: 0,
+            utf16: 0,
+        }
+    }
+
+    /// Returns the text whose offsets are converted.
+    pub fn text(&self) -> &'t str {
+        self.text
+    }
+
+    /// Converts a byte offset to an offset in UTF-16 code units.
+    ///
+    /// # Panics
+    ///
+    /// If `byte` is not on a character boundary of the text.
+    pub fn offset(&mut self, byte: usize) -> usize {
+        let count = |s: &str| s.chars().map(char::len_utf16).sum::<usize>();
+        if byte >= self.byte {
+            self.utf16 += count(&self.text[self.byte..byte]);
+        } else {
+            self.utf16 -= count(&self.text[byte..self.byte]);
+        }
+        self.byte = byte;
+        self.utf16
+    }
+
+    /// Converts a range of byte offsets to a range of offsets in UTF-16 code units.
+    ///
+    /// # Panics
+    ///
+    /// If the start or end is not on a character boundary of the text.
+    pub fn range(&mut self, range: Range<usize>) -> Range<usize> {
+        self.offset(range.start)..self.offset(range.end)
+    }
+
+    /// Converts the ranges of all the groups of `captures` to ranges in UTF-16 code units, or
+    /// `None` for groups that didn't match.
+    pub fn captures(&mut self, captures: &Captures<'_>) -> Vec<Option<Range<usize>>> {
+        captures
+            .iter()
+            .map(|group| group.map(|m| self.range(m.range())))
+            .collect()
+    }
+}
+
+/// An iterator over all non-overlapping matches, with their range in UTF-16 code units, created
+/// by [`Regex::find_iter_utf16`](crate::Regex::find_iter_utf16).
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the matched string.
+#[derive(Debug)]
+pub struct Utf16Matches<'r, 't> {
+    pub(crate) matches: Matches<'r, 't>,
+    pub(crate) offsets: Utf16Offsets<'t>,
+}
+
+impl<'r, 't> Iterator for Utf16Matches<'r, 't> {
+    type Item = Result<(Match<'t>, Range<usize>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offsets = &mut self.offsets;
+        self.matches
+            .next()
+            .map(|result| result.map(|m| (m, offsets.range(m.range()))))
+    }
+}
+
+/// An iterator over all non-overlapping capture groups, with the range of each group in UTF-16
+/// code units, created by [`Regex::captures_iter_utf16`](crate::Regex::captures_iter_utf16).
+///
+/// `'r` is the lifetime of the compiled regular expression and `'t` is the
+/// lifetime of the matched string.
+#[derive(Debug)]
+pub struct Utf16CaptureMatches<'r, 't> {
+    pub(crate) captures: CaptureMatches<'r, 't>,
+    pub(crate) offsets: Utf16Offsets<'t>,
+}
+
+impl<'r, 't> Iterator for Utf16CaptureMatches<'r, 't> {
+    type Item = Result<(Captures<'t>, Vec<Option<Range<usize>>>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offsets = &mut self.offsets;
+        self.captures.next().map(|result| {
+            result.map(|captures| {
+                let ranges = offsets.captures(&captures);
+                (captures, ranges)
+            })
+        })
+    }
+}
@@ -69,23 +69,34 @@
 //! 5. We continue with the previously saved thread at PC 4 and IX 0 (backtracking)
 //! 6. Both `Lit("a")` and `Lit("c")` match and we reach `End` -> successful match (index 0 to 2)
 
-use alloc::collections::BTreeSet;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
+use bit_set::BitSet;
+use core::mem;
 use core::usize;
 use regex_automata::meta::Regex;
 use regex_automata::util::look::LookMatcher;
+use regex_automata::util::prefilter::Prefilter;
 use regex_automata::util::primitives::NonMaxUsize;
 use regex_automata::Anchored;
 use regex_automata::Input;
+use regex_automata::Span;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 use crate::error::RuntimeError;
-use crate::prev_codepoint_ix;
+use crate::fuzzy::{Edit, EditKind};
+use crate::onepass::{split_bytes, ByteSet, SplitBytes};
+use crate::prefilter::InnerLiteral;
+use crate::script_run::is_script_run;
 use crate::Assertion;
 use crate::Error;
 use crate::Result;
-use crate::{codepoint_len, RegexOptions};
+use crate::{codepoint_len_at, RegexOptions, SearchOptions};
+use crate::{next_grapheme, prev_codepoint_ix};
+use crate::{Callout, CalloutId, CalloutResult};
 
 /// Enable tracing of VM execution. Only for debugging/investigating.
 const OPTION_TRACE: u32 = 1 << 0;
@@ -96,9 +107,29 @@ const OPTION_TRACE: u32 = 1 << 0;
 /// the fact that we skipped because of an empty match, it would still treat `\G` as matching. So
 /// this option is for communicating that to the VM. Phew.
 pub(crate) const OPTION_SKIPPED_EMPTY_MATCH: u32 = 1 << 1;
+/// Only accept a match that ends at the end of the text, backtracking otherwise. Together with
+/// running the program from its anchored start, this only finds matches of the whole text.
+pub(crate) const OPTION_FULL_MATCH: u32 = 1 << 2;
+/// Return a partial match as soon as the match needs more text than there is, like
+/// `PCRE2_PARTIAL_HARD`. The end of the text is also needed by assertions like `$` and `\b`.
+pub(crate) const OPTION_PARTIAL: u32 = 1 << 3;
+/// Find every match at the leftmost position where the program matches, by recording each
+/// match and backtracking into the other ways of matching there, like the search for the
+/// leftmost-longest match. Only finds every match of programs without multi-character
+/// delegates, which don't backtrack.
+const OPTION_ALL_MATCHES: u32 = 1 << 4;
 
 // TODO: make configurable
 const MAX_STACK: usize = 1_000_000;
+/// The most instructions that a program can have to be memoized without a memo size limit, so
+/// that small programs run like a bit-state backtracker, see `Prog::memoize`.
+pub(crate) const AUTO_MEMO_MAX_INSNS: usize = 500;
+/// The size limit in bytes of the memoization table of a search without a memo size limit,
+/// which makes only searches of short texts memoized.
+const AUTO_MEMO_SIZE_LIMIT: usize = 32 * 1024;
+/// How many times to backtrack between checks of the clock, when there is a timeout.
+#[cfg(feature = "std")]
+const TIMEOUT_CHECK_INTERVAL: usize = 256;
 
 /// Instruction of the VM.
 #[derive(Debug, Clone)]
@@ -172,8 +203,15 @@ pub enum Insn {
     FailNegativeLookAround,
     /// Set IX back by the specified number of characters
     GoBack(usize),
+    /// Set IX back by the specified number of bytes
+    GoBackBytes(usize),
     /// Back reference to a group number to check
-    Backref(usize),
+    Backref {
+        /// The save slot of the start of the group
+        slot: usize,
+        /// Whether the matching is case-insensitive or not
+        casei: bool,
+    },
     /// Begin of atomic group
     BeginAtomic,
     /// End of atomic group
@@ -186,11 +224,155 @@ pub enum Insn {
         start_group: usize,
         /// The last group number
         end_group: usize,
+        /// The bytes that a match of the regex can start with, or `None` if it can match the
+        /// empty string
+        first_bytes: Option<ByteSet>,
+    },
+    /// Match all text before the next match of the regex, or until the end of the text
+    Absent {
+        /// The regex of the absent expression
+        inner: Regex,
+        /// The slot for the position where the text starts
+        start: usize,
+    },
+    /// Give back the last character of the text matched by `Absent`, on backtracking, until the
+    /// position in the slot is reached
+    GiveBack(usize),
+    /// Match an extended grapheme cluster
+    GraphemeCluster,
+    /// Check that the text since the position in the slot is a script run
+    ScriptRun(usize),
+    /// Call the callout function of the regex, if there is one
+    Callout {
+        /// The identifier of the callout
+        id: CalloutId,
+        /// The number of capture groups, whose captures are passed to the function
+        groups: usize,
+    },
+    /// Call a predicate registered with the regex, failing unless it returns true
+    Predicate {
+        /// The name of the predicate, as a string identifier
+        id: CalloutId,
+        /// The number of capture groups, whose captures are passed to the predicate
+        groups: usize,
     },
     /// Anchor to match at the position where the previous match ended
     ContinueFromPreviousMatchEnd,
     /// Continue only if the specified capture group has already been populated as part of the match
     BackrefExistsCondition(usize),
+    /// Call a capture group as a subroutine, remembering all saves to restore them on return
+    Call {
+        /// The instruction that starts the group
+        target: usize,
+        /// The group number
+        group: usize,
+        /// The group after the last group inside the called group; the captures of the called
+        /// group and the groups inside it are reset for the call
+        end_group: usize,
+        /// The slot for the position of the innermost call frame on the explicit stack
+        frame: usize,
+        /// The slot for the position in the text where the group was last called, to prevent
+        /// infinite recursion
+        call_ix: usize,
+    },
+    /// Return from a subroutine call at the end of a group, if the innermost call is for it
+    Return {
+        /// The group number
+        group: usize,
+        /// The slot for the position of the innermost call frame on the explicit stack
+        frame: usize,
+    },
+    /// Fail, for `(*FAIL)`
+    Fail,
+    /// Backtracked into `(*PRUNE)`, fail the match at the current start position
+    Prune,
+    /// Backtracked into `(*SKIP)`, fail the match and continue searching at the current position
+    Skip,
+    /// Backtracked into `(*THEN)`, continue with the branch of the next alternative at the
+    /// specified position
+    Then(usize),
+    /// Push the capture of a group onto its stack of captures, after the group matched
+    PushCapture {
+        /// The group number
+        group: usize,
+        /// The slot for the position of the last capture of the group in the capture history
+        head: usize,
+        /// The slot for the size of the capture history
+        top: usize,
+    },
+    /// Remove the last capture of a group for a balancing group, failing if there is none, and
+    /// capture the text between it and the balancing group
+    Balance {
+        /// The group that captures the text between, or `usize::MAX` for none
+        group: usize,
+        /// The group whose last capture is removed
+        balance: usize,
+        /// The slot for the position of the last capture of `balance` in the capture history
+        head: usize,
+        /// The slot where the match of the balancing group started
+        start: usize,
+    },
+    /// Begin of fuzzy group, saving the position where it starts and allowing no edits at first
+    BeginFuzzy {
+        /// The slot for the number of edits that the group is allowed to make
+        limit: usize,
+        /// The slot for the position where the group starts
+        start: usize,
+    },
+    /// Match the fuzzy group with the number of edits in the `limit` slot, and when backtracked
+    /// into, try again with one more edit, up to `max_cost`
+    RetryFuzzy {
+        /// The maximum number of edits
+        max_cost: usize,
+        /// The slot for the number of edits that the group made so far
+        cost: usize,
+        /// The slot for the number of edits that the group is allowed to make
+        limit: usize,
+    },
+    /// End of fuzzy group, which fails unless the group made as many edits as it's allowed to,
+    /// as matches with fewer edits were tried before
+    EndFuzzy {
+        /// The slot for the number of edits that the group made
+        cost: usize,
+        /// The slot for the number of edits that the group is allowed to make
+        limit: usize,
+    },
+    /// Try the edits of the innermost fuzzy group for the character that is matched by the
+    /// instruction 4 after this one, unless the group made all its edits already. The
+    /// instructions in between substitute it, delete it and insert a character before it.
+    FuzzyEdit {
+        /// The slot for the number of edits that the group made so far
+        cost: usize,
+        /// The slot for the number of edits that the group is allowed to make
+        limit: usize,
+        /// The slot for the position where the group starts, before which nothing is inserted
+        start: usize,
+    },
+    /// Make an edit of a fuzzy group and record it in the edit log, then continue at `next`
+    Edit {
+        /// The kind of the edit
+        kind: EditKind,
+        /// The slot for the number of edits that the group made so far
+        cost: usize,
+        /// The slot for the size of the edit log
+        top: usize,
+        /// The instruction to continue at
+        next: usize,
+    },
+}
+
+impl Insn {
+    /// Returns the memory that the instruction uses on the heap, including the regex it
+    /// delegates to, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        match *self {
+            Insn::Lit(ref val) => val.capacity(),
+            Insn::Delegate { ref inner, .. } | Insn::Absent { ref inner, .. } => {
+                inner.memory_usage()
+            }
+            _ => 0,
+        }
+    }
 }
 
 /// Sequence of instructions for the VM to execute.
@@ -199,11 +381,218 @@ pub struct Prog {
     /// Instructions of the program
     pub body: Vec<Insn>,
     n_saves: usize,
+    /// Whether the start of the overall match is saved again later, with `\K`
+    keeps_out: bool,
+    /// The slot for the size of the edit log, if there are fuzzy groups
+    edits_slot: Option<usize>,
+    /// The number of each split that is a memoization point, or `usize::MAX` for the other
+    /// instructions. Empty unless the program is memoized, see `memoize`.
+    memo_points: Vec<usize>,
+    /// The number of memoization points
+    n_memo_points: usize,
+    /// The regex that finds where a match can start, see the `prefilter` module
+    prefilter: Option<Regex>,
+    /// The literals that every match starts with one of, see the `prefilter` module
+    literal_prefilter: Option<Prefilter>,
+    /// The literals that every match contains one of near its start, see the `prefilter` module
+    inner_literal: Option<InnerLiteral>,
+    /// The minimum length in bytes of the text that a match consumes
+    min_len: usize,
+    /// The bytes that the threads of each split can continue with, see the `onepass` module
+    split_bytes: Vec<SplitBytes>,
 }
 
 impl Prog {
     pub(crate) fn new(body: Vec<Insn>, n_saves: usize) -> Prog {
-        Prog { body, n_saves }
+        let keeps_out = body
+            .iter()
+            .filter(|insn| matches!(insn, Insn::Save(0)))
+            .count()
+            > 1;
+        let edits_slot = body.iter().find_map(|insn| match *insn {
+            Insn::Edit { top, .. } => Some(top),
+            _ => None,
+        });
+        Prog {
+            split_bytes: split_bytes(&body),
+            body,
+            n_saves,
+            keeps_out,
+            edits_slot,
+            memo_points: Vec::new(),
+            n_memo_points: 0,
+            prefilter: None,
+            literal_prefilter: None,
+            inner_literal: None,
+            min_len: 0,
+        }
+    }
+
+    /// Sets the prefilter that finds where a match can start, so that searches from the start
+    /// of the program start there instead of at the position where the search starts.
+    pub(crate) fn set_prefilter(&mut self, prefilter: Option<Regex>) {
+        self.prefilter = prefilter;
+    }
+
+    /// Sets the prefilter for the literals that every match starts with one of, so that the
+    /// loop at the start of the program that tries each start position skips to the next
+    /// position where one of them occurs. Programs without that loop don't use it.
+    pub(crate) fn set_literal_prefilter(&mut self, literal_prefilter: Option<Prefilter>) {
+        let anchored_start = self.anchored_start();
+        if matches!(self.body.first(), Some(&Insn::Split(x, 1)) if x == anchored_start) {
+            self.literal_prefilter = literal_prefilter;
+        }
+    }
+
+    /// Sets the literals that every match contains one of near its start, so that the loop at
+    /// the start of the program that tries each start position only tries the positions before
+    /// where they occur. Programs without that loop don't use them.
+    pub(crate) fn set_inner_literal(&mut self, inner_literal: Option<InnerLiteral>) {
+        let anchored_start = self.anchored_start();
+        if matches!(self.body.first(), Some(&Insn::Split(x, 1)) if x == anchored_start) {
+            self.inner_literal = inner_literal;
+        }
+    }
+
+    /// Returns the memory used by the program on the heap, including the regexes it delegates
+    /// to and its prefilters, in bytes.
+    pub(crate) fn memory_usage(&self) -> usize {
+        let insns: usize = self.body.iter().map(Insn::memory_usage).sum();
+        self.body.capacity() * mem::size_of::<Insn>()
+            + insns
+            + self.memo_points.capacity() * mem::size_of::<usize>()
+            + self.split_bytes.capacity() * mem::size_of::<SplitBytes>()
+            + self.prefilter.as_ref().map_or(0, Regex::memory_usage)
+            + self
+                .literal_prefilter
+                .as_ref()
+                .map_or(0, Prefilter::memory_usage)
+            + self
+                .inner_literal
+                .as_ref()
+                .map_or(0, InnerLiteral::memory_usage)
+    }
+
+    /// Sets the minimum length in bytes of the text that a match consumes, so that searches
+    /// don't try to match where too little text is left. Programs with callouts try every
+    /// position regardless, as the callouts have to be called there.
+    pub(crate) fn set_min_len(&mut self, min_len: usize) {
+        if !self
+            .body
+            .iter()
+            .any(|insn| matches!(insn, Insn::Callout { .. }))
+        {
+            self.min_len = min_len;
+        }
+    }
+
+    /// Finds the memoization points of the program, which are the splits where whether the
+    /// program matches only depends on the position in the text. No instruction after such a
+    /// split reads a slot that wasn't saved after it, or discards branches that were pushed
+    /// before it. So once the VM backtracked past a memoization point at a position, reaching it
+    /// again at that position is bound to fail.
+    pub(crate) fn memoize(&mut self) {
+        let flows: Vec<Flow> = (0..self.body.len())
+            .map(|pc| Flow::new(&self.body, pc))
+            .collect();
+        // the resources that are read are numbered, the others don't matter
+        let mut resources = BTreeMap::new();
+        for flow in &flows {
+            for &resource in &flow.uses {
+                let n = resources.len();
+                resources.entry(resource).or_insert(n);
+            }
+        }
+        let mut preds = vec![Vec::new(); flows.len()];
+        for (pc, flow) in flows.iter().enumerate() {
+            for &succ in &flow.succs {
+                if succ < flows.len() {
+                    preds[succ].push(pc);
+                }
+            }
+        }
+
+        // backwards data flow analysis of the resources that are read before they're written
+        // (the live resources), and whether an opaque instruction can be reached
+        let mut live = vec![BitSet::new(); flows.len()];
+        let mut opaque = vec![false; flows.len()];
+        let mut queued = vec![true; flows.len()];
+        let mut work: Vec<usize> = (0..flows.len()).collect();
+        while let Some(pc) = work.pop() {
+            queued[pc] = false;
+            let flow = &flows[pc];
+            let mut pc_live = BitSet::new();
+            let mut pc_opaque = flow.opaque;
+            for &succ in &flow.succs {
+                if succ < flows.len() {
+                    pc_live.union_with(&live[succ]);
+                    pc_opaque |= opaque[succ];
+                }
+            }
+            for resource in &flow.defs {
+                if let Some(&n) = resources.get(resource) {
+                    pc_live.remove(n);
+                }
+            }
+            for resource in &flow.uses {
+                pc_live.insert(resources[resource]);
+            }
+            if pc_live != live[pc] || pc_opaque != opaque[pc] {
+                live[pc] = pc_live;
+                opaque[pc] = pc_opaque;
+                for &pred in &preds[pc] {
+                    if !queued[pred] {
+                        queued[pred] = true;
+                        work.push(pred);
+                    }
+                }
+            }
+        }
+
+        self.n_memo_points = 0;
+        self.memo_points = vec![usize::MAX; self.body.len()];
+        for (pc, insn) in self.body.iter().enumerate() {
+            if matches!(insn, Insn::Split(..)) && !opaque[pc] && live[pc].is_empty() {
+                self.memo_points[pc] = self.n_memo_points;
+                self.n_memo_points += 1;
+            }
+        }
+    }
+
+    /// Returns the edits of the fuzzy groups from the saves of a match, where the edit log
+    /// follows the slots.
+    pub(crate) fn edits(&self, saves: &[usize]) -> Vec<Edit> {
+        if self.edits_slot.is_none() {
+            return Vec::new();
+        }
+        saves[self.n_saves..]
+            .chunks(3)
+            .map(|edit| Edit::new(EDIT_KINDS[edit[0]], edit[1], edit[2]))
+            .collect()
+    }
+
+    /// Returns whether the program uses `\K`, so that a match can consume text before its
+    /// reported start.
+    pub(crate) fn keeps_out(&self) -> bool {
+        self.keeps_out
+    }
+
+    /// Returns the index of the instruction that starts the overall match, right after the
+    /// prefix that lets the program search from any position. Running the program from there
+    /// only finds matches that start at the search position.
+    pub(crate) fn anchored_start(&self) -> usize {
+        self.body
+            .iter()
+            .position(|insn| matches!(insn, Insn::Save(0)))
+            .unwrap_or(0)
+    }
+
+    /// Returns whether the program uses `\G`, so that where it matches depends on the position
+    /// that the search starts at.
+    pub(crate) fn continues_from_previous_match(&self) -> bool {
+        self.body
+            .iter()
+            .any(|insn| matches!(insn, Insn::ContinueFromPreviousMatchEnd))
     }
 
     #[doc(hidden)]
@@ -215,6 +604,187 @@ impl Prog {
     }
 }
 
+/// Something that the outcome of running an instruction can depend on, for finding the
+/// memoization points of a program.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Resource {
+    /// A slot
+    Slot(usize),
+    /// The branches pushed since the start of the atomic group or negative look-around that
+    /// starts at the instruction, which its end discards
+    Branches(usize),
+}
+
+/// How an instruction continues, and which resources it writes and reads.
+struct Flow {
+    /// The instructions that can be executed next
+    succs: Vec<usize>,
+    /// The resources that are written
+    defs: Vec<Resource>,
+    /// The resources that are read
+    uses: Vec<Resource>,
+    /// Whether the instruction depends on more than the resources, e.g. by calling a callout
+    opaque: bool,
+}
+
+impl Flow {
+    fn new(body: &[Insn], pc: usize) -> Flow {
+        let mut flow = Flow {
+            succs: vec![pc + 1],
+            defs: Vec::new(),
+            uses: Vec::new(),
+            opaque: false,
+        };
+        match body[pc] {
+            Insn::End | Insn::Fail => flow.succs.clear(),
+            Insn::Any
+            | Insn::AnyNoNL
+            | Insn::Assertion(_)
+            | Insn::Lit(_)
+            | Insn::GoBack(_)
+            | Insn::GoBackBytes(_)
+            | Insn::GraphemeCluster
+            | Insn::ContinueFromPreviousMatchEnd => {}
+            Insn::Split(x, y) => {
+                flow.succs = vec![x, y];
+                if is_negative_lookaround(body, pc) {
+                    flow.defs.push(Resource::Branches(pc));
+                }
+            }
+            Insn::Jmp(target) => flow.succs = vec![target],
+            Insn::Save(slot) | Insn::Save0(slot) | Insn::Absent { start: slot, .. } => {
+                flow.defs.push(Resource::Slot(slot));
+            }
+            Insn::Restore(slot) | Insn::ScriptRun(slot) => flow.uses.push(Resource::Slot(slot)),
+            Insn::GiveBack(start) => {
+                flow.succs.push(pc);
+                flow.uses.push(Resource::Slot(start));
+            }
+            Insn::RepeatGr { next, repeat, .. } | Insn::RepeatNg { next, repeat, .. } => {
+                flow.succs.push(next);
+                flow.uses.push(Resource::Slot(repeat));
+            }
+            Insn::RepeatEpsilonGr {
+                next,
+                repeat,
+                check,
+                ..
+            }
+            | Insn::RepeatEpsilonNg {
+                next,
+                repeat,
+                check,
+                ..
+            } => {
+                flow.succs.push(next);
+                flow.uses.push(Resource::Slot(repeat));
+                flow.uses.push(Resource::Slot(check));
+            }
+            Insn::Backref { slot, .. } => {
+                flow.uses.push(Resource::Slot(slot));
+                flow.uses.push(Resource::Slot(slot + 1));
+            }
+            Insn::BackrefExistsCondition(group) => flow.uses.push(Resource::Slot(group * 2)),
+            Insn::Delegate {
+                start_group,
+                end_group,
+                ..
+            } => {
+                flow.defs
+                    .extend((start_group * 2..end_group * 2).map(Resource::Slot));
+            }
+            Insn::BeginAtomic => flow.defs.push(Resource::Branches(pc)),
+            Insn::EndAtomic => match atomic_start(body, pc) {
+                Some(start) => flow.uses.push(Resource::Branches(start)),
+                None => flow.opaque = true,
+            },
+            Insn::FailNegativeLookAround => {
+                flow.succs.clear();
+                match (0..pc)
+                    .rev()
+                    .find(|&start| is_negative_lookaround_of(body, start, pc))
+                {
+                    Some(start) => flow.uses.push(Resource::Branches(start)),
+                    None => flow.opaque = true,
+                }
+            }
+            _ => flow.opaque = true,
+        }
+        flow
+    }
+}
+
+/// Returns whether the split at `pc` starts a negative look-around.
+pub(crate) fn is_negative_lookaround(body: &[Insn], pc: usize) -> bool {
+    match body[pc] {
+        Insn::Split(x, y) if x == pc + 1 && y > 0 && y <= body.len() => {
+            matches!(body[y - 1], Insn::FailNegativeLookAround)
+                && (0..y - 1)
+                    .rev()
+                    .find(|&start| is_negative_lookaround_of(body, start, y - 1))
+                    == Some(pc)
+        }
+        _ => false,
+    }
+}
+
+/// Returns whether the instruction at `start` can be the split of the negative look-around
+/// that ends at `end`, which continues after it. The innermost such split is the one.
+fn is_negative_lookaround_of(body: &[Insn], start: usize, end: usize) -> bool {
+    matches!(body[start], Insn::Split(x, y) if x == start + 1 && y == end + 1)
+}
+
+/// Returns the start of the atomic group that ends at `end`, as atomic groups are nested.
+fn atomic_start(body: &[Insn], end: usize) -> Option<usize> {
+    let mut depth = 0;
+    for pc in (0..end).rev() {
+        match body[pc] {
+            Insn::EndAtomic => depth += 1,
+            Insn::BeginAtomic if depth == 0 => return Some(pc),
+            Insn::BeginAtomic => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The memory that running a program needs, which can be reused between runs so that running a
+/// program doesn't allocate once the buffers are big enough, see `Cache`.
+#[derive(Debug)]
+pub(crate) struct Scratch {
+    state: State,
+    /// Slots for searches of delegates
+    inner_slots: Vec<Option<NonMaxUsize>>,
+    /// The memoization points that were reached, see `run_impl`
+    memo: BitSet,
+    /// The saves of the longest match so far, when looking for the leftmost-longest match
+    longest: Vec<usize>,
+    /// The start and end of each match so far, when looking for all matches
+    all: Vec<(usize, usize)>,
+}
+
+impl Scratch {
+    pub(crate) fn new() -> Scratch {
+        Scratch {
+            state: State::new(0, MAX_STACK, 0),
+            inner_slots: Vec::new(),
+            memo: BitSet::new(),
+            longest: Vec::new(),
+            all: Vec::new(),
+        }
+    }
+
+    /// Returns the saves of the last match, followed by the edit log if the program has fuzzy
+    /// groups.
+    pub(crate) fn saves(&self) -> &[usize] {
+        &self.state.saves
+    }
+
+    fn into_saves(self) -> Vec<usize> {
+        self.state.saves
+    }
+}
+
 #[derive(Debug)]
 struct Branch {
     pc: usize,
@@ -228,6 +798,7 @@ struct Save {
     value: usize,
 }
 
+#[derive(Debug)]
 struct State {
     /// Saved values indexed by slot. Mostly indices to s, but can be repeat values etc.
     /// Always contains the saves of the current state.
@@ -238,7 +809,17 @@ struct State {
     oldsave: Vec<Save>,
     /// Number of saves at the end of `oldsave` that need to be restored to `saves` on pop
     nsave: usize,
+    /// Index in `oldsave` of the last old save of each slot, so that checking whether a slot is
+    /// already saved doesn't need to go through all the saves of the branch
+    saved_at: Vec<usize>,
     explicit_sp: usize,
+    /// Captures of groups with a stack of captures, as the start, end and position of the
+    /// previous capture of the group. Its size in use is kept in a slot, so that backtracking
+    /// restores it.
+    history: Vec<usize>,
+    /// Edits of fuzzy groups, as the kind, start and end of each edit. Its size in use is kept in
+    /// a slot, so that backtracking restores it.
+    edits: Vec<usize>,
     /// Maximum size of the stack. If the size would be exceeded during execution, a `StackOverflow`
     /// error is raised.
     max_stack: usize,
@@ -261,12 +842,30 @@ impl State {
             stack: Vec::new(),
             oldsave: Vec::new(),
             nsave: 0,
+            saved_at: Vec::new(),
             explicit_sp: n_saves,
+            history: Vec::new(),
+            edits: Vec::new(),
             max_stack,
             options,
         }
     }
 
+    /// Resets the state for another run, keeping the memory of its buffers.
+    fn reset(&mut self, n_saves: usize, max_stack: usize, options: u32) {
+        self.saves.clear();
+        self.saves.resize(n_saves, usize::MAX);
+        self.stack.clear();
+        self.oldsave.clear();
+        self.nsave = 0;
+        self.saved_at.clear();
+        self.explicit_sp = n_saves;
+        self.history.clear();
+        self.edits.clear();
+        self.max_stack = max_stack;
+        self.options = options;
+    }
+
     // push a backtrack branch
     fn push(&mut self, pc: usize, ix: usize) -> Result<()> {
         if self.stack.len() < self.max_stack {
@@ -293,14 +892,19 @@ impl State {
     }
 
     fn save(&mut self, slot: usize, val: usize) {
-        for i in 0..self.nsave {
-            // could avoid this iteration with some overhead; worth it?
-            if self.oldsave[self.oldsave.len() - i - 1].slot == slot {
+        let branch_start = self.oldsave.len() - self.nsave;
+        if let Some(&ix) = self.saved_at.get(slot) {
+            // the index is stale if the save was popped since
+            if (branch_start..self.oldsave.len()).contains(&ix) && self.oldsave[ix].slot == slot {
                 // already saved, just update
                 self.saves[slot] = val;
                 return;
             }
         }
+        if self.saved_at.len() <= slot {
+            self.saved_at.resize(slot + 1, usize::MAX);
+        }
+        self.saved_at[slot] = self.oldsave.len();
         self.oldsave.push(Save {
             slot,
             value: self.saves[slot],
@@ -343,6 +947,91 @@ impl State {
         result
     }
 
+    // push a frame for a subroutine call onto the explicit stack, consisting of all the saves
+    // followed by the group and the pc to return to; returns the position of the frame
+    fn push_frame(&mut self, group: usize, return_pc: usize) -> Result<usize> {
+        for slot in 0..self.explicit_sp {
+            self.stack_push(self.saves[slot]);
+        }
+        self.stack_push(group);
+        self.stack_push(return_pc);
+        let sp = self.get(self.explicit_sp);
+        if sp > self.max_stack {
+            return Err(Error::RuntimeError(RuntimeError::StackOverflow));
+        }
+        Ok(sp - 1)
+    }
+
+    // get the group of the frame at `frame`
+    fn frame_group(&self, frame: usize) -> usize {
+        self.get(frame - 1)
+    }
+
+    // pop the frame at `frame` from the explicit stack, restoring the saves as they were
+    // before the call; returns the pc to return to
+    fn pop_frame(&mut self, frame: usize) -> usize {
+        let return_pc = self.get(frame);
+        let start = frame - 1 - self.explicit_sp;
+        for slot in 0..self.explicit_sp {
+            let value = self.get(start + slot);
+            if self.saves[slot] != value {
+                self.save(slot, value);
+            }
+        }
+        self.save(self.explicit_sp, start);
+        return_pc
+    }
+
+    // push the capture of `group` onto its stack of captures, where `head` is the slot for the
+    // position of its last capture in the history and `top` the slot for the size of the history
+    fn push_capture(&mut self, group: usize, head: usize, top: usize) {
+        let pos = match self.get(top) {
+            usize::MAX => 0,
+            top => top,
+        };
+        // captures after `top` were discarded by backtracking
+        self.history.truncate(pos);
+        let (start, end, prev) = (self.get(group * 2), self.get(group * 2 + 1), self.get(head));
+        self.history.extend_from_slice(&[start, end, prev]);
+        self.save(top, pos + 3);
+        self.save(head, pos);
+    }
+
+    // pop the last capture of `group` from its stack of captures, which makes the capture before
+    // it the capture of the group; returns the popped capture
+    fn pop_capture(&mut self, group: usize, head: usize) -> Option<(usize, usize)> {
+        let pos = self.get(head);
+        if pos == usize::MAX {
+            return None;
+        }
+        let (start, end, prev) = (
+            self.history[pos],
+            self.history[pos + 1],
+            self.history[pos + 2],
+        );
+        let (prev_start, prev_end) = if prev == usize::MAX {
+            (usize::MAX, usize::MAX)
+        } else {
+            (self.history[prev], self.history[prev + 1])
+        };
+        self.save(head, prev);
+        self.save(group * 2, prev_start);
+        self.save(group * 2 + 1, prev_end);
+        Some((start, end))
+    }
+
+    // record an edit of a fuzzy group in the edit log, where `top` is the slot for its size
+    fn push_edit(&mut self, top: usize, kind: EditKind, start: usize, end: usize) {
+        let pos = match self.get(top) {
+            usize::MAX => 0,
+            top => top,
+        };
+        // edits after `top` were discarded by backtracking
+        self.edits.truncate(pos);
+        self.edits.extend_from_slice(&[kind as usize, start, end]);
+        self.save(top, pos + 3);
+    }
+
     /// Get the current number of backtrack branches
     fn backtrack_count(&self) -> usize {
         self.stack.len()
@@ -383,6 +1072,7 @@ impl State {
                 // put the save we want to keep (ix) after the ones we already have (oldsave_ix)
                 // note that it's fine if the indexes are the same (then swapping is a no-op)
                 self.oldsave.swap(oldsave_ix, ix);
+                self.saved_at[slot] = oldsave_ix;
                 oldsave_ix += 1;
             }
         }
@@ -401,46 +1091,333 @@ impl State {
     }
 }
 
-fn codepoint_len_at(s: &str, ix: usize) -> usize {
-    codepoint_len(s.as_bytes()[ix])
-}
-
 #[inline]
-fn matches_literal(s: &str, ix: usize, end: usize, literal: &str) -> bool {
+fn matches_literal(s: &[u8], ix: usize, end: usize, literal: &[u8]) -> bool {
     // Compare as bytes because the literal might be a single byte char whereas ix
-    // points to a multibyte char. Comparing with str would result in an error like
-    // "byte index N is not a char boundary".
-    end <= s.len() && &s.as_bytes()[ix..end] == literal.as_bytes()
+    // points to a multibyte char.
+    end <= s.len() && &s[ix..end] == literal
+}
+
+/// The kinds of edits by their number in the edit log.
+const EDIT_KINDS: [EditKind; 3] = [
+    EditKind::Insertion,
+    EditKind::Deletion,
+    EditKind::Substitution,
+];
+
+/// Turns the saves of a match into the saves followed by the edit log if the program has fuzzy
+/// groups.
+fn finish_saves(prog: &Prog, saves: &mut Vec<usize>, edits: &[usize]) {
+    if let Some(top) = prog.edits_slot {
+        let len = match saves[top] {
+            usize::MAX => 0,
+            len => len,
+        };
+        // the explicit stack isn't needed anymore
+        saves.truncate(prog.n_saves);
+        saves.extend_from_slice(&edits[..len]);
+    }
+}
+
+/// A character, or a byte that isn't valid UTF-8.
+type Unit = core::result::Result<char, u8>;
+
+/// Returns whether `assertion` holds at `ix`.
+fn assertion_matches(
+    look_matcher: &LookMatcher,
+    assertion: Assertion,
+    s: &[u8],
+    ix: usize,
+) -> bool {
+    match assertion {
+        Assertion::StartText => look_matcher.is_start(s, ix),
+        Assertion::EndText => look_matcher.is_end(s, ix),
+        Assertion::EndTextOptionalNewline => {
+            look_matcher.is_end(s, ix) || ix + 1 == s.len() && s[ix] == b'\n'
+        }
+        Assertion::StartLine { crlf: false } => look_matcher.is_start_lf(s, ix),
+        Assertion::StartLine { crlf: true } => look_matcher.is_start_crlf(s, ix),
+        Assertion::EndLine { crlf: false } => look_matcher.is_end_lf(s, ix),
+        Assertion::EndLine { crlf: true } => look_matcher.is_end_crlf(s, ix),
+        Assertion::LeftWordBoundary => look_matcher.is_word_start_unicode(s, ix).unwrap(),
+        Assertion::RightWordBoundary => look_matcher.is_word_end_unicode(s, ix).unwrap(),
+        Assertion::WordBoundary => look_matcher.is_word_unicode(s, ix).unwrap(),
+        Assertion::NotWordBoundary => look_matcher.is_word_unicode_negate(s, ix).unwrap(),
+    }
+}
+
+/// Returns the end of the text at `ix` that matches `literal` ignoring case. This uses full case
+/// folding, so that e.g. `ß` matches `SS`, while bytes that aren't valid UTF-8 match exactly.
+fn matches_literal_casei(s: &[u8], ix: usize, literal: &[u8]) -> Option<usize> {
+    let mut expected = units(literal).flat_map(case_fold).peekable();
+    let mut end = ix;
+    while expected.peek().is_some() {
+        if end >= s.len() {
+            return None;
+        }
+        let (unit, len) = unit_at(s, end);
+        for folded in case_fold(unit) {
+            if expected.next() != Some(folded) {
+                return None;
+            }
+        }
+        end += len;
+    }
+    Some(end)
+}
+
+/// Returns the character at `ix` and its length, or the byte if it isn't valid UTF-8.
+fn unit_at(s: &[u8], ix: usize) -> (Unit, usize) {
+    let len = codepoint_len_at(s, ix);
+    match core::str::from_utf8(&s[ix..ix + len]) {
+        Ok(c) => (Ok(c.chars().next().unwrap()), len),
+        Err(_) => (Err(s[ix]), 1),
+    }
+}
+
+/// Returns the characters of `s`, with the bytes that aren't valid UTF-8 in between.
+fn units(s: &[u8]) -> impl Iterator<Item = Unit> + '_ {
+    let mut ix = 0;
+    core::iter::from_fn(move || {
+        if ix >= s.len() {
+            return None;
+        }
+        let (unit, len) = unit_at(s, ix);
+        ix += len;
+        Some(unit)
+    })
+}
+
+/// Returns the case folding of a character, which is the same for all characters that only
+/// differ in case, such as `ß`, `ẞ` and `SS`. Bytes that aren't valid UTF-8 are kept as they are.
+fn case_fold(unit: Unit) -> impl Iterator<Item = Unit> {
+    let folded = unit.ok().into_iter().flat_map(|c| {
+        c.to_lowercase()
+            .flat_map(char::to_uppercase)
+            .flat_map(char::to_lowercase)
+    });
+    folded.map(Ok).chain(unit.err().map(Err))
 }
 
 /// Run the program with trace printing for debugging.
 pub fn run_trace(prog: &Prog, s: &str, pos: usize) -> Result<Option<Vec<usize>>> {
-    run(prog, s, pos, OPTION_TRACE, &RegexOptions::default())
+    run(
+        prog,
+        s.as_bytes(),
+        pos,
+        OPTION_TRACE,
+        &RegexOptions::default(),
+    )
 }
 
 /// Run the program with default options.
 pub fn run_default(prog: &Prog, s: &str, pos: usize) -> Result<Option<Vec<usize>>> {
-    run(prog, s, pos, 0, &RegexOptions::default())
+    run(prog, s.as_bytes(), pos, 0, &RegexOptions::default())
 }
 
 /// Run the program with options.
-#[allow(clippy::cognitive_complexity)]
+///
+/// The input doesn't have to be valid UTF-8: a byte that isn't part of a valid UTF-8 sequence
+/// is treated as a single character by `.` and look-behind.
 pub(crate) fn run(
     prog: &Prog,
-    s: &str,
+    s: &[u8],
+    pos: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+) -> Result<Option<Vec<usize>>> {
+    run_from(prog, 0, s, pos, option_flags, options)
+}
+
+/// Run the program with options, starting at the instruction `start_pc`.
+pub(crate) fn run_from(
+    prog: &Prog,
+    start_pc: usize,
+    s: &[u8],
     pos: usize,
     option_flags: u32,
     options: &RegexOptions,
 ) -> Result<Option<Vec<usize>>> {
-    let mut state = State::new(prog.n_saves, MAX_STACK, option_flags);
-    let mut inner_slots: Vec<Option<NonMaxUsize>> = Vec::new();
+    run_with(
+        prog,
+        start_pc,
+        s,
+        pos,
+        option_flags,
+        options,
+        &SearchOptions::new(),
+    )
+}
+
+/// Run the program with options, some of which are overridden by `search`, starting at the
+/// instruction `start_pc`.
+pub(crate) fn run_with(
+    prog: &Prog,
+    start_pc: usize,
+    s: &[u8],
+    pos: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+    search: &SearchOptions,
+) -> Result<Option<Vec<usize>>> {
+    let mut scratch = Scratch::new();
+    let result = run_impl(
+        prog,
+        &mut scratch,
+        start_pc,
+        s,
+        pos,
+        option_flags,
+        options,
+        search,
+    )?;
+    Ok(result.map(|_| scratch.into_saves()))
+}
+
+/// Run the program with options, reusing the memory of `scratch`. Returns whether there is a
+/// match, whose saves are then in `scratch`, see `Scratch::saves`.
+pub(crate) fn run_with_scratch(
+    prog: &Prog,
+    scratch: &mut Scratch,
+    s: &[u8],
+    pos: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+) -> Result<bool> {
+    let search = SearchOptions::new();
+    let result = run_impl(prog, scratch, 0, s, pos, option_flags, options, &search)?;
+    Ok(result.is_some())
+}
+
+/// Run the program for a partial match, see `OPTION_PARTIAL`. Returns the saves of the match and
+/// whether it's partial, in which case it ends at the end of the text.
+pub(crate) fn run_partial(
+    prog: &Prog,
+    s: &[u8],
+    pos: usize,
+    options: &RegexOptions,
+) -> Result<Option<(Vec<usize>, bool)>> {
+    let search = SearchOptions::new();
+    let mut scratch = Scratch::new();
+    let result = run_impl(
+        prog,
+        &mut scratch,
+        0,
+        s,
+        pos,
+        OPTION_PARTIAL,
+        options,
+        &search,
+    )?;
+    Ok(result.map(|partial| (scratch.into_saves(), partial)))
+}
+
+/// Run the program for all matches at the leftmost position from `pos` where it matches, see
+/// `OPTION_ALL_MATCHES`. Returns the start and end of each match, in the order they were found,
+/// which may repeat matches that were found in different ways.
+pub(crate) fn run_all_matches(
+    prog: &Prog,
+    s: &[u8],
+    pos: usize,
+    options: &RegexOptions,
+) -> Result<Vec<(usize, usize)>> {
+    let search = SearchOptions::new();
+    let mut scratch = Scratch::new();
+    run_impl(
+        prog,
+        &mut scratch,
+        0,
+        s,
+        pos,
+        OPTION_ALL_MATCHES,
+        options,
+        &search,
+    )?;
+    Ok(scratch.all)
+}
+
+/// Like `run_with`, but leaves the saves of the match in `scratch` and returns whether the
+/// match is partial.
+#[allow(clippy::cognitive_complexity, clippy::too_many_arguments)]
+fn run_impl(
+    prog: &Prog,
+    scratch: &mut Scratch,
+    start_pc: usize,
+    s: &[u8],
+    pos: usize,
+    option_flags: u32,
+    options: &RegexOptions,
+    search: &SearchOptions,
+) -> Result<Option<bool>> {
+    let partial = option_flags & OPTION_PARTIAL != 0;
+    if !partial && s.len().saturating_sub(pos) < prog.min_len {
+        return Ok(None);
+    }
+    let match_end = search.get_end();
+    // a match can't start after the position that it must not end after
+    let search_end = match_end.unwrap_or(s.len());
+    let mut pos = pos;
+    if let (0, false, Some(prefilter)) = (start_pc, partial, &prog.prefilter) {
+        match prefilter.find(Input::new(s).span(pos..search_end)) {
+            Some(m) => pos = m.start(),
+            None => return Ok(None),
+        }
+    }
+    let Scratch {
+        state,
+        inner_slots,
+        memo: memo_set,
+        longest: longest_saves,
+        all: all_matches,
+    } = scratch;
+    state.reset(prog.n_saves, MAX_STACK, option_flags);
+    all_matches.clear();
     let look_matcher = LookMatcher::new();
     #[cfg(feature = "std")]
     if option_flags & OPTION_TRACE != 0 {
         println!("pos\tinstruction");
     }
     let mut backtrack_count = 0;
-    let mut pc = 0;
+    let backtrack_limit = search
+        .get_backtrack_limit()
+        .unwrap_or(options.backtrack_limit);
+    #[cfg(feature = "std")]
+    let deadline = search
+        .get_timeout()
+        .or(options.timeout)
+        .map(|timeout| Instant::now() + timeout);
+    let find_all = option_flags & OPTION_ALL_MATCHES != 0;
+    let leftmost_longest = find_all || (options.leftmost_longest && !search.get_earliest());
+    // the longest match so far, when looking for the leftmost-longest match
+    let mut longest = false;
+    let anchored_start = prog.anchored_start();
+    // A match that needs more text is partial if it started before the end of the text, so that
+    // the empty text at the end isn't a partial match of every regex, or at the search position.
+    // Reaching the end while searching for the start of the match doesn't count.
+    let is_partial = |state: &State, pc: usize| {
+        let start = state.get(0);
+        partial && pc > anchored_start && (start < s.len() || start == pos)
+    };
+    // whether the last instruction failed because it needed more text
+    let mut hit_end = false;
+    // the last start position before the occurrence of the inner literal that was found last
+    let mut inner_window_end: Option<usize> = None;
+    // The memoization points that were reached at each position from `pos`, see
+    // `Prog::memoize`. Partial matching needs to know whether a failed state hit the end.
+    let memo_width = s.len() - pos + 1;
+    let memo_limit = options.memo_size_limit.unwrap_or(AUTO_MEMO_SIZE_LIMIT);
+    let memo_size = prog
+        .n_memo_points
+        .saturating_mul(memo_width)
+        .saturating_add(7)
+        / 8;
+    let mut memo = if prog.n_memo_points > 0 && !partial && memo_size <= memo_limit {
+        memo_set.clear();
+        Some(memo_set)
+    } else {
+        None
+    };
+    let mut pc = start_pc;
     let mut ix = pos;
     loop {
         // break from this loop to fail, causes stack to pop
@@ -451,6 +1428,12 @@ pub(crate) fn run(
             }
             match prog.body[pc] {
                 Insn::End => {
+                    if option_flags & OPTION_FULL_MATCH != 0 && ix != s.len() {
+                        break 'fail;
+                    }
+                    if match_end.map_or(false, |end| ix > end) {
+                        break 'fail;
+                    }
                     // save of end position into slot 1 is now done
                     // with an explicit group; we might want to
                     // optimize that.
@@ -466,62 +1449,104 @@ pub(crate) fn run(
                             state.save(0, slot1);
                         }
                     }
-                    return Ok(Some(state.saves));
+                    if !leftmost_longest {
+                        finish_saves(prog, &mut state.saves, &state.edits);
+                        return Ok(Some(false));
+                    }
+                    if find_all {
+                        all_matches.push((state.get(0), state.get(1)));
+                    }
+                    // keep backtracking for a longer match at the same start
+                    if !longest || state.saves[1] > longest_saves[1] {
+                        longest_saves.clear();
+                        longest_saves.extend_from_slice(&state.saves);
+                        finish_saves(prog, longest_saves, &state.edits);
+                        longest = true;
+                    }
+                    break 'fail;
                 }
                 Insn::Any => {
                     if ix < s.len() {
                         ix += codepoint_len_at(s, ix);
                     } else {
+                        hit_end = true;
                         break 'fail;
                     }
                 }
                 Insn::AnyNoNL => {
-                    if ix < s.len() && s.as_bytes()[ix] != b'\n' {
+                    if ix < s.len() && s[ix] != b'\n' {
                         ix += codepoint_len_at(s, ix);
                     } else {
+                        hit_end = ix == s.len();
                         break 'fail;
                     }
                 }
                 Insn::Lit(ref val) => {
                     let ix_end = ix + val.len();
-                    if !matches_literal(s, ix, ix_end, val) {
+                    if !matches_literal(s, ix, ix_end, val.as_bytes()) {
+                        hit_end = ix_end > s.len() && val.as_bytes().starts_with(&s[ix..]);
                         break 'fail;
                     }
                     ix = ix_end
                 }
                 Insn::Assertion(assertion) => {
-                    if !match assertion {
-                        Assertion::StartText => look_matcher.is_start(s.as_bytes(), ix),
-                        Assertion::EndText => look_matcher.is_end(s.as_bytes(), ix),
-                        Assertion::StartLine { crlf: false } => {
-                            look_matcher.is_start_lf(s.as_bytes(), ix)
-                        }
-                        Assertion::StartLine { crlf: true } => {
-                            look_matcher.is_start_crlf(s.as_bytes(), ix)
+                    if ix == s.len() && assertion.needs_end() && is_partial(state, pc) {
+                        hit_end = true;
+                        break 'fail;
+                    }
+                    if !assertion_matches(&look_matcher, assertion, s, ix) {
+                        break 'fail;
+                    }
+                }
+                Insn::Split(x, y) => {
+                    if pc == 0 && y == 1 && x == anchored_start && !partial {
+                        // the loop that tries each start position can stop where too little
+                        // text is left for a match
+                        if ix > search_end || search_end - ix < prog.min_len {
+                            break 'fail;
                         }
-                        Assertion::EndLine { crlf: false } => {
-                            look_matcher.is_end_lf(s.as_bytes(), ix)
+                        if let Some(inner_literal) = &prog.inner_literal {
+                            // skip to the start positions before the next occurrence of the
+                            // literals, unless still before the current one
+                            if inner_window_end.map_or(true, |end| ix > end) {
+                                match inner_literal.next_starts(s, ix, search_end) {
+                                    Some(starts) => {
+                                        ix = *starts.start();
+                                        inner_window_end = Some(*starts.end());
+                                    }
+                                    None => break 'fail,
+                                }
+                            }
                         }
-                        Assertion::EndLine { crlf: true } => {
-                            look_matcher.is_end_crlf(s.as_bytes(), ix)
+                    }
+                    if let (0, false, Some(literal_prefilter)) =
+                        (pc, partial, &prog.literal_prefilter)
+                    {
+                        // skip the start positions where the match can't start
+                        match literal_prefilter.find(s, Span::from(ix..search_end)) {
+                            Some(span) => ix = span.start,
+                            None => break 'fail,
                         }
-                        Assertion::LeftWordBoundary => look_matcher
-                            .is_word_start_unicode(s.as_bytes(), ix)
-                            .unwrap(),
-                        Assertion::RightWordBoundary => {
-                            look_matcher.is_word_end_unicode(s.as_bytes(), ix).unwrap()
+                    }
+                    if let Some(memo) = &mut memo {
+                        let point = prog.memo_points[pc];
+                        if point != usize::MAX
+                            && ix >= pos
+                            && !memo.insert(point * memo_width + ix - pos)
+                        {
+                            // the state was reached before, so it can't lead to a new match
+                            break 'fail;
                         }
-                        Assertion::WordBoundary => {
-                            look_matcher.is_word_unicode(s.as_bytes(), ix).unwrap()
+                    }
+                    if !partial {
+                        // when only one of the threads can match, there's no need to come back
+                        if let Some(thread) =
+                            prog.split_bytes[pc].only_thread(x, y, s.get(ix).copied())
+                        {
+                            pc = thread;
+                            continue;
                         }
-                        Assertion::NotWordBoundary => look_matcher
-                            .is_word_unicode_negate(s.as_bytes(), ix)
-                            .unwrap(),
-                    } {
-                        break 'fail;
                     }
-                }
-                Insn::Split(x, y) => {
                     state.push(y, ix)?;
                     pc = x;
                     continue;
@@ -611,6 +1636,12 @@ pub(crate) fn run(
                         ix = prev_codepoint_ix(s, ix);
                     }
                 }
+                Insn::GoBackBytes(count) => {
+                    if ix < count {
+                        break 'fail;
+                    }
+                    ix -= count;
+                }
                 Insn::FailNegativeLookAround => {
                     // Reaching this instruction means that the body of the
                     // look-around matched. Because it's a *negative* look-around,
@@ -629,27 +1660,40 @@ pub(crate) fn run(
                     }
                     break 'fail;
                 }
-                Insn::Backref(slot) => {
+                Insn::Backref { slot, casei } => {
                     let lo = state.get(slot);
                     if lo == usize::MAX {
                         // Referenced group hasn't matched, so the backref doesn't match either
                         break 'fail;
                     }
                     let hi = state.get(slot + 1);
-                    if hi == usize::MAX {
+                    if hi == usize::MAX || lo > hi {
                         // Referenced group hasn't matched, so the backref doesn't match either
                         break 'fail;
                     }
                     let ref_text = &s[lo..hi];
-                    let ix_end = ix + ref_text.len();
-                    if !matches_literal(s, ix, ix_end, ref_text) {
-                        break 'fail;
+                    if casei {
+                        match matches_literal_casei(s, ix, ref_text) {
+                            Some(ix_end) => ix = ix_end,
+                            None => {
+                                // whether the rest of the text is the start of the group's text
+                                hit_end = matches_literal_casei(ref_text, 0, &s[ix..]).is_some();
+                                break 'fail;
+                            }
+                        }
+                    } else {
+                        let ix_end = ix + ref_text.len();
+                        if !matches_literal(s, ix, ix_end, ref_text) {
+                            hit_end = ix_end > s.len() && ref_text.starts_with(&s[ix..]);
+                            break 'fail;
+                        }
+                        ix = ix_end;
                     }
-                    ix = ix_end;
                 }
                 Insn::BackrefExistsCondition(group) => {
                     let lo = state.get(group * 2);
-                    if lo == usize::MAX {
+                    let hi = state.get(group * 2 + 1);
+                    if lo == usize::MAX || (hi != usize::MAX && lo > hi) {
                         // Referenced group hasn't matched, so the backref doesn't match either
                         break 'fail;
                     }
@@ -666,17 +1710,22 @@ pub(crate) fn run(
                     ref inner,
                     start_group,
                     end_group,
+                    ..
                 } => {
                     let input = Input::new(s).span(ix..s.len()).anchored(Anchored::Yes);
                     if start_group == end_group {
                         // No groups, so we can use faster methods
                         match inner.search_half(&input) {
                             Some(m) => ix = m.offset(),
-                            _ => break 'fail,
+                            _ => {
+                                // for partial matches, delegates only match single characters
+                                hit_end = ix == s.len();
+                                break 'fail;
+                            }
                         }
                     } else {
                         inner_slots.resize((end_group - start_group + 1) * 2, None);
-                        if inner.search_slots(&input, &mut inner_slots).is_some() {
+                        if inner.search_slots(&input, inner_slots).is_some() {
                             for i in 0..(end_group - start_group) {
                                 let slot = (start_group + i) * 2;
                                 if let Some(start) = inner_slots[(i + 1) * 2] {
@@ -694,11 +1743,203 @@ pub(crate) fn run(
                         }
                     }
                 }
+                Insn::Absent { ref inner, start } => {
+                    state.save(start, ix);
+                    let input = Input::new(s).span(ix..s.len());
+                    ix = inner.find(input).map_or(s.len(), |m| m.start());
+                }
+                Insn::GiveBack(start) => {
+                    if ix > state.get(start) {
+                        state.push(pc, prev_codepoint_ix(s, ix))?;
+                    }
+                }
+                Insn::GraphemeCluster => {
+                    if ix >= s.len() {
+                        hit_end = true;
+                        break 'fail;
+                    }
+                    ix = next_grapheme(s, ix);
+                }
+                Insn::ScriptRun(start) => {
+                    if !is_script_run(&s[state.get(start)..ix]) {
+                        break 'fail;
+                    }
+                }
+                Insn::Callout { ref id, groups } => {
+                    if let Some(callout) = &options.callout {
+                        let saves = &state.saves[..groups * 2];
+                        match (callout.0)(&Callout::new(id, s, ix, saves)) {
+                            CalloutResult::Continue => {}
+                            CalloutResult::Fail => break 'fail,
+                            CalloutResult::Abort => return Ok(None),
+                        }
+                    }
+                }
+                Insn::Predicate { ref id, groups } => {
+                    let predicate = match id {
+                        CalloutId::String(name) => options.predicates.get(name),
+                        CalloutId::Number(_) => None,
+                    };
+                    let saves = &state.saves[..groups * 2];
+                    if !predicate.map_or(false, |p| (p.0)(&Callout::new(id, s, ix, saves))) {
+                        break 'fail;
+                    }
+                }
                 Insn::ContinueFromPreviousMatchEnd => {
                     if ix > pos || option_flags & OPTION_SKIPPED_EMPTY_MATCH != 0 {
                         break 'fail;
                     }
                 }
+                Insn::Call {
+                    target,
+                    group,
+                    end_group,
+                    frame,
+                    call_ix,
+                } => {
+                    // Calling the group again at the same position would recurse forever.
+                    // Positions only increase from one call to the next, so it's enough to
+                    // compare with the innermost call of the group.
+                    if state.get(call_ix) == ix {
+                        break 'fail;
+                    }
+                    let new_frame = state.push_frame(group, pc + 1)?;
+                    state.save(frame, new_frame);
+                    state.save(call_ix, ix);
+                    // Each level of recursion has its own captures, otherwise a group could
+                    // start in the call while its end is still the one of the caller.
+                    for slot in group * 2..end_group * 2 {
+                        if state.get(slot) != usize::MAX {
+                            state.save(slot, usize::MAX);
+                        }
+                    }
+                    pc = target;
+                    continue;
+                }
+                Insn::Return { group, frame } => {
+                    let frame = state.get(frame);
+                    if frame != usize::MAX && state.frame_group(frame) == group {
+                        pc = state.pop_frame(frame);
+                        continue;
+                    }
+                }
+                Insn::PushCapture { group, head, top } => {
+                    state.push_capture(group, head, top);
+                }
+                Insn::Balance {
+                    group,
+                    balance,
+                    head,
+                    start,
+                } => {
+                    let Some((balance_start, balance_end)) = state.pop_capture(balance, head)
+                    else {
+                        break 'fail;
+                    };
+                    if group != usize::MAX {
+                        // Capture the text between the removed capture and the balancing group,
+                        // or the text they share if they overlap, like .NET
+                        let start = state.get(start);
+                        let (start, end) = if start >= balance_end {
+                            (balance_end, start)
+                        } else if ix <= balance_start {
+                            (ix, balance_start)
+                        } else {
+                            (start.max(balance_start), ix.min(balance_end))
+                        };
+                        state.save(group * 2, start);
+                        state.save(group * 2 + 1, end);
+                    }
+                }
+                Insn::BeginFuzzy { limit, start } => {
+                    state.save(start, ix);
+                    state.save(limit, 0);
+                }
+                Insn::RetryFuzzy {
+                    max_cost,
+                    cost,
+                    limit,
+                } => {
+                    let current = state.get(limit);
+                    if current < max_cost {
+                        // the branch is taken with the limit saved before pushing it
+                        state.save(limit, current + 1);
+                        state.push(pc, ix)?;
+                        state.save(limit, current);
+                    }
+                    state.save(cost, 0);
+                }
+                Insn::EndFuzzy { cost, limit } => {
+                    if state.get(cost) != state.get(limit) {
+                        break 'fail;
+                    }
+                }
+                Insn::FuzzyEdit { cost, limit, start } => {
+                    // the slots are unset when a group in the fuzzy group is called from outside
+                    if state.get(cost) < state.get(limit) {
+                        if ix < s.len() && ix > state.get(start) {
+                            state.push(pc + 3, ix)?;
+                        }
+                        state.push(pc + 2, ix)?;
+                        if ix < s.len() {
+                            state.push(pc + 1, ix)?;
+                        }
+                    }
+                    pc += 4;
+                    continue;
+                }
+                Insn::Edit {
+                    kind,
+                    cost,
+                    top,
+                    next,
+                } => {
+                    let end = match kind {
+                        EditKind::Deletion => ix,
+                        EditKind::Insertion | EditKind::Substitution => {
+                            ix + codepoint_len_at(s, ix)
+                        }
+                    };
+                    state.push_edit(top, kind, ix, end);
+                    state.save(cost, state.get(cost) + 1);
+                    ix = end;
+                    pc = next;
+                    continue;
+                }
+                Insn::Fail => break 'fail,
+                Insn::Prune | Insn::Skip => {
+                    // Discard all branches of the match at the current start position, which
+                    // leaves the branch of the prefix that searches from later start positions.
+                    while state
+                        .stack
+                        .last()
+                        .map_or(false, |branch| branch.pc >= anchored_start)
+                    {
+                        state.pop();
+                    }
+                    let start = state.stack.last().map(|branch| branch.ix);
+                    let skip = matches!(prog.body[pc], Insn::Skip);
+                    if skip && !longest && start.map_or(false, |start| start < ix) {
+                        // Search again from where (*SKIP) was reached, by running the prefix
+                        // (which starts the program) from there.
+                        state.pop();
+                        pc = 0;
+                        continue;
+                    }
+                    break 'fail;
+                }
+                Insn::Then(target) => {
+                    // Discard branches up to the one for the next alternative, and take it
+                    loop {
+                        let (popped_pc, popped_ix) = state.pop();
+                        if popped_pc == target {
+                            ix = popped_ix;
+                            break;
+                        }
+                    }
+                    pc = target;
+                    continue;
+                }
             }
             pc += 1;
         }
@@ -707,14 +1948,34 @@ pub(crate) fn run(
             println!("fail");
         }
         // "break 'fail" goes here
-        if state.stack.is_empty() {
-            return Ok(None);
+        if hit_end {
+            hit_end = false;
+            if is_partial(state, pc) {
+                state.save(1, s.len());
+                finish_saves(prog, &mut state.saves, &state.edits);
+                return Ok(Some(true));
+            }
+        }
+        // Branches before the start of the overall match are from the prefix that searches
+        // from later start positions, which can't produce the leftmost match anymore.
+        if state.stack.is_empty() || (longest && state.stack.last().unwrap().pc < anchored_start) {
+            if !longest {
+                return Ok(None);
+            }
+            mem::swap(&mut state.saves, longest_saves);
+            return Ok(Some(false));
         }
 
         backtrack_count += 1;
-        if backtrack_count > options.backtrack_limit {
+        if backtrack_count > backtrack_limit {
             return Err(Error::RuntimeError(RuntimeError::BacktrackLimitExceeded));
         }
+        #[cfg(feature = "std")]
+        if let Some(deadline) = deadline {
+            if backtrack_count % TIMEOUT_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                return Err(Error::RuntimeError(RuntimeError::TimeoutExceeded));
+            }
+        }
 
         let (newpc, newix) = state.pop();
         pc = newpc;
@@ -0,0 +1,227 @@
+use fancy_regex::bytes::{NoExpand, Regex};
+use fancy_regex::{EmptyMatchAdvance, RegexBuilder};
+use std::ops::Range;
+
+fn regex(re: &str) -> Regex {
+    let parse_result = Regex::new(re);
+    assert!(
+        parse_result.is_ok(),
+        "Expected regex '{}' to be compiled successfully, got {:?}",
+        re,
+        parse_result.err()
+    );
+    parse_result.unwrap()
+}
+
+fn find(re: &str, text: &[u8]) -> Option<Range<usize>> {
+    regex(re).find(text).unwrap().map(|m| m.range())
+}
+
+fn find_all(re: &str, text: &[u8]) -> Vec<Range<usize>> {
+    regex(re)
+        .find_iter(text)
+        .map(|m| m.unwrap().range())
+        .collect()
+}
+
+#[test]
+fn find_wrap_invalid_utf8() {
+    assert_eq!(find(r"\w+", b"\xff\xfeabc\x80"), Some(2..5));
+    assert_eq!(find(r".", b"\xff\xe2\x82\xac"), Some(1..4));
+    assert_eq!(find(r"b", b"\xe2\x82"), None);
+}
+
+#[test]
+fn find_fancy_invalid_utf8() {
+    assert_eq!(find(r"(\w)\1", b"\xffab\xc3bb"), Some(4..6));
+    assert_eq!(find(r"(?<=\xff)a", b"\xffa\xc3\xbfa"), Some(4..5));
+    assert_eq!(find(r"a(?=\d)", b"a\x80a1"), Some(2..3));
+    assert_eq!(find(r"(?<!a)b", b"ab\x80b"), Some(3..4));
+}
+
+#[test]
+fn backref_matches_invalid_utf8() {
+    assert_eq!(find(r"(\w+)=\1", b"a\xff=a\xff x=x"), Some(6..9));
+    assert_eq!(find(r"(\w)\1", b"a\xffa"), None);
+}
+
+#[test]
+fn lookbehind_steps_over_invalid_bytes() {
+    // Look-behind steps over invalid bytes one at a time, but never matches them.
+    assert_eq!(find(r"(?<=a.)b", b"a\x80b"), None);
+    assert_eq!(find(r"(?<=\w)\d", b"\x80\xff1"), None);
+    assert_eq!(find(r"(?<=\w)\d", b"\xe2\x82a1"), Some(3..4));
+}
+
+#[test]
+fn find_iter_invalid_utf8() {
+    assert_eq!(find_all(r"(?=\w)", b"a\xffb"), vec![0..0, 2..2]);
+    assert_eq!(find_all(r"", b"\xff\xc3\xa9"), vec![0..0, 1..1, 3..3]);
+    assert_eq!(find_all(r"(\w)\1", b"aa\xffbb"), vec![0..2, 3..5]);
+}
+
+#[test]
+fn captures_invalid_utf8() {
+    let re = regex(r"(?<key>\w+)=(?<value>\w+)(?!\w)");
+    let caps = re.captures(b"\x80x=1\xff").unwrap().unwrap();
+    assert_eq!(caps.len(), 3);
+    assert_eq!(&caps[0], b"x=1");
+    assert_eq!(&caps["key"], b"x");
+    assert_eq!(caps.name("value").unwrap().range(), 3..4);
+    let groups: Vec<_> = caps.iter().map(|m| m.unwrap().as_bytes()).collect();
+    assert_eq!(groups, vec![&b"x=1"[..], b"x", b"1"]);
+
+    let all: Vec<_> = re
+        .captures_iter(b"a=1\xff\x80b=2\xff")
+        .map(|caps| caps.unwrap().get(2).unwrap().as_bytes())
+        .collect();
+    assert_eq!(all, vec![b"1", b"2"]);
+}
+
+#[test]
+fn replace_invalid_utf8() {
+    let re = regex(r"(\w)\1");
+    assert_eq!(
+        &re.replace_all(b"\xffaa\x80bb", &b"<$1>"[..])[..],
+        b"\xff<a>\x80<b>"
+    );
+    assert_eq!(
+        &re.replace(b"\xffaa\x80bb", NoExpand(b"$1"))[..],
+        b"\xff$1\x80bb"
+    );
+    let replaced = re.replace_all(b"aabb", |caps: &fancy_regex::bytes::Captures<'_>| {
+        caps[1].to_ascii_uppercase()
+    });
+    assert_eq!(&replaced[..], b"AB");
+}
+
+#[test]
+fn split_invalid_utf8() {
+    let re = regex(r"(?<!a),");
+    let fields: Vec<_> = re.split(b"a,\xff,b").map(|x| x.unwrap()).collect();
+    assert_eq!(fields, vec![&b"a,\xff"[..], b"b"]);
+}
+
+#[test]
+fn non_unicode_matches_bytes() {
+    // the whole regex is delegated
+    assert_eq!(find(r"(?-u)\xFF+", b"a\xff\xffa"), Some(1..3));
+    assert_eq!(find(r"(?-u)[\x80-\xFF]+", b"ab\xc3\xa9\xff"), Some(2..5));
+    assert_eq!(find_all(r"(?-u).", b"\xc3\xa9\n"), vec![0..1, 1..2]);
+    assert_eq!(find(r"(?s-u).", b"\n"), Some(0..1));
+    assert_eq!(
+        find_all(r"(?i-u)k", "kK\u{212a}".as_bytes()),
+        vec![0..1, 1..2]
+    );
+    // only the characters escaped with Unicode disabled match bytes
+    assert_eq!(find(r"\xFF", b"\xff\xc3\xbf"), Some(1..3));
+    assert_eq!(find(r"(?-u:\xFF)\xFF", b"\xff\xff\xc3\xbf"), Some(1..4));
+    assert_eq!(find(r"(?-u)ÿ", b"\xff\xc3\xbf"), Some(1..3));
+
+    // the VM matches bytes in look-arounds and backrefs
+    assert_eq!(find(r"(?-u)(?<=\xFF)a", b"a\xffa"), Some(2..3));
+    assert_eq!(find(r"(?-u)(?<!\xFF)a", b"\xffaa"), Some(2..3));
+    assert_eq!(find(r"(?-u)(?<=\xA9)x", b"\xc3\xa9x"), Some(2..3));
+    assert_eq!(find(r"(?-u)(?<=a\xFF|\xFEb)c", b"a\xfec\xfebc"), Some(5..6));
+    assert_eq!(find(r"(?-u)\w+(?=\xFF)", b"\xc3\xa9ab\xff"), Some(2..4));
+    assert_eq!(
+        find(r"(?-u)([\x80-\xFF])\1", b"\xc3\xa9\xff\xff"),
+        Some(2..4)
+    );
+    assert_eq!(find(r"(?-u)(?>\xFF+)\xFE", b"\xff\xff\xfe"), Some(0..3));
+
+    // a look-behind can have a constant length in bytes but not in characters
+    assert_eq!(find(r"(?<=ab|é)x", "abxéx".as_bytes()), Some(2..3));
+}
+
+#[test]
+fn non_unicode_only_for_bytes() {
+    assert!(fancy_regex::Regex::new(r"(?-u)\xFF").is_err());
+    let re = fancy_regex::RegexBuilder::new(r"(?-u)(?<=\xFF)a")
+        .anchored(true)
+        .build_bytes()
+        .unwrap();
+    assert_eq!(re.find(b"\xffa").unwrap(), None);
+    assert_eq!(
+        re.find_from_pos(b"\xffa", 1).unwrap().unwrap().range(),
+        1..2
+    );
+    // an error of the regex crate for Unicode classes with Unicode disabled
+    assert!(Regex::new(r"(?-u)\pL").is_err());
+}
+
+#[test]
+fn from_regex() {
+    let re: Regex = fancy_regex::Regex::new(r"(\w)\1").unwrap().into();
+    assert_eq!(re.as_str(), r"(\w)\1");
+    assert!(re.is_match(b"\xffxx").unwrap());
+}
+
+#[test]
+fn expand_with_expander() {
+    use fancy_regex::Expander;
+
+    let re = regex(r"(?<key>\w+)=(\w+)(?=;)");
+    let caps = re.captures(b"\xffkey=value;").unwrap().unwrap();
+    assert_eq!(
+        Expander::python().expansion_bytes(b"\\2\xfe\\g<key>", &caps),
+        b"value\xfekey"
+    );
+    assert_eq!(
+        Expander::sed().expansion_bytes(b"\\U\\1\\E \xff &", &caps),
+        b"KEY \xff key=value"
+    );
+
+    let mut dst = b">".to_vec();
+    Expander::python().append_expansion_bytes(&mut dst, br"\2", &caps);
+    assert_eq!(dst, b">value");
+
+    #[cfg(feature = "std")]
+    {
+        let mut dst = Vec::new();
+        Expander::sed()
+            .write_expansion_bytes(&mut dst, br"[\2]", &caps)
+            .unwrap();
+        assert_eq!(dst, b"[value]");
+    }
+}
+
+#[test]
+fn empty_match_advance_byte() {
+    let starts = |re: &Regex| {
+        re.find_iter("aé\u{301}".as_bytes())
+            .map(|m| m.unwrap().start())
+            .collect::<Vec<_>>()
+    };
+    let build = |re: &str, advance| {
+        RegexBuilder::new(re)
+            .empty_match_advance(advance)
+            .build_bytes()
+            .unwrap()
+    };
+    assert_eq!(
+        starts(&build(r"", EmptyMatchAdvance::Char)),
+        vec![0, 1, 3, 5]
+    );
+    assert_eq!(
+        starts(&build(r"", EmptyMatchAdvance::Byte)),
+        vec![0, 1, 2, 3, 4, 5]
+    );
+    assert_eq!(
+        starts(&build(r"(?-u)(?!a)", EmptyMatchAdvance::Char)),
+        vec![1, 3, 5]
+    );
+    assert_eq!(
+        starts(&build(r"(?-u)(?!a)", EmptyMatchAdvance::Byte)),
+        vec![1, 2, 3, 4, 5]
+    );
+
+    // A regex for strings still advances by characters.
+    let re = RegexBuilder::new(r"")
+        .empty_match_advance(EmptyMatchAdvance::Byte)
+        .build()
+        .unwrap();
+    let str_starts: Vec<_> = re.find_iter("aé").map(|m| m.unwrap().start()).collect();
+    assert_eq!(str_starts, vec![0, 1, 3]);
+    assert_eq!(starts(&Regex::from(re)), vec![0, 1, 3, 5]);
+}
@@ -1,6 +1,10 @@
-use fancy_regex::{Captures, CompileError, Error, Expander, Match, Result};
+use fancy_regex::{
+    Captures, CaseMapping, CompileError, EditKind, Error, ExpandError, ExpandErrorKind, Expander,
+    ExpanderBuilder, GroupRef, Match, Result,
+};
 use std::borrow::Cow;
 use std::ops::Index;
+use std::panic::{RefUnwindSafe, UnwindSafe};
 
 mod common;
 
@@ -11,6 +15,46 @@ fn capture_names() {
     assert_eq!(capture_names, vec![None, Some("foo"), None, Some("bar")]);
 }
 
+#[test]
+fn static_captures_len() {
+    let len = |re: &str| common::regex(re).static_captures_len();
+    // wrapped
+    assert_eq!(len(r"(a)(b)"), Some(3));
+    assert_eq!(len(r"(a)|(b)"), Some(2));
+    assert_eq!(len(r"(a)|b"), None);
+    // fancy
+    assert_eq!(len(r"(a)(?=(b))\1"), Some(3));
+    assert_eq!(len(r"(a)(b)?\1"), None);
+    assert_eq!(len(r"(?:(a)|(b))\1"), Some(2));
+    assert_eq!(len(r"(?:(a)|b)\1"), None);
+    assert_eq!(len(r"(a)+(?!(b))\1"), Some(2));
+    assert_eq!(len(r"(a)(?(1)(b)|(c))"), Some(3));
+    assert_eq!(len(r"(a)?(?(1)(b)|c)"), None);
+}
+
+#[test]
+fn iter_named() {
+    let named = |re: &str, text: &str| -> Vec<(String, Option<String>)> {
+        common::regex(re)
+            .captures(text)
+            .unwrap()
+            .unwrap()
+            .iter_named()
+            .map(|(name, m)| (name.to_string(), m.map(|m| m.as_str().to_string())))
+            .collect()
+    };
+    let pair = |name: &str, m: Option<&str>| (name.to_string(), m.map(String::from));
+    assert_eq!(
+        named(r"(?<z>\d)(?<a>\w)?(x)(?<m>\w)", "1xy"),
+        vec![pair("z", Some("1")), pair("a", None), pair("m", Some("y"))]
+    );
+    assert_eq!(
+        named(r"(?<b>\w)(?<a>\w)?\k<b>", "xx"),
+        vec![pair("b", Some("x")), pair("a", None)]
+    );
+    assert_eq!(named(r"(\w)\1", "aa"), vec![]);
+}
+
 #[test]
 fn captures_fancy() {
     let captures = captures(r"\s*(\w+)(?=\.)", "foo bar.");
@@ -97,6 +141,186 @@ fn captures_with_conditional() {
     assert!(captures.get(2).is_none());
 }
 
+#[test]
+fn captures_with_branch_reset() {
+    let first = captures(r"(?|(\w)(\d)|(\d))-(\w)", "a1-b");
+    assert_eq!(first.len(), 4);
+    assert_match(first.get(1), "a", 0, 1);
+    assert_match(first.get(2), "1", 1, 2);
+    assert_match(first.get(3), "b", 3, 4);
+
+    let second = captures(r"(?|(\w)(\d)|(\d))-(\w)", "7-b");
+    assert_eq!(second.len(), 4);
+    assert_match(second.get(1), "7", 0, 1);
+    assert!(second.get(2).is_none());
+    assert_match(second.get(3), "b", 2, 3);
+
+    let named = captures(r"(?|(?<x>a)|(?<x>b))\k<x>", "abb");
+    assert_match(named.name("x"), "b", 1, 2);
+}
+
+#[test]
+fn captures_with_duplicate_names() {
+    let re = r"(?J)(?<v>\d+)|(?<v>[a-z]+)";
+    let digits = captures(re, "42");
+    assert_match(digits.name("v"), "42", 0, 2);
+    let letters = captures(re, "ab");
+    assert_match(letters.name("v"), "ab", 0, 2);
+    assert!(letters.get(1).is_none());
+    let named: Vec<_> = letters
+        .iter_named()
+        .map(|(name, m)| (name, m.map(|m| m.as_str())))
+        .collect();
+    assert_eq!(named, vec![("v", Some("ab"))]);
+
+    // the name refers to the first of the groups that matched
+    let both = captures(r"(?J)(?<v>a)?(?<v>b)", "ab");
+    assert_match(both.name("v"), "a", 0, 1);
+    let second = captures(r"(?J)(?<v>a)?(?<v>b)", "b");
+    assert_match(second.name("v"), "b", 0, 1);
+
+    // backrefs and conditions refer to all the groups with the name
+    let backref = common::regex(r"(?J)(?:(?<v>a)|(?<v>b))\k<v>");
+    assert!(backref.is_match("bb").unwrap());
+    assert!(!backref.is_match("ba").unwrap());
+    let condition = common::regex(r"(?J)(?:(?<v>a)|(?<v>b)|c)(?(<v>)x|y)");
+    assert!(condition.is_match("bx").unwrap());
+    assert!(condition.is_match("cy").unwrap());
+    assert!(!condition.is_match("by").unwrap());
+
+    let regex = common::regex(r"(?J)(?<v>a)|(?<v>b)|(?<w>c)");
+    assert_eq!(regex.group_indices("v"), &[1, 2]);
+    assert_eq!(regex.group_indices("w"), &[3]);
+    assert!(regex.group_indices("x").is_empty());
+    let names: Vec<_> = regex.capture_names().collect();
+    assert_eq!(names, vec![None, Some("v"), Some("v"), Some("w")]);
+}
+
+#[test]
+fn captures_with_many_groups() {
+    let n = 5000;
+    let mut re = String::new();
+    let mut text = String::new();
+    for i in 0..n {
+        re.push_str(&format!("(x{})", i % 10));
+        text.push_str(&format!("x{}", i % 10));
+    }
+    let backref_text = format!("{}yx8", text);
+    let caps = captures(&format!(r"{}(y)\4999", re), &backref_text);
+    assert_eq!(caps.len(), n + 2);
+    assert_match(caps.get(1), "x0", 0, 2);
+    assert_match(caps.get(4999), "x8", 9996, 9998);
+    assert_match(caps.get(5001), "y", 10000, 10001);
+    assert_match(caps.get(0), &backref_text, 0, 10003);
+
+    let named_text = format!("{}y", text);
+    let caps = captures(&format!("{}(?<last>y)", re), &named_text);
+    assert_match(caps.name("last"), "y", 10000, 10001);
+    assert_match(caps.get(2500), "x9", 4998, 5000);
+}
+
+#[test]
+fn captures_with_accept() {
+    // (*ACCEPT) closes the enclosing groups, later groups don't participate
+    let accept = captures(r"(a(b(*ACCEPT)c)d)(e)", "abx");
+    assert_eq!(accept.len(), 4);
+    assert_match(accept.get(0), "ab", 0, 2);
+    assert_match(accept.get(1), "ab", 0, 2);
+    assert_match(accept.get(2), "b", 1, 2);
+    assert!(accept.get(3).is_none());
+}
+
+#[test]
+fn captures_with_balancing_groups() {
+    // the capture of a group is its last capture that wasn't removed
+    let removed = captures(r"(?<a>\w)+(?<-a>!)+", "abc!!");
+    assert_match(removed.name("a"), "a", 0, 1);
+    let all_removed = captures(r"(?<a>\w)+(?<-a>!)+", "ab!!");
+    assert!(all_removed.name("a").is_none());
+
+    // the text between the removed capture and the balancing group is captured
+    let tags = captures(
+        r"^[^<>]*(((?<Open><)[^<>]*)+((?<Close-Open>>)[^<>]*)+)*(?(Open)(?!))$",
+        "<abc><mno<xyz>>",
+    );
+    assert!(tags.name("Open").is_none());
+    assert_match(tags.name("Close"), "mno<xyz>", 6, 14);
+}
+
+#[test]
+fn captures_with_subroutine_call() {
+    // captures set inside a call are reset when it returns
+    let calls = captures(r"(\w)(\d)?-(?1)(?2)", "a-b7");
+    assert_eq!(calls.len(), 3);
+    assert_match(calls.get(0), "a-b7", 0, 4);
+    assert_match(calls.get(1), "a", 0, 1);
+    assert!(calls.get(2).is_none());
+
+    let palindrome = captures(r"^((.)(?1)\2|.?)$", "abcba");
+    assert_match(palindrome.get(1), "abcba", 0, 5);
+    assert_match(palindrome.get(2), "a", 0, 1);
+}
+
+#[test]
+fn captures_with_fuzzy_groups() {
+    let edits = |caps: &Captures<'_>| -> Vec<_> {
+        caps.edits()
+            .iter()
+            .map(|edit| (edit.kind(), edit.range()))
+            .collect()
+    };
+
+    // the fewest edits are preferred
+    let exact = captures(r"(?:abc){~2}", "abc");
+    assert_eq!(exact.cost(), 0);
+    assert!(exact.edits().is_empty());
+    let substituted = captures(r"^(?:GATTACA){~2}$", "GATACCA");
+    assert_eq!(substituted.cost(), 2);
+    assert_eq!(
+        edits(&substituted),
+        vec![
+            (EditKind::Substitution, 3..4),
+            (EditKind::Substitution, 4..5)
+        ]
+    );
+    let deleted = captures(r"^(?:abc){~1}$", "ac");
+    assert_eq!(edits(&deleted), vec![(EditKind::Deletion, 1..1)]);
+    let inserted = captures(r"^(?:abc){~1}$", "abxc");
+    assert_eq!(edits(&inserted), vec![(EditKind::Insertion, 2..3)]);
+    assert!(common::regex(r"^(?:abc){~1}$")
+        .captures("xbxc")
+        .unwrap()
+        .is_none());
+
+    // text before the match isn't inserted
+    let later = captures(r"(?:abc){~1}", "xabc");
+    assert_match(later.get(0), "abc", 1, 4);
+    assert_eq!(later.cost(), 0);
+
+    // groups in a fuzzy group are captured, and classes and case-insensitive literals are edited
+    let email = captures(r"(\w+)@((?i:example)){~2}\.com", "bob@EXMAPLE.com");
+    assert_match(email.get(1), "bob", 0, 3);
+    assert_match(email.get(2), "EXMAPLE", 4, 11);
+    assert_eq!(email.cost(), 2);
+    let digits = captures(r"^(?:\d{3}-\d{4}){~1}$", "555-12a4");
+    assert_eq!(edits(&digits), vec![(EditKind::Substitution, 6..7)]);
+
+    // each repetition of a fuzzy group can make its own edits
+    let repeated = captures(r"^(?:(?:ab){~1})+$", "abaxbb");
+    assert_eq!(repeated.cost(), 2);
+
+    // each match has its own edits
+    let regex = common::regex(r"(?:cat){~1}");
+    let costs: Vec<_> = regex
+        .captures_iter("cat cut at")
+        .map(|caps| caps.unwrap().cost())
+        .collect();
+    assert_eq!(costs, vec![0, 1, 1]);
+
+    let plain = captures(r"(\w)\1", "aa");
+    assert!(plain.edits().is_empty());
+}
+
 #[test]
 fn captures_iter() {
     let text = "11 21 33";
@@ -198,6 +422,55 @@ fn captures_from_pos_looking_left() {
     assert_match(captures.get(1), "x", 1, 2);
 }
 
+#[test]
+fn captures_read() {
+    for pattern in [r"(\w)(\d)?", r"(\w)(\d)?(?=!)"] {
+        let regex = common::regex(pattern);
+        let mut locations = regex.capture_locations();
+        assert_eq!(locations.len(), 3);
+        assert_eq!(locations.get(0), None);
+
+        let m = regex.captures_read(&mut locations, "..a1!").unwrap();
+        assert_match(m, "a1", 2, 4);
+        assert_eq!(locations.get(1), Some((2, 3)));
+        assert_eq!(locations.get(2), Some((3, 4)));
+
+        let m = regex.captures_read_at(&mut locations, "a- b!", 1).unwrap();
+        assert_match(m, "b", 3, 4);
+        assert_eq!(locations.get(1), Some((3, 4)));
+        assert_eq!(locations.get(2), None);
+        assert_eq!(locations.len(), 3);
+
+        assert!(regex.captures_read(&mut locations, "..").unwrap().is_none());
+        assert_eq!(locations.get(0), None);
+        assert_eq!(locations.get(3), None);
+    }
+}
+
+#[test]
+fn captures_read_with_cache() {
+    for pattern in [r"(\w)(\d)?", r"(\w)(\d)?(?=!)"] {
+        let regex = common::regex(pattern);
+        let mut cache = regex.create_cache();
+        let mut locations = regex.capture_locations();
+
+        let m = regex.captures_read_with_cache(&mut cache, &mut locations, "..a1!");
+        assert_match(m.unwrap(), "a1", 2, 4);
+        assert_eq!(locations.get(1), Some((2, 3)));
+        assert_eq!(locations.get(2), Some((3, 4)));
+
+        let m = regex.captures_read_at_with_cache(&mut cache, &mut locations, "a- b!", 1);
+        assert_match(m.unwrap(), "b", 3, 4);
+        assert_eq!(locations.get(1), Some((3, 4)));
+        assert_eq!(locations.get(2), None);
+        assert_eq!(locations.len(), 3);
+
+        let m = regex.captures_read_with_cache(&mut cache, &mut locations, "..");
+        assert!(m.unwrap().is_none());
+        assert_eq!(locations.get(0), None);
+    }
+}
+
 #[cfg_attr(feature = "track_caller", track_caller)]
 fn captures<'a>(re: &str, text: &'a str) -> Captures<'a> {
     let regex = common::regex(re);
@@ -306,6 +579,13 @@ fn expander_escape() {
     }
     assert_eq!(Expander::default().escape("a$b\\c"), "a$$b\\c");
     assert_eq!(Expander::python().escape("a$b\\c"), "a$b\\\\c");
+
+    let exp = ExpanderBuilder::new().case_escapes(true).build();
+    let text = r"C:\Users\$USER";
+    assert_eq!(exp.escape(text), r"C:\\Users\\$$USER");
+    let regex = common::regex("x");
+    let cap = regex.captures("x").unwrap().unwrap();
+    assert_eq!(exp.expansion(&exp.escape(text), &cap), text);
 }
 
 #[test]
@@ -369,3 +649,543 @@ fn expander_errors() {
         Error::CompileError(CompileError::InvalidBackref)
     );
 }
+
+#[test]
+fn expand_case_escapes() {
+    let regex = common::regex(r"(\w+) (\w+)");
+    let cap = regex.captures("hello wORLD").unwrap().expect("matched");
+    let exp = ExpanderBuilder::new().case_escapes(true).build();
+    assert_eq!(exp.expansion(r"\U$1\E-$2", &cap), "HELLO-wORLD");
+    assert_eq!(exp.expansion(r"\L$2", &cap), "world");
+    assert_eq!(exp.expansion(r"\u$1 \l$2", &cap), "Hello wORLD");
+    assert_eq!(exp.expansion(r"\u\L$2", &cap), "World");
+    assert_eq!(exp.expansion(r"\Uabc\Ldef\Eghi", &cap), "ABCdefghi");
+    assert_eq!(exp.expansion(r"\\U$1\n", &cap), r"\Uhello\n");
+    assert_eq!(exp.expansion(r"\Ustraße", &cap), "STRASSE");
+
+    // Disabled by default.
+    assert_eq!(Expander::default().expansion(r"\U$1", &cap), r"\Uhello");
+
+    let python = ExpanderBuilder::from(Expander::python())
+        .case_escapes(true)
+        .build();
+    assert_eq!(python.expansion(r"\U\1\E \u\2", &cap), "HELLO WORLD");
+    assert_eq!(python.expansion(r"\\U\1", &cap), r"\Uhello");
+}
+
+#[test]
+fn compiled_template() {
+    let regex = common::regex(r"(?<first>\w+) (?<last>\w+)");
+    let template = Expander::default().compile("$last, ${first}! $$ $missing");
+    let mut dst = String::new();
+    for caps in regex.captures_iter("ada lovelace alan turing") {
+        template.expand(&caps.unwrap(), &mut dst).unwrap();
+        dst.push('\n');
+    }
+    assert_eq!(dst, "lovelace, ada! $ \nturing, alan! $ \n");
+
+    let cap = regex.captures("grace hopper").unwrap().unwrap();
+    let case = ExpanderBuilder::new().case_escapes(true).build();
+    assert_eq!(
+        case.compile(r"\U$last\E $first").expansion(&cap),
+        "HOPPER grace"
+    );
+    assert_eq!(
+        Expander::python().compile(r"\g<last>\2\").expansion(&cap),
+        "hopperhopper\\"
+    );
+}
+
+#[test]
+fn expand_filters() {
+    let regex = common::regex(r"(?<name>\w+): +(?<value>.*)");
+    let cap = regex
+        .captures("Greeting:   Hello World  ")
+        .unwrap()
+        .unwrap();
+    let exp = ExpanderBuilder::new()
+        .filter("upper", |s| s.to_uppercase())
+        .filter("trim", |s| s.trim().to_string())
+        .filter("len", |s| s.len().to_string())
+        .build();
+    assert_eq!(exp.expansion("${name|upper}", &cap), "GREETING");
+    assert_eq!(
+        exp.expansion("[${value|trim|upper}]", &cap),
+        "[HELLO WORLD]"
+    );
+    assert_eq!(exp.expansion("${value|len}", &cap), "13");
+    assert_eq!(exp.expansion("${0|len}", &cap), "25");
+    assert_eq!(exp.expansion("${missing|upper}", &cap), "");
+
+    // Unknown filters and filters outside of delimiters are left alone.
+    assert_eq!(exp.expansion("${name|nope}", &cap), "${name|nope}");
+    assert_eq!(exp.expansion("$name|upper", &cap), "Greeting|upper");
+    assert!(exp.check("${name|upper}", &regex).is_ok());
+    assert!(exp.check("${name|nope}", &regex).is_err());
+
+    let template = exp.compile("${name|upper}=${value|trim}");
+    assert_eq!(template.expansion(&cap), "GREETING=Hello World");
+
+    fn assert_auto_traits<T: Send + Sync + UnwindSafe + RefUnwindSafe>(_: &T) {}
+    assert_auto_traits(&exp);
+}
+
+#[test]
+fn expand_default_values() {
+    let regex = common::regex(r"(?<key>\w+)(?:=(?<value>\w*))?");
+    let exp = Expander::default();
+    let cap = regex.captures("debug").unwrap().unwrap();
+    assert_eq!(exp.expansion("$key=${value:-true}", &cap), "debug=true");
+    assert_eq!(exp.expansion("${2:-none}", &cap), "none");
+    assert_eq!(exp.expansion("${missing:-x y}", &cap), "x y");
+    assert_eq!(exp.expansion("${value:-}", &cap), "");
+
+    // A group that matched the empty string still participated.
+    let cap = regex.captures("level=").unwrap().unwrap();
+    assert_eq!(exp.expansion("[${value:-true}]", &cap), "[]");
+    let cap = regex.captures("level=3").unwrap().unwrap();
+    assert_eq!(exp.expansion("${value:-true}", &cap), "3");
+
+    // An unterminated fallback is not a reference.
+    assert_eq!(exp.expansion("${value:-true", &cap), "${value:-true");
+    assert!(exp.check("${value:-true}", &regex).is_ok());
+    assert_eq!(
+        exp.compile("${key:-x}/${value:-y}").expansion(&cap),
+        "level/3"
+    );
+}
+
+#[test]
+fn expand_conditionals() {
+    let regex = common::regex(r"(?<word>\w+)(?<bang>!)?");
+    let exp = Expander::default();
+    let cap = regex.captures("stop!").unwrap().unwrap();
+    assert_eq!(exp.expansion("${bang:+loud:quiet}", &cap), "loud");
+    assert_eq!(exp.expansion("${2:+yes}", &cap), "yes");
+    assert_eq!(exp.expansion("${bang:+has a: colon:no}", &cap), "has a");
+    let cap = regex.captures("go").unwrap().unwrap();
+    assert_eq!(exp.expansion("$word ${bang:+loud:quiet}", &cap), "go quiet");
+    assert_eq!(exp.expansion("[${2:+yes}]", &cap), "[]");
+    assert_eq!(exp.expansion("${bang:+:none}", &cap), "none");
+
+    assert_eq!(exp.expansion("${bang:+loud", &cap), "${bang:+loud");
+    assert!(exp.check("${bang:+loud:quiet}", &regex).is_ok());
+    assert_eq!(exp.compile("${word:+w}${bang:+b:-}").expansion(&cap), "w-");
+}
+
+#[test]
+fn expand_bytes() {
+    let regex = common::regex(r"(?<key>\w+)=(?<value>\w*)");
+    let cap = regex.captures("name=Ann").unwrap().unwrap();
+    let exp = ExpanderBuilder::new().case_escapes(true).build();
+    assert_eq!(exp.expansion_bytes(b"$value\xff$key", &cap), b"Ann\xffname");
+    assert_eq!(
+        exp.expansion_bytes(b"\xfe${key}\xff", &cap),
+        b"\xfename\xff"
+    );
+    assert_eq!(exp.expansion_bytes(b"$$\xc3\xa9", &cap), "$é".as_bytes());
+    assert_eq!(exp.expansion_bytes(b"\\U$value\xff\\E", &cap), b"ANN\xff");
+    assert_eq!(exp.expansion_bytes(b"${x:-\xff}", &cap), b"\xff");
+    assert_eq!(
+        exp.expansion_bytes(b"$key=$value", &cap),
+        exp.expansion("$key=$value", &cap).as_bytes()
+    );
+
+    let mut dst = b"\x00".to_vec();
+    exp.append_expansion_bytes(&mut dst, b"$1", &cap);
+    assert_eq!(dst, b"\x00name");
+
+    #[cfg(feature = "std")]
+    {
+        let mut dst = Vec::new();
+        exp.write_expansion_bytes(&mut dst, b"[$2]", &cap).unwrap();
+        assert_eq!(dst, b"[Ann]");
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn write_expansion() {
+    use std::io::{self, Write};
+
+    /// Accepts a limited number of bytes, one write at a time.
+    struct Limited(Vec<Vec<u8>>, usize);
+
+    impl Write for Limited {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.0.concat().len() + buf.len() > self.1 {
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "full"));
+            }
+            self.0.push(buf.to_vec());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let regex = common::regex(r"(\w+) (\w+)");
+    let cap = regex.captures("hello world").unwrap().unwrap();
+    let exp = Expander::default();
+
+    // the pieces are written as they're expanded
+    let mut dst = Limited(Vec::new(), 100);
+    exp.write_expansion(&mut dst, "$2 $1!", &cap).unwrap();
+    assert_eq!(dst.0.concat(), b"world hello!");
+    assert!(dst.0.len() > 1);
+
+    let mut dst = Limited(Vec::new(), 8);
+    let error = exp.write_expansion(&mut dst, "$2 $1!", &cap).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+    assert_eq!(dst.0.concat(), b"world ");
+
+    let mut dst = b"> ".to_vec();
+    exp.write_expansion_vec(&mut dst, "[$2]", &cap).unwrap();
+    assert_eq!(dst, b"> [world]");
+}
+
+#[test]
+fn expand_fmt() {
+    use core::fmt::Write;
+
+    let regex = common::regex(r"(\w+) (\w+)");
+    let cap = regex.captures("hello world").unwrap().unwrap();
+    let mut dst = String::from("> ");
+    Expander::default()
+        .expand_fmt(&mut dst, "$2, ${1}!", &cap)
+        .unwrap();
+    writeln!(dst).unwrap();
+    assert_eq!(dst, "> world, hello!\n");
+}
+
+fn expand_error<T: std::fmt::Debug>(
+    result: std::result::Result<T, ExpandError>,
+) -> (ExpandErrorKind, usize) {
+    let error = result.unwrap_err();
+    (error.kind().clone(), error.position())
+}
+
+#[test]
+fn check_template() {
+    let regex = common::regex(r"(?<key>\w+)=(\w+)");
+    let exp = Expander::default();
+    assert_eq!(exp.check_template("$key=$2 ${0}", &regex), Ok(()));
+    assert_eq!(exp.check_template("$$5 $1", &regex), Ok(()));
+    assert_eq!(
+        expand_error(exp.check_template("$key=$kye", &regex)),
+        (ExpandErrorKind::UnknownGroupName("kye".to_string()), 5)
+    );
+    assert_eq!(
+        expand_error(exp.check_template("${3}", &regex)),
+        (ExpandErrorKind::UnknownGroupNumber(3), 0)
+    );
+    assert_eq!(
+        expand_error(exp.check_template("ok $", &regex)),
+        (ExpandErrorKind::BadSyntax, 3)
+    );
+    assert_eq!(
+        expand_error(Expander::python().check_template(r"\g<key> \g<1", &regex)),
+        (ExpandErrorKind::BadSyntax, 8)
+    );
+    assert_eq!(
+        exp.check_template("$key=$kye", &regex)
+            .unwrap_err()
+            .to_string(),
+        "Unknown group name: kye at position 5: `$kye` in `$key=$kye`"
+    );
+}
+
+#[test]
+fn expand_special_vars() {
+    let regex = common::regex(r"(?<=a)b+(?=c)");
+    let cap = regex.captures("xab bbc abbcd").unwrap().unwrap();
+    let exp = ExpanderBuilder::new().special_vars(true).build();
+    assert_eq!(exp.expansion("$`<$&>$'", &cap), "xab bbc a<bb>cd");
+    assert_eq!(exp.expansion("$$& $&$0", &cap), "$& bbbb");
+    assert_eq!(exp.expansion_bytes(b"$'\xff$`", &cap), b"cd\xffxab bbc a");
+    assert_eq!(exp.check_template("$`$&$'", &regex), Ok(()));
+
+    // Disabled by default
+    let exp = Expander::default();
+    assert_eq!(exp.expansion("$`$&$'", &cap), "$`$&$'");
+    assert!(exp.check_template("$&", &regex).is_err());
+}
+
+#[test]
+fn try_expansion() {
+    let regex = common::regex(r"(\w+)(?:=(\w+))?(!)?");
+    let exp = Expander::default();
+    let cap = regex.captures("a=b").unwrap().unwrap();
+    assert_eq!(exp.try_expansion("$2=$1", &cap), Ok("b=a".to_string()));
+    assert_eq!(
+        expand_error(exp.try_expansion("$1$3", &cap)),
+        (ExpandErrorKind::GroupDidNotMatch("3".to_string()), 2)
+    );
+    assert_eq!(
+        exp.try_expansion("${3:-?}${3:+!}", &cap),
+        Ok("?".to_string())
+    );
+    assert_eq!(
+        expand_error(exp.try_expansion("${4}", &cap)),
+        (ExpandErrorKind::UnknownGroupNumber(4), 0)
+    );
+    assert_eq!(
+        expand_error(exp.try_expansion("$x", &cap)),
+        (ExpandErrorKind::UnknownGroupName("x".to_string()), 0)
+    );
+    assert_eq!(
+        expand_error(exp.try_expansion("$1 costs $", &cap)),
+        (ExpandErrorKind::BadSyntax, 9)
+    );
+}
+
+#[test]
+fn expand_dotnet() {
+    let regex = common::regex(r"(?<word>\w+)(?:(,)|(;))?");
+    let exp = Expander::dotnet();
+    let cap = regex.captures("one, two").unwrap().unwrap();
+    assert_eq!(exp.expansion("$1|${word}|$word", &cap), "one|one|$word");
+    assert_eq!(exp.expansion("$`[$&]$'", &cap), "[one,] two");
+    assert_eq!(exp.expansion("[$+] $_ $$_", &cap), "[] one, two $_");
+    let cap = regex.captures("three;").unwrap().unwrap();
+    assert_eq!(exp.expansion("$+", &cap), ";");
+    assert_eq!(exp.check_template("$+$_$&", &regex), Ok(()));
+
+    // `$_` is a group name when names don't need delimiters
+    let exp = ExpanderBuilder::new().special_vars(true).build();
+    assert_eq!(exp.expansion("$_$+", &cap), ";");
+}
+
+#[test]
+fn expand_padding() {
+    let regex = common::regex(r"(?<name>\w+)=(?<num>\d+)(!)?");
+    let cap = regex.captures("héllo=42").unwrap().unwrap();
+    let exp = ExpanderBuilder::new()
+        .filter("upper", |s| s.to_uppercase())
+        .build();
+    assert_eq!(exp.expansion("[${name:>8}]", &cap), "[   héllo]");
+    assert_eq!(exp.expansion("[${name:<8}]", &cap), "[héllo   ]");
+    assert_eq!(exp.expansion("[${name:8}]", &cap), "[héllo   ]");
+    assert_eq!(exp.expansion("[${name:^8}]", &cap), "[ héllo  ]");
+    assert_eq!(exp.expansion("[${num:05}]", &cap), "[00042]");
+    assert_eq!(exp.expansion("[${num:<05}]", &cap), "[42000]");
+    assert_eq!(exp.expansion("[${name:3}]", &cap), "[héllo]");
+    assert_eq!(exp.expansion("[${name|upper:>6}]", &cap), "[ HÉLLO]");
+    assert_eq!(exp.expansion("[${3:>3}]", &cap), "[   ]");
+    assert_eq!(exp.expansion_bytes(b"${num:>4}\xff", &cap), b"  42\xff");
+
+    // Invalid specs are not references.
+    assert_eq!(exp.expansion("${num:x}", &cap), "${num:x}");
+    assert_eq!(exp.expansion("${num:>}", &cap), "${num:>}");
+
+    // Widths are limited, so that a template can't allocate without bounds.
+    assert_eq!(exp.expansion("${num:>65535}", &cap).len(), 65535);
+    assert_eq!(
+        exp.expansion("${num:>99999999999}", &cap),
+        "${num:>99999999999}"
+    );
+    let err = exp.check_template("${num:>65536}", &regex).unwrap_err();
+    assert_eq!(err.kind(), &ExpandErrorKind::BadSyntax);
+    assert_eq!(
+        expand_error(exp.try_expansion("${num:>65536}", &cap)),
+        (ExpandErrorKind::BadSyntax, 0)
+    );
+    assert_eq!(
+        expand_error(exp.try_expansion("${3:>3}", &cap)),
+        (ExpandErrorKind::GroupDidNotMatch("3".to_string()), 0)
+    );
+}
+
+#[test]
+fn expand_dialects() {
+    let regex = common::regex(r"(\w+)-(\w+)-(\w+)-(\w+)-(\w+)-(\w+)-(\w+)-(\w+)-(\w+)-(\w+)");
+    let cap = regex.captures("a-b-c-d-e-f-g-h-i-j").unwrap().unwrap();
+
+    let sed = Expander::sed();
+    assert_eq!(
+        sed.expansion(r"\1\10 & \& \\ \U\2\E", &cap),
+        "aa0 a-b-c-d-e-f-g-h-i-j & \\ B"
+    );
+    assert_eq!(sed.escape(r"a&b\c"), r"a\&b\\c");
+    assert_eq!(sed.expansion(&sed.escape(r"a&b\c"), &cap), r"a&b\c");
+
+    let pcre2 = Expander::pcre2();
+    assert_eq!(pcre2.expansion(r"$10 ${1:+x} \u$2", &cap), "j x B");
+
+    let js = Expander::javascript();
+    // Without named groups, `$<1>` is literal text.
+    assert_eq!(js.expansion("$10 $100 $1a $<1>", &cap), "j j0 aa $<1>");
+    assert_eq!(js.expansion("$` $$", &cap), " $");
+
+    // Without group 10, `$10` is group 1 followed by `0`, like in ECMAScript.
+    let regex = common::regex(r"(a)(b)");
+    let cap = regex.captures("ab").unwrap().unwrap();
+    assert_eq!(js.expansion("$10", &cap), "a0");
+    assert_eq!(js.expansion("$21$3", &cap), "b1$3");
+    assert_eq!(js.compile("$10").expansion(&cap), "a0");
+    assert_eq!(js.check_template("$10", &regex), Ok(()));
+
+    // References that don't refer to a group are literal text, like in ECMAScript.
+    assert_eq!(js.expansion("$0 $00 $01", &cap), "$0 $00 a");
+    assert_eq!(js.expansion("$5 $30 $05", &cap), "$5 $30 $05");
+    assert_eq!(js.expansion("$<name>", &cap), "$<name>");
+    assert_eq!(js.compile("$0$3").expansion(&cap), "$0$3");
+    assert_eq!(js.check_template("$0 $30 $<name>", &regex), Ok(()));
+    assert!(js.check("$0 $5 $<name>", &regex).is_ok());
+    assert_eq!(
+        js.template_groups("$0 $00 $5 $05 $20 $<name>"),
+        vec![
+            GroupRef::Numbered(5),
+            GroupRef::Numbered(20),
+            GroupRef::Numbered(2),
+            GroupRef::Named("name".to_string()),
+        ]
+    );
+
+    // With named groups, names that don't exist expand to the empty string.
+    let named = common::regex(r"(?<first>a)(b)");
+    let named_cap = named.captures("ab").unwrap().unwrap();
+    assert_eq!(js.expansion("[$<first>|$<name>]", &named_cap), "[a|]");
+
+    let strict = ExpanderBuilder::from(js).strict(true).build();
+    assert_eq!(strict.try_expansion("$10", &cap).unwrap(), "a0");
+    assert_eq!(strict.try_expansion("$0 $5", &cap).unwrap(), "$0 $5");
+    assert_eq!(Expander::default().expansion("$10", &cap), "");
+}
+
+#[test]
+fn expander_replace_all() {
+    let regex = common::regex(r"(?<key>\w+)(?:=(?<value>\w+))?");
+    let exp = Expander::default();
+    let result = exp.replace_all(&regex, "a=1, b, c=3", "$value:$key");
+    assert_eq!(result.unwrap(), "1:a, :b, 3:c");
+    assert_eq!(exp.replace_all(&regex, "", "$key").unwrap(), "");
+
+    // Empty matches
+    let empty = common::regex(r"x*");
+    let result = exp.replace_all(&empty, "axxb", "[$0]");
+    assert_eq!(result.unwrap(), "[]a[xx]b[]");
+
+    let strict = ExpanderBuilder::new().strict(true).build();
+    let result = strict.replace_all(&regex, "a=1, b", "${value:-?}:$key");
+    assert_eq!(result.unwrap(), "1:a, ?:b");
+    match strict.replace_all(&regex, "a=1, b", "$value:$key") {
+        Err(Error::ExpandError(e)) => {
+            assert_eq!(
+                e.kind(),
+                &ExpandErrorKind::GroupDidNotMatch("value".to_string())
+            )
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn expand_named_only() {
+    let regex = common::regex(r"(?<key>\w+)=(?<value>\w+)");
+    let cap = regex.captures("a=1").unwrap().unwrap();
+    let exp = ExpanderBuilder::new().allow_numbered(false).build();
+    assert_eq!(exp.expansion("$value=$key", &cap), "1=a");
+    assert_eq!(exp.expansion("$2 ${1} $0", &cap), "$2 ${1} $0");
+    assert_eq!(
+        expand_error(exp.check_template("$key ${2}", &regex)),
+        (ExpandErrorKind::BadSyntax, 5)
+    );
+    assert_eq!(
+        expand_error(exp.try_expansion("$key $0", &cap)),
+        (ExpandErrorKind::BadSyntax, 5)
+    );
+
+    let python = ExpanderBuilder::from(Expander::python())
+        .allow_numbered(false)
+        .build();
+    assert_eq!(python.expansion(r"\g<key> \1 \g<1>", &cap), r"a \1 \g<1>");
+}
+
+#[test]
+fn expander_as_replacer() {
+    let regex = common::regex(r"(?<k>\w+)=(?<v>\w+)");
+    let text = "a=1 b=2";
+    assert_eq!(
+        regex.replace_all(text, (Expander::python(), r"\g<v>=\g<k>")),
+        "1=a 2=b"
+    );
+    let sed = Expander::sed();
+    assert_eq!(regex.replace_all(text, (&sed, r"<&>")), "<a=1> <b=2>");
+    assert_eq!(regex.replace_all(text, (&sed, "x")), "x x");
+    assert_eq!(regex.replace(text, (&sed, r"\&")), "& b=2");
+
+    let template = Expander::default().compile("$v");
+    assert_eq!(regex.replace_all(text, &template), "1 2");
+    let template = Expander::default().compile("$$");
+    assert_eq!(regex.replace_all(text, &template), "$ $");
+}
+
+#[test]
+fn template_groups() {
+    let exp = ExpanderBuilder::new().special_vars(true).build();
+    assert_eq!(
+        exp.template_groups("${b:-x} $1 $a${1} $& $` $b $ $$c"),
+        vec![
+            GroupRef::Named("b".to_string()),
+            GroupRef::Numbered(1),
+            GroupRef::Named("a".to_string()),
+            GroupRef::Numbered(0),
+        ]
+    );
+    assert_eq!(Expander::default().template_groups("none"), vec![]);
+    assert_eq!(
+        Expander::python().template_groups(r"\g<x>\10"),
+        vec![GroupRef::Named("x".to_string()), GroupRef::Numbered(10)]
+    );
+}
+
+#[test]
+fn expand_case_mapping() {
+    let regex = common::regex(r"(\w+) (\w+)");
+    let cap = regex.captures("Straße İstanbul").unwrap().unwrap();
+    let mut builder = ExpanderBuilder::new();
+    builder.case_escapes(true);
+    let unicode = builder.build();
+    assert_eq!(
+        unicode.expansion(r"\U$1 \L$2", &cap),
+        "STRASSE i\u{307}stanbul"
+    );
+    let explicit = builder.case_mapping(CaseMapping::Unicode).build();
+    assert_eq!(
+        explicit.expansion(r"\U$1 \L$2", &cap),
+        unicode.expansion(r"\U$1 \L$2", &cap)
+    );
+
+    let ascii = builder.case_mapping(CaseMapping::Ascii).build();
+    assert_eq!(ascii.expansion(r"\U$1 \L$2", &cap), "STRAßE İstanbul");
+
+    let turkic = builder.case_mapping(CaseMapping::Turkic).build();
+    assert_eq!(turkic.expansion(r"\L$2", &cap), "istanbul");
+    let cap = regex.captures("Izmir istanbul").unwrap().unwrap();
+    assert_eq!(turkic.expansion(r"\L$1 \U$2", &cap), "ızmir İSTANBUL");
+    assert_eq!(turkic.compile(r"\u$2").expansion(&cap), "İstanbul");
+}
+
+#[test]
+fn expand_error_context() {
+    let regex = common::regex(r"(?<year>\d+)-(?<month>\d+)");
+    let cap = regex.captures("2024-05").unwrap().unwrap();
+    let exp = Expander::default();
+    let template = "Released in ${month|upper} of ${year}";
+    let error = exp.try_expansion(template, &cap).unwrap_err();
+    assert_eq!(error.kind(), &ExpandErrorKind::BadSyntax);
+    assert_eq!(error.position(), 12);
+    assert_eq!(error.reference(), "${month|upper}");
+    assert_eq!(error.snippet(), "leased in ${month|upper} of ${year");
+
+    let error = exp.check_template("é${x}é", &regex).unwrap_err();
+    assert_eq!(error.reference(), "${x}");
+    assert_eq!(error.snippet(), "é${x}é");
+
+    let template = "0123456789abcdef ${day} 0123456789abcdef";
+    let error = exp.check_template(template, &regex).unwrap_err();
+    assert_eq!(error.position(), 17);
+    assert_eq!(error.snippet(), "789abcdef ${day} 012345678");
+}
@@ -1,6 +1,6 @@
 mod common;
 
-use fancy_regex::{Match, Regex};
+use fancy_regex::{EmptyMatchAdvance, Match, MatchesState, Regex, RegexBuilder};
 use std::ops::Range;
 
 #[test]
@@ -220,6 +220,25 @@ fn find_iter_zero_length_longer_codepoint() {
     }
 }
 
+#[test]
+fn find_iter_keepout_with_zero_length_match() {
+    // the matches consume text before their reported start, so the next match can start at the
+    // end of an empty match
+    let ranges = |re: &str, text: &str| -> Vec<(usize, usize)> {
+        common::regex(re)
+            .find_iter(text)
+            .map(|m| {
+                let m = m.unwrap();
+                (m.start(), m.end())
+            })
+            .collect()
+    };
+    assert_eq!(ranges(r",\K", "a,,b"), vec![(2, 2), (3, 3)]);
+    assert_eq!(ranges(r"a\K", "aaa"), vec![(1, 1), (2, 2), (3, 3)]);
+    assert_eq!(ranges(r"x*\K", "xxa"), vec![(2, 2), (3, 3)]);
+    assert_eq!(ranges(r"foo\Kbar", "foobarfoobar"), vec![(3, 6), (9, 12)]);
+}
+
 #[test]
 fn find_iter_continue_from_previous_match_end() {
     let text = "1122 33";
@@ -267,6 +286,289 @@ fn find_conditional() {
     assert_eq!(find(r"(a)?b(?(1)c|d)", "abd"), Some((1, 3)));
 }
 
+#[test]
+fn find_with_prefilter() {
+    // the search starts where the approximation without look-arounds and backrefs matches
+    let text = format!("{}foo@foo", "xy ".repeat(1000));
+    assert_eq!(find(r"(\w+)@\1", &text), Some((3000, 3007)));
+    assert_eq!(find(r"(?<=o)(\w+)@\1", &text), None);
+    assert_eq!(find(r"(?<=xy )fo\Ko@", &text), Some((3002, 3004)));
+    assert_eq!(find(r"(?>oo|o)@", "xoo@"), Some((1, 4)));
+    assert_eq!(find(r"(f)?(?(1)o|x)o", "xfoo"), Some((1, 4)));
+    assert_eq!(find(r"c((?<=c)){2}", "c"), Some((0, 1)));
+    let matches: Vec<_> = common::regex(r"(?<=y)\s(?=x)")
+        .find_iter(&text)
+        .map(|m| m.unwrap().start())
+        .collect();
+    assert_eq!(matches.len(), 999);
+    assert_eq!(matches[998], 2996);
+
+    // the regex crate folds case differently from literals in the VM
+    assert_eq!(find(r"(?i)(?=x)xss", "XSS"), Some((0, 3)));
+    assert_eq!(find(r"(?i)(?=.)k", "\u{212A}"), Some((0, 3)));
+    // callouts, \G and (*SKIP) need to run at every position
+    assert_eq!(find(r"a+(*SKIP)b|\w+", "aaac"), Some((3, 4)));
+}
+
+#[test]
+fn find_with_literal_prefilter() {
+    // every match starts with one of the literals, so the VM skips to where they occur
+    let text = format!("{}foo bar foobar", "xy ".repeat(1000));
+    assert_eq!(find(r"(?:foo|bar)(?=bar)", &text), Some((3008, 3011)));
+    assert_eq!(find(r"fo(o)\s*\1?b", &text), Some((3000, 3005)));
+    assert_eq!(find(r"(?=\w)(?:ba|fo)r?(?!\w)", &text), Some((3004, 3007)));
+    assert_eq!(find(r"(?:bar)+(?<=foobar)", &text), Some((3011, 3014)));
+    assert_eq!(find(r"(?>foo|bar)\b", &text), Some((3000, 3003)));
+    assert_eq!(find(r"y\Kz|(?!x)foo(?=\s)", &text), Some((3000, 3003)));
+    assert_eq!(find(r"(?:fo|xy)(*SKIP)o(?=b)", &text), Some((3008, 3011)));
+    let matches: Vec<_> = common::regex(r"(?:foo|bar)(?!\w)")
+        .find_iter(&text)
+        .map(|m| m.unwrap().range())
+        .collect();
+    assert_eq!(matches, vec![3000..3003, 3004..3007, 3011..3014]);
+
+    // the start of the match isn't known without the literals
+    assert_eq!(find(r"(?:foo)?bar(?=\s)", &text), Some((3004, 3007)));
+    assert_eq!(find(r"foo(*ACCEPT)|xy", &text), Some((0, 2)));
+}
+
+#[test]
+fn find_with_inner_literal() {
+    // every match contains one of the literals near its start, so the VM only tries the start
+    // positions before where they occur
+    let text = format!("{}ab@example.com ab@example.org", "xy@ ".repeat(1000));
+    let find_all = |re: &str, text: &str| -> Vec<_> {
+        common::regex(re)
+            .find_iter(text)
+            .map(|m| m.unwrap().range())
+            .collect()
+    };
+    assert_eq!(
+        find_all(r"([a-z]{2,8})@example\.(?!org)", &text),
+        vec![4000..4011]
+    );
+    assert_eq!(
+        find_all(r"(?<=\s)\w{1,3}(?:@e|@f)(?=x)", &text),
+        vec![4000..4004, 4015..4019]
+    );
+    assert_eq!(find_all(r"(\w)\1?@x", "aa@x b@x"), vec![0..4, 5..8]);
+    assert_eq!(find_all(r"\w{1,2}\K@x(?!y)", "a@xb@xy"), vec![1..3]);
+    // the window before a literal starts at a character
+    assert_eq!(
+        find_all(r"\w{0,3}(?=é)é@(?!\d)", "ééé@ éé@1 é@"),
+        vec![0..7, 15..18]
+    );
+    assert_eq!(find_all(r"(?:.{2}|x)@(?!\d)", "éé@ x@"), vec![0..5, 5..8]);
+    assert_eq!(find_all(r"(?:x|.)@(?!\d)", "a€€@"), vec![4..8]);
+    // matches that end early don't contain the literals
+    assert_eq!(find_all(r"a(?:b(*ACCEPT))?c@", "ab c@"), vec![0..2]);
+}
+
+#[test]
+fn find_with_cache() {
+    for pattern in [r"\w+(?=!)", r"(?<=\s)\w+!", r"\w+!"] {
+        let regex = common::regex(pattern);
+        let mut cache = regex.create_cache();
+        for (text, expected) in [
+            ("so fancy!", Some("fancy")),
+            ("no", None),
+            (" a!", Some("a")),
+        ] {
+            let m = regex.find_with_cache(&mut cache, text).unwrap();
+            assert_eq!(m.map(|m| m.as_str().trim_end_matches('!')), expected);
+            assert_eq!(
+                regex.is_match_with_cache(&mut cache, text).unwrap(),
+                m.is_some()
+            );
+        }
+        let m = regex
+            .find_from_pos_with_cache(&mut cache, "a! b!", 1)
+            .unwrap();
+        assert_eq!(m.map(|m| m.start()), Some(3));
+    }
+
+    // the cache keeps nothing from a search that affects the next one
+    let regex = RegexBuilder::new(r"(a|ab)(c|bcd)(?=(d*))")
+        .leftmost_longest(true)
+        .build()
+        .unwrap();
+    let mut cache = regex.create_cache();
+    for _ in 0..2 {
+        let m = regex.find_with_cache(&mut cache, "xabcd").unwrap().unwrap();
+        assert_eq!(m.range(), 1..5);
+        assert!(regex.find_with_cache(&mut cache, "ab").unwrap().is_none());
+    }
+}
+
+#[test]
+#[should_panic(expected = "cache was created by a different regex")]
+fn find_with_cache_of_different_regex() {
+    let mut cache = common::regex(r"\w+").create_cache();
+    let _ = common::regex(r"(\w)\1").find_with_cache(&mut cache, "aa");
+}
+
+#[test]
+fn shortest_match() {
+    let regex = common::regex(r"\d+");
+    assert_eq!(regex.shortest_match("ab123").unwrap(), Some(3));
+    assert_eq!(regex.shortest_match_at("1 23", 1).unwrap(), Some(3));
+    assert_eq!(regex.shortest_match("abc").unwrap(), None);
+
+    let regex = common::regex(r"(\d)\1+(?!\d)");
+    assert_eq!(regex.shortest_match("12 3334").unwrap(), None);
+    assert_eq!(regex.shortest_match("12 333 4").unwrap(), Some(6));
+    assert_eq!(regex.shortest_match_at("11 22", 1).unwrap(), Some(5));
+}
+
+#[test]
+fn find_overlapping_iter() {
+    let overlapping = |re: &str, text: &str| -> Vec<(usize, usize)> {
+        common::regex(re)
+            .find_overlapping_iter(text)
+            .map(|m| {
+                let m = m.unwrap();
+                (m.start(), m.end())
+            })
+            .collect()
+    };
+    assert_eq!(overlapping(r"aa", "aaaa"), vec![(0, 2), (1, 3), (2, 4)]);
+    assert_eq!(overlapping(r"a+", "aab"), vec![(0, 1), (0, 2), (1, 2)]);
+    assert_eq!(overlapping(r"a|ab", "ab"), vec![(0, 1), (0, 2)]);
+    assert_eq!(overlapping(r"(?:a|ab)(?!c)", "abc"), vec![(0, 1)]);
+    assert_eq!(
+        overlapping(r"\w+(?<=b)", "abab"),
+        vec![(0, 2), (0, 4), (1, 2), (1, 4), (2, 4), (3, 4)]
+    );
+    // atomic groups don't give back what they matched
+    assert_eq!(overlapping(r"(?>a+)", "aa"), vec![(0, 2), (1, 2)]);
+    assert_eq!(overlapping(r"(a)\1", "aaaba"), vec![(0, 2), (1, 3)]);
+    assert_eq!(overlapping(r"(?<=a)", "aé a"), vec![(1, 1), (5, 5)]);
+    assert_eq!(overlapping(r"x", "abc"), vec![]);
+}
+
+#[test]
+fn rfind() {
+    let rfind = |re: &str, text: &str| -> Option<(usize, usize)> {
+        let regex = common::regex(re);
+        let last = regex.find_iter(text).last().map(|m| m.unwrap().range());
+        let found = regex.rfind(text).unwrap().map(|m| (m.start(), m.end()));
+        assert_eq!(found, last.map(|range| (range.start, range.end)));
+        found
+    };
+    assert_eq!(rfind(r"a+", "aa baa"), Some((4, 6)));
+    assert_eq!(rfind(r"\d+(?!\d)", "12 345"), Some((3, 6)));
+    assert_eq!(rfind(r"aa", "aaaaa"), Some((2, 4)));
+    assert_eq!(rfind(r"(a)\1", "aaaaa"), Some((2, 4)));
+    assert_eq!(rfind(r"a|ab", "abab"), Some((2, 3)));
+    assert_eq!(rfind(r"(?<=a)b*", "abab"), Some((3, 4)));
+    assert_eq!(rfind(r"x*", "ab"), Some((2, 2)));
+    assert_eq!(rfind(r"x", "ab"), None);
+
+    let regex = common::regex(r"(\d)(\d)+(?!\d)");
+    let caps = regex.rcaptures("12 345").unwrap().unwrap();
+    assert_eq!(caps.get(0).unwrap().range(), 3..6);
+    assert_eq!(&caps[1], "3");
+    assert_eq!(&caps[2], "5");
+}
+
+#[test]
+fn find_backtracking_verbs() {
+    assert_eq!(find(r"a(*F)|b", "ab"), Some((1, 2)));
+    assert_eq!(find(r"x(*ACCEPT)y", "xz"), Some((0, 1)));
+    // (*ACCEPT) in a look-around only ends the look-around
+    assert_eq!(find(r"(?=a(*ACCEPT)b)\w+", "ax"), Some((0, 2)));
+    assert_eq!(find(r"(?!a(*ACCEPT)b)\w+", "ax"), Some((1, 2)));
+
+    // the other alternative isn't tried at the same start position
+    assert_eq!(find(r"a+(*PRUNE)c|a", "aaab"), None);
+    assert_eq!(find(r"a+(*PRUNE)c|b", "aaab"), Some((3, 4)));
+    assert_eq!(find(r"aa(*PRUNE)b|a", "aaab"), Some((1, 4)));
+    // the search continues after the text matched before (*SKIP)
+    assert_eq!(find(r"aa(*SKIP)b|a", "aaab"), Some((2, 3)));
+
+    // (*THEN) only skips to the next alternative of the innermost alternation
+    assert_eq!(find(r"a(*THEN)b|ac", "ac"), Some((0, 2)));
+    assert_eq!(find(r"a(*PRUNE)b|ac", "ac"), None);
+    assert_eq!(find(r"(?:a(?:x|y(*THEN)z)|ayw)", "ayw"), Some((0, 3)));
+    assert_eq!(find(r"(?:a(?:y(*THEN)z|x)|ayw)", "ayw"), Some((0, 3)));
+    assert_eq!(find(r"b(*THEN)c|b", "bd"), Some((0, 1)));
+}
+
+#[test]
+fn find_iter_skip_fail() {
+    let words: Vec<_> = common::regex(r#""[^"]*"(*SKIP)(*FAIL)|\w+"#)
+        .find_iter(r#"a "b c" d "e" f"#)
+        .map(|m| m.unwrap().as_str())
+        .collect();
+    assert_eq!(words, vec!["a", "d", "f"]);
+}
+
+#[test]
+fn find_absent() {
+    assert_eq!(find(r"/\*(?~\*/)\*/", "/* a */ b */"), Some((0, 7)));
+    // the text stops before the next match, even if part of the match would fit
+    assert_eq!(find(r"(?~abc)", "xxabc"), Some((0, 2)));
+    assert_eq!(find(r"(?~abc)", "xxab"), Some((0, 4)));
+    // characters are given back on backtracking
+    assert_eq!(find(r"<(?~>)>", "<a> b>"), Some((0, 3)));
+    assert_eq!(find(r"(?~\d)b", "ab1"), Some((0, 2)));
+    // an empty match of the absent expression leaves only the empty text
+    assert_eq!(find(r"a(?~)", "aa"), Some((0, 1)));
+    assert_eq!(find(r"(?~b)é", "aéb"), Some((0, 3)));
+    // the absent expression is searched for by the regex crate
+    assert!(Regex::new(r"(?~a(?=b))").is_err());
+}
+
+#[test]
+fn find_partial() {
+    assert_eq!(
+        find_partial_match(r"^\d{3}-\d{4}$", "555-12"),
+        Some((0, 6, true))
+    );
+    assert_eq!(find_partial_match(r"^\d{3}-\d{4}$", ""), Some((0, 0, true)));
+    assert_eq!(find_partial_match(r"^\d{3}-\d{4}$", "55a"), None);
+    // the end of the text is reached before a complete match is found
+    assert_eq!(find_partial_match(r"abc|a", "ab"), Some((0, 2, true)));
+    assert_eq!(find_partial_match(r"a+", "aaa"), Some((0, 3, true)));
+    assert_eq!(find_partial_match(r"foo", "xfoo"), Some((1, 4, false)));
+    assert_eq!(find_partial_match(r"abc", "xyzab"), Some((3, 5, true)));
+    assert_eq!(find_partial_match(r"abc", "xyzabd"), None);
+    assert_eq!(
+        find_partial_match(r"(?i)straße", "STRA"),
+        Some((0, 4, true))
+    );
+    // fancy features
+    assert_eq!(
+        find_partial_match(r"(\w+) \1", "foo fo"),
+        Some((0, 6, true))
+    );
+    assert_eq!(
+        find_partial_match(r"(?i)(\w+) \1", "foo FO"),
+        Some((0, 6, true))
+    );
+    assert_eq!(find_partial_match(r"^(\w+) \1", "foo fox"), None);
+    assert_eq!(find_partial_match(r"a(?=bc)", "ab"), Some((0, 2, true)));
+    assert_eq!(find_partial_match(r"a(?=bc)", "abd"), None);
+    // assertions that look past the end of the text
+    assert_eq!(find_partial_match(r"\bfoo\b", "foo"), Some((0, 3, true)));
+    assert_eq!(find_partial_match(r"\Afoo", "foo"), Some((0, 3, false)));
+    // a partial match doesn't start at the end of the text
+    assert_eq!(find_partial_match(r"x*", "abc"), Some((0, 0, false)));
+    assert_eq!(find_partial_match(r"b", "a"), None);
+
+    let regex = common::regex(r"\d+-");
+    let m = regex.find_partial_from_pos("1- 23", 2).unwrap().unwrap();
+    assert_eq!(m.range(), 3..5);
+    assert_eq!(m.as_str(), "23");
+    assert!(m.is_partial());
+}
+
+fn find_partial_match(re: &str, text: &str) -> Option<(usize, usize, bool)> {
+    let regex = common::regex(re);
+    let m = regex.find_partial(text).unwrap()?;
+    Some((m.start(), m.end(), m.is_partial()))
+}
+
 fn find(re: &str, text: &str) -> Option<(usize, usize)> {
     find_match(re, text).map(|m| (m.start(), m.end()))
 }
@@ -289,3 +591,131 @@ fn incomplete_escape_sequences() {
     assert!(Regex::new("\\U").is_err());
     assert!(Regex::new("\\x").is_err());
 }
+
+#[test]
+fn find_iter_utf16() {
+    let regex = common::regex(r"(?<=\s)\w+");
+    let text = "😀 ab é😀 cd";
+    let ranges: Vec<_> = regex
+        .find_iter_utf16(text)
+        .map(|result| {
+            let (m, utf16) = result.unwrap();
+            (m.range(), utf16)
+        })
+        .collect();
+    assert_eq!(ranges, vec![(5..7, 3..5), (8..10, 6..7), (15..17, 10..12)]);
+}
+
+#[test]
+fn captures_iter_utf16() {
+    let regex = common::regex(r"(?<=(\w))(😀)(x)?");
+    let text = "a😀 é😀x";
+    let ranges: Vec<_> = regex
+        .captures_iter_utf16(text)
+        .map(|result| result.unwrap().1)
+        .collect();
+    assert_eq!(
+        ranges,
+        vec![
+            vec![Some(1..3), Some(0..1), Some(1..3), None],
+            vec![Some(5..8), Some(4..5), Some(5..7), Some(7..8)],
+        ]
+    );
+}
+
+#[test]
+fn resume_find_iter() {
+    let regex = common::regex(r"(?<=\w)|\d+");
+    let text = "a12 b";
+    let all: Vec<_> = regex.find_iter(text).map(|m| m.unwrap().range()).collect();
+    assert_eq!(all, vec![1..1, 2..2, 3..3, 5..5]);
+
+    for paused_after in 0..all.len() {
+        let mut matches = regex.find_iter(text);
+        let mut found: Vec<_> = (&mut matches)
+            .take(paused_after)
+            .map(|m| m.unwrap().range())
+            .collect();
+        let state = matches.state();
+        let state = MatchesState::from_parts(state.last_end(), state.last_match());
+        found.extend(
+            regex
+                .captures_iter_from_state(text, state)
+                .map(|caps| caps.unwrap().get(0).unwrap().range()),
+        );
+        assert_eq!(found, all);
+    }
+}
+
+#[test]
+fn empty_match_after_match() {
+    let re = RegexBuilder::new(r"\w*(?!\d)")
+        .allow_empty_after_match(true)
+        .build()
+        .unwrap();
+    let matches: Vec<_> = re.find_iter("ab c").map(|m| m.unwrap().range()).collect();
+    assert_eq!(matches, vec![0..2, 2..2, 3..4, 4..4]);
+    let default = Regex::new(r"\w*(?!\d)").unwrap();
+    let matches: Vec<_> = default
+        .find_iter("ab c")
+        .map(|m| m.unwrap().range())
+        .collect();
+    assert_eq!(matches, vec![0..2, 3..4]);
+
+    // same as `re.sub(r"x*", "-", "abxd")` in Python
+    let re = RegexBuilder::new(r"x*")
+        .allow_empty_after_match(true)
+        .build()
+        .unwrap();
+    assert_eq!(re.replace_all("abxd", "-"), "-a-b--d-");
+}
+
+#[test]
+fn empty_match_advance_grapheme() {
+    let re = RegexBuilder::new(r"(?=.)")
+        .empty_match_advance(EmptyMatchAdvance::Grapheme)
+        .build()
+        .unwrap();
+    let text = "e\u{301}🇩🇪x\r\n한";
+    let starts: Vec<_> = re.find_iter(text).map(|m| m.unwrap().start()).collect();
+    assert_eq!(starts, vec![0, 3, 11, 12, 14]);
+    let starts: Vec<_> = re
+        .captures_iter(text)
+        .map(|c| c.unwrap().get(0).unwrap().start())
+        .collect();
+    assert_eq!(starts, vec![0, 3, 11, 12, 14]);
+}
+
+#[test]
+fn count_matches() {
+    let texts = ["", "a", "aa bb cc", "ab c  d", "é😀a\n\nb"];
+    for pattern in [
+        r"\w",
+        r"\w*",
+        r"",
+        r"(?m)^",
+        r"(\w)\1",
+        r"\w+(?=\s)",
+        r"(?<=a)",
+    ] {
+        let re = common::regex(pattern);
+        for text in texts {
+            let expected = re.find_iter(text).count();
+            assert_eq!(
+                re.count_matches(text).unwrap(),
+                expected,
+                "{} {:?}",
+                pattern,
+                text
+            );
+        }
+    }
+
+    let re = RegexBuilder::new(r"\d*")
+        .allow_empty_after_match(true)
+        .build()
+        .unwrap();
+    assert_eq!(re.count_matches("1a22").unwrap(), 4);
+    let re = RegexBuilder::new(r"\d").anchored(true).build().unwrap();
+    assert_eq!(re.count_matches("12a3").unwrap(), 2);
+}
@@ -0,0 +1,109 @@
+use fancy_regex::{Error, GlobOptions, ParseError, Regex};
+
+fn glob_matches(glob: &str, options: &GlobOptions, text: &str) -> bool {
+    let re = Regex::from_glob(glob, options).unwrap();
+    re.is_match(text).unwrap()
+}
+
+fn matches(glob: &str, text: &str) -> bool {
+    glob_matches(glob, &GlobOptions::new(), text)
+}
+
+#[test]
+fn wildcards() {
+    assert!(matches("*.rs", "lib.rs"));
+    assert!(matches("*.rs", ".rs"));
+    assert!(!matches("*.rs", "src/lib.rs"));
+    assert!(!matches("*.rs", "lib.rsx"));
+    assert!(matches("a?c", "abc"));
+    assert!(matches("a?c", "aéc"));
+    assert!(!matches("a?c", "a/c"));
+    assert!(!matches("a?c", "ac"));
+    assert!(matches("*", "line\nbreak"));
+    // everything else matches itself
+    assert!(matches("a+(b)|$.txt", "a+(b)|$.txt"));
+    assert!(!matches("a.txt", "abtxt"));
+}
+
+#[test]
+fn double_star() {
+    assert!(matches("**/*.rs", "lib.rs"));
+    assert!(matches("**/*.rs", "src/a/lib.rs"));
+    assert!(matches("src/**/*.rs", "src/lib.rs"));
+    assert!(matches("src/**/*.rs", "src/a/b/lib.rs"));
+    assert!(!matches("src/**/*.rs", "tests/lib.rs"));
+    assert!(matches("src/**", "src/a/b"));
+    assert!(!matches("src/**", "src"));
+    assert!(matches("**", "a/b"));
+    // not a whole component of the path
+    assert!(matches("a**b", "axxb"));
+    assert!(!matches("a**b", "a/b"));
+}
+
+#[test]
+fn classes() {
+    assert!(matches("[abc]", "b"));
+    assert!(!matches("[abc]", "d"));
+    assert!(matches("[a-c][0-9]", "c7"));
+    assert!(matches("[!a-c]", "d"));
+    assert!(matches("[^a-c]", "d"));
+    assert!(!matches("[!a-c]", "b"));
+    assert!(!matches("[!a-c]", "/"));
+    assert!(matches("[]a]", "]"));
+    assert!(matches("[!]]", "a"));
+    assert!(matches("[-a]", "-"));
+    assert!(matches("[a-]", "-"));
+    assert!(matches("[[&~^\\]]", "^"));
+    assert!(matches("[[:digit:]x]", "5"));
+    assert!(!matches("[[:digit:]x]", "y"));
+    assert!(matches("[*?{]", "{"));
+}
+
+#[test]
+fn alternatives() {
+    assert!(matches("*.{rs,toml}", "Cargo.toml"));
+    assert!(matches("*.{rs,toml}", "lib.rs"));
+    assert!(!matches("*.{rs,toml}", "lib.c"));
+    assert!(matches("{a,b{c,d}}e", "bde"));
+    assert!(matches("{a,}b", "b"));
+    assert!(matches("{**/,}lib.rs", "src/lib.rs"));
+    assert!(matches("{[,],x}", ","));
+    // outside of braces, `,` and `}` match themselves
+    assert!(matches("a,b}", "a,b}"));
+}
+
+#[test]
+fn escapes() {
+    assert!(matches(r"\*", "*"));
+    assert!(!matches(r"\*", "a"));
+    assert!(matches(r"\{a,b\}", "{a,b}"));
+    let options = GlobOptions::new().backslash_escape(false).clone();
+    assert!(glob_matches(r"C:\*.txt", &options, r"C:\a.txt"));
+}
+
+#[test]
+fn options() {
+    let mut options = GlobOptions::new();
+    options.case_insensitive(true);
+    assert!(glob_matches("*.RS", &options, "lib.rs"));
+    options.case_insensitive(false).literal_separator(false);
+    assert!(!glob_matches("*.RS", &options, "lib.rs"));
+    assert!(glob_matches("*.rs", &options, "src/lib.rs"));
+    assert!(glob_matches("[!a]", &options, "/"));
+}
+
+#[test]
+fn errors() {
+    let error = |glob: &str| match Regex::from_glob(glob, &GlobOptions::new()) {
+        Err(Error::ParseError(position, error)) => (position, error.to_string()),
+        result => panic!("unexpected result for {}: {:?}", glob, result),
+    };
+    assert_eq!(error("a[bc"), (1, ParseError::InvalidClass.to_string()));
+    assert_eq!(error("[]"), (0, ParseError::InvalidClass.to_string()));
+    assert_eq!(
+        error("a{b,{c}"),
+        (1, ParseError::UnclosedGlobAlternatives.to_string())
+    );
+    assert_eq!(error("a\\"), (1, ParseError::TrailingBackslash.to_string()));
+    assert!(Regex::from_glob(&"{".repeat(100), &GlobOptions::new()).is_err());
+}
@@ -1,4 +1,7 @@
-use fancy_regex::{Error, RegexBuilder, RuntimeError};
+use fancy_regex::{CompileError, Error, RegexBuilder, RuntimeError};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+#[cfg(feature = "std")]
+use std::time::Duration;
 
 mod common;
 
@@ -53,6 +56,95 @@ fn character_class_intersection() {
     assert_no_match(r"[[0-9]&&[^4]]", "4");
 }
 
+#[test]
+fn character_class_set_operations() {
+    // in backreferences, look-around and other fancy parts of a pattern
+    assert_match(r"^([[a-z]&&[^aeiou]])\1$", "bb");
+    assert_no_match(r"^([[a-z]&&[^aeiou]])\1$", "aa");
+    assert_match(r"(?<=[\p{L}--\p{Lu}])x", "ax");
+    assert_no_match(r"(?<=[\p{L}--\p{Lu}])x", "Ax");
+    assert_match(r"^([a-c~~b-d])\1$", "dd");
+    assert_no_match(r"^([a-c~~b-d])\1$", "bb");
+    assert_match(r"^(?=[\w--\d])([\w&&[^_]]+)\1$", "abab");
+    assert_no_match(r"^(?=[\w--\d])([\w&&[^_]]+)\1$", "1a1a");
+    assert_match(r"(?i)^([[a-z]&&[^aeiou]])\1$", "BB");
+    assert_match(r"^([[:alpha:]--[a-m]])\1$", "zz");
+    assert_no_match(r"^([[:alpha:]--[a-m]])\1$", "aa");
+}
+
+#[test]
+fn unicode_property_escapes() {
+    // in backreferences, look-around and atomic groups
+    assert_match(r"^(\p{Script=Greek})\1$", "αα");
+    assert_no_match(r"^(\p{Script=Greek})\1$", "aa");
+    assert_match(r"(?<=\p{General_Category=Lu})x", "Éx");
+    assert_no_match(r"(?<=\p{General_Category=Lu})x", "éx");
+    assert_match(r"^(?>\p{scx=Hira}+)$", "ひらがな");
+    assert_match(r"^(?=\p{Emoji_Presentation})\X$", "\u{1F44D}\u{1F3FD}");
+    assert_match(r"^(\p{^Greek})\1$", "aa");
+    assert_no_match(r"^(\p{^Greek})\1$", "αα");
+    assert_match(r"^(?=[\P{^L&}])\w$", "a");
+    assert_no_match(r"^(?=\p{L&})\w$", "1");
+}
+
+#[test]
+fn backref_case_insensitive() {
+    assert_match(r"(?i)(<(\w+)>).*?</\2>", "<B>bold</b>");
+    assert_match(r"(?i)^(\w+) \1$", "Hello hELLO");
+    // full case folding
+    assert_match(r"(?i)^(ß)\1$", "ßSS");
+    assert_match(r"(?i)^(ss)\1$", "ssẞ");
+    assert_match(r"(?i)^(k)\1$", "k\u{212A}");
+    assert_match(r"(?i)^(σ)\1\1$", "σςΣ");
+    assert_no_match(r"(?i)^(s)\1$", "sß");
+    // the flag in effect at the backref counts
+    assert_match(r"^(a)(?i)\1$", "aA");
+    assert_no_match(r"(?i)^(a)(?-i)\1$", "aA");
+    assert_no_match(r"^(a)\1$", "aA");
+    assert_match(r"^(?i:(a))\1$", "aa");
+    assert_no_match(r"^(?i:(a))\1$", "aA");
+    assert_no_match(r"^((?i)a)\1$", "Aa");
+    assert_match(r"^((?i)a)\1$", "AA");
+}
+
+#[test]
+fn flags_scoped_to_groups() {
+    // the same whether the group is delegated or needs backtracking
+    for prefix in ["", "(?=.)"] {
+        let re = |re: &str| format!("^{}{}$", prefix, re);
+        assert_no_match(&re("(a(?i)b)c"), "aBC");
+        assert_match(&re("(a(?i)b)c"), "aBc");
+        assert_no_match(&re("(?>a(?i)b)c"), "aBC");
+        assert_match(&re("(?:a(?i)b|c)d"), "Cd");
+        assert_no_match(&re("(?:a(?i)b|c)d"), "CD");
+        assert_no_match(&re("(a(?s).)."), "a\n\n");
+        assert_match(&re("(a(?U)b*)(b*)c"), "abbc");
+    }
+}
+
+#[test]
+fn free_spacing_in_look_arounds() {
+    assert_match("(?x) a (?= b # a comment with ) and (\n c ) bc", "abc");
+    assert_no_match("(?x) a (?= b c ) b", "ab c");
+    assert_match("(?x) (?<= a \\  b # no [class\n ) c", "a bc");
+    assert_no_match("(?x) (?<! a\tb | c ) d", "abd");
+    assert_match("(?x) (?> a + # possessive\n ) b", "aab");
+    assert_match("(?x) (?= \u{2028} a \u{85} )", "a");
+}
+
+#[test]
+fn quoting() {
+    assert_match(r"^\Q.*(a|b)\E$", ".*(a|b)");
+    assert_no_match(r"^\Q.*(a|b)\E$", "xa");
+    assert_match(r"^(\Q(?x)\E)\1$", "(?x)(?x)");
+    assert_match(r"(?<=\Q$\E)\d+", "$12");
+    assert_match(r"^\Qab\E+$", "abbb");
+    assert_match(r"^[\Q]^\E]+$", "]^]");
+    assert_match(r"(?x)^\Qa b\E c$", "a bc");
+    assert_match(r"(?i)^\Q+A\E$", "+a");
+    assert_match(r"^a\Q.b", "a.b");
+}
+
 #[test]
 fn alternation_with_empty_arm() {
     assert_match(r"^(a|)$", "a");
@@ -90,9 +182,22 @@ fn atomic_group() {
     assert_no_match(r"^a(?>bc(?=d)|b)cd$", "abcd");
 }
 
+#[test]
+fn possessive_quantifiers() {
+    assert_match(r"^a*+b", "aab");
+    assert_no_match(r"^a*+a", "aaa");
+    assert_no_match(r"^a++a", "aaa");
+    assert_match(r"^a?+b", "ab");
+    assert_no_match(r"^a?+a$", "a");
+    assert_match(r"^a{1,2}+a$", "aaa");
+    assert_no_match(r"^a{1,3}+a$", "aaa");
+    assert_no_match(r#"^"(?:[^"\\]++|\\.)*+"$"#, r#""abc\"#);
+    assert_match(r#"^"(?:[^"\\]++|\\.)*+"$"#, r#""a\"bc""#);
+}
+
 #[test]
 fn backtrack_limit() {
-    let re = RegexBuilder::new("(?i)(a|b|ab)*(?=c)")
+    let re = RegexBuilder::new(r"(?i)(a|b|ab)*\1?(?=c)")
         .backtrack_limit(100_000)
         .build()
         .unwrap();
@@ -105,6 +210,178 @@ fn backtrack_limit() {
     }
 }
 
+#[test]
+fn memo_size_limit() {
+    let memoized = |re: &str| {
+        RegexBuilder::new(re)
+            .memo_size_limit(1 << 20)
+            .build()
+            .unwrap()
+    };
+    let s = format!("{}c", "a".repeat(1000));
+    let unmemoized = RegexBuilder::new(r"(?:a|(?=a)a)*(?=b)c")
+        .memo_size_limit(0)
+        .build()
+        .unwrap();
+    assert!(unmemoized.is_match(&s).is_err());
+    assert!(!memoized(r"(?:a|(?=a)a)*(?=b)c").is_match(&s).unwrap());
+    // small regexes are memoized on short texts by default
+    let re = common::regex(r"(?:a|(?=a)a)*(?=b)c");
+    assert!(!re.is_match(&s).unwrap());
+    assert!(re.is_match(&format!("{}c", "a".repeat(1_000_000))).is_err());
+    let re = memoized(r"(\w)\1(?:a|(?!b)a)*(?=c)[bc]");
+    assert!(!re.is_match(&s.replace('c', "b")).unwrap());
+    assert!(re.is_match("xxaac").unwrap());
+    assert!(!memoized(r"(?:a|(?<=a)a|(?>a))*(?=b)c")
+        .is_match(&s)
+        .unwrap());
+
+    // states that depend on captures aren't memoized
+    let backref = memoized(r"(?:(a)|a\1?)*(?=b)[bc]");
+    assert!(backref.is_match(&s).is_err());
+    assert!(backref.is_match("aaab").unwrap());
+
+    // without memoization if the table would be too large
+    let re = RegexBuilder::new(r"(?:a|(?=a)a)*(?=b)c")
+        .memo_size_limit(10)
+        .build()
+        .unwrap();
+    assert!(re.is_match(&s).is_err());
+}
+
+#[test]
+fn size_limit() {
+    for pattern in [r"\w+", r"(\w+)\1", r"(?:a|\w+)(?=!)"] {
+        let size = common::regex(pattern).memory_usage();
+        assert!(size > 0, "{}", pattern);
+        assert!(RegexBuilder::new(pattern).size_limit(size).build().is_ok());
+        let result = RegexBuilder::new(pattern).size_limit(size / 2).build();
+        assert!(result.is_err(), "{}", pattern);
+    }
+
+    // too big as a whole, as a delegate of the regex crate, and as a delegate of the program
+    for pattern in [r"(a)\1", r"\w{100}", r"\w{100}(?=x)"] {
+        let result = RegexBuilder::new(pattern).size_limit(1_000).build();
+        match result.err() {
+            Some(Error::CompileError(CompileError::CompiledTooBig(1_000))) => {}
+            _ => panic!("Expected CompileError::CompiledTooBig for {}", pattern),
+        }
+    }
+    // the program is checked while it's compiled
+    let pattern = format!("(?:{})(?=x)", r"a(?!b)".repeat(100_000));
+    let result = RegexBuilder::new(&pattern).size_limit(1 << 16).build();
+    match result.err() {
+        Some(Error::CompileError(CompileError::CompiledTooBig(limit))) => {
+            assert_eq!(limit, 1 << 16)
+        }
+        _ => panic!("Expected CompileError::CompiledTooBig"),
+    }
+
+    assert_eq!(common::regex(r"\w+").program_size(), 0);
+    assert!(common::regex(r"(\w+)\1").program_size() > 0);
+}
+
+#[test]
+fn one_pass() {
+    // the next character decides whether to repeat, so nothing is backtracked into
+    for (pattern, repeated) in [
+        (r"\w+(?=!)", "a"),
+        (r"(?:ab|cd)*(?=!)", "ab"),
+        (r"(?:a(?!b))+(?=!)", "a"),
+    ] {
+        let re = RegexBuilder::new(pattern)
+            .memo_size_limit(0)
+            .backtrack_limit(100_000)
+            .build()
+            .unwrap();
+        let text = repeated.repeat(1_000);
+        assert!(!re.is_match(&format!("{}?", text)).unwrap());
+        let m = re.find(&format!("x {}!", text)).unwrap().map(|m| m.range());
+        assert_eq!(m, Some(2..2 + text.len()));
+    }
+
+    // the threads of the alternation start with the same character
+    assert_match(r"(?:ab|ac)+(?=!)", "abac!");
+    assert_no_match(r"(?:ab|ac)+(?=!)", "abad!");
+}
+
+#[test]
+fn one_pass_with_backtracking_control() {
+    // splits that reach a negative look-around or the end of an atomic group without consuming
+    // text can't be skipped based on the next byte, as these discard branches or fail
+    for (pattern, text, expected) in [
+        (r"(?!(?!a))b", "ab", None),
+        (r"a(?!\b)", "a b", None),
+        (r"(?:ba(?=x)|\n)*+\n", "\nb", None),
+        (r"(?>(?:(?>ca\n))*?|.)c", "1c", Some(1..2)),
+    ] {
+        for memo_size_limit in [0, 1_000_000] {
+            let re = RegexBuilder::new(pattern)
+                .memo_size_limit(memo_size_limit)
+                .build()
+                .unwrap();
+            let m = re.find(text).unwrap().map(|m| m.range());
+            assert_eq!(m, expected, "{:?} on {:?}", pattern, text);
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn timeout() {
+    let re = RegexBuilder::new(r"(?i)(a|b|ab)*\1?(?=c)")
+        .backtrack_limit(usize::MAX)
+        .timeout(Duration::from_millis(10))
+        .build()
+        .unwrap();
+    let s = "abababababababababababababababababababababababababababab";
+    let result = re.is_match(s);
+    match result.err() {
+        Some(Error::RuntimeError(RuntimeError::TimeoutExceeded)) => {}
+        _ => panic!("Expected RuntimeError::TimeoutExceeded"),
+    }
+
+    let re = RegexBuilder::new(r"(\w)\1")
+        .timeout(Duration::from_secs(60))
+        .build()
+        .unwrap();
+    assert!(re.is_match("xaa").unwrap());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn cached() {
+    use fancy_regex::Regex;
+    use std::sync::Arc;
+
+    let re = Regex::cached(r"(\w)\1").unwrap();
+    assert!(re.is_match("xaa").unwrap());
+    assert!(Arc::ptr_eq(&re, &Regex::cached(r"(\w)\1").unwrap()));
+
+    // the options are part of the key
+    let limited = RegexBuilder::new(r"(\w)\1")
+        .backtrack_limit(1_000)
+        .build_cached()
+        .unwrap();
+    assert!(!Arc::ptr_eq(&re, &limited));
+
+    assert!(Regex::cached(r"(\w").is_err());
+
+    // the least recently used regex is dropped
+    Regex::set_cached_capacity(2);
+    let re = Regex::cached(r"(\w)\1").unwrap();
+    let other = Regex::cached(r"(?=a)a").unwrap();
+    Regex::cached(r"(\w)\1").unwrap();
+    Regex::cached(r"(?!b)a").unwrap();
+    assert!(Arc::ptr_eq(&re, &Regex::cached(r"(\w)\1").unwrap()));
+    assert!(!Arc::ptr_eq(&other, &Regex::cached(r"(?=a)a").unwrap()));
+
+    Regex::set_cached_capacity(0);
+    let re = Regex::cached(r"(\w)\1").unwrap();
+    assert!(!Arc::ptr_eq(&re, &Regex::cached(r"(\w)\1").unwrap()));
+    Regex::set_cached_capacity(512);
+}
+
 #[test]
 fn end_of_hard_expression_cannot_be_delegated() {
     assert_match(r"(?!x)(?:a|ab)c", "abc");
@@ -154,6 +431,312 @@ fn conditional_with_lookaround_condition() {
     assert_no_match(r"^(?((?=\d))\wabc|\d!)$", "5!");
 }
 
+#[test]
+fn conditional_for_optional_quotes() {
+    for re in [
+        r#"^(["'])?\w+(?(1)\1)$"#,
+        r#"^(?<q>["'])?\w+(?(<q>)\k<q>)$"#,
+        r#"^(?<q>["'])?\w+(?('q')\k<q>)$"#,
+    ] {
+        assert_match(re, "abc");
+        assert_match(re, "'abc'");
+        assert_match(re, r#""abc""#);
+        assert_no_match(re, r#"'abc""#);
+        assert_no_match(re, "'abc");
+        assert_no_match(re, "abc'");
+    }
+}
+
+#[test]
+fn subroutine_calls() {
+    assert_match(r"^(\((?:[^()]|(?1))*\))$", "(a(b)(c(d)))");
+    assert_no_match(r"^(\((?:[^()]|(?1))*\))$", "(a(b)(c(d))");
+    assert_match(r"\((?:[^()]|(?R))*\)", "(a(b)(c(d)))");
+    // the recursion includes the anchors
+    assert_no_match(r"\A\((?:[^()]|(?R))*\)", "(a(b)(c(d)))");
+    assert_match(r"^(?<p>\[(?:\d|(?&p))*\])$", "[1[2[]]3]");
+    assert_no_match(r"^(?<p>\[(?:\d|(?P>p))*\])$", "[1[2[]]3]]");
+    assert_match(r"^(a|b(?1)c)$", "bbacc");
+    assert_no_match(r"^(a|b(?1)c)$", "bbac");
+
+    // palindromes
+    assert_match(r"^((.)(?1)\2|.?)$", "racecar");
+    assert_match(r"^((.)(?1)\2|.?)$", "abba");
+    assert_no_match(r"^((.)(?1)\2|.?)$", "abca");
+
+    // calls to groups before and after the call
+    assert_match(r"^(\d+)-(?1)$", "12-345");
+    assert_match(r"^(?1)-(\d+)$", "12-345");
+    assert_match(r"^(\d+)-(?-1)$", "12-345");
+    assert_match(r"^(?+1)-(\d+)$", "12-345");
+
+    // recursing without consuming anything fails instead of looping forever
+    assert_no_match(r"(?R)", "a");
+    assert_match(r"^(a|(?1)b)c$", "abc");
+
+    // the recursion has its own captures, so a backref in it doesn't see a group whose start
+    // is from the recursion and whose end is from the caller
+    for pattern in [r"(a|\1)*b(?R)?", r"(a|\1)*b(?0)?"] {
+        let re = common::regex(pattern);
+        assert_eq!(re.find("aab").unwrap().map(|m| m.range()), Some(0..3));
+        assert_eq!(re.captures_iter("aab aab").count(), 2);
+        assert_eq!(re.rfind("aab").unwrap().map(|m| m.range()), Some(0..3));
+        assert!(re.is_full_match("aab").unwrap());
+    }
+}
+
+#[test]
+fn define() {
+    let ipv4 = r"^(?(DEFINE)(?<byte>25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d))(?&byte)(?:\.(?&byte)){3}$";
+    assert_match(ipv4, "192.168.0.255");
+    assert_no_match(ipv4, "192.168.0.256");
+    // the definitions don't match anything by themselves
+    assert_match(r"^(?(DEFINE)(a))b$", "b");
+
+    // calls to groups that are named later
+    let json = r"(?x)
+        (?(DEFINE)
+            (?<value> (?&num) | (?&list) )
+            (?<num> \d+ )
+            (?<list> \[ (?: (?&value) (?: , (?&value) )* )? \] )
+        )
+        ^(?&value)$";
+    assert_match(json, "[1,[2,[]],3]");
+    assert_no_match(json, "[1,[2,[],3]");
+}
+
+#[test]
+fn balancing_groups() {
+    let parens = r"^(?:[^()]|(?<open>\()|(?<-open>\)))*(?(open)(?!))$";
+    assert_match(parens, "(a(b)c)");
+    assert_match(parens, "()()");
+    assert_no_match(parens, "(a(b)c");
+    assert_no_match(parens, "a)(b");
+
+    // as many `b` as `a`
+    let counted = r"^(?<n>a)+(?<-n>b)+(?(n)(?!))$";
+    assert_match(counted, "aaabbb");
+    assert_no_match(counted, "aaabb");
+    assert_no_match(counted, "aabbb");
+}
+
+#[test]
+fn vertical_space() {
+    for text in ["\n", "\x0B", "\x0C", "\r", "\u{85}", "\u{2028}", "\u{2029}"] {
+        assert_match(r"^\v$", text);
+        assert_no_match(r"^\V$", text);
+        // the same in the part of the pattern that uses backtracking
+        assert_match(r"^(\v)\1$", &text.repeat(2));
+        assert_match(r"^[a\v](?<=\v)$", text);
+    }
+    for text in [" ", "\t", "a", "\u{A0}"] {
+        assert_no_match(r"^\v$", text);
+        assert_match(r"^\V$", text);
+        assert_match(r"^(\V)\1$", &text.repeat(2));
+    }
+}
+
+#[test]
+fn line_break() {
+    for text in [
+        "a\r\nb",
+        "a\rb",
+        "a\nb",
+        "a\x0Bb",
+        "a\x0Cb",
+        "a\u{85}b",
+        "a\u{2028}b",
+    ] {
+        assert_match(r"^a\Rb$", text);
+    }
+    assert_no_match(r"^a\Rb$", "a\n\nb");
+    assert_match(r"^a\R+b$", "a\n\r\n\rb");
+    // CRLF is one line break, which isn't split by backtracking
+    assert_no_match(r"^\R\n$", "\r\n");
+    assert_match(r"^\R\n$", "\r\n\n");
+    assert_match(r"^(\w+)\R\1$", "ab\r\nab");
+}
+
+#[test]
+fn grapheme_cluster() {
+    assert_match(r"^\X$", "e\u{301}");
+    assert_match(r"^\X$", "\r\n");
+    // emoji with skin tone, ZWJ sequence and flag
+    assert_match(r"^\X$", "\u{1F44D}\u{1F3FD}");
+    assert_match(r"^\X$", "\u{1F469}\u{200D}\u{1F4BB}");
+    assert_match(r"^\X\X$", "\u{1F1E9}\u{1F1EA}\u{1F1EB}\u{1F1F7}");
+    // a cluster isn't split to make the rest of the pattern match
+    assert_no_match(r"^\X\X$", "e\u{301}");
+    assert_no_match(r"^\X\p{M}$", "e\u{301}");
+    // in look-around
+    assert_match(r"^a(?=\X$)", "ae\u{301}");
+    assert_no_match(r"^a(?=\X$)", "ae\u{301}x");
+    assert_match(r"^(?:(?!\u{1F44D})\X)+$", "ae\u{301}");
+    assert_no_match(r"^(?:(?!\u{1F44D})\X)+$", "a\u{1F44D}\u{1F3FD}");
+}
+
+#[test]
+fn word_start_and_end() {
+    for (start, end) in [(r"\<", r"\>"), (r"\b{start}", r"\b{end}")] {
+        let whole_word = format!("{}cat{}", start, end);
+        assert_match(&whole_word, "a cat.");
+        assert_no_match(&whole_word, "concatenate");
+        assert_no_match(&format!("{}cat", end), "cat");
+        assert_no_match(&format!("cat{}", start), "cat");
+        // together with fancy features
+        assert_match(&format!(r"{}(\w+) \1{}", start, end), "the the");
+        assert_no_match(&format!(r"{}(\w+) \1{}", start, end), "the then");
+        assert_match(&format!(r"(?<=a{})\.", end), "a.");
+        assert_no_match(&format!(r"(?<=a{})\.", end), "a .");
+        assert_match(&format!(r"(?<={}a)b", start), "ab");
+        assert_no_match(&format!(r"(?<={}a)b", start), "aab");
+    }
+    let word = common::regex(r"\b{start}\w+\b{end}(?!\()");
+    let words: Vec<_> = word
+        .find_iter("f(x) + y")
+        .map(|m| m.unwrap().as_str())
+        .collect();
+    assert_eq!(words, ["x", "y"]);
+}
+
+#[test]
+fn script_runs() {
+    assert_match(r"^(*sr:\w+)$", "paypal");
+    assert_no_match(r"^(*sr:\w+)$", "p\u{430}ypal");
+    assert_match(r"^(*sr:\w+)$", "\u{395}\u{3BB}\u{3BB}\u{3AC}\u{3B4}\u{3B1}");
+    // characters that are used with any script
+    assert_match(r"^(*sr:.+)$", "e\u{301}t\u{E9}, 2024!");
+    // digits have to be from the same set of ten
+    assert_match(r"^(*sr:\d+)$", "\u{661}\u{662}");
+    assert_no_match(r"^(*sr:\d+)$", "1\u{662}");
+    // scripts that are written together
+    assert_match(r"^(*sr:.+)$", "\u{65E5}\u{672C}\u{306E}\u{30AB}");
+    assert_match(r"^(*sr:.+)$", "\u{D55C}\u{6F22}");
+    assert_no_match(r"^(*sr:.+)$", "\u{D55C}\u{30AB}");
+    // backtracking into the script run, unless it's atomic
+    let find = |re: &str, text: &str| common::regex(re).find(text).unwrap().unwrap().range();
+    assert_eq!(find(r"(*sr:\w+)", "p\u{430}ypal"), 0..1);
+    assert_eq!(find(r"(*asr:\w+)", "p\u{430}ypal"), 3..7);
+    assert_match(r"^(*script_run:(\w+))\1$", "abab");
+    assert_match(r"(?<=(*sr:ab))c", "abc");
+    assert_no_match(r"(?<=(*sr:a\u{430}))c", "a\u{430}c");
+}
+
+#[test]
+fn full_match() {
+    let full = |re: &str, text: &str| common::regex(re).is_full_match(text).unwrap();
+    // wrapped
+    assert!(full(r"a|ab", "ab"));
+    assert!(full(r"\d+?", "123"));
+    assert!(!full(r"\d+", "123a"));
+    assert!(!full(r"\d+", "a123"));
+    assert!(full(r"a\z|b", "a"));
+    // fancy
+    assert!(full(r"(a)\1|(a)\2b", "aab"));
+    assert!(full(r"(\w)\1*?", "aaa"));
+    assert!(!full(r"(\w)\1", "aaa"));
+    assert!(full(r"(?=a)\w+(?<=c)", "abc"));
+    assert!(full(r"", ""));
+    assert!(!full(r"(?!)", ""));
+
+    let regex = common::regex(r"(\w+?)(\d*)(?=$)");
+    let captures = regex.full_captures("ab12").unwrap().unwrap();
+    assert_eq!(&captures[1], "ab");
+    assert_eq!(&captures[2], "12");
+    assert!(regex.full_captures("ab 12").unwrap().is_none());
+
+    fn assert_auto_traits<T: Send + Sync + UnwindSafe + RefUnwindSafe>(_: &T) {}
+    assert_auto_traits(&regex);
+    assert_auto_traits(&common::regex(r"\w+"));
+}
+
+#[test]
+fn match_len() {
+    let len = |re: &str| {
+        let regex = common::regex(re);
+        (regex.min_match_len(), regex.max_match_len())
+    };
+    // wrapped
+    assert_eq!(len(r"ab|c"), (1, Some(2)));
+    assert_eq!(len(r"a.{2,3}"), (3, Some(13)));
+    assert_eq!(len(r"a+b?"), (1, None));
+    assert_eq!(len(r""), (0, Some(0)));
+    // fancy
+    assert_eq!(len(r"(a)\1"), (1, None));
+    assert_eq!(len(r"(?<=ab)c(?=d)"), (1, Some(1)));
+    assert_eq!(len(r"ab\Kc"), (3, Some(3)));
+    assert_eq!(len(r"(a)?(?(1)bc|d)"), (1, Some(3)));
+    assert_eq!(len(r"(?(ab)c|d)"), (1, Some(3)));
+    assert_eq!(len(r"(?>x{2}|y)*"), (0, None));
+    assert_eq!(len(r"abc(*ACCEPT)d"), (0, Some(4)));
+    assert_eq!(len(r"(?:abc){~1}(?=x)"), (0, Some(7)));
+    // ignoring case, `k` matches the Kelvin sign and `ß` matches `ss`
+    assert_eq!(len(r"(?i)k(?=x)"), (1, Some(3)));
+    assert_eq!(len(r"(?i)ß(?=x)"), (1, Some(12)));
+}
+
+#[test]
+fn match_len_near_end() {
+    // searches don't try to match where too little text is left
+    assert!(common::regex(r"(\d)\d\1").is_match("x121").unwrap());
+    assert!(!common::regex(r"(\d)\d\1").is_match("x12").unwrap());
+    assert!(common::regex(r"ab\Kc(?!d)").is_match("xabc").unwrap());
+    assert!(common::regex(r"(?i)k(?=$)").is_match("x\u{212A}").unwrap());
+    assert!(common::regex(r"ab(*ACCEPT)cd(?=x)")
+        .is_match("xab")
+        .unwrap());
+    let regex = common::regex(r"(\w)\w(?!\1)");
+    assert_eq!(regex.find_from_pos("aba", 2).unwrap(), None);
+    let m = regex.find_from_pos("abab", 2).unwrap().unwrap();
+    assert_eq!(m.range(), 2..4);
+    let partial = common::regex(r"(\w)\w\w(?!\1)")
+        .find_partial("ab a")
+        .unwrap()
+        .unwrap();
+    assert_eq!(partial.range(), 3..4);
+    assert!(partial.is_partial());
+}
+
+#[test]
+fn clone_across_threads() {
+    for pattern in [r"(\w)\1(?=!)", r"\w\w!"] {
+        let regex = common::regex(pattern);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let regex = regex.clone();
+                std::thread::spawn(move || {
+                    let text = format!("{}aa!", "-".repeat(i));
+                    let m = regex.find(&text).unwrap().unwrap();
+                    let partial = regex.find_partial("xb").unwrap().unwrap();
+                    (m.start(), partial.is_partial())
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            assert_eq!(handle.join().unwrap(), (i, true));
+        }
+        assert_eq!(regex.as_str(), pattern);
+    }
+}
+
+#[test]
+fn parse_with_from_str_and_try_from() {
+    use fancy_regex::Regex;
+    use std::convert::TryFrom;
+    use std::str::FromStr;
+
+    fn parse_all<T: FromStr>(values: &[&str]) -> Result<Vec<T>, T::Err> {
+        values.iter().map(|value| value.parse()).collect()
+    }
+
+    let regexes: Vec<Regex> = parse_all(&[r"(\w)\1", r"\d+"]).unwrap();
+    assert!(regexes[0].is_match("xaa").unwrap());
+    assert!(parse_all::<Regex>(&[r"\d+", r"(?<=a+)b"]).is_err());
+
+    assert!(Regex::try_from(r"a(?=b)").unwrap().is_match("ab").unwrap());
+    assert!(Regex::try_from(String::from("(")).is_err());
+}
+
 #[cfg_attr(feature = "track_caller", track_caller)]
 fn assert_match(re: &str, text: &str) {
     let result = match_text(re, text);
@@ -185,3 +768,51 @@ fn match_text(re: &str, text: &str) -> bool {
     );
     result.unwrap()
 }
+
+#[test]
+#[cfg(feature = "std")]
+fn match_os_str_and_path() {
+    use std::ffi::OsStr;
+    use std::path::Path;
+
+    let regex = common::regex(r"(?<=/)(\w)\1");
+    assert!(regex.is_match_os(OsStr::new("a/bb")).unwrap());
+    assert!(!regex.is_match_os(OsStr::new("abb")).unwrap());
+    assert!(regex.is_match_path(Path::new("x/yy/z")).unwrap());
+    assert_eq!(
+        regex.find_os(OsStr::new("a/bb/c")).unwrap().unwrap(),
+        OsStr::new("bb")
+    );
+    assert_eq!(
+        regex.find_path(Path::new("x/yy/z")).unwrap().unwrap(),
+        OsStr::new("yy")
+    );
+    assert!(regex.find_os(OsStr::new("abb")).unwrap().is_none());
+
+    let captures = regex.captures_path(Path::new("x/yy")).unwrap().unwrap();
+    assert_eq!(captures.len(), 2);
+    assert_eq!(captures.get(0).unwrap(), OsStr::new("yy"));
+    assert_eq!(captures.get(1).unwrap(), OsStr::new("y"));
+    assert!(captures.get(2).is_none());
+
+    let regex = common::regex(r"(?<ext>\w+)$");
+    let captures = regex.captures_os(OsStr::new("a.rs")).unwrap().unwrap();
+    assert_eq!(captures.name("ext").unwrap(), OsStr::new("rs"));
+    assert!(captures.name("stem").is_none());
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let regex = common::regex(r"(?<=/)(\w)\1");
+        let text = OsStr::from_bytes(b"\xff/cc\xfe");
+        assert!(regex.is_match_os(text).unwrap());
+        assert_eq!(regex.find_os(text).unwrap().unwrap(), OsStr::new("cc"));
+        let not_utf8 = OsStr::from_bytes(b"/\xff\xff");
+        assert!(!regex.is_match_os(not_utf8).unwrap());
+
+        let regex = common::regex(r"/(.*)");
+        let text = OsStr::from_bytes(b"\xff/a\xfeb");
+        let captures = regex.captures_os(text).unwrap().unwrap();
+        assert_eq!(captures.get(1).unwrap(), OsStr::new("a"));
+    }
+}
@@ -0,0 +1,75 @@
+use fancy_regex::{MultiRegex, Regex};
+use std::ops::Range;
+
+fn find_all(patterns: &[&str], text: &str) -> Vec<(usize, Range<usize>)> {
+    MultiRegex::new(patterns)
+        .unwrap()
+        .find_iter(text)
+        .map(|m| {
+            let m = m.unwrap();
+            (m.pattern(), m.range())
+        })
+        .collect()
+}
+
+#[test]
+fn leftmost_match_wins() {
+    assert_eq!(
+        find_all(&[r"b+", r"(\w)\1"], "xaabbb"),
+        vec![(1, 1..3), (0, 3..6)]
+    );
+    assert_eq!(find_all(&[r"\d", r"(?<=a)\w"], "a1b"), vec![(0, 1..2)]);
+}
+
+#[test]
+fn first_pattern_wins_at_same_position() {
+    assert_eq!(
+        find_all(&[r"\w+", r"\w"], "ab c"),
+        vec![(0, 0..2), (0, 3..4)]
+    );
+    assert_eq!(find_all(&[r"\w", r"\w+"], "ab"), vec![(0, 0..1), (0, 1..2)]);
+    assert_eq!(find_all(&[r"(a)\1", r"aa+"], "aaa"), vec![(0, 0..2)]);
+    assert_eq!(find_all(&[r"aa+", r"(a)\1"], "aaa"), vec![(0, 0..3)]);
+}
+
+#[test]
+fn same_as_alternation() {
+    let patterns = [r"\d+", r"(\w)\1", r"\w+(?=\()", r"\s*", r"[a-z]"];
+    let alternation = Regex::new(&format!("(?:{})", patterns.join(")|(?:"))).unwrap();
+    let text = "ab(cc 12) dd xyz";
+    let expected: Vec<_> = alternation
+        .find_iter(text)
+        .map(|m| m.unwrap().range())
+        .collect();
+    let ranges: Vec<_> = find_all(&patterns, text)
+        .into_iter()
+        .map(|(_, range)| range)
+        .collect();
+    assert_eq!(ranges, expected);
+}
+
+#[test]
+fn continue_from_previous_match() {
+    assert_eq!(
+        find_all(&[r"\G\w", r"\G\s"], "ab c,d"),
+        vec![(0, 0..1), (0, 1..2), (1, 2..3), (0, 3..4)]
+    );
+    assert_eq!(
+        find_all(&[r"\d", r"\Ga"], "a1aa2"),
+        vec![(1, 0..1), (0, 1..2), (1, 2..3), (1, 3..4), (0, 4..5)]
+    );
+}
+
+#[test]
+fn find() {
+    let multi = MultiRegex::new([r"x", r"(?<=\s)\w"]).unwrap();
+    let m = multi.find("ab cx").unwrap().unwrap();
+    assert_eq!((m.pattern(), m.as_str()), (1, "c"));
+    assert!(multi.find("abc").unwrap().is_none());
+    assert_eq!(multi.len(), 2);
+    assert!(MultiRegex::new(Vec::<String>::new())
+        .unwrap()
+        .find("a")
+        .unwrap()
+        .is_none());
+}
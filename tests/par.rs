@@ -0,0 +1,41 @@
+#![cfg(feature = "rayon")]
+
+use fancy_regex::Regex;
+
+mod common;
+
+fn assert_same_matches(re: &Regex, text: &str) {
+    let expected: Vec<_> = re.find_iter(text).map(|m| m.unwrap().range()).collect();
+    let actual: Vec<_> = re.par_find_iter(text).map(|m| m.unwrap().range()).collect();
+    assert_eq!(actual.len(), expected.len(), "{}", re.as_str());
+    assert!(actual == expected, "{}", re.as_str());
+}
+
+#[test]
+fn par_find_iter_same_as_find_iter() {
+    // longer than a chunk, with multi-byte characters that chunks can't be split in
+    let text = "aab  ébba cab\naa\u{2028}aba bb:ccc a ".repeat(40_000);
+    for pattern in [
+        r"(\w)\1",
+        r"(?<=\s)\w{1,3}(?=\s)",
+        r"a{0,2}",
+        r"\b\w(?!\w)",
+        r"\Ga",
+        r"(?m)^a|b$",
+        r"(a)b?\Kc?",
+        r"[^a]{3,9}",
+        r"(?s).{10,50}",
+        r"",
+        r"(?<=a)\w?",
+    ] {
+        assert_same_matches(&common::regex(pattern), &text);
+    }
+}
+
+#[test]
+fn par_find_iter_unbounded() {
+    let re = common::regex(r"(\w)\1\w*");
+    let text = "xaa bb cc";
+    let matches: Vec<_> = re.par_find_iter(text).map(|m| m.unwrap().range()).collect();
+    assert_eq!(matches, vec![1..3, 4..6, 7..9]);
+}
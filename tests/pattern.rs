@@ -0,0 +1,28 @@
+#![cfg(feature = "pattern")]
+
+use fancy_regex::Regex;
+
+#[test]
+fn str_methods() {
+    let re = Regex::new(r"(\w)\1").unwrap();
+    let text = "xaa bb c dd";
+    assert_eq!(text.find(&re), Some(1));
+    assert_eq!(
+        text.matches(&re).collect::<Vec<_>>(),
+        vec!["aa", "bb", "dd"]
+    );
+    assert_eq!(
+        text.split(&re).collect::<Vec<_>>(),
+        vec!["x", " ", " c ", ""]
+    );
+    assert_eq!(text.replacen(&re, "_", 2), "x_ _ c dd");
+    assert!(text.contains(&re));
+    assert!(!"abc".contains(&re));
+}
+
+#[test]
+fn empty_matches() {
+    let re = Regex::new(r"(?=\w)").unwrap();
+    let positions: Vec<_> = "ab é".match_indices(&re).map(|(i, _)| i).collect();
+    assert_eq!(positions, vec![0, 1, 3]);
+}
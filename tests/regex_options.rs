@@ -42,3 +42,361 @@ fn check_casing_insensitive_option() {
         _ => panic!("builder should be able to compile with casing options"),
     }
 }
+
+#[test]
+fn check_anchored_option() {
+    for pattern in [r"\d+", r"(\d)\1*"] {
+        let regex = RegexBuilder::new(pattern).anchored(true).build().unwrap();
+        assert!(regex.is_match("12a").unwrap());
+        assert!(!regex.is_match("a12").unwrap());
+        assert!(regex.find("a12").unwrap().is_none());
+        assert_eq!(regex.find_from_pos("a12", 1).unwrap().unwrap().start(), 1);
+        assert!(regex.captures_from_pos("a 12", 1).unwrap().is_none());
+        let matches: Vec<_> = regex
+            .find_iter("11a1")
+            .map(|m| m.unwrap().as_str())
+            .collect();
+        assert_eq!(matches, vec!["11"]);
+    }
+}
+
+#[test]
+fn check_leftmost_longest_option() {
+    let longest = |pattern: &str, text: &str| {
+        let regex = RegexBuilder::new(pattern)
+            .leftmost_longest(true)
+            .build()
+            .unwrap();
+        let found = regex.find(text).unwrap().map(|m| m.range());
+        let captures = regex.captures(text).unwrap();
+        assert_eq!(captures.map(|caps| caps.get(0).unwrap().range()), found);
+        found
+    };
+    // wrapped
+    assert_eq!(longest(r"a|ab|abc", "xabcd"), Some(1..4));
+    assert_eq!(longest(r"a+?", "aaa"), Some(0..3));
+    // fancy
+    assert_eq!(longest(r"(a|ab)(c|bcd)(?!x)", "abcd"), Some(0..4));
+    assert_eq!(longest(r"(\w)(\1|\1\1)", "xaaab"), Some(1..4));
+    assert_eq!(longest(r"(?<=x)(a|ab)", "abxab"), Some(3..5));
+    assert_eq!(longest(r"(a|ab)(?=b)", "ac xabb"), Some(4..6));
+    assert_eq!(longest(r"(a|ab)\1", "ba"), None);
+    // atomic groups still commit
+    assert_eq!(longest(r"(?>a|ab)c?", "abc"), Some(0..1));
+}
+
+#[test]
+fn check_crlf_line_breaks_option() {
+    let regex = RegexBuilder::new(r"^a\R+b$")
+        .crlf_line_breaks(true)
+        .build()
+        .unwrap();
+    assert!(regex.is_match("a\r\n\n\rb").unwrap());
+    assert!(!regex.is_match("a\x0Bb").unwrap());
+    assert!(!regex.is_match("a\u{2028}b").unwrap());
+}
+
+#[test]
+fn check_h_horizontal_space_option() {
+    let horizontal = |pattern: &str| {
+        RegexBuilder::new(pattern)
+            .h_horizontal_space(true)
+            .build()
+            .unwrap()
+    };
+    for text in ["\t", " ", "\u{A0}", "\u{2003}", "\u{3000}"] {
+        assert!(horizontal(r"^\h$").is_match(text).unwrap());
+        assert!(horizontal(r"^(\h)\1$").is_match(&text.repeat(2)).unwrap());
+        assert!(!horizontal(r"^[\H]$").is_match(text).unwrap());
+    }
+    for text in ["\n", "a", "5"] {
+        assert!(!horizontal(r"^\h$").is_match(text).unwrap());
+        assert!(horizontal(r"^\H$").is_match(text).unwrap());
+    }
+    // hex digits by default
+    assert!(RegexBuilder::new(r"^\h$")
+        .build()
+        .unwrap()
+        .is_match("5")
+        .unwrap());
+}
+
+#[test]
+fn check_equality_of_options() {
+    use fancy_regex::Regex;
+    use std::collections::HashSet;
+
+    let plain = Regex::new(r"(\w)\1").unwrap();
+    let same = Regex::new(r"(\w)\1").unwrap();
+    let anchored = RegexBuilder::new(r"(\w)\1").anchored(true).build().unwrap();
+    let insensitive = RegexBuilder::new(r"(\w)\1")
+        .case_insensitive(true)
+        .build()
+        .unwrap();
+    let other = Regex::new(r"(\w)\1+").unwrap();
+
+    assert_eq!(plain, same);
+    assert_ne!(plain, anchored);
+    assert_ne!(plain, insensitive);
+    assert_ne!(plain, other);
+
+    #[allow(clippy::mutable_key_type)] // Hash and Eq only look at the pattern and the options
+    let set: HashSet<_> = vec![plain, same, anchored, insensitive, other]
+        .into_iter()
+        .collect();
+    assert_eq!(set.len(), 4);
+}
+
+#[test]
+fn check_callout_option() {
+    use fancy_regex::{CalloutId, CalloutResult, Regex};
+    use std::sync::{Arc, Mutex};
+
+    // callouts can trace the match
+    let trace = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&trace);
+    let regex = RegexBuilder::new(r#"(a+)(?C1)b(?C"end")"#)
+        .callout(move |callout| {
+            seen.lock()
+                .unwrap()
+                .push((callout.id().clone(), callout.position(), callout.group(1)));
+            CalloutResult::Continue
+        })
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("xaab").unwrap().unwrap().range(), 1..4);
+    assert_eq!(
+        *trace.lock().unwrap(),
+        vec![
+            (CalloutId::Number(1), 3, Some(1..3)),
+            (CalloutId::String("end".to_string()), 4, Some(1..3)),
+        ]
+    );
+
+    // failing at a callout backtracks
+    let regex = RegexBuilder::new(r"(\w+)(?C)")
+        .callout(|callout| match callout.group(1) {
+            Some(group) if group.len() > 2 => CalloutResult::Fail,
+            _ => CalloutResult::Continue,
+        })
+        .build()
+        .unwrap();
+    assert_eq!(regex.find("abcd").unwrap().unwrap().as_str(), "ab");
+
+    // aborting stops the search
+    let regex = RegexBuilder::new(r"a(?C)|b")
+        .callout(|_| CalloutResult::Abort)
+        .build()
+        .unwrap();
+    assert!(regex.find("ab").unwrap().is_none());
+    assert_eq!(regex.find("b").unwrap().unwrap().as_str(), "b");
+
+    // callouts are ignored without a function
+    assert!(Regex::new(r"a(?C)b").unwrap().is_match("ab").unwrap());
+}
+
+#[test]
+fn check_predicate_option() {
+    use fancy_regex::{CalloutId, CompileError, Error};
+
+    // predicates can look at the text matched so far and the captures
+    let regex = RegexBuilder::new(r"(\d+)(?{even})-(\d+)(?{sum_10})")
+        .predicate("even", |callout| {
+            assert_eq!(callout.id(), &CalloutId::String("even".to_string()));
+            callout.text()[callout.position() - 1] % 2 == 0
+        })
+        .predicate("sum_10", |callout| {
+            let number = |i| {
+                let text = &callout.text()[callout.group(i).unwrap()];
+                std::str::from_utf8(text).unwrap().parse::<u32>().unwrap()
+            };
+            number(1) + number(2) == 10
+        })
+        .build()
+        .unwrap();
+    let found: Vec<_> = regex
+        .find_iter("3-7 4-6 12-8 2-9")
+        .map(|m| m.unwrap().as_str())
+        .collect();
+    assert_eq!(found, vec!["4-6", "2-8"]);
+
+    // a predicate that isn't registered is an error
+    let result = RegexBuilder::new(r"a(?{missing})")
+        .predicate("other", |_| true)
+        .build();
+    assert!(matches!(
+        result,
+        Err(Error::CompileError(CompileError::UnknownPredicate(name))) if name == "missing"
+    ));
+}
+
+#[test]
+fn check_unicode_posix_classes_option() {
+    use fancy_regex::Regex;
+
+    let unicode = |pattern: &str| {
+        RegexBuilder::new(pattern)
+            .unicode_posix_classes(true)
+            .build()
+            .unwrap()
+    };
+    let ascii = |pattern: &str| Regex::new(pattern).unwrap();
+    for (pattern, text) in [
+        (r"^[[:alpha:]]+$", "éa"),
+        (r"^[[:digit:]]$", "٣"),
+        (r"^[[:punct:]]$", "«"),
+        (r"^[[:space:]]$", "\u{3000}"),
+        (r"^[[:upper:]][[:lower:]]$", "Éé"),
+        (r"^[[:graph:]]$", "€"),
+        (r"^[[:print:]]$", "\u{3000}"),
+    ] {
+        assert!(unicode(pattern).is_match(text).unwrap(), "{}", pattern);
+        assert!(!ascii(pattern).is_match(text).unwrap(), "{}", pattern);
+    }
+    assert!(unicode(r"^[[:punct:]]$").is_match("$").unwrap());
+    assert!(!unicode(r"^[[:punct:]]$").is_match("a").unwrap());
+    assert!(!unicode(r"^[^[:alnum:][:space:]]$").is_match("é").unwrap());
+    assert!(!unicode(r"^[[:graph:][:print:]]$").is_match("\n").unwrap());
+    assert!(unicode(r"^([[:word:]])\1$").is_match("éé").unwrap());
+    assert!(ascii(r"^([[:word:]])\1$").is_match("ee").unwrap());
+    assert!(!unicode(r"^[[:^alpha:]]$").is_match("é").unwrap());
+    assert!(ascii(r"^[[:^alpha:]]$").is_match("é").unwrap());
+}
+
+#[test]
+fn check_octal_escapes_option() {
+    let octal = |pattern: &str| RegexBuilder::new(pattern).octal_escapes(true).build();
+    let regex = octal(r"^(a)\1\101[\102]\0$").unwrap();
+    assert!(regex.is_match("aaAB\0").unwrap());
+    let regex = octal(r"^(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)\10\11$").unwrap();
+    assert!(regex.is_match("abcdefghijj\t").unwrap());
+    assert!(octal(r"\8").is_err());
+    // backrefs by default
+    assert!(RegexBuilder::new(r"(a)\101").build().is_err());
+}
+
+#[test]
+fn check_python_syntax_dialect() {
+    use fancy_regex::SyntaxDialect;
+
+    let python = |pattern: &str| {
+        RegexBuilder::new(pattern)
+            .syntax_dialect(SyntaxDialect::Python)
+            .build()
+    };
+    let is_match = |pattern: &str, text: &str| python(pattern).unwrap().is_match(text).unwrap();
+    // end of text
+    assert!(is_match(r"a$", "a\n"));
+    assert!(!is_match(r"a$", "a\n\n"));
+    assert!(!is_match(r"a\Z", "a\n"));
+    assert!(is_match(r"(?m)a$\nb", "a\nb"));
+    // flags
+    assert!(is_match(r"(?i)(?m)^b", "a\nB"));
+    assert!(is_match(r"(?i:a)b", "Ab"));
+    assert!(python(r"a(?i)b").is_err());
+    assert!(python(r"(?U)a").is_err());
+    assert!(is_match(r"(?u)^\w$", "\u{E9}"));
+    assert!(!is_match(r"(?a)^\w$", "\u{E9}"));
+    assert!(!is_match(r"(?a)^[\d\s]$", "\u{663}"));
+    assert!(is_match(r"(?a)\bx", "\u{E9}x"));
+    assert!(!is_match(r"\bx", "\u{E9}x"));
+    // groups, backrefs and octal escapes
+    assert!(is_match(r"^(?P<a>a)\1(?P=a)$", "aaa"));
+    assert!(is_match(r"^(a)\141[\1]$", "aa\x01"));
+    let regex = python(r"^(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)\108$").unwrap();
+    assert!(regex.is_match("abcdefghijj8").unwrap());
+    assert!(python(r"\400").is_err());
+    // escapes
+    assert!(is_match(r"^\v$", "\x0B"));
+    assert!(!is_match(r"^\v$", "\n"));
+    assert!(is_match(r"^\<\>$", "<>"));
+    assert!(python(r"\h").is_err());
+    assert!(python(r"\Qa").is_err());
+    // classes
+    assert!(is_match(r"^[[&&~~]+$", "[&~"));
+    assert!(is_match(r"^[+--]+$", ",-+"));
+    assert!(is_match(r"^[a-c-e]+$", "b-e"));
+    assert!(is_match(r"^[]-a]$", "^"));
+    assert!(is_match(r"^[[:alpha:]]$", ":]"));
+    // possessive quantifiers
+    assert!(python(r"a*+").is_err());
+    assert!(python(r"a{1,2}+").is_err());
+}
+
+#[test]
+fn check_ecmascript_syntax_dialect() {
+    use fancy_regex::SyntaxDialect;
+
+    let build = |pattern: &str, dialect| RegexBuilder::new(pattern).syntax_dialect(dialect).build();
+    let ecmascript = |pattern: &str| build(pattern, SyntaxDialect::EcmaScript);
+    let unicode = |pattern: &str| build(pattern, SyntaxDialect::EcmaScriptUnicode);
+    let unicode_sets = |pattern: &str| build(pattern, SyntaxDialect::EcmaScriptUnicodeSets);
+    let is_match = |pattern: &str, text: &str| {
+        let ecmascript = ecmascript(pattern).unwrap().is_match(text).unwrap();
+        let unicode = unicode(pattern).unwrap().is_match(text).unwrap();
+        assert_eq!(ecmascript, unicode, "{}", pattern);
+        ecmascript
+    };
+    // line terminators
+    assert!(!is_match(r"a$", "a\n"));
+    assert!(!is_match(r"^.$", "\u{2028}"));
+    assert!(is_match(r"^(?s:.)$", "\u{2028}"));
+    assert!(is_match(r"(?m:^b$)", "a\u{2029}b\rc"));
+    assert!(!is_match(r"(?m:^b)", "ab"));
+    // classes
+    assert!(!is_match(r"\d", "\u{663}"));
+    assert!(!is_match(r"\w", "\u{E9}"));
+    assert!(is_match(r"\bx", "\u{E9}x"));
+    assert!(is_match(r"^\s+$", "\u{A0}\u{FEFF}\u{2028}"));
+    assert!(!is_match(r"\s", "\u{85}"));
+    assert!(!is_match(r"[]", "a"));
+    assert!(is_match(r"^[^]$", "\n"));
+    assert!(is_match(r"^[a-c-e]+$", "b-e"));
+    assert!(is_match(r"^[[&&~~]+$", "[&~"));
+    // backrefs
+    assert!(is_match(r"^\1(a)$", "a"));
+    assert!(is_match(r"^(?:(a)|b)\1$", "b"));
+    assert!(is_match(r"^(?:b\1|(a))+$", "ba"));
+    assert!(is_match(r"^(?<a>a)\k<a>\1$", "aaa"));
+    // escapes
+    assert!(is_match(r"^\cJ\v\0$", "\n\x0B\0"));
+    assert!(is_match(r"^\uD83D\uDE00$", "\u{1F600}"));
+    // the tolerances of Annex B without the `u` flag
+    let is_match = |pattern: &str, text: &str| ecmascript(pattern).unwrap().is_match(text).unwrap();
+    assert!(is_match(r"^\a\-\k\8$", "a-k8"));
+    assert!(is_match(r"^\101\0101$", "A\x08\x31"));
+    assert!(is_match(r"^\c1$", "\\c1"));
+    assert!(is_match(r"^\x4\u{2}$", "x4uu"));
+    assert!(is_match(r"^[\c1\w-]+$", "\x11-a"));
+    assert!(is_match(r"^a{,2}\]$", "a{,2}]"));
+    assert!(is_match(r"^(?=a)*a$", "a"));
+    // without them
+    for pattern in [
+        r"\a", r"\-", r"\1", r"\c1", r"\x4", r"a{,2}", r"]", r"(?=a)*", r"[\w-a]",
+    ] {
+        assert!(unicode(pattern).is_err(), "{}", pattern);
+    }
+    assert!(unicode(r"\u{1F600}")
+        .unwrap()
+        .is_match("\u{1F600}")
+        .unwrap());
+    assert!(unicode(r"\p{Lu}").unwrap().is_match("\u{C9}").unwrap());
+    // set operations with the `v` flag
+    let regex = unicode_sets(r"^[\p{L}--[a-z]]$").unwrap();
+    assert!(regex.is_match("\u{E9}").unwrap());
+    assert!(!regex.is_match("e").unwrap());
+    assert!(unicode_sets(r"[a&&&b]").is_err());
+    assert!(unicode_sets(r"[(]").is_err());
+    // groups and flags
+    assert!(is_match(r"(?i:a)b", "Ab"));
+    for pattern in [
+        r"(?i)a",
+        r"(?x:a)",
+        r"(?>a)",
+        r"(?P<a>a)",
+        r"(*FAIL)",
+        r"a*+",
+    ] {
+        assert!(ecmascript(pattern).is_err(), "{}", pattern);
+    }
+}
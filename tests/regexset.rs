@@ -0,0 +1,57 @@
+use fancy_regex::{Error, RegexSet, RuntimeError};
+
+fn matches(set: &RegexSet, text: &str) -> Vec<usize> {
+    set.matches(text).unwrap().into_iter().collect()
+}
+
+#[test]
+fn set_mixed_patterns() {
+    let set = RegexSet::new([r"\d+", r"(\w)\1", r"foo", r"(?<=@)\w+", r"^b"]).unwrap();
+    assert_eq!(set.len(), 5);
+    assert_eq!(matches(&set, "foo@bar"), vec![1, 2, 3]);
+    assert_eq!(matches(&set, "bar 42"), vec![0, 4]);
+    assert_eq!(matches(&set, ""), Vec::<usize>::new());
+    assert!(set.is_match("x@y").unwrap());
+    assert!(!set.is_match("xyz").unwrap());
+}
+
+#[test]
+fn set_matches_overlap() {
+    let set = RegexSet::new([r"\w+", r"\w", r"(?=\w)", r"a(?!b)"]).unwrap();
+    let result = set.matches("ab").unwrap();
+    assert!(result.matched_any());
+    assert!(result.matched(0));
+    assert!(result.matched(2));
+    assert!(!result.matched(3));
+    assert_eq!(result.iter().collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(result.len(), 4);
+}
+
+#[test]
+fn set_only_fancy_or_only_delegated() {
+    let fancy = RegexSet::new(vec![r"(a)\1".to_string(), r"(?<!a)b".to_string()]).unwrap();
+    assert_eq!(matches(&fancy, "aab"), vec![0]);
+    let delegated = RegexSet::new(["a", "b"]).unwrap();
+    assert_eq!(matches(&delegated, "b"), vec![1]);
+    assert_eq!(delegated.patterns(), &["a", "b"]);
+}
+
+#[test]
+fn set_empty() {
+    let set = RegexSet::empty();
+    assert!(set.is_empty());
+    assert!(!set.is_match("a").unwrap());
+    assert!(!set.matches("a").unwrap().matched_any());
+}
+
+#[test]
+fn set_errors() {
+    assert!(RegexSet::new(["a", "(b"]).is_err());
+    let set = RegexSet::new(["a", r"(?i)(a|b|ab)*\1?(?=c)"]).unwrap();
+    // The delegated pattern matches, but the fancy one still has to run
+    let result = set.matches(&"ab".repeat(40));
+    assert!(matches!(
+        result,
+        Err(Error::RuntimeError(RuntimeError::BacktrackLimitExceeded))
+    ));
+}
@@ -1,4 +1,4 @@
-use fancy_regex::{Captures, NoExpand};
+use fancy_regex::{Captures, Error, NoExpand, RegexBuilder, RuntimeError};
 use std::borrow::Cow;
 
 mod common;
@@ -60,6 +60,26 @@ fn replacer_callback() {
     assert_eq!(result, "a Approx. b");
 }
 
+#[test]
+fn replacer_stateful_callback() {
+    let regex = common::regex(r"(\w)\1");
+
+    // Replacer impl for FnMut(&Captures) keeps its state between matches
+    let mut count = 0;
+    let result = regex.replace_all("aa bb cc", |cap: &Captures| {
+        count += 1;
+        format!("{}{}", &cap[1], count)
+    });
+    assert_eq!(result, "a1 b2 c3");
+
+    let mut count = 0;
+    let result = regex.replacen("aa bb cc", 2, |_: &Captures| {
+        count += 1;
+        count.to_string()
+    });
+    assert_eq!(result, "1 2 cc");
+}
+
 /// `replace()` does only one replacement
 #[test]
 fn replace_one() {
@@ -74,9 +94,37 @@ fn replace_all() {
     assert_eq!(regex.replace_all("aaaa aaa aa a", "xx"), "xxxx xxa xx a");
 }
 
+/// `replace_all()` with `\K` only replaces the text after it
+#[test]
+fn replace_all_keepout() {
+    let regex = common::regex(r"\w+=\K\w+");
+    assert_eq!(regex.replace_all("a=1, bb=22", "x"), "a=x, bb=x");
+    let regex = common::regex(r",\K");
+    assert_eq!(regex.replace_all("a,,b", " "), "a, , b");
+}
+
 /// `replacen()` replaces predefined number of times
 #[test]
 fn replacen() {
     let regex = common::regex("bla");
     assert_eq!(regex.replacen("blablabla", 2, "foo"), "foofoobla");
 }
+
+/// `try_replace()` and `try_replace_all()` propagate errors from matching
+#[test]
+fn try_replace() {
+    let regex = common::regex("bla");
+    assert_eq!(regex.try_replace("blabla", "foo").unwrap(), "foobla");
+    assert_eq!(regex.try_replace_all("blabla", "foo").unwrap(), "foofoo");
+
+    let regex = RegexBuilder::new(r"(?i)(a|b|ab)*\1?(?=c)")
+        .backtrack_limit(100_000)
+        .build()
+        .unwrap();
+    let text = "abababababababababababababababababababababababababababab";
+    match regex.try_replace_all(text, "") {
+        Err(Error::RuntimeError(RuntimeError::BacktrackLimitExceeded)) => {}
+        _ => panic!("Expected RuntimeError::BacktrackLimitExceeded"),
+    }
+    assert!(regex.try_replace(text, "").is_err());
+}
@@ -0,0 +1,109 @@
+use fancy_regex::{Regex, Scanner};
+use std::ops::Range;
+
+fn scan(re: &str, max_len: usize, chunks: &[&[u8]]) -> Vec<Range<usize>> {
+    let regex = Regex::new(re).unwrap();
+    let mut scanner = Scanner::new(&regex, max_len);
+    let mut matches = Vec::new();
+    for chunk in chunks {
+        matches.extend(scanner.push(chunk).unwrap());
+    }
+    matches.extend(scanner.finish().unwrap());
+    for m in &matches {
+        assert_eq!(m.as_bytes().len(), m.range().len());
+    }
+    matches.iter().map(|m| m.range()).collect()
+}
+
+#[test]
+fn matches_across_chunks() {
+    let chunks: &[&[u8]] = &[b"ab", b"c a", b"bc", b"", b"abcab", b"c"];
+    assert_eq!(scan(r"abc", 3, chunks), vec![0..3, 4..7, 7..10, 10..13]);
+    assert_eq!(scan(r"\w+", 4, &[b"ab", b"c d"]), vec![0..3, 4..5]);
+}
+
+#[test]
+fn same_as_find_iter_with_single_bytes() {
+    let text = "xaa bb(c) a1 dd e,e\n f";
+    for re in [
+        r"(\w)\1",
+        r"\w+(?=\()",
+        r"(?<=\()\w",
+        r"\b\w",
+        r"\w$",
+        r"(?m)^\s*\w",
+        r"\w(?!\w)",
+        r"",
+        r"\d*",
+    ] {
+        let expected: Vec<_> = Regex::new(re)
+            .unwrap()
+            .find_iter(text)
+            .map(|m| m.unwrap().range())
+            .collect();
+        let chunks: Vec<_> = text.as_bytes().chunks(1).collect();
+        assert_eq!(scan(re, 4, &chunks), expected, "{}", re);
+        let chunks: Vec<_> = text.as_bytes().chunks(5).collect();
+        assert_eq!(scan(re, 4, &chunks), expected, "{}", re);
+    }
+}
+
+#[test]
+fn many_chunks() {
+    let regex = Regex::new(r"(?<=a)b").unwrap();
+    let mut scanner = Scanner::new(&regex, 2);
+    let mut count = 0;
+    for _ in 0..1000 {
+        count += scanner.push(b"xab").unwrap().len();
+    }
+    count += scanner.finish().unwrap().len();
+    assert_eq!(count, 1000);
+}
+
+#[test]
+fn matches_invalid_utf8() {
+    assert_eq!(
+        scan(r"(\w)\1", 2, &[b"\xffa", b"a\xfe", b"bb"]),
+        vec![1..3, 4..6]
+    );
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn find_read() {
+    let regex = Regex::new(r"(?<=\s)(\w)\1").unwrap();
+    let reader = std::io::BufReader::with_capacity(3, &b"aa bb\xff cc dd"[..]);
+    let matches: Vec<_> = regex
+        .find_read(reader, 8)
+        .map(|m| m.unwrap().range())
+        .collect();
+    assert_eq!(matches, vec![3..5, 7..9, 10..12]);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn captures_read_iter() {
+    let regex = Regex::new(r"(?<key>\w+)=(?<value>\d+)?(?=;)").unwrap();
+    let reader = std::io::BufReader::with_capacity(2, &b"a=1; bc=; d=23;"[..]);
+    let captures: Vec<_> = regex
+        .captures_read_iter(reader, 16)
+        .map(|caps| caps.unwrap())
+        .collect();
+    assert_eq!(captures.len(), 3);
+    assert_eq!(captures[1].len(), 3);
+    assert_eq!(captures[1].name("key").unwrap().as_bytes(), b"bc");
+    assert!(captures[1].name("value").is_none());
+    assert_eq!(captures[2].get(2).unwrap().range(), 12..14);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn read_errors() {
+    let regex = Regex::new(r"(?i)(a|b|ab)*\1?(?=c)").unwrap();
+    let text = "ab".repeat(40);
+    let reader = std::io::BufReader::new(text.as_bytes());
+    let errors: Vec<_> = regex.find_read(reader, 100).collect();
+    assert_eq!(errors.len(), 1);
+    let error = errors[0].as_ref().unwrap_err();
+    assert!(error.get_ref().unwrap().is::<fancy_regex::Error>());
+}
@@ -0,0 +1,104 @@
+use fancy_regex::{Error, RegexBuilder, RuntimeError, SearchOptions};
+
+mod common;
+
+#[test]
+fn search_range() {
+    let re = common::regex(r"(?<=\s)(\w)\1");
+    let text = "xaa bb cc";
+    let mut options = SearchOptions::new();
+    options.range(0..6);
+    assert_eq!(re.find_with(text, &options).unwrap().unwrap().range(), 4..6);
+    options.range(0..5);
+    assert!(re.find_with(text, &options).unwrap().is_none());
+    options.range(5..9);
+    assert_eq!(re.find_with(text, &options).unwrap().unwrap().range(), 7..9);
+
+    // the text before the range is still seen by look-behind
+    let re = common::regex(r"(?<=a)b");
+    options.range(1..2);
+    assert!(re.is_match_with("ab", &options).unwrap());
+
+    // the text after the range is not
+    let re = common::regex(r"a$");
+    options.range(0..1);
+    assert!(re.is_match_with("ab", &options).unwrap());
+}
+
+#[test]
+fn search_anchored() {
+    for pattern in [r"\d+", r"(\d)\1"] {
+        let re = common::regex(pattern);
+        let mut options = SearchOptions::new();
+        options.anchored(true);
+        assert!(re.find_with("x11", &options).unwrap().is_none());
+        options.range(1..3);
+        assert_eq!(
+            re.find_with("x11", &options).unwrap().unwrap().as_str(),
+            "11"
+        );
+    }
+}
+
+#[test]
+fn search_backtrack_limit() {
+    let re = common::regex(r"(?i)(a|b|ab)*\1?(?=c)");
+    let text = "ab".repeat(10);
+    assert!(!re.is_match(&text).unwrap());
+    let result = re.is_match_with(&text, SearchOptions::new().backtrack_limit(100));
+    match result.err() {
+        Some(Error::RuntimeError(RuntimeError::BacktrackLimitExceeded)) => {}
+        _ => panic!("Expected RuntimeError::BacktrackLimitExceeded"),
+    }
+}
+
+#[test]
+fn search_earliest() {
+    let re = RegexBuilder::new(r"(a|ab)(?=b|$)")
+        .leftmost_longest(true)
+        .build()
+        .unwrap();
+    assert_eq!(re.find("ab").unwrap().unwrap().as_str(), "ab");
+    let mut options = SearchOptions::new();
+    options.earliest(true);
+    assert_eq!(re.find_with("ab", &options).unwrap().unwrap().as_str(), "a");
+}
+
+#[test]
+fn search_captures() {
+    let re = common::regex(r"(\w+)=(\d+)(?=;)");
+    let text = "a=1; b=2; c=3";
+    let mut options = SearchOptions::new();
+    options.range(5..text.len());
+    let caps = re.captures_with(text, &options).unwrap().unwrap();
+    assert_eq!(&caps[1], "b");
+    assert_eq!(caps.get(2).unwrap().range(), 7..8);
+}
+
+#[test]
+#[should_panic]
+fn search_range_out_of_bounds() {
+    let re = common::regex(r"a");
+    let _ = re.find_with("a", SearchOptions::new().range(0..2));
+}
+
+#[test]
+fn search_region() {
+    let re = common::regex(r"(?<=\w)\d+\b");
+    let text = "a12 345b6 78";
+    assert_eq!(
+        re.find_in_region(text, 0..3).unwrap().unwrap().range(),
+        1..3
+    );
+    // the match would have to end after the region
+    assert!(re.find_in_region(text, 0..2).unwrap().is_none());
+    // the look-behind and word boundary see outside the region
+    assert!(re.find_in_region(text, 5..7).unwrap().is_none());
+    assert!(re.find_in_region(text, 8..9).unwrap().is_some());
+    assert!(re.find(&text[4..7]).unwrap().is_some());
+
+    let re = common::regex(r"(\w+)\b");
+    let caps = re.captures_in_region("foo bar", 4..7).unwrap().unwrap();
+    assert_eq!(&caps[1], "bar");
+    assert!(re.captures_in_region("foobar", 0..3).unwrap().is_none());
+}
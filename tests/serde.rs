@@ -0,0 +1,79 @@
+#![cfg(feature = "serde")]
+
+use fancy_regex::{CalloutResult, Regex, RegexBuilder};
+
+#[test]
+fn serialize_pattern_only() {
+    let regex = Regex::new(r"(\w)\1").unwrap();
+    assert_eq!(serde_json::to_string(&regex).unwrap(), r#""(\\w)\\1""#);
+}
+
+#[test]
+fn serialize_with_options() {
+    let regex = RegexBuilder::new(r"ab+")
+        .case_insensitive(true)
+        .anchored(true)
+        .build()
+        .unwrap();
+    let json = serde_json::to_string(&regex).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["pattern"], "ab+");
+    assert_eq!(value["case_insensitive"], true);
+    assert_eq!(value["anchored"], true);
+    assert_eq!(value["leftmost_longest"], false);
+    assert_eq!(value["backtrack_limit"], 1_000_000);
+
+    let regex: Regex = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&regex).unwrap(), json);
+    assert!(regex.is_match("AB").unwrap());
+    assert!(!regex.is_match("xab").unwrap());
+}
+
+#[test]
+fn serialize_errors() {
+    let regex = RegexBuilder::new(r"\w+(?C1)")
+        .callout(|_| CalloutResult::Continue)
+        .build()
+        .unwrap();
+    let error = serde_json::to_string(&regex).unwrap_err();
+    assert!(error.to_string().contains("callout"), "{}", error);
+
+    let regex = RegexBuilder::new(r"\w+(?{long})")
+        .predicate("long", |callout| callout.position() - callout.start() > 3)
+        .build()
+        .unwrap();
+    let error = serde_json::to_string(&regex).unwrap_err();
+    assert!(error.to_string().contains("predicates"), "{}", error);
+}
+
+#[test]
+fn deserialize() {
+    let regex: Regex = serde_json::from_str(r#""(\\w)\\1""#).unwrap();
+    assert!(regex.is_match("xaa").unwrap());
+
+    let json = r#"{"pattern": "(a|ab)c?(?!x)", "leftmost_longest": true}"#;
+    let regex: Regex = serde_json::from_str(json).unwrap();
+    assert_eq!(regex.find("abc").unwrap().unwrap().as_str(), "abc");
+
+    let json = r#"{"pattern": "a$", "syntax_dialect": "python"}"#;
+    let regex: Regex = serde_json::from_str(json).unwrap();
+    assert!(regex.is_match("a\n").unwrap());
+
+    let json = r#"{"pattern": "[^]", "syntax_dialect": "ecmascript_unicode"}"#;
+    let regex: Regex = serde_json::from_str(json).unwrap();
+    assert!(regex.is_match("\n").unwrap());
+
+    let config: Vec<Regex> = serde_json::from_str(r#"["a+", {"pattern": "b+"}]"#).unwrap();
+    assert_eq!(config.len(), 2);
+}
+
+#[test]
+fn deserialize_errors() {
+    let error = serde_json::from_str::<Regex>(r#""(a""#).unwrap_err();
+    assert!(error.to_string().contains("Parsing error"), "{}", error);
+    assert!(serde_json::from_str::<Regex>(r#"{"anchored": true}"#).is_err());
+    assert!(serde_json::from_str::<Regex>(r#"{"pattern": "a", "unknown": 1}"#).is_err());
+    assert!(serde_json::from_str::<Regex>("1").is_err());
+    let json = r#"{"pattern": "a", "syntax_dialect": "perl"}"#;
+    assert!(serde_json::from_str::<Regex>(json).is_err());
+}
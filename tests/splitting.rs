@@ -95,6 +95,17 @@ mod split_tests {
         let result: Vec<&str> = split_to_vec(r"(?<=2)1(?=2)", "12112122");
         assert_eq!(result, vec!["12112", "22"]);
     }
+
+    #[test]
+    fn split_propagates_errors() {
+        let re = fancy_regex::RegexBuilder::new(r"(?i)(a|b|ab)*\1?(?=c)")
+            .backtrack_limit(100_000)
+            .build()
+            .unwrap();
+        let target = "x abababababababababababababababababababababababababababab";
+        let mut split = re.split(target);
+        assert!(split.next().unwrap().is_err());
+    }
 }
 
 #[cfg(test)]
@@ -169,4 +180,23 @@ mod splitn_tests {
             assert_eq!(&result, expected);
         }
     }
+
+    #[test]
+    fn split_inclusive() {
+        let split_inclusive_test_cases = [
+            ("1", "123", vec!["1", "23"]),
+            ("2", "123", vec!["12", "3"]),
+            ("3", "123", vec!["123"]),
+            ("4", "123", vec!["123"]),
+            ("1", "", vec![]),
+            ("1", "111", vec!["1", "1", "1"]),
+            (r"\d(?=\D)", "1a2b3", vec!["1", "a2", "b3"]),
+        ];
+
+        for (re_str, target, expected) in split_inclusive_test_cases {
+            let re = Regex::new(re_str).unwrap();
+            let result: Vec<&str> = re.split_inclusive(target).map(|x| x.unwrap()).collect();
+            assert_eq!(result, expected);
+        }
+    }
 }